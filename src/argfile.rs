@@ -0,0 +1,120 @@
+//! Argument files, read with `wasm-pack build @build.args`.
+//!
+//! Like with `rustc` and `gcc`, an argument of `@<path>` is replaced by the
+//! arguments in the file at `<path>`, so that long invocations can be
+//! committed and reviewed. The arguments are separated by whitespace and
+//! newlines, can be quoted like in a shell, and `#` starts a comment that runs
+//! to the end of the line.
+
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+
+/// Replace each `@<path>` in `args` by the arguments in the file at `<path>`.
+///
+/// The first argument is the program, and is kept as it is, as are the
+/// arguments after a `--`, which are passed on to other tools. An `@<path>`
+/// with no file at `<path>` is kept too, since it can be an argument starting
+/// with `@`, such as in `--scope @corp`. The arguments read from a file aren't
+/// expanded again.
+pub fn expand(args: impl IntoIterator<Item = OsString>) -> Result<Vec<OsString>> {
+    let mut args = args.into_iter();
+    let mut expanded: Vec<OsString> = args.next().into_iter().collect();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args);
+            break;
+        }
+        let path = match arg.to_str().and_then(|arg| arg.strip_prefix('@')) {
+            Some(path) if !path.is_empty() => path,
+            _ => {
+                expanded.push(arg);
+                continue;
+            }
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                expanded.push(arg);
+                continue;
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to read the argument file {}", path))
+            }
+        };
+        let file_args =
+            parse(&contents).with_context(|| format!("invalid argument file {}", path))?;
+        expanded.extend(file_args.into_iter().map(OsString::from));
+    }
+    Ok(expanded)
+}
+
+/// Split the contents of an argument file into its arguments.
+///
+/// Inside single quotes everything is taken as it is. Inside double quotes
+/// and outside of quotes, a backslash takes the next character as it is.
+pub fn parse(contents: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    // Whether `arg` was started, so that `''` is an empty argument.
+    let mut in_arg = false;
+    let mut chars = contents.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            '#' if !in_arg => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => bail!("unterminated single quote"),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => arg.push(c),
+                            None => bail!("unterminated double quote"),
+                        },
+                        Some(c) => arg.push(c),
+                        None => bail!("unterminated double quote"),
+                    }
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                match chars.next() {
+                    Some(c) => arg.push(c),
+                    None => bail!("a backslash at the end of the file escapes nothing"),
+                }
+            }
+            c => {
+                in_arg = true;
+                arg.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+    Ok(args)
+}
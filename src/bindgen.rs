@@ -6,7 +6,8 @@ use crate::install::{self, Tool};
 use crate::manifest::CrateData;
 use anyhow::{bail, Context, Result};
 use semver;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// Run the `wasm-bindgen` CLI to generate bindings for the current crate's
@@ -23,28 +24,9 @@ pub fn wasm_bindgen_build(
     profile: BuildProfile,
     extra_options: &Vec<String>,
 ) -> Result<()> {
-    let profile_name = match profile.clone() {
-        BuildProfile::Release | BuildProfile::Profiling => "release",
-        BuildProfile::Dev => "debug",
-        BuildProfile::Custom(profile_name) => &profile_name.clone(),
-    };
-
     let out_dir = out_dir.to_str().unwrap();
 
-    let target_directory = {
-        let mut has_target_dir_iter = extra_options.iter();
-        has_target_dir_iter
-            .find(|&it| it == "--target-dir")
-            .and_then(|_| has_target_dir_iter.next())
-            .map(Path::new)
-            .unwrap_or(data.target_directory())
-    };
-
-    let wasm_path = target_directory
-        .join("wasm32-unknown-unknown")
-        .join(profile_name)
-        .join(data.crate_name())
-        .with_extension("wasm");
+    let wasm_path = canonical_wasm_path(&wasm_path(data, profile.clone(), extra_options))?;
 
     let dts_arg = if disable_dts {
         "--no-typescript"
@@ -100,6 +82,45 @@ pub fn wasm_bindgen_build(
     Ok(())
 }
 
+/// The path of the `.wasm` that `cargo build` produces for the current crate
+/// and that `wasm-bindgen` takes as its input.
+pub fn wasm_path(data: &CrateData, profile: BuildProfile, extra_options: &[String]) -> PathBuf {
+    let profile_name = match profile {
+        BuildProfile::Release | BuildProfile::Profiling => "release".to_string(),
+        BuildProfile::Dev => "debug".to_string(),
+        BuildProfile::Custom(profile_name) => profile_name,
+    };
+
+    let target_directory = {
+        let mut has_target_dir_iter = extra_options.iter();
+        has_target_dir_iter
+            .find(|&it| it == "--target-dir")
+            .and_then(|_| has_target_dir_iter.next())
+            .map(Path::new)
+            .unwrap_or(data.target_directory())
+    };
+
+    target_directory
+        .join("wasm32-unknown-unknown")
+        .join(profile_name)
+        .join(data.crate_name())
+        .with_extension("wasm")
+}
+
+/// Resolve any symlinks in the path of the compiled `.wasm`, such as a target
+/// directory that links to a cache volume, so `wasm-bindgen` is handed the
+/// real file.
+fn canonical_wasm_path(path: &Path) -> Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(resolved) if resolved.is_file() => Ok(resolved),
+        _ => bail!(
+            "The compiled wasm file {} does not exist. If the target directory is a symlink, \
+             make sure it still points to an existing directory.",
+            path.display()
+        ),
+    }
+}
+
 /// Check if the `wasm-bindgen` dependency is locally satisfied for the web target
 fn supports_web_target(cli_path: &Path) -> Result<bool> {
     let cli_version = semver::Version::parse(&install::get_cli_version(
@@ -120,7 +141,20 @@ fn supports_dash_dash_target(cli_path: &Path) -> Result<bool> {
     Ok(cli_version >= expected_version)
 }
 
+/// Check if the `wasm-bindgen` dependency is locally satisfied for the deno target
+fn supports_deno_target(cli_path: &Path) -> Result<bool> {
+    let cli_version = semver::Version::parse(&install::get_cli_version(
+        &install::Tool::WasmBindgen,
+        cli_path,
+    )?)?;
+    let expected_version = semver::Version::parse("0.2.65")?;
+    Ok(cli_version >= expected_version)
+}
+
 fn build_target_arg(target: Target, cli_path: &Path) -> Result<String> {
+    if matches!(target, Target::Deno) && !supports_deno_target(cli_path)? {
+        bail!("Your current version of wasm-bindgen does not support the 'deno' target. Please update your project to wasm-bindgen version >= 0.2.65.")
+    }
     if !supports_dash_dash_target(cli_path)? {
         Ok(build_target_arg_legacy(target, cli_path)?)
     } else {
@@ -141,7 +175,7 @@ fn build_target_arg_legacy(target: Target, cli_path: &Path) -> Result<String> {
             }
         }
         Target::Bundler => "--browser",
-        Target::Deno => "--deno",
+        Target::Deno => unreachable!("the deno target requires wasm-bindgen >= 0.2.65"),
     };
     Ok(target_arg.to_string())
 }
@@ -3,13 +3,16 @@
 use crate::child;
 use crate::command::build::BuildProfile;
 use crate::emoji;
-use crate::manifest::Crate;
+use crate::manifest::{Crate, CrateData};
 use crate::PBAR;
 use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeSet;
+use std::env;
 use std::path::Path;
 use std::process::Command;
 use std::str;
 
+pub mod threads;
 pub mod wasm_target;
 
 /// Used when comparing the currently installed
@@ -22,6 +25,34 @@ pub struct WasmPackVersion {
     pub latest: String,
 }
 
+/// How the wasm is compiled and linked, beyond cargo's own options.
+#[derive(Clone, Debug, Default)]
+pub struct Codegen {
+    /// Compile with shared memory and atomics, rebuilding `std` with them.
+    pub threads: bool,
+    /// The linker to link the wasm with instead of the default one.
+    pub linker: Option<String>,
+    /// Extra arguments to pass to the linker.
+    pub link_args: Vec<String>,
+}
+
+impl Codegen {
+    /// The flags that have to be added to the rustflags.
+    fn rustflags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if self.threads {
+            flags.extend(threads::RUSTFLAGS.split(' ').map(|flag| flag.to_string()));
+        }
+        if let Some(linker) = &self.linker {
+            flags.push(format!("-Clinker={}", linker));
+        }
+        for arg in &self.link_args {
+            flags.push(format!("-Clink-arg={}", arg));
+        }
+        flags
+    }
+}
+
 /// Ensure that `rustc` is present and that it is >= 1.30.0
 pub fn check_rustc_version() -> Result<String> {
     let local_minor_version = rustc_minor_version();
@@ -77,12 +108,70 @@ pub fn cargo_build_wasm(
     path: &Path,
     profile: BuildProfile,
     extra_options: &[String],
+    codegen: &Codegen,
+    bin: Option<&str>,
+    jobs: Option<u32>,
 ) -> Result<()> {
     let msg = format!("{}Compiling to Wasm...", emoji::CYCLONE);
     PBAR.info(&msg);
 
+    let cmd = cargo_build_wasm_command(path, profile, extra_options, codegen, bin, jobs)?;
+    child::run(cmd, "cargo build").context("Compiling your crate to WebAssembly failed")?;
+    Ok(())
+}
+
+/// Run `cargo check` for the wasm target, like `cargo_build_wasm` but
+/// without producing the `.wasm`.
+pub fn cargo_check_wasm(
+    path: &Path,
+    profile: BuildProfile,
+    extra_options: &[String],
+    codegen: &Codegen,
+    bin: Option<&str>,
+    jobs: Option<u32>,
+) -> Result<()> {
+    let msg = format!("{}Checking the crate for Wasm...", emoji::CYCLONE);
+    PBAR.info(&msg);
+
+    let cmd = cargo_wasm_command("check", path, profile, extra_options, codegen, bin, jobs)?;
+    child::run(cmd, "cargo check").context("Checking your crate for WebAssembly failed")?;
+    Ok(())
+}
+
+/// The `cargo build` command that `cargo_build_wasm` runs, compiled and linked
+/// as `codegen` says, building the bin target `bin` if one is given and the
+/// lib otherwise, with at most `jobs` parallel jobs if given.
+pub fn cargo_build_wasm_command(
+    path: &Path,
+    profile: BuildProfile,
+    extra_options: &[String],
+    codegen: &Codegen,
+    bin: Option<&str>,
+    jobs: Option<u32>,
+) -> Result<Command> {
+    cargo_wasm_command("build", path, profile, extra_options, codegen, bin, jobs)
+}
+
+/// `cargo build` or `cargo check`, as given by `subcommand`, for the wasm
+/// target.
+fn cargo_wasm_command(
+    subcommand: &str,
+    path: &Path,
+    profile: BuildProfile,
+    extra_options: &[String],
+    codegen: &Codegen,
+    bin: Option<&str>,
+    jobs: Option<u32>,
+) -> Result<Command> {
     let mut cmd = Command::new("cargo");
-    cmd.current_dir(path).arg("build").arg("--lib");
+    cmd.current_dir(path).arg(subcommand);
+    match bin {
+        Some(bin) => cmd.arg("--bin").arg(bin),
+        None => cmd.arg("--lib"),
+    };
+    if let Some(jobs) = jobs {
+        cmd.arg("--jobs").arg(jobs.to_string());
+    }
 
     if PBAR.quiet() {
         cmd.arg("--quiet");
@@ -111,6 +200,14 @@ pub fn cargo_build_wasm(
 
     cmd.arg("--target").arg("wasm32-unknown-unknown");
 
+    if codegen.threads {
+        cmd.args(threads::cargo_args());
+    }
+    let rustflags = codegen.rustflags();
+    if !rustflags.is_empty() {
+        add_rustflags(&mut cmd, &rustflags);
+    }
+
     // The `cargo` command is executed inside the directory at `path`, so relative paths set via extra options won't work.
     // To remedy the situation, all detected paths are converted to absolute paths.
     let mut handle_path = false;
@@ -131,9 +228,126 @@ pub fn cargo_build_wasm(
         })
         .collect::<Result<Vec<_>>>()?;
     cmd.args(extra_options_with_absolute_paths);
+    Ok(cmd)
+}
 
-    child::run(cmd, "cargo build").context("Compiling your crate to WebAssembly failed")?;
-    Ok(())
+/// Make the `cargo` command `cmd` compile with `flags` after the rustflags
+/// that are already set.
+///
+/// Without `RUSTFLAGS` or `CARGO_ENCODED_RUSTFLAGS` in the environment, they
+/// are passed with `--config` as `target.wasm32-unknown-unknown.rustflags`,
+/// which cargo adds to the ones of that target in the `.cargo/config.toml`
+/// files, since setting either variable would make cargo ignore those.
+/// Otherwise they are added to the variable: flags with spaces in them, like
+/// some linker arguments, can only be passed in `CARGO_ENCODED_RUSTFLAGS`,
+/// which cargo prefers over `RUSTFLAGS`.
+fn add_rustflags(cmd: &mut Command, flags: &[String]) {
+    let encoded = env::var("CARGO_ENCODED_RUSTFLAGS").ok();
+    let plain = env::var("RUSTFLAGS").ok();
+    if encoded.is_none() && plain.is_none() {
+        let flags: Vec<String> = flags.iter().map(|flag| format!("{:?}", flag)).collect();
+        cmd.arg("--config").arg(format!(
+            "target.wasm32-unknown-unknown.rustflags=[{}]",
+            flags.join(", ")
+        ));
+        return;
+    }
+    let mut all: Vec<String> = match &encoded {
+        Some(encoded) => encoded
+            .split('\x1f')
+            .filter(|flag| !flag.is_empty())
+            .map(|flag| flag.to_string())
+            .collect(),
+        None => plain
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|flag| flag.to_string())
+            .collect(),
+    };
+    all.extend(flags.iter().cloned());
+    if encoded.is_some() || flags.iter().any(|flag| flag.contains(char::is_whitespace)) {
+        cmd.env("CARGO_ENCODED_RUSTFLAGS", all.join("\x1f"));
+    } else {
+        cmd.env("RUSTFLAGS", all.join(" "));
+    }
+}
+
+/// Get the extra `cargo build` arguments needed to enable the features that
+/// the lib target lists in `required-features` but that `extra_options` does
+/// not already turn on.
+///
+/// Cargo itself ignores `required-features` on a lib target, so wasm-pack
+/// honors it instead: the features are enabled automatically unless
+/// `--no-default-features` was passed, in which case the user is asked to
+/// pick the features explicitly.
+pub fn required_features_args(data: &CrateData, extra_options: &[String]) -> Result<Vec<String>> {
+    let required = data.lib_required_features();
+    if required.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut requested = Vec::new();
+    let mut all_features = false;
+    let mut default_features = true;
+    let mut options = extra_options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--all-features" => all_features = true,
+            "--no-default-features" => default_features = false,
+            "--features" | "-F" => requested.extend(options.next().cloned()),
+            _ => {
+                if let Some(value) = option.strip_prefix("--features=") {
+                    requested.push(value.to_string());
+                }
+            }
+        }
+    }
+    if all_features {
+        return Ok(vec![]);
+    }
+
+    let mut pending: Vec<String> = requested
+        .iter()
+        .flat_map(|list| list.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|feature| !feature.is_empty())
+        .map(|feature| feature.to_string())
+        .collect();
+    if default_features {
+        pending.push("default".to_string());
+    }
+
+    // Follow each enabled feature to the features it turns on in turn.
+    let features = data.crate_features();
+    let mut enabled = BTreeSet::new();
+    while let Some(feature) = pending.pop() {
+        if enabled.insert(feature.clone()) {
+            pending.extend(features.get(&feature).into_iter().flatten().cloned());
+        }
+    }
+
+    let missing: Vec<String> = required
+        .into_iter()
+        .filter(|feature| !enabled.contains(feature))
+        .collect();
+    if missing.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let list = missing.join(",");
+    if !default_features {
+        bail!(
+            "The lib target of `{}` requires the features `{}`, which are not enabled. Pass `--features {}` to enable them.",
+            data.crate_name(),
+            missing.join("`, `"),
+            list
+        );
+    }
+
+    PBAR.info(&format!(
+        "Enabling features required by the lib target: `{}`",
+        missing.join("`, `")
+    ));
+    Ok(vec!["--features".to_string(), list])
 }
 
 /// Runs `cargo build --tests` targeting `wasm32-unknown-unknown`.
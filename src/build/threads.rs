@@ -0,0 +1,70 @@
+//! Building wasm that runs on several threads, sharing its memory.
+
+use crate::command::build::Target;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// The target features that make `std` use atomics and the linker emit a
+/// shared memory.
+pub const RUSTFLAGS: &str = "-C target-feature=+atomics,+bulk-memory,+mutable-globals";
+
+/// The `cargo build` arguments rebuilding `std` with `RUSTFLAGS`, since the
+/// prebuilt one doesn't use atomics.
+pub fn cargo_args() -> Vec<String> {
+    vec!["-Z".to_string(), "build-std=panic_abort,std".to_string()]
+}
+
+/// Make sure that wasm-bindgen can generate the glue for threads for
+/// `target`.
+pub fn check_target(target: Target) -> Result<()> {
+    match target {
+        Target::Web | Target::NoModules => Ok(()),
+        _ => bail!(
+            "`--threads` is only supported for the `web` and `no-modules` targets, not `{}`",
+            target
+        ),
+    }
+}
+
+/// Make sure that the toolchain can rebuild `std` with threads: it must be a
+/// nightly one, with the `rust-src` component.
+pub fn check_toolchain() -> Result<()> {
+    let output = Command::new("rustc")
+        .arg("-vV")
+        .output()
+        .context("failed to run `rustc -vV`")?;
+    let version = String::from_utf8_lossy(&output.stdout);
+    let release = version
+        .lines()
+        .find_map(|line| line.strip_prefix("release: "))
+        .unwrap_or_default();
+    if !release.contains("nightly") && !release.contains("-dev") {
+        bail!(
+            "`--threads` needs a nightly toolchain to rebuild `std` with `-Z build-std`, but \
+             rustc is {}. Run `rustup toolchain install nightly --component rust-src` and build \
+             with `rustup run nightly wasm-pack build --threads`, or add a `rust-toolchain.toml` \
+             to the crate.",
+            if release.is_empty() {
+                "unknown"
+            } else {
+                release
+            }
+        )
+    }
+
+    let output = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .context("failed to run `rustc --print sysroot`")?;
+    let sysroot = String::from_utf8_lossy(&output.stdout);
+    let library = Path::new(sysroot.trim()).join("lib/rustlib/src/rust/library");
+    if !library.is_dir() {
+        bail!(
+            "`--threads` needs the source of `std` to rebuild it, but the `rust-src` component \
+             of the {} toolchain is missing. Run `rustup component add rust-src` to install it.",
+            release
+        )
+    }
+    Ok(())
+}
@@ -1,9 +1,18 @@
 //! Getting and configuring wasm-pack's binary cache.
 
-use anyhow::Result;
+use crate::test::webdriver;
+use anyhow::{Context, Result};
 use binary_install::Cache;
+use std::cmp::Ordering;
 use std::env;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// The file in a downloaded tool's cache entry that holds the URL it was
+/// downloaded from.
+pub const SOURCE_URL_FILE: &str = ".url";
 
 /// Get wasm-pack's binary cache.
 pub fn get_wasm_pack_cache() -> Result<Cache> {
@@ -13,3 +22,210 @@ pub fn get_wasm_pack_cache() -> Result<Cache> {
         Cache::new("wasm-pack")
     }
 }
+
+/// A tool in wasm-pack's binary cache.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Entry {
+    /// The name of the tool, such as `wasm-bindgen` or `geckodriver`.
+    pub tool: String,
+    /// The version of the tool, if it is known.
+    pub version: Option<String>,
+    /// Where the tool is in the cache.
+    pub path: PathBuf,
+    /// How many bytes its files take.
+    pub size: u64,
+    /// When its files were last read, or written if that is unknown.
+    pub last_used: Option<SystemTime>,
+}
+
+impl Entry {
+    fn new(tool: String, version: Option<String>, path: PathBuf) -> Entry {
+        let (size, last_used) = usage(&path);
+        Entry {
+            tool,
+            version,
+            path,
+            size,
+            last_used,
+        }
+    }
+}
+
+/// List the tools in `cache`, sorted by name and version. Each version of a
+/// tool is in an entry of its own.
+pub fn list(cache: &Cache) -> Result<Vec<Entry>> {
+    let root = cache.join(Path::new(""));
+    let dir = match fs::read_dir(&root) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", root.display())),
+    };
+    let mut entries = Vec::new();
+    for dir_entry in dir {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name().to_string_lossy().into_owned();
+        // Unfinished downloads and installs start with a `.`.
+        if name.starts_with('.')
+            || name == webdriver::KEPT_DRIVERS_DIR
+            || !dir_entry.file_type()?.is_dir()
+        {
+            continue;
+        }
+        let path = dir_entry.path();
+        let entry = match name.split_once("-cargo-install-") {
+            Some((tool, rest)) => Entry::new(
+                tool.to_string(),
+                Some(rest.split("-features-").next().unwrap().to_string()),
+                path,
+            ),
+            None => {
+                let tool = match name.rsplit_once('-') {
+                    Some((tool, _hash)) => tool.to_string(),
+                    None => name.clone(),
+                };
+                let version = fs::read_to_string(path.join(SOURCE_URL_FILE))
+                    .ok()
+                    .and_then(|url| version_in_url(&url));
+                Entry::new(tool, version, path)
+            }
+        };
+        entries.push(entry);
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// List what unfinished downloads and installs left in `cache`, such as
+/// after wasm-pack was interrupted. Each is named after its directory.
+pub fn leftovers(cache: &Cache) -> Result<Vec<Entry>> {
+    let root = cache.join(Path::new(""));
+    let dir = match fs::read_dir(&root) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", root.display())),
+    };
+    let mut entries = Vec::new();
+    for dir_entry in dir {
+        let dir_entry = dir_entry?;
+        let name = dir_entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            entries.push(Entry::new(name, None, dir_entry.path()));
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Pick the entries of `entries` that aren't among the `keep` newest versions
+/// of their tool. Versions are compared number by number, and entries whose
+/// version isn't known are older than the others and compared by when they
+/// were last used.
+pub fn beyond_latest(mut entries: Vec<Entry>, keep: usize) -> Vec<Entry> {
+    entries.sort_by(|a, b| a.tool.cmp(&b.tool).then_with(|| newer(b, a)));
+    let mut old = Vec::new();
+    let mut tool = None;
+    let mut versions: Vec<Option<String>> = Vec::new();
+    for entry in entries {
+        if tool.as_ref() != Some(&entry.tool) {
+            tool = Some(entry.tool.clone());
+            versions.clear();
+        }
+        // Entries of an unknown version count as a version each.
+        if entry.version.is_none() || !versions.contains(&entry.version) {
+            versions.push(entry.version.clone());
+        }
+        if versions.len() > keep {
+            old.push(entry);
+        }
+    }
+    old.sort();
+    old
+}
+
+/// How `a` compares to `b` in age, `Greater` if it is the newer one.
+fn newer(a: &Entry, b: &Entry) -> Ordering {
+    let numbers = |version: &str| -> Vec<u64> {
+        version
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|n| n.parse().ok())
+            .collect()
+    };
+    match (&a.version, &b.version) {
+        (Some(a), Some(b)) => numbers(a).cmp(&numbers(b)),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+    .then_with(|| a.last_used.cmp(&b.last_used))
+}
+
+/// Remove `entry` from the cache.
+pub fn remove(entry: &Entry) -> Result<()> {
+    let removed = if entry.path.is_dir() {
+        fs::remove_dir_all(&entry.path)
+    } else {
+        fs::remove_file(&entry.path)
+    };
+    removed.with_context(|| format!("failed to remove {}", entry.path.display()))
+}
+
+/// Format a size in bytes, e.g. `1.5 GiB` or `340 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 10.0 {
+        format!("{:.1} {}", size, UNITS[unit])
+    } else {
+        format!("{:.0} {}", size, UNITS[unit])
+    }
+}
+
+/// The total size of the files below `path`, and when they were last used.
+///
+/// Directories, and the file with the source URL, are read by listing the
+/// cache itself, so they don't tell when the tool was used.
+fn usage(path: &Path) -> (u64, Option<SystemTime>) {
+    let mut size = 0;
+    let mut last_used = None;
+    for entry in WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+    {
+        let metadata = match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => continue,
+        };
+        size += metadata.len();
+        if entry.file_name() != SOURCE_URL_FILE {
+            let used = metadata.accessed().or_else(|_| metadata.modified()).ok();
+            last_used = last_used.max(used);
+        }
+    }
+    (size, last_used)
+}
+
+/// Find the version in the download URL of a tool, such as `0.35.0` in
+/// `.../releases/download/v0.35.0/geckodriver-v0.35.0-linux64.tar.gz`.
+pub fn version_in_url(url: &str) -> Option<String> {
+    url.trim().split('/').find_map(|segment| {
+        let version = segment
+            .strip_prefix("version_")
+            .or_else(|| segment.strip_prefix('v'))
+            .unwrap_or(segment);
+        let is_version = version.starts_with(|c: char| c.is_ascii_digit())
+            && version.chars().all(|c| c.is_ascii_digit() || c == '.');
+        if is_version {
+            Some(version.to_string())
+        } else {
+            None
+        }
+    })
+}
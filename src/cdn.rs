@@ -0,0 +1,76 @@
+//! Leaving the `.wasm` out of the npm package, for teams that serve it from a
+//! CDN and only publish the JS glue.
+//!
+//! The glue generated for the `web` and `no-modules` targets fetches the
+//! `.wasm` from next to itself by default, so it is patched to fetch it from
+//! the given base URL instead.
+
+use crate::command::build::Target;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Make sure that the glue for `target` fetches the `.wasm` at runtime, which
+/// is what makes it possible to serve it from elsewhere.
+pub fn check_target(target: Target) -> Result<()> {
+    match target {
+        Target::Web | Target::NoModules => Ok(()),
+        _ => bail!(
+            "`--no-bundle-wasm` is only supported for the `web` and `no-modules` targets, not `{}`, \
+             whose JS loads the `.wasm` from next to itself",
+            target
+        ),
+    }
+}
+
+/// Patch the default `.wasm` URL in the glue named `name_prefix` in
+/// `out_dir` to point into `base_url`.
+pub fn set_wasm_base_url(
+    out_dir: &Path,
+    name_prefix: &str,
+    target: Target,
+    base_url: &str,
+) -> Result<()> {
+    check_target(target)?;
+    let path = out_dir.join(format!("{}.js", name_prefix));
+    let glue =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    let wasm = format!("{}_bg.wasm", name_prefix);
+    let url = format!("{}/{}", base_url.trim_end_matches('/'), wasm);
+    let (from, to) = match target {
+        Target::Web => (
+            format!("new URL('{}', import.meta.url)", wasm),
+            format!("new URL('{}', import.meta.url)", url),
+        ),
+        _ => (
+            "script_src.replace(/\\.js$/, '_bg.wasm')".to_string(),
+            format!("'{}'", url),
+        ),
+    };
+    if !glue.contains(&from) {
+        bail!(
+            "Could not find where {} loads `{}` from, so it can't be loaded from {}",
+            path.display(),
+            wasm,
+            base_url
+        )
+    }
+    fs::write(&path, glue.replace(&from, &to))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Remove the `.wasm` named `name_prefix` from the `files` of the
+/// `package.json` in `out_dir`.
+pub fn remove_from_package_json(out_dir: &Path, name_prefix: &str) -> Result<()> {
+    let path = out_dir.join("package.json");
+    let mut package: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?,
+    )?;
+    let wasm = format!("{}_bg.wasm", name_prefix);
+    if let Some(files) = package["files"].as_array_mut() {
+        files.retain(|file| file.as_str() != Some(&wasm));
+    }
+    fs::write(&path, serde_json::to_string_pretty(&package)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
@@ -3,32 +3,71 @@
 //! This module helps us ensure that all child processes that we spawn get
 //! properly logged and their output is logged as well.
 
-use crate::install::Tool;
-use anyhow::{bail, Result};
-use log::info;
+use crate::PBAR;
+use anyhow::{anyhow, bail, Result};
+use log::{debug, info};
+use std::fmt;
+use std::io::{Read, Write};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Instant;
 
-/// Return a new Command object
-pub fn new_command(program: &str) -> Command {
-    // On Windows, initializes launching <program> as `cmd /c <program>`.
-    // Initializing only with `Command::new("npm")` will launch
-    //   `npm` with quotes, `"npm"`, causing a run-time error on Windows.
-    // See rustc: #42436, #42791, #44542
-
-    if cfg!(windows) {
-        let mut cmd = Command::new("cmd");
-        cmd.arg("/c").arg(program);
-        cmd
-    } else {
-        Command::new(program)
+static ECHO_COMMANDS: AtomicBool = AtomicBool::new(false);
+static STDOUT_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Print the command line of every child process before running it, instead
+/// of only logging it.
+pub fn set_echo_commands(echo: bool) {
+    ECHO_COMMANDS.store(echo, Ordering::SeqCst);
+}
+
+/// Send what child processes print to stdout to stderr instead, when stdout
+/// is reserved for wasm-pack's own output.
+pub fn set_stdout_to_stderr(redirect: bool) {
+    STDOUT_TO_STDERR.store(redirect, Ordering::SeqCst);
+}
+
+/// Log that `command` is about to run, returning when it started.
+fn log_command(command: &Command) -> Instant {
+    info!("Running {:?}", command);
+    if ECHO_COMMANDS.load(Ordering::SeqCst) {
+        PBAR.info(&format!("Running `{}`", command_line(command)));
     }
+    Instant::now()
+}
+
+/// Log how long `command_name`, which started at `started`, took.
+fn log_finished(command_name: &dyn fmt::Display, started: Instant) {
+    debug!("`{}` finished in {:.2?}", command_name, started.elapsed());
+}
+
+/// The command as it would be typed into a shell.
+fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("'{}'", arg)
+            } else {
+                arg.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 /// Run the given command and return on success.
 pub fn run(mut command: Command, command_name: &str) -> Result<()> {
-    info!("Running {:?}", command);
+    let started = log_command(&command);
+
+    if STDOUT_TO_STDERR.load(Ordering::SeqCst) {
+        command.stdout(std::io::stderr());
+    }
 
     let status = command.status()?;
+    log_finished(&command_name, started);
 
     if status.success() {
         Ok(())
@@ -42,14 +81,148 @@ pub fn run(mut command: Command, command_name: &str) -> Result<()> {
     }
 }
 
+/// What a command run with `run_keeping_output` wrote.
+#[derive(Debug, Default)]
+pub struct Output {
+    /// What it wrote to stdout, if that was kept.
+    pub stdout: String,
+    /// What it wrote to stderr.
+    pub stderr: String,
+}
+
+/// Run the given command like `run`, and also return what it wrote to stderr,
+/// and to stdout if `keep_stdout` is set. Both are still passed through as
+/// they are written.
+pub fn run_keeping_output(
+    mut command: Command,
+    command_name: &str,
+    keep_stdout: bool,
+) -> (Result<()>, Output) {
+    let started = log_command(&command);
+
+    if keep_stdout {
+        command.stdout(Stdio::piped());
+    }
+    let mut child = match command.stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(e) => return (Err(e.into()), Output::default()),
+    };
+    let stdout = child
+        .stdout
+        .take()
+        .map(|stdout| tee(stdout, std::io::stdout));
+    let stderr = tee(child.stderr.take().unwrap(), std::io::stderr);
+    let status = child.wait();
+    log_finished(&command_name, started);
+    let kept = |reader: thread::JoinHandle<Vec<u8>>| {
+        String::from_utf8_lossy(&reader.join().unwrap()).into_owned()
+    };
+    let output = Output {
+        stdout: stdout.map(kept).unwrap_or_default(),
+        stderr: kept(stderr),
+    };
+
+    let result = match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(anyhow!(
+            "failed to execute `{}`: exited with {}\n  full command: {:?}",
+            command_name,
+            status,
+            command,
+        )),
+        Err(e) => Err(e.into()),
+    };
+    (result, output)
+}
+
+/// Copy everything from `reader` to `writer` on another thread, returning
+/// what was copied when it is joined.
+fn tee<R, W>(mut reader: R, writer: fn() -> W) -> thread::JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+    W: Write + 'static,
+{
+    thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    // Flush every chunk, so that output that doesn't end
+                    // in a newline, like a prompt or a progress line, still
+                    // shows up as it's written.
+                    let mut writer = writer();
+                    let _ = writer.write_all(&buf[..n]).and_then(|()| writer.flush());
+                    kept.extend_from_slice(&buf[..n]);
+                }
+            }
+        }
+        kept
+    })
+}
+
+/// Run the given command with its output captured instead of streamed, so it
+/// doesn't interfere with a spinner. The output is only shown if it fails.
+pub fn run_captured(mut command: Command, command_name: &str) -> Result<()> {
+    let started = log_command(&command);
+
+    let output = command.stdin(Stdio::null()).output()?;
+    log_finished(&command_name, started);
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        bail!(
+            "failed to execute `{}`: exited with {}\n  full command: {:?}\n{}{}",
+            command_name,
+            output.status,
+            command,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr),
+        )
+    }
+}
+
+/// Run the given command with `input` on its stdin, and return its stdout.
+pub fn run_filter(mut command: Command, command_name: &str, input: Vec<u8>) -> Result<Vec<u8>> {
+    let started = log_command(&command);
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    // Write from another thread, so that a command that starts writing before
+    // it has read all of its input can't deadlock us.
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    let written = writer.join().unwrap();
+    log_finished(&command_name, started);
+
+    if output.status.success() {
+        written?;
+        Ok(output.stdout)
+    } else {
+        bail!(
+            "failed to execute `{}`: exited with {}\n  full command: {:?}",
+            command_name,
+            output.status,
+            command,
+        )
+    }
+}
+
 /// Run the given command and return its stdout.
-pub fn run_capture_stdout(mut command: Command, command_name: &Tool) -> Result<String> {
-    info!("Running {:?}", command);
+pub fn run_capture_stdout(mut command: Command, command_name: &dyn fmt::Display) -> Result<String> {
+    let started = log_command(&command);
 
     let output = command
         .stderr(Stdio::inherit())
         .stdin(Stdio::inherit())
         .output()?;
+    log_finished(command_name, started);
 
     if output.status.success() {
         Ok(String::from_utf8_lossy(&output.stdout).into_owned())
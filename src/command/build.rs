@@ -3,21 +3,35 @@
 use crate::bindgen;
 use crate::build;
 use crate::cache;
-use crate::command::utils::{create_pkg_dir, get_crate_path};
+use crate::cdn;
+use crate::child;
+use crate::command::utils::{create_pkg_dir, get_crate_path, report_members};
+use crate::demo;
 use crate::emoji;
+use crate::fingerprint::{self, Fingerprint};
+use crate::init_name;
 use crate::install::{self, InstallMode, Tool};
+use crate::intermediates;
 use crate::license;
-use crate::lockfile::Lockfile;
+use crate::lockfile::{self, Lockfile};
 use crate::manifest;
+use crate::pinned;
 use crate::readme;
+use crate::rollback;
+use crate::tarball;
+use crate::timings::{self, Summary, Timings, WorkspaceSummary};
+use crate::transform;
+use crate::wasm_features;
 use crate::wasm_opt;
 use crate::PBAR;
-use anyhow::{anyhow, bail, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use binary_install::Cache;
 use clap::Args;
 use log::info;
 use path_clean::PathClean;
 use std::fmt;
+use std::fs;
+use std::io;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Instant;
@@ -37,10 +51,42 @@ pub struct Build {
     pub profile: BuildProfile,
     pub mode: InstallMode,
     pub out_dir: PathBuf,
+    pub stdout_tar: bool,
     pub out_name: Option<String>,
     pub bindgen: Option<install::Status>,
     pub cache: Cache,
     pub extra_options: Vec<String>,
+    pub force: bool,
+    pub fingerprint: Option<Fingerprint>,
+    pub up_to_date: bool,
+    pub wasm_opt: Option<PathBuf>,
+    pub demo_html: bool,
+    pub demo_html_in_package: bool,
+    pub demo_files: Vec<String>,
+    pub no_bundle_wasm: bool,
+    pub wasm_base_url: Option<String>,
+    pub transform_js: Option<String>,
+    pub init_name: Option<String>,
+    pub threads: bool,
+    pub linker: Option<String>,
+    pub link_args: Vec<String>,
+    pub keep_tmp: bool,
+    pub kept: Vec<(&'static str, PathBuf)>,
+    pub no_rollback: bool,
+    pub summary_json: Option<PathBuf>,
+    pub timings: Timings,
+    pub summary: Option<Summary>,
+    pub workspace_member: bool,
+    pub tool_sources: Vec<install::ToolSource>,
+    pub readme_from_docs: bool,
+    pub target_features: bool,
+    pub wasm_features: Vec<&'static str>,
+    pub bindgen_features: Vec<String>,
+    pub bindgen_path: Option<PathBuf>,
+    pub emit_manifest_only: bool,
+    pub check: bool,
+    pub jobs: Option<u32>,
+    pub build_args: Vec<String>,
 }
 
 /// What sort of output we're going to be generating and flags we're invoking
@@ -71,6 +117,48 @@ impl Default for Target {
     }
 }
 
+impl Target {
+    /// Every target, in the order `--list-targets` prints them.
+    pub const ALL: [Target; 5] = [
+        Target::Bundler,
+        Target::Web,
+        Target::Nodejs,
+        Target::NoModules,
+        Target::Deno,
+    ];
+
+    /// What the JS that `wasm-bindgen --target` generates for this target is,
+    /// and where it runs.
+    pub fn description(self) -> &'static str {
+        match self {
+            Target::Bundler => {
+                "An ES module that imports the .wasm, for bundlers like webpack, Rollup and \
+                 Vite. The default."
+            }
+            Target::Web => {
+                "An ES module for browsers without a bundler, whose default export loads \
+                 the .wasm."
+            }
+            Target::Nodejs => "A CommonJS module for Node.js, loaded with `require`.",
+            Target::NoModules => {
+                "A classic script for browsers, which sets a global `wasm_bindgen` that \
+                 loads the .wasm."
+            }
+            Target::Deno => {
+                "An ES module for Deno, with a deno.json instead of a package.json. Needs \
+                 wasm-bindgen 0.2.65 or later."
+            }
+        }
+    }
+}
+
+/// Print each target `--target` takes, with what it generates.
+pub fn list_targets() {
+    for target in Target::ALL {
+        println!("{:<12} {}", target.to_string(), target.description());
+    }
+}
+
 impl fmt::Display for Target {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let s = match self {
@@ -98,6 +186,48 @@ impl FromStr for Target {
     }
 }
 
+/// The module system the generated `package.json` declares in its `type`
+/// field, which decides how Node.js loads the glue code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModuleType {
+    /// `"type": "module"`, for ES modules.
+    Module,
+    /// `"type": "commonjs"`, for modules loaded with `require`.
+    CommonJs,
+}
+
+impl ModuleType {
+    /// The module type the glue code generated for `target` needs, if it has
+    /// to be declared at all.
+    pub fn for_target(target: Target) -> Option<ModuleType> {
+        match target {
+            Target::Bundler | Target::Web => Some(ModuleType::Module),
+            Target::Nodejs | Target::NoModules | Target::Deno => None,
+        }
+    }
+}
+
+impl fmt::Display for ModuleType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ModuleType::Module => "module",
+            ModuleType::CommonJs => "commonjs",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for ModuleType {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "module" => Ok(ModuleType::Module),
+            "commonjs" => Ok(ModuleType::CommonJs),
+            _ => bail!("Unknown module type: {}", s),
+        }
+    }
+}
+
 /// The build profile controls whether optimizations, debug info, and assertions
 /// are enabled or disabled.
 #[derive(Clone, Debug)]
@@ -113,7 +243,7 @@ pub enum BuildProfile {
 }
 
 /// Everything required to configure and run the `wasm-pack build` command.
-#[derive(Debug, Args)]
+#[derive(Clone, Debug, Args)]
 #[command(allow_hyphen_values = true, trailing_var_arg = true)]
 pub struct BuildOptions {
     /// The path to the Rust crate. If not set, searches up the path from the current directory.
@@ -145,6 +275,11 @@ pub struct BuildOptions {
     /// Sets the target environment. [possible values: bundler, nodejs, web, no-modules, deno]
     pub target: Target,
 
+    #[clap(long = "list-targets")]
+    /// Print the targets `--target` takes, with what each one generates, and
+    /// exit without building.
+    pub list_targets: bool,
+
     #[clap(long = "debug")]
     /// Deprecated. Renamed to `--dev`.
     pub debug: bool,
@@ -166,22 +301,162 @@ pub struct BuildOptions {
     /// User-defined profile with --profile flag
     pub profile: Option<String>,
 
+    #[clap(long = "workspace", alias = "all")]
+    /// Build every `cdylib` member of the workspace, each into the output
+    /// directory below its own crate.
+    pub workspace: bool,
+
+    #[clap(long = "exclude", value_name = "MEMBER", requires = "workspace")]
+    /// Leave the workspace member named `MEMBER` out of `--workspace`. Can be
+    /// given several times.
+    pub exclude: Vec<String>,
+
     #[clap(long = "out-dir", short = 'd', default_value = "pkg")]
-    /// Sets the output directory with a relative path.
+    /// Sets the output directory with a relative path, or `-` to write the
+    /// package to stdout as a tarball instead.
     pub out_dir: String,
 
-    #[clap(long = "out-name")]
-    /// Sets the output file names. Defaults to package name.
+    #[clap(long = "out-name", value_parser = parse_out_name)]
+    /// Sets the output file names, without an extension: `foo` names
+    /// `foo.js`, `foo.d.ts` and `foo_bg.wasm`. Defaults to package name.
     pub out_name: Option<String>,
 
+    #[clap(long = "module-type")]
+    /// Sets the `type` field of the generated package.json, overriding the one
+    /// implied by the target. [possible values: module, commonjs]
+    pub module_type: Option<ModuleType>,
+
+    #[clap(long = "no-license")]
+    /// Leave the license out of the package: don't copy the LICENSE files of
+    /// the crate, or set `license` in the package.json.
+    pub no_license: bool,
+
+    #[clap(long = "crate-name")]
+    /// The name of the crate's lib target, which the compiled `.wasm` is named
+    /// after. Defaults to the `[lib] name` in Cargo.toml.
+    pub crate_name: Option<String>,
+
+    #[clap(long = "lib", conflicts_with = "bin")]
+    /// Build the `cdylib` lib target, in a crate that also has bin targets.
+    pub lib: bool,
+
+    #[clap(long = "bin", value_name = "NAME")]
+    /// Build the bin target named `NAME` instead of the lib.
+    pub bin: Option<String>,
+
     #[clap(long = "no-pack", alias = "no-package")]
     /// Option to not generate a package.json
     pub no_pack: bool,
 
+    #[clap(long = "emit-manifest-only", conflicts_with = "no_pack")]
+    /// Only write the package.json, README and license into the existing
+    /// output directory again, without running cargo or wasm-bindgen.
+    pub emit_manifest_only: bool,
+
+    #[clap(long = "check", conflicts_with = "emit_manifest_only")]
+    /// Only run `cargo check` for the wasm target, to find errors quickly,
+    /// without running wasm-bindgen or writing a package.
+    pub check: bool,
+
     #[clap(long = "no-opt", alias = "no-optimization")]
     /// Option to skip optimization with wasm-opt
     pub no_opt: bool,
 
+    #[clap(long = "force")]
+    /// Always run wasm-bindgen and wasm-opt, even if their inputs haven't
+    /// changed since the last build, and overwrite a modified demo page.
+    pub force: bool,
+
+    #[clap(long = "demo-html", alias = "emit-index-html")]
+    /// Write an index.html demo page for the `web` and `no-modules` targets.
+    pub demo_html: bool,
+
+    #[clap(long = "demo-html-in-package", requires = "demo_html")]
+    /// List the demo page in the `files` of the generated package.json.
+    pub demo_html_in_package: bool,
+
+    #[clap(long = "no-bundle-wasm", requires = "wasm_base_url")]
+    /// Leave the `.wasm` out of the `files` of the generated package.json, to
+    /// serve it from `--wasm-base-url` instead. Only for the `web` and
+    /// `no-modules` targets.
+    pub no_bundle_wasm: bool,
+
+    #[clap(long = "wasm-base-url", requires = "no_bundle_wasm")]
+    /// The URL the JS glue fetches the `.wasm` from with `--no-bundle-wasm`,
+    /// e.g. `https://cdn.example.com/my-crate/1.0.0/`.
+    pub wasm_base_url: Option<String>,
+
+    #[clap(long = "transform-js")]
+    /// A shell command that each generated `.js` file is piped through after
+    /// `wasm-bindgen` runs, e.g. a codemod. The file is replaced with what the
+    /// command writes to stdout.
+    pub transform_js: Option<String>,
+
+    #[clap(long = "init-name", value_name = "NAME")]
+    /// Also export the init function of the `web` target's JS glue, its
+    /// default export, as `NAME`, in the `.d.ts` too.
+    pub init_name: Option<String>,
+
+    #[clap(long = "threads")]
+    /// Build wasm that can run on several threads: compile with atomics and
+    /// bulk memory, and rebuild `std` with them, which needs a nightly
+    /// toolchain with `rust-src`. Only for the `web` and `no-modules` targets.
+    pub threads: bool,
+
+    #[clap(long = "linker", value_name = "PATH")]
+    /// Link the wasm with the linker at `PATH` instead of `rust-lld`, passed
+    /// to rustc as `-C linker` after the `RUSTFLAGS` that are set.
+    pub linker: Option<String>,
+
+    #[clap(long = "link-arg", value_name = "ARG", allow_hyphen_values = true)]
+    /// Pass `ARG` to the linker, as `-C link-arg` after the `RUSTFLAGS` that
+    /// are set. Can be given several times.
+    pub link_args: Vec<String>,
+
+    #[clap(long = "keep-tmp")]
+    /// Keep copies of the intermediate artifacts, the wasm built by cargo and
+    /// the output of `wasm-bindgen` before `wasm-opt`, as well as the
+    /// directory of a failed `cargo install`, and print where they are.
+    pub keep_tmp: bool,
+
+    #[clap(long = "no-rollback")]
+    /// When the build fails, keep what it wrote to the output directory,
+    /// marked with an `.incomplete` file, instead of putting the output
+    /// directory back as it was before the build.
+    pub no_rollback: bool,
+
+    #[clap(long = "summary-json", value_name = "FILE")]
+    /// Write how long each phase of the build took, and the peak memory of
+    /// the tools it ran, to `FILE` as JSON, whether the build succeeds or
+    /// fails.
+    pub summary_json: Option<PathBuf>,
+
+    #[clap(long = "readme-from-docs")]
+    /// Without a README in the crate, write the crate-level `//!` docs of the
+    /// lib target to the README.md of the package instead.
+    pub readme_from_docs: bool,
+
+    #[clap(long = "target-features")]
+    /// Write the WebAssembly features the built `.wasm` needs, such as
+    /// `simd128`, to a `features.json` next to it.
+    pub target_features: bool,
+
+    #[clap(long = "bindgen-features", value_delimiter = ',')]
+    /// Features to enable when wasm-bindgen-cli has to be built with
+    /// `cargo install`, e.g. `vendored-openssl`.
+    pub bindgen_features: Vec<String>,
+
+    #[clap(long = "bindgen-path", value_name = "PATH")]
+    /// Use the `wasm-bindgen` binary at `PATH` instead of installing one, or
+    /// build it from the wasm-bindgen checkout at `PATH` first.
+    pub bindgen_path: Option<PathBuf>,
+
+    #[clap(long = "jobs", short = 'j', value_name = "N", value_parser = parse_jobs)]
+    /// Run at most `N` parallel jobs in `cargo build`, and in `cargo install`
+    /// when wasm-bindgen-cli has to be built from source, e.g. to fit the
+    /// build into the memory of a small CI runner.
+    pub jobs: Option<u32>,
+
     /// List of extra options to pass to `cargo build`
     pub extra_options: Vec<String>,
 }
@@ -196,22 +471,219 @@ impl Default for BuildOptions {
             weak_refs: false,
             reference_types: false,
             target: Target::default(),
+            list_targets: false,
             debug: false,
             dev: false,
             no_pack: false,
+            emit_manifest_only: false,
+            check: false,
             no_opt: false,
+            force: false,
+            demo_html: false,
+            demo_html_in_package: false,
+            no_bundle_wasm: false,
+            wasm_base_url: None,
+            transform_js: None,
+            init_name: None,
+            threads: false,
+            linker: None,
+            link_args: Vec::new(),
+            keep_tmp: false,
+            no_rollback: false,
+            summary_json: None,
+            readme_from_docs: false,
+            target_features: false,
+            bindgen_features: Vec::new(),
+            bindgen_path: None,
+            jobs: None,
             release: false,
             profiling: false,
             profile: None,
+            workspace: false,
+            exclude: Vec::new(),
             out_dir: String::new(),
             out_name: None,
+            crate_name: None,
+            lib: false,
+            bin: None,
+            module_type: None,
+            no_license: false,
             extra_options: Vec::new(),
         }
     }
 }
 
+impl BuildOptions {
+    /// The arguments of `wasm-pack build` that give these options, leaving
+    /// out the path, the output directory and the options that don't change
+    /// the package that is built, such as `--jobs`.
+    pub fn args(&self) -> Vec<String> {
+        let flags = [
+            (self.disable_dts, "--no-typescript"),
+            (self.weak_refs, "--weak-refs"),
+            (self.reference_types, "--reference-types"),
+            (self.dev || self.debug, "--dev"),
+            (self.release, "--release"),
+            (self.profiling, "--profiling"),
+            (self.no_license, "--no-license"),
+            (self.lib, "--lib"),
+            (self.no_pack, "--no-pack"),
+            (self.no_opt, "--no-opt"),
+            (self.demo_html, "--demo-html"),
+            (self.demo_html_in_package, "--demo-html-in-package"),
+            (self.no_bundle_wasm, "--no-bundle-wasm"),
+            (self.threads, "--threads"),
+            (self.readme_from_docs, "--readme-from-docs"),
+            (self.target_features, "--target-features"),
+        ];
+        let values = [
+            ("--scope", self.scope.clone()),
+            ("--profile", self.profile.clone()),
+            ("--out-name", self.out_name.clone()),
+            ("--module-type", self.module_type.map(|ty| ty.to_string())),
+            ("--crate-name", self.crate_name.clone()),
+            ("--bin", self.bin.clone()),
+            ("--wasm-base-url", self.wasm_base_url.clone()),
+            ("--transform-js", self.transform_js.clone()),
+            ("--init-name", self.init_name.clone()),
+            ("--linker", self.linker.clone()),
+            (
+                "--bindgen-path",
+                self.bindgen_path
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+            ),
+        ];
+
+        let mut args = vec!["--target".to_string(), self.target.to_string()];
+        for (set, flag) in flags {
+            if set {
+                args.push(flag.to_string());
+            }
+        }
+        for (flag, value) in values {
+            if let Some(value) = value {
+                args.extend([flag.to_string(), value]);
+            }
+        }
+        for arg in &self.link_args {
+            args.extend(["--link-arg".to_string(), arg.clone()]);
+        }
+        if !self.bindgen_features.is_empty() {
+            args.extend([
+                "--bindgen-features".to_string(),
+                self.bindgen_features.join(","),
+            ]);
+        }
+        args.extend(self.extra_options.iter().cloned());
+        args
+    }
+}
+
 type BuildStep = fn(&mut Build) -> Result<()>;
 
+/// Build every `cdylib` member of the workspace that isn't excluded, one
+/// after the other, each into the output directory below its own crate.
+///
+/// A member that fails to build doesn't stop the others. Each member's
+/// outcome is reported at the end.
+pub fn build_workspace(mut build_opts: BuildOptions) -> Result<()> {
+    if let Some(path) = &build_opts.path {
+        if path.to_string_lossy().starts_with("--") {
+            let path = build_opts.path.take().unwrap();
+            build_opts
+                .extra_options
+                .insert(0, path.to_string_lossy().into_owned());
+        }
+    }
+    if build_opts.out_dir == tarball::STDOUT {
+        bail!("`--out-dir -` can't be used with `--workspace`, which builds several packages")
+    }
+    let root = get_crate_path(build_opts.path.take())?;
+    let members = manifest::workspace_members(&root)?;
+    for excluded in &build_opts.exclude {
+        if !members.iter().any(|member| &member.name == excluded) {
+            PBAR.warn(&format!(
+                "`--exclude {}` matches no member of the workspace at {}",
+                excluded,
+                root.display()
+            ));
+        }
+    }
+    let members: Vec<_> = members
+        .into_iter()
+        .filter(|member| member.is_cdylib && !build_opts.exclude.contains(&member.name))
+        .collect();
+    if members.is_empty() {
+        bail!(
+            "None of the members of the workspace at {} are `cdylib` crates that wasm-pack can \
+             build.",
+            root.display()
+        )
+    }
+
+    let mut outcomes = Vec::new();
+    let mut summaries = Vec::new();
+    for member in members {
+        PBAR.info(&format!("Building {}...", member.name));
+        // An absolute output directory would be shared by all the members.
+        let out_dir = if PathBuf::from(&build_opts.out_dir).is_absolute() {
+            PathBuf::from(&build_opts.out_dir)
+                .join(&member.name)
+                .display()
+                .to_string()
+        } else {
+            build_opts.out_dir.clone()
+        };
+        let member_opts = BuildOptions {
+            path: Some(member.path.clone()),
+            workspace: false,
+            exclude: Vec::new(),
+            out_dir: out_dir.clone(),
+            summary_json: None,
+            ..build_opts.clone()
+        };
+        let result = Build::try_from_opts(member_opts).and_then(|mut build| {
+            build.workspace_member = true;
+            let result = build.run();
+            summaries.extend(build.summary.take());
+            result
+        });
+        let outcome = match result {
+            Ok(()) if build_opts.check => Some("checked".to_string()),
+            Ok(()) => Some(format!(
+                "built in {}",
+                member.path.join(&out_dir).clean().display()
+            )),
+            Err(error) => {
+                PBAR.error(&format!("Failed to build {}: {:#}", member.name, error));
+                None
+            }
+        };
+        outcomes.push((member.name, outcome));
+    }
+
+    let failed = report_members(&outcomes);
+    let peak_child_memory_bytes = timings::peak_child_memory();
+    if let Some(bytes) = peak_child_memory_bytes {
+        PBAR.info(&format!(
+            "Peak memory of the tools run: {}",
+            timings::format_memory(bytes)
+        ));
+    }
+    if let Some(path) = &build_opts.summary_json {
+        let summary = WorkspaceSummary {
+            members: summaries,
+            peak_child_memory_bytes,
+        };
+        timings::write_summary(path, &summary)?;
+    }
+    if !failed.is_empty() {
+        bail!("Failed to build {}.", failed.join(", "))
+    }
+    Ok(())
+}
+
 impl Build {
     /// Construct a build command from the given options.
     pub fn try_from_opts(mut build_opts: BuildOptions) -> Result<Self> {
@@ -223,9 +695,20 @@ impl Build {
                     .insert(0, path.to_string_lossy().into_owned());
             }
         }
+        let build_args = build_opts.args();
         let crate_path = get_crate_path(build_opts.path)?;
-        let crate_data = manifest::CrateData::new(&crate_path, build_opts.out_name.clone())?;
-        let out_dir = crate_path.join(PathBuf::from(build_opts.out_dir)).clean();
+        lockfile::generate_if_missing(&crate_path, &build_opts.extra_options)?;
+        let mut crate_data = manifest::CrateData::new(&crate_path, build_opts.out_name.clone())?;
+        crate_data.set_crate_name(build_opts.crate_name);
+        crate_data.select_target(build_opts.lib, build_opts.bin)?;
+        crate_data.set_module_type(build_opts.module_type);
+        crate_data.set_no_license(build_opts.no_license);
+        let stdout_tar = build_opts.out_dir == tarball::STDOUT;
+        let out_dir = if stdout_tar {
+            tarball::staging_dir(crate_data.target_directory(), &crate_data.name_prefix())
+        } else {
+            crate_path.join(PathBuf::from(build_opts.out_dir)).clean()
+        };
 
         let dev = build_opts.dev || build_opts.debug;
         let profile = match (
@@ -243,6 +726,26 @@ impl Build {
             _ => bail!("Can only supply one of the --dev, --release, --profiling, or --profile 'name' flags"),
         };
 
+        if build_opts.demo_html {
+            demo::check_target(build_opts.target)?;
+        }
+
+        if build_opts.no_bundle_wasm {
+            cdn::check_target(build_opts.target)?;
+        }
+
+        if let Some(name) = &build_opts.init_name {
+            init_name::check(build_opts.target, name)?;
+        }
+
+        if build_opts.threads {
+            build::threads::check_target(build_opts.target)?;
+        }
+
+        if build_opts.check && stdout_tar {
+            bail!("`--check` doesn't write a package, so it can't be used with `--out-dir -`")
+        }
+
         Ok(Build {
             crate_path,
             crate_data,
@@ -256,10 +759,42 @@ impl Build {
             profile,
             mode: build_opts.mode,
             out_dir,
+            stdout_tar,
             out_name: build_opts.out_name,
             bindgen: None,
             cache: cache::get_wasm_pack_cache()?,
             extra_options: build_opts.extra_options,
+            force: build_opts.force,
+            fingerprint: None,
+            up_to_date: false,
+            wasm_opt: None,
+            demo_html: build_opts.demo_html,
+            demo_html_in_package: build_opts.demo_html_in_package,
+            demo_files: Vec::new(),
+            no_bundle_wasm: build_opts.no_bundle_wasm,
+            wasm_base_url: build_opts.wasm_base_url,
+            transform_js: build_opts.transform_js,
+            init_name: build_opts.init_name,
+            threads: build_opts.threads,
+            linker: build_opts.linker,
+            link_args: build_opts.link_args,
+            keep_tmp: build_opts.keep_tmp,
+            kept: Vec::new(),
+            no_rollback: build_opts.no_rollback,
+            summary_json: build_opts.summary_json,
+            timings: Timings::default(),
+            summary: None,
+            workspace_member: false,
+            readme_from_docs: build_opts.readme_from_docs,
+            target_features: build_opts.target_features,
+            wasm_features: Vec::new(),
+            tool_sources: Vec::new(),
+            bindgen_features: build_opts.bindgen_features,
+            bindgen_path: build_opts.bindgen_path,
+            emit_manifest_only: build_opts.emit_manifest_only,
+            check: build_opts.check,
+            jobs: build_opts.jobs,
+            build_args,
         })
     }
 
@@ -270,15 +805,122 @@ impl Build {
 
     /// Execute this `Build` command.
     pub fn run(&mut self) -> Result<()> {
-        let process_steps = Build::get_process_steps(self.mode, self.no_pack, self.no_opt);
+        let process_steps = Build::get_process_steps(
+            self.mode,
+            self.no_pack,
+            self.no_opt,
+            self.demo_html,
+            self.threads,
+            self.emit_manifest_only,
+            self.check,
+        );
 
         let started = Instant::now();
 
-        for (_, process_step) in process_steps {
-            process_step(self)?;
+        install::set_keep_tmp(self.keep_tmp);
+        // Nothing but the tarball may go to stdout.
+        child::set_stdout_to_stderr(self.stdout_tar);
+        // `--check` doesn't write to the output directory, and the one of
+        // `--out-dir -` is removed in any case.
+        let snapshot = if self.check || self.stdout_tar || self.no_rollback {
+            None
+        } else {
+            let backup = rollback::backup_dir(
+                self.crate_data.target_directory(),
+                &self.crate_data.name_prefix(),
+                &self.out_dir,
+            );
+            Some(rollback::Snapshot::take(&self.out_dir, &backup)?)
+        };
+        let mut result = process_steps
+            .into_iter()
+            .try_for_each(|(name, process_step)| {
+                let step_started = Instant::now();
+                let result = process_step(self);
+                self.timings
+                    .record(timings::phase(name), step_started.elapsed());
+                result
+            });
+        for (artifact, path) in &self.kept {
+            PBAR.info(&format!("Kept {} at {}", artifact, path.display()));
+        }
+        if self.stdout_tar {
+            let tar_started = Instant::now();
+            result = result.and_then(|()| {
+                tarball::write_package(&self.out_dir, io::stdout().lock())
+                    .context("Writing the package to stdout failed")
+                    .map(drop)
+            });
+            self.timings.record("packaging", tar_started.elapsed());
+            drop(fs::remove_dir_all(&self.out_dir));
         }
+        match (&result, snapshot) {
+            (Ok(()), snapshot) => {
+                if let Some(snapshot) = snapshot {
+                    snapshot.discard();
+                }
+                if !self.check && !self.stdout_tar {
+                    rollback::clear_incomplete(&self.out_dir)?;
+                    if self.out_dir.is_dir() {
+                        fingerprint::save_build_args(&self.out_dir, &self.build_args)?;
+                    }
+                }
+            }
+            (Err(_), Some(snapshot)) => match snapshot.restore() {
+                Ok(()) => PBAR.info(&format!(
+                    "The build failed, so {} was put back as it was before it. Use \
+                     `--no-rollback` to keep what the build wrote instead.",
+                    self.out_dir.display()
+                )),
+                Err(e) => PBAR.warn(&format!(
+                    "The build failed, and putting {} back as it was failed too: {:#}",
+                    self.out_dir.display(),
+                    e
+                )),
+            },
+            (Err(e), None) => {
+                if self.no_rollback && self.out_dir.is_dir() {
+                    rollback::mark_incomplete(&self.out_dir, e)?;
+                    fingerprint::save_build_args(&self.out_dir, &self.build_args)?;
+                    PBAR.warn(&format!(
+                        "The build failed, so {} is incomplete and marked with an {} file.",
+                        self.out_dir.display(),
+                        rollback::INCOMPLETE_MARKER
+                    ));
+                }
+            }
+        }
+        let name = &self.crate_data.metadata()[self.crate_data.package_id()].name;
+        let mut summary = self.timings.summary(
+            name,
+            result.is_ok(),
+            started.elapsed(),
+            self.tool_sources.clone(),
+            self.wasm_features.clone(),
+        );
+        // A workspace reports the peak memory once, for all its members.
+        if !self.workspace_member {
+            summary.peak_child_memory_bytes = timings::peak_child_memory();
+        }
+        let written = match &self.summary_json {
+            Some(path) => timings::write_summary(path, &summary),
+            None => Ok(()),
+        };
+        self.summary = Some(summary);
+        result?;
+        written?;
 
         let duration = crate::command::utils::elapsed(started.elapsed());
+        if self.check {
+            info!("Checked in {}.", &duration);
+            PBAR.info(&format!(
+                "{} Check passed in {}. This was a check-only run, nothing was built.",
+                emoji::SPARKLE,
+                &duration
+            ));
+            self.report_timings();
+            return Ok(());
+        }
         info!("Done in {}.", &duration);
         info!(
             "Your wasm pkg is ready to publish at {}.",
@@ -286,19 +928,54 @@ impl Build {
         );
 
         PBAR.info(&format!("{} Done in {}", emoji::SPARKLE, &duration));
+        self.report_timings();
 
-        PBAR.info(&format!(
-            "{} Your wasm pkg is ready to publish at {}.",
-            emoji::PACKAGE,
-            self.out_dir.display()
-        ));
+        if !self.wasm_features.is_empty() {
+            PBAR.info(&format!(
+                "The wasm needs these WebAssembly features: {}",
+                self.wasm_features.join(", ")
+            ));
+        }
+
+        if self.stdout_tar {
+            PBAR.info(&format!(
+                "{} Your wasm pkg was written to stdout as a tarball.",
+                emoji::PACKAGE
+            ));
+        } else {
+            PBAR.info(&format!(
+                "{} Your wasm pkg is ready to publish at {}.",
+                emoji::PACKAGE,
+                self.out_dir.display()
+            ));
+        }
         Ok(())
     }
 
+    /// Print the time spent in each phase, and the peak memory of the tools
+    /// that were run.
+    fn report_timings(&self) {
+        PBAR.info(&format!("Time spent: {}", self.timings.describe()));
+        if let Some(bytes) = self
+            .summary
+            .as_ref()
+            .and_then(|s| s.peak_child_memory_bytes)
+        {
+            PBAR.info(&format!(
+                "Peak memory of the tools run: {}",
+                timings::format_memory(bytes)
+            ));
+        }
+    }
+
     fn get_process_steps(
         mode: InstallMode,
         no_pack: bool,
         no_opt: bool,
+        demo_html: bool,
+        threads: bool,
+        emit_manifest_only: bool,
+        check: bool,
     ) -> Vec<(&'static str, BuildStep)> {
         macro_rules! steps {
             ($($name:ident),+) => {
@@ -310,6 +987,14 @@ impl Build {
                 };
             ($($name:ident,)*) => (steps![$($name),*])
         }
+        if emit_manifest_only {
+            return steps![
+                step_check_built_package,
+                step_create_json,
+                step_copy_readme,
+                step_copy_license,
+            ];
+        }
         let mut steps = Vec::new();
         match &mode {
             InstallMode::Force => {}
@@ -319,10 +1004,19 @@ impl Build {
                     step_check_crate_config,
                     step_check_for_wasm_target,
                 ]);
+                if threads {
+                    steps.extend(steps![step_check_threads_toolchain]);
+                }
             }
         }
 
+        if check {
+            steps.extend(steps![step_check_wasm]);
+            return steps;
+        }
+
         steps.extend(steps![
+            step_check_wasm_opt,
             step_build_wasm,
             step_create_dir,
             step_install_wasm_bindgen,
@@ -333,6 +1027,12 @@ impl Build {
             steps.extend(steps![step_run_wasm_opt]);
         }
 
+        steps.extend(steps![step_check_wasm_features, step_save_fingerprint]);
+
+        if demo_html {
+            steps.extend(steps![step_write_demo_html]);
+        }
+
         if !no_pack {
             steps.extend(steps![
                 step_create_json,
@@ -366,21 +1066,80 @@ impl Build {
         Ok(())
     }
 
+    fn step_check_threads_toolchain(&mut self) -> Result<()> {
+        info!("Checking that the toolchain can build with threads...");
+        build::threads::check_toolchain()?;
+        info!("The toolchain can build with threads.");
+        Ok(())
+    }
+
+    /// The extra options for `cargo`, with the features the lib requires.
+    fn cargo_options(&self) -> Result<Vec<String>> {
+        let mut extra_options = self.extra_options.clone();
+        if self.crate_data.bin_target().is_none() {
+            extra_options.extend(build::required_features_args(
+                &self.crate_data,
+                &self.extra_options,
+            )?);
+        }
+        Ok(extra_options)
+    }
+
+    fn codegen(&self) -> build::Codegen {
+        build::Codegen {
+            threads: self.threads,
+            linker: self.linker.clone(),
+            link_args: self.link_args.clone(),
+        }
+    }
+
     fn step_build_wasm(&mut self) -> Result<()> {
         info!("Building wasm...");
-        build::cargo_build_wasm(&self.crate_path, self.profile.clone(), &self.extra_options)?;
+        let extra_options = self.cargo_options()?;
+        build::cargo_build_wasm(
+            &self.crate_path,
+            self.profile.clone(),
+            &extra_options,
+            &self.codegen(),
+            self.crate_data.bin_target(),
+            self.jobs,
+        )?;
+
+        if self.keep_tmp {
+            let keep_dir = self.keep_dir();
+            intermediates::reset(&keep_dir)?;
+            let wasm_path =
+                bindgen::wasm_path(&self.crate_data, self.profile.clone(), &self.extra_options);
+            let kept = intermediates::keep_file(&wasm_path, &keep_dir)?;
+            self.kept.push(("the wasm built by cargo", kept));
+        }
 
         info!(
             "wasm built at {:#?}.",
             &self
-                .crate_path
-                .join("target")
+                .crate_data
+                .target_directory()
                 .join("wasm32-unknown-unknown")
                 .join("release")
         );
         Ok(())
     }
 
+    fn step_check_wasm(&mut self) -> Result<()> {
+        info!("Checking wasm...");
+        let extra_options = self.cargo_options()?;
+        build::cargo_check_wasm(
+            &self.crate_path,
+            self.profile.clone(),
+            &extra_options,
+            &self.codegen(),
+            self.crate_data.bin_target(),
+            self.jobs,
+        )?;
+        info!("wasm checked.");
+        Ok(())
+    }
+
     fn step_create_dir(&mut self) -> Result<()> {
         info!("Creating a pkg directory...");
         create_pkg_dir(&self.out_dir)?;
@@ -388,6 +1147,29 @@ impl Build {
         Ok(())
     }
 
+    fn step_check_built_package(&mut self) -> Result<()> {
+        info!("Checking for an existing build in {:#?}...", &self.out_dir);
+        let name = self.crate_data.name_prefix();
+        for file in [format!("{}_bg.wasm", name), format!("{}.js", name)] {
+            if !self.out_dir.join(&file).is_file() {
+                bail!(
+                    "`--emit-manifest-only` needs an existing build, but {} is missing; \
+                     run `wasm-pack build` first.",
+                    self.out_dir.join(&file).display()
+                )
+            }
+        }
+        // Start over from the `package.json` that `wasm-bindgen` generated,
+        // rather than merging into the one written by the last build.
+        let package_json = self.out_dir.join("package.json");
+        if package_json.exists() {
+            fs::remove_file(&package_json)
+                .with_context(|| format!("failed to remove {}", package_json.display()))?;
+        }
+        Fingerprint::restore(&self.out_dir)?;
+        Ok(())
+    }
+
     fn step_create_json(&mut self) -> Result<()> {
         self.crate_data.write_package_json(
             &self.out_dir,
@@ -395,6 +1177,18 @@ impl Build {
             self.disable_dts,
             self.target,
         )?;
+        if self.demo_html_in_package {
+            demo::add_to_package_json(&self.out_dir, &self.demo_files)?;
+        }
+        if self.target_features && self.out_dir.join(wasm_features::FEATURES_JSON).is_file() {
+            wasm_features::add_to_package_json(&self.out_dir)?;
+        }
+        if self.no_bundle_wasm {
+            cdn::remove_from_package_json(&self.out_dir, &self.crate_data.name_prefix())?;
+        }
+        if !matches!(self.target, Target::Deno) {
+            manifest::check_package_entries(&self.out_dir)?;
+        }
         info!(
             "Wrote a package.json at {:#?}.",
             &self.out_dir.join("package.json")
@@ -402,14 +1196,34 @@ impl Build {
         Ok(())
     }
 
+    fn step_write_demo_html(&mut self) -> Result<()> {
+        info!("Writing the demo page...");
+        self.demo_files = demo::write_demo_html(
+            &self.out_dir,
+            &self.crate_data.name_prefix(),
+            self.target,
+            self.force,
+        )?;
+        info!("Wrote the demo page at {:#?}.", &self.out_dir);
+        Ok(())
+    }
+
     fn step_copy_readme(&mut self) -> Result<()> {
         info!("Copying readme from crate...");
-        readme::copy_from_crate(&self.crate_data, &self.crate_path, &self.out_dir)?;
+        if self.readme_from_docs && !readme::crate_has_readme(&self.crate_data, &self.crate_path) {
+            readme::write_from_docs(&self.crate_data, &self.out_dir)?;
+        } else {
+            readme::copy_from_crate(&self.crate_data, &self.crate_path, &self.out_dir)?;
+        }
         info!("Copied readme from crate to {:#?}.", &self.out_dir);
         Ok(())
     }
 
     fn step_copy_license(&mut self) -> Result<()> {
+        if self.crate_data.no_license() {
+            info!("Leaving the license out of the package.");
+            return Ok(());
+        }
         info!("Copying license from crate...");
         license::copy_from_crate(&self.crate_data, &self.crate_path, &self.out_dir)?;
         info!("Copied license from crate to {:#?}.", &self.out_dir);
@@ -417,22 +1231,50 @@ impl Build {
     }
 
     fn step_install_wasm_bindgen(&mut self) -> Result<()> {
+        if let Some(path) = &self.bindgen_path {
+            info!("Using the local wasm-bindgen at {}...", path.display());
+            self.bindgen = Some(install::local_wasm_bindgen(path)?);
+            self.tool_sources.extend(install::take_reported_sources());
+            return Ok(());
+        }
         info!("Identifying wasm-bindgen dependency...");
         let lockfile = Lockfile::new(&self.crate_data)?;
-        let bindgen_version = lockfile.require_wasm_bindgen()?;
+        let bindgen_version =
+            pinned::wasm_bindgen_version(&self.crate_path, &self.crate_data, &lockfile)?;
         info!("Installing wasm-bindgen-cli...");
         let bindgen = install::download_prebuilt_or_cargo_install(
             Tool::WasmBindgen,
             &self.cache,
-            bindgen_version,
+            &bindgen_version,
             self.mode.install_permitted(),
+            &self.bindgen_features,
+            self.jobs,
+            Some(&self.crate_path),
         )?;
         self.bindgen = Some(bindgen);
+        self.tool_sources.extend(install::take_reported_sources());
         info!("Installing wasm-bindgen-cli was successful.");
         Ok(())
     }
 
     fn step_run_wasm_bindgen(&mut self) -> Result<()> {
+        let fingerprint = self.fingerprint()?;
+        let bg_wasm = format!("{}_bg.wasm", self.crate_data.name_prefix());
+        // The fingerprint only has the command line of a transform, not what
+        // the scripts it runs do, so a transformed build is always redone.
+        if !self.force
+            && !self.keep_tmp
+            && self.transform_js.is_none()
+            && fingerprint.is_up_to_date(&self.out_dir)
+            && self.out_dir.join(bg_wasm).is_file()
+        {
+            Fingerprint::restore(&self.out_dir)?;
+            PBAR.info("wasm bindings are up to date, skipping `wasm-bindgen` and `wasm-opt`.");
+            self.up_to_date = true;
+            return Ok(());
+        }
+        Fingerprint::clear(&self.out_dir);
+
         info!("Building the wasm bindings...");
         bindgen::wasm_bindgen_build(
             &self.crate_data,
@@ -446,11 +1288,128 @@ impl Build {
             self.profile.clone(),
             &self.extra_options,
         )?;
+        if self.keep_tmp {
+            let kept = intermediates::keep_bindgen_output(
+                &self.out_dir,
+                &self.crate_data.name_prefix(),
+                &self.keep_dir(),
+            )?;
+            self.kept.push(("the wasm-bindgen output", kept));
+        }
+        if let Some(base_url) = &self.wasm_base_url {
+            cdn::set_wasm_base_url(
+                &self.out_dir,
+                &self.crate_data.name_prefix(),
+                self.target,
+                base_url,
+            )?;
+        }
+        if let Some(name) = &self.init_name {
+            init_name::export_init_as(&self.out_dir, &self.crate_data.name_prefix(), name)?;
+        }
+        if let Some(script) = &self.transform_js {
+            transform::transform_js(&self.out_dir, &self.crate_data.name_prefix(), script)?;
+        }
         info!("wasm bindings were built at {:#?}.", &self.out_dir);
+        self.fingerprint = Some(fingerprint);
+        Ok(())
+    }
+
+    /// The directory that `--keep-tmp` keeps the intermediate artifacts in.
+    fn keep_dir(&self) -> PathBuf {
+        intermediates::keep_dir(
+            self.crate_data.target_directory(),
+            &self.crate_data.name_prefix(),
+        )
+    }
+
+    /// Fingerprint everything that goes into `wasm-bindgen` and `wasm-opt`.
+    fn fingerprint(&self) -> Result<Fingerprint> {
+        let bindgen_path =
+            install::get_tool_path(self.bindgen.as_ref().unwrap(), Tool::WasmBindgen)?
+                .binary(&Tool::WasmBindgen.to_string())?;
+        let bindgen_version = install::get_cli_version(&Tool::WasmBindgen, &bindgen_path)?;
+
+        let profile = self.crate_data.configured_profile(self.profile.clone());
+        let wasm_opt_args = if self.no_opt || self.wasm_opt.is_none() {
+            None
+        } else {
+            profile.wasm_opt_args()
+        };
+        let options = vec![
+            self.target.to_string(),
+            format!("{:?}", self.out_name),
+            format!("{:?}", self.profile),
+            format!(
+                "{:?}",
+                [self.disable_dts, self.weak_refs, self.reference_types]
+            ),
+            format!(
+                "{:?}",
+                [
+                    profile.wasm_bindgen_debug_js_glue(),
+                    profile.wasm_bindgen_demangle_name_section(),
+                    profile.wasm_bindgen_dwarf_debug_info(),
+                    profile.wasm_bindgen_omit_default_module_path(),
+                    profile.wasm_bindgen_split_linked_modules(),
+                ]
+            ),
+            format!("{:?}", wasm_opt_args),
+            format!("{:?}", self.wasm_base_url),
+            format!("{:?}", self.transform_js),
+            format!("{:?}", self.init_name),
+            format!("{:?}", self.threads),
+        ];
+
+        let wasm_path =
+            bindgen::wasm_path(&self.crate_data, self.profile.clone(), &self.extra_options);
+        Fingerprint::new(&wasm_path, &bindgen_version, &options)
+    }
+
+    fn step_check_wasm_features(&mut self) -> Result<()> {
+        let wasm = self
+            .out_dir
+            .join(format!("{}_bg.wasm", self.crate_data.name_prefix()));
+        let features = match wasm_features::scan_file(&wasm) {
+            Ok(features) => features,
+            Err(error) => {
+                PBAR.warn(&format!(
+                    "Could not find out which WebAssembly features the wasm uses: {:#}",
+                    error
+                ));
+                return Ok(());
+            }
+        };
+        if self.target_features {
+            wasm_features::write_features_json(&self.out_dir, &features)?;
+        }
+        self.wasm_features = features.names();
+        Ok(())
+    }
+
+    fn step_save_fingerprint(&mut self) -> Result<()> {
+        if let Some(fingerprint) = self.fingerprint.take() {
+            fingerprint.save(&self.out_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Find `wasm-opt` before building, downloading it if needed, so that a
+    /// missing one is reported up front.
+    fn step_check_wasm_opt(&mut self) -> Result<()> {
+        let profile = self.crate_data.configured_profile(self.profile.clone());
+        if self.no_opt || profile.wasm_opt_args().is_none() {
+            return Ok(());
+        }
+        info!("Checking for wasm-opt...");
+        self.wasm_opt = wasm_opt::find_wasm_opt(&self.cache, self.mode.install_permitted())?;
         Ok(())
     }
 
     fn step_run_wasm_opt(&mut self) -> Result<()> {
+        if self.up_to_date {
+            return Ok(());
+        }
         let mut args = match self
             .crate_data
             .configured_profile(self.profile.clone())
@@ -462,16 +1421,52 @@ impl Build {
         if self.reference_types {
             args.push("--enable-reference-types".into());
         }
+        if self.threads {
+            args.push("--enable-threads".into());
+            args.push("--enable-bulk-memory".into());
+        }
+        let wasm_opt_path = match &self.wasm_opt {
+            Some(path) => path,
+            // `step_check_wasm_opt` has already warned that it is skipped.
+            None => return Ok(()),
+        };
         info!("executing wasm-opt with {:?}", args);
-        wasm_opt::run(
-            &self.cache,
-            &self.out_dir,
-            &args,
-            self.mode.install_permitted(),
-        ).map_err(|e| {
+        wasm_opt::run(wasm_opt_path, &self.out_dir, &args).map_err(|e| {
             anyhow!(
-                "{}\nTo disable `wasm-opt`, add `wasm-opt = false` to your package metadata in your `Cargo.toml`.", e
+                "{:#}\nTo disable `wasm-opt`, add `wasm-opt = false` to your package metadata in your `Cargo.toml`.", e
             )
         })
     }
 }
+
+fn parse_jobs(value: &str) -> Result<u32> {
+    match value.parse() {
+        Ok(0) | Err(_) => bail!("expected a positive number of jobs, not {:?}", value),
+        Ok(jobs) => Ok(jobs),
+    }
+}
+
+/// Parse `--out-name`, which is the stem of the generated files. With an
+/// extension or a directory in it, the `.d.ts` and the `types` of the
+/// `package.json` wouldn't match the path the package is imported by.
+fn parse_out_name(value: &str) -> Result<String> {
+    if value.is_empty() || value.contains(['/', '\\']) {
+        bail!(
+            "expected a file name for `--out-name`, not {:?}; use `--out-dir` for the directory",
+            value
+        )
+    }
+    if let Some(ext) = [".d.ts", ".js", ".mjs", ".cjs", ".ts", ".wasm"]
+        .iter()
+        .find(|ext| value.ends_with(*ext))
+    {
+        let stem = value.trim_end_matches(ext);
+        bail!(
+            "`--out-name` takes the name without an extension: `--out-name {0}` names `{0}.js`, \
+             `{0}.d.ts` and `{0}_bg.wasm`, so use it instead of {1:?}",
+            stem,
+            value
+        )
+    }
+    Ok(value.to_string())
+}
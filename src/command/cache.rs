@@ -0,0 +1,136 @@
+//! Implementation of the `wasm-pack cache` command.
+
+use crate::cache::{self, Entry};
+use crate::PBAR;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::Subcommand;
+
+/// The `wasm-pack cache` subcommands.
+#[derive(Clone, Debug, Subcommand)]
+pub enum CacheCommand {
+    #[clap(name = "list")]
+    /// List the tools in the cache, with their versions and where they are
+    List,
+
+    #[clap(name = "clean")]
+    /// Remove the tools in the cache, all of them unless narrowed down with
+    /// `--tool` or `--keep-latest`
+    Clean {
+        #[clap(long = "tool", value_name = "NAME")]
+        /// Only remove versions of the tool `NAME`, such as `wasm-bindgen` or
+        /// `geckodriver`. Can be given several times.
+        tools: Vec<String>,
+
+        #[clap(long = "keep-latest", value_name = "N")]
+        /// Keep the `N` newest versions of each tool, and remove the others.
+        keep_latest: Option<usize>,
+
+        #[clap(long = "dry-run")]
+        /// List what would be removed, with its size, without removing
+        /// anything.
+        dry_run: bool,
+    },
+}
+
+/// Run a `wasm-pack cache` subcommand.
+pub fn cache(command: CacheCommand) -> Result<()> {
+    match command {
+        CacheCommand::List => list(),
+        CacheCommand::Clean {
+            tools,
+            keep_latest,
+            dry_run,
+        } => clean(&tools, keep_latest, dry_run),
+    }
+}
+
+/// Print a line for each tool in the cache, with its name, version and path.
+fn list() -> Result<()> {
+    let cache = cache::get_wasm_pack_cache()?;
+    let entries = cache::list(&cache)?;
+    if entries.is_empty() {
+        PBAR.info("The cache is empty.");
+        return Ok(());
+    }
+    for entry in entries {
+        println!(
+            "{:<16} {:<16} {}",
+            entry.tool,
+            entry.version.as_deref().unwrap_or("unknown"),
+            entry.path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Remove the tools in the cache picked by `tools` and `keep_latest`, or list
+/// them with `dry_run`. Without either, what unfinished downloads left is
+/// removed too, but not the state of the WebDriver clients kept running.
+fn clean(tools: &[String], keep_latest: Option<usize>, dry_run: bool) -> Result<()> {
+    let cache = cache::get_wasm_pack_cache()?;
+    let mut entries = cache::list(&cache)?;
+    if !tools.is_empty() {
+        for tool in tools {
+            if !entries.iter().any(|entry| &entry.tool == tool) {
+                PBAR.warn(&format!("There is no {} in the cache.", tool));
+            }
+        }
+        entries.retain(|entry| tools.contains(&entry.tool));
+    }
+    if let Some(keep) = keep_latest {
+        entries = cache::beyond_latest(entries, keep);
+    }
+    if tools.is_empty() && keep_latest.is_none() {
+        entries.extend(cache::leftovers(&cache)?);
+    }
+    if entries.is_empty() {
+        PBAR.info("There is nothing to remove from the cache.");
+        return Ok(());
+    }
+
+    let prefix = if dry_run { "[dry run] " } else { "" };
+    let mut freed = 0;
+    for entry in &entries {
+        if !dry_run {
+            cache::remove(entry)?;
+        }
+        freed += entry.size;
+        PBAR.info(&format!(
+            "{}{} {}",
+            prefix,
+            if dry_run { "Would remove" } else { "Removed" },
+            describe(entry)
+        ));
+    }
+    PBAR.info(&format!(
+        "{}{} {} in {} {}",
+        prefix,
+        if dry_run { "Would free" } else { "Freed" },
+        cache::format_size(freed),
+        entries.len(),
+        if entries.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        }
+    ));
+    Ok(())
+}
+
+/// Describe `entry` for `wasm-pack cache clean`, e.g. `wasm-bindgen 0.2.74,
+/// 12 MiB, last used 2024-03-01, at <path>`.
+fn describe(entry: &Entry) -> String {
+    let last_used = match entry.last_used {
+        Some(time) => DateTime::<Local>::from(time).format("%Y-%m-%d").to_string(),
+        None => "unknown".to_string(),
+    };
+    format!(
+        "{} {}, {}, last used {}, at {}",
+        entry.tool,
+        entry.version.as_deref().unwrap_or("(unknown version)"),
+        cache::format_size(entry.size),
+        last_used,
+        entry.path.display()
+    )
+}
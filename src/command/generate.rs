@@ -14,6 +14,9 @@ pub fn generate(template: String, name: String, install_permitted: bool) -> Resu
         &cache::get_wasm_pack_cache()?,
         "latest",
         install_permitted,
+        &[],
+        None,
+        None,
     )?;
     generate::generate(&template, &name, &download)?;
 
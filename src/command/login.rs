@@ -1,14 +1,41 @@
-use crate::npm;
+use crate::command::utils::get_crate_path;
+use crate::npm::{self, PackageManager};
+use crate::npmrc;
 use crate::PBAR;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use log::info;
+use std::env;
+
+/// The environment variable a token is read from when `--token` isn't given.
+pub const NPM_TOKEN_ENV: &str = "NPM_TOKEN";
 
 pub fn login(
     registry: Option<String>,
     scope: &Option<String>,
     auth_type: &Option<String>,
+    package_manager: Option<PackageManager>,
+    token: Option<String>,
+    project: bool,
 ) -> Result<()> {
     let registry = registry.unwrap_or_else(|| npm::DEFAULT_NPM_REGISTRY.to_string());
+    // Asking for an auth type asks for the interactive login, so `$NPM_TOKEN`
+    // is only used without one.
+    let token = token
+        .or_else(|| env::var(NPM_TOKEN_ENV).ok().filter(|_| auth_type.is_none()))
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+    if let Some(token) = token {
+        return login_with_token(&registry, scope, &token, project);
+    }
+    if project {
+        bail!(
+            "`--project` only applies when logging in with a token, from `--token` or ${}",
+            NPM_TOKEN_ENV
+        )
+    }
+
+    let crate_path = get_crate_path(None)?;
+    let package_manager = PackageManager::choose(package_manager, &crate_path, &crate_path)?;
 
     info!("Logging in to npm...");
     info!(
@@ -16,9 +43,40 @@ pub fn login(
         &scope, &registry, &auth_type
     );
     info!("npm info located in the npm debug log");
-    npm::npm_login(&registry, &scope, &auth_type)?;
+    info!("Logging in with {}", package_manager);
+    npm::npm_login(package_manager, &registry, &scope, &auth_type)?;
     info!("Logged you in!");
 
     PBAR.info(&"👋  logged you in!".to_string());
     Ok(())
 }
+
+/// Log in without asking anything: check `token` with the registry, then
+/// save it in the user `.npmrc`, or the one of the crate with `project`.
+fn login_with_token(
+    registry: &str,
+    scope: &Option<String>,
+    token: &str,
+    project: bool,
+) -> Result<()> {
+    info!("Logging in to {} with a token...", registry);
+    let username = npm::whoami(registry, token)?;
+    let path = if project {
+        get_crate_path(None)?.join(".npmrc")
+    } else {
+        npmrc::user_npmrc().context(
+            "couldn't find your home directory to save the token in; \
+             pass `--project` to save it in the `.npmrc` of the crate instead",
+        )?
+    };
+    npmrc::set_auth_token(&path, registry, scope.as_deref(), token)?;
+    info!("Saved the token for {} in {}", registry, path.display());
+
+    PBAR.info(&format!(
+        "👋  logged in to {} as {}, with the token saved in {}",
+        registry,
+        username,
+        path.display()
+    ));
+    Ok(())
+}
@@ -2,25 +2,31 @@
 #![allow(clippy::redundant_closure)]
 
 pub mod build;
+pub mod cache;
 mod generate;
 mod login;
 mod pack;
+pub mod prepare;
 /// Data structures and functions for publishing a package.
 pub mod publish;
 pub mod test;
 pub mod utils;
+pub mod version;
 
 use self::build::{Build, BuildOptions};
+use self::cache::{cache, CacheCommand};
 use self::generate::generate;
 use self::login::login;
-use self::pack::pack;
-use self::publish::{access::Access, publish};
+use self::pack::{pack, PackOptions};
+use self::prepare::{prepare, PrepareOptions};
+use self::publish::{publish, PublishOptions};
 use self::test::{Test, TestOptions};
+use self::version::{version, VersionOptions};
 use crate::install::InstallMode;
+use crate::npm::{self, PackageManager};
 use anyhow::Result;
 use clap::Subcommand;
 use log::info;
-use std::path::PathBuf;
 /// The various kinds of commands that `wasm-pack` can execute.
 #[derive(Debug, Subcommand)]
 pub enum Command {
@@ -30,15 +36,7 @@ pub enum Command {
 
     #[clap(name = "pack")]
     /// 🍱  create a tar of your npm package but don't publish!
-    Pack {
-        #[clap(long = "pkg-dir", short = 'd', default_value = "pkg")]
-        /// The name of the output directory where the npm package is stored
-        pkg_directory: PathBuf,
-
-        /// The path to the Rust crate. If not set, searches up the path from the current directory.
-        #[clap()]
-        path: Option<PathBuf>,
-    },
+    Pack(PackOptions),
 
     #[clap(name = "new")]
     /// 🐑 create a new project with a template
@@ -58,33 +56,16 @@ pub enum Command {
 
     #[clap(name = "publish")]
     /// 🎆  pack up your npm package and publish!
-    Publish {
-        #[clap(long = "target", short = 't', default_value = "bundler")]
-        /// Sets the target environment. [possible values: bundler, nodejs, web, no-modules]
-        target: String,
-
-        /// The access level for the package to be published
-        #[clap(long = "access", short = 'a')]
-        access: Option<Access>,
-
-        /// The distribution tag being used for publishing.
-        /// See https://docs.npmjs.com/cli/dist-tag
-        #[clap(long = "tag")]
-        tag: Option<String>,
-
-        #[clap(long = "pkg-dir", short = 'd', default_value = "pkg")]
-        /// The name of the output directory where the npm package is stored
-        pkg_directory: PathBuf,
-
-        /// The path to the Rust crate. If not set, searches up the path from the current directory.
-        #[clap()]
-        path: Option<PathBuf>,
-    },
+    Publish(PublishOptions),
+
+    #[clap(name = "version")]
+    /// 🔖  bump the version of your crate and keep the package.json in sync!
+    Version(VersionOptions),
 
     #[clap(name = "login", alias = "adduser", alias = "add-user")]
     /// 👤  Add an npm registry user account! (aliases: adduser, add-user)
     Login {
-        #[clap(long = "registry", short = 'r')]
+        #[clap(long = "registry", short = 'r', value_parser = npm::parse_registry)]
         /// Default: 'https://registry.npmjs.org/'.
         /// The base URL of the npm package registry. If scope is also
         /// specified, this registry will only be used for packages with that
@@ -105,11 +86,47 @@ pub enum Command {
         /// registries (for example, npmE) might support alternative auth
         /// strategies besides classic username/password entry in legacy npm.
         auth_type: Option<String>,
+
+        #[clap(
+            long = "package-manager",
+            alias = "pkg-manager",
+            value_name = "npm|yarn|pnpm"
+        )]
+        /// The package manager to log in with. Defaults to the
+        /// `package-manager` in `[package.metadata.wasm-pack]` of the crate
+        /// in the current directory, then to the one whose lockfile is
+        /// closest, or npm.
+        package_manager: Option<PackageManager>,
+
+        #[clap(long = "token", value_name = "TOKEN", conflicts_with = "auth_type")]
+        /// Log in with an existing token, such as an npm automation token,
+        /// instead of interactively. Defaults to `$NPM_TOKEN` when it is set and
+        /// no `--auth-type` is given.
+        /// The token is checked with the registry and saved in the user
+        /// `.npmrc`.
+        token: Option<String>,
+
+        #[clap(long = "project")]
+        /// Save the token in the `.npmrc` of the crate instead of the user
+        /// `.npmrc`.
+        project: bool,
     },
 
     #[clap(name = "test")]
     /// 👩‍🔬  test your wasm!
     Test(TestOptions),
+
+    #[clap(name = "prepare", alias = "install-deps")]
+    /// 🧰  install the tools to build and test with, without building anything!
+    Prepare(PrepareOptions),
+
+    #[clap(name = "cache")]
+    /// 🗄️  look at the tools wasm-pack has downloaded!
+    Cache {
+        #[clap(subcommand)]
+        /// What to do with the cache
+        cmd: CacheCommand,
+    },
 }
 
 /// Run a command with the given logger!
@@ -119,15 +136,19 @@ pub fn run_wasm_pack(command: Command) -> Result<()> {
     match command {
         Command::Build(build_opts) => {
             info!("Running build command...");
-            Build::try_from_opts(build_opts).and_then(|mut b| b.run())
+            if build_opts.list_targets {
+                build::list_targets();
+                Ok(())
+            } else if build_opts.workspace {
+                build::build_workspace(build_opts)
+            } else {
+                Build::try_from_opts(build_opts).and_then(|mut b| b.run())
+            }
         }
-        Command::Pack {
-            path,
-            pkg_directory,
-        } => {
+        Command::Pack(pack_opts) => {
             info!("Running pack command...");
-            info!("Path: {:?}", &path);
-            pack(path, pkg_directory)
+            info!("Path: {:?}", &pack_opts.path);
+            pack(pack_opts)
         }
         Command::Generate {
             template,
@@ -139,32 +160,60 @@ pub fn run_wasm_pack(command: Command) -> Result<()> {
             info!("Name: {:?}", &name);
             generate(template, name, mode.install_permitted())
         }
-        Command::Publish {
-            target,
-            path,
-            access,
-            tag,
-            pkg_directory,
-        } => {
+        Command::Publish(publish_opts) => {
             info!("Running publish command...");
-            info!("Path: {:?}", &path);
-            publish(&target, path, access, tag, pkg_directory)
+            info!("Path: {:?}", &publish_opts.path);
+            if publish_opts.workspace || !publish_opts.packages.is_empty() {
+                publish::publish_workspace(publish_opts)
+            } else {
+                publish(publish_opts)
+            }
+        }
+        Command::Version(version_opts) => {
+            info!("Running version command...");
+            info!("Path: {:?}", &version_opts.path);
+            version(version_opts)
         }
         Command::Login {
             registry,
             scope,
             auth_type,
+            package_manager,
+            token,
+            project,
         } => {
             info!("Running login command...");
             info!(
                 "Registry: {:?}, Scope: {:?}, Auth Type: {:?}",
                 &registry, &scope, &auth_type
             );
-            login(registry, &scope, &auth_type)
+            login(
+                registry,
+                &scope,
+                &auth_type,
+                package_manager,
+                token,
+                project,
+            )
         }
         Command::Test(test_opts) => {
             info!("Running test command...");
-            Test::try_from_opts(test_opts).and_then(|t| t.run())
+            if test_opts.shutdown_driver {
+                test::shutdown_drivers()
+            } else if test_opts.workspace {
+                test::test_workspace(test_opts)
+            } else {
+                Test::try_from_opts(test_opts).and_then(|t| t.run())
+            }
+        }
+        Command::Prepare(prepare_opts) => {
+            info!("Running prepare command...");
+            info!("Path: {:?}", &prepare_opts.path);
+            prepare(prepare_opts)
+        }
+        Command::Cache { cmd } => {
+            info!("Running cache command...");
+            cache(cmd)
         }
     }
 }
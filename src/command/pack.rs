@@ -1,29 +1,184 @@
-use crate::command::utils::{find_pkg_directory, get_crate_path};
-use crate::npm;
+use crate::command::build::Target;
+use crate::command::utils::{built_pkg_directory, get_crate_path};
+use crate::npm::{self, PackageManager};
 use crate::PBAR;
-use anyhow::{anyhow, Result};
+use anyhow::{bail, Context, Result};
+use clap::Args;
 use log::info;
-use std::path::PathBuf;
+use path_clean::PathClean;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{is_separator, Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Clone, Debug, Args)]
+/// Everything required to configure the `wasm-pack pack` command.
+pub struct PackOptions {
+    #[clap(long = "pkg-dir", short = 'd', value_name = "DIR")]
+    /// The directory with the package.json of the npm package to pack,
+    /// relative to the crate given as the path if it is there, and to the
+    /// current directory otherwise. Defaults to the `pkg` directory in the
+    /// crate.
+    pub pkg_directory: Option<PathBuf>,
+
+    #[clap(long = "target", short = 't')]
+    /// The target to build the package for if it hasn't been built yet. A
+    /// package that is out of date is rebuilt the way it was built instead.
+    /// Defaults to bundler.
+    /// [possible values: bundler, nodejs, web, no-modules, deno]
+    pub target: Option<Target>,
+
+    #[clap(long = "no-build")]
+    /// Pack the pkg directory as it is, without building it when it is
+    /// missing or older than the crate's sources.
+    pub no_build: bool,
+
+    #[clap(
+        long = "package-manager",
+        alias = "pkg-manager",
+        value_name = "npm|yarn|pnpm"
+    )]
+    /// The package manager to pack with. Defaults to the `package-manager`
+    /// in `[package.metadata.wasm-pack]`, then to the one whose lockfile
+    /// is closest to the pkg directory, or npm.
+    pub package_manager: Option<PackageManager>,
+
+    #[clap(long = "registry", value_name = "URL", value_parser = npm::parse_registry)]
+    /// The registry to pack against. Defaults to `publishConfig.registry`
+    /// in the package.json, and then to the registry configured for npm.
+    pub registry: Option<String>,
+
+    #[clap(long = "out", value_name = "PATH")]
+    /// Move the tarball to `PATH`, or into it if it is a directory or ends
+    /// with a `/`, and print where it ended up.
+    pub out: Option<PathBuf>,
+
+    #[clap(long = "force", requires = "out")]
+    /// Overwrite a file that is already at the `--out` destination.
+    pub force: bool,
+
+    /// The path to the Rust crate. If not set, searches up the path from the current directory.
+    #[clap()]
+    pub path: Option<PathBuf>,
+}
 
 /// Executes the 'npm pack' command on the 'pkg' directory
 /// which creates a tarball that can be published to the NPM registry
-pub fn pack(path: Option<PathBuf>, pkg_directory: PathBuf) -> Result<()> {
+pub fn pack(opts: PackOptions) -> Result<()> {
+    let PackOptions {
+        pkg_directory,
+        target,
+        no_build,
+        package_manager,
+        registry,
+        out,
+        force,
+        path,
+    } = opts;
+    let crate_path_given = path.is_some();
     let crate_path = get_crate_path(path)?;
 
     info!("Packing up the npm package...");
-    let pkg_directory = find_pkg_directory(&crate_path, &pkg_directory).ok_or_else(|| {
-        anyhow!(
-            "Unable to find the pkg directory at path {:#?}, or in a child directory of {:#?}",
-            &crate_path,
-            &crate_path
-        )
-    })?;
-    npm::npm_pack(&pkg_directory.to_string_lossy())?;
+    let pkg_directory = built_pkg_directory(
+        &crate_path,
+        crate_path_given,
+        pkg_directory.as_deref(),
+        target,
+        no_build,
+    )?;
+    let package_manager = PackageManager::choose(package_manager, &crate_path, &pkg_directory)?;
+    info!("Packing with {}", package_manager);
+    let registry = registry.or_else(|| npm::publish_config_registry(&pkg_directory));
+    let before = tarballs(&pkg_directory)?;
+    npm::npm_pack(
+        &pkg_directory.to_string_lossy(),
+        package_manager,
+        registry.as_deref(),
+    )?;
     info!(
         "Your package is located at {:#?}",
-        crate_path.join(pkg_directory)
+        crate_path.join(&pkg_directory)
     );
 
     PBAR.info("🎒  packed up your package!");
+
+    if let Some(out) = out {
+        let tarball = packed_tarball(&pkg_directory, &before, package_manager)?;
+        let destination = move_tarball(&tarball, &out, force)?;
+        // Alone on stdout, so that scripts can pick up where the tarball is.
+        println!("{}", destination.display());
+    }
     Ok(())
 }
+
+/// The tarballs in `dir`, with when they were last modified.
+fn tarballs(dir: &Path) -> Result<HashMap<PathBuf, SystemTime>> {
+    let mut tarballs = HashMap::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|extension| extension == "tgz") {
+            tarballs.insert(path, entry.metadata()?.modified()?);
+        }
+    }
+    Ok(tarballs)
+}
+
+/// The tarball that the package manager wrote into `dir`: the one that is
+/// new or changed since `before`.
+fn packed_tarball(
+    dir: &Path,
+    before: &HashMap<PathBuf, SystemTime>,
+    package_manager: PackageManager,
+) -> Result<PathBuf> {
+    let mut packed: Vec<_> = tarballs(dir)?
+        .into_iter()
+        .filter(|(path, modified)| before.get(path) != Some(modified))
+        .map(|(path, _)| path)
+        .collect();
+    if packed.len() != 1 {
+        bail!(
+            "couldn't tell which tarball `{} pack` wrote into {}",
+            package_manager,
+            dir.display()
+        );
+    }
+    Ok(packed.remove(0))
+}
+
+/// Move `tarball` to `out`, or into it if it is a directory, returning the
+/// absolute path it ended up at.
+fn move_tarball(tarball: &Path, out: &Path, force: bool) -> Result<PathBuf> {
+    let ends_with_separator = out.as_os_str().to_string_lossy().ends_with(is_separator);
+    let out = env::current_dir()?.join(out).clean();
+    let into_dir = ends_with_separator || out.is_dir();
+    let destination = if into_dir {
+        out.join(tarball.file_name().unwrap())
+    } else {
+        out
+    };
+    if destination.exists() && !force {
+        bail!(
+            "{} already exists; pass `--force` to overwrite it",
+            destination.display()
+        );
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    if fs::rename(tarball, &destination).is_err() {
+        // Renaming doesn't work across file systems.
+        fs::copy(tarball, &destination).with_context(|| {
+            format!(
+                "failed to move {} to {}",
+                tarball.display(),
+                destination.display()
+            )
+        })?;
+        fs::remove_file(tarball)
+            .with_context(|| format!("failed to remove {}", tarball.display()))?;
+    }
+    Ok(destination)
+}
@@ -0,0 +1,103 @@
+//! Implementation of the `wasm-pack prepare` command.
+
+use crate::cache;
+use crate::command::utils::get_crate_path;
+use crate::install::{self, InstallMode, Tool};
+use crate::lockfile::{self, Lockfile};
+use crate::manifest;
+use crate::pinned;
+use crate::test::webdriver;
+use crate::PBAR;
+use anyhow::Result;
+use clap::Args;
+use log::info;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Default, Args)]
+/// Everything required to configure the `wasm-pack prepare` command.
+pub struct PrepareOptions {
+    #[clap(long = "chrome")]
+    /// Also get the `chromedriver` for the installed Chrome, as
+    /// `wasm-pack test --chrome` would.
+    pub chrome: bool,
+
+    #[clap(long = "firefox")]
+    /// Also get `geckodriver`, as `wasm-pack test --firefox` would.
+    pub firefox: bool,
+
+    #[clap(long = "edge")]
+    /// Also get the `msedgedriver` for the installed Edge, as
+    /// `wasm-pack test --edge` would.
+    pub edge: bool,
+
+    #[clap(long = "mode", short = 'm', default_value = "normal")]
+    /// Sets steps to be run. [possible values: no-install, normal, force]
+    pub mode: InstallMode,
+
+    #[clap(long = "package", short = 'p', value_name = "MEMBER")]
+    /// Prepare for the workspace member named `MEMBER`, from anywhere in the
+    /// workspace.
+    pub package: Option<String>,
+
+    /// The path to the Rust crate. If not set, searches up the path from the current directory.
+    #[clap()]
+    pub path: Option<PathBuf>,
+}
+
+/// Get the `wasm-bindgen` CLI that the crate at `path` needs, and the
+/// WebDriver clients for the chosen browsers, into the cache without
+/// building anything.
+pub fn prepare(opts: PrepareOptions) -> Result<()> {
+    let PrepareOptions {
+        chrome,
+        firefox,
+        edge,
+        mode,
+        package,
+        path,
+    } = opts;
+
+    let crate_path = get_crate_path(path)?;
+    let crate_path = match &package {
+        Some(name) => manifest::workspace_member(&crate_path, name)?,
+        None => crate_path,
+    };
+    lockfile::generate_if_missing(&crate_path, &[])?;
+    let crate_data = manifest::CrateData::new(&crate_path, None)?;
+    let cache = cache::get_wasm_pack_cache()?;
+
+    info!("Identifying wasm-bindgen dependency...");
+    let lockfile = Lockfile::new(&crate_data)?;
+    let bindgen_version = pinned::wasm_bindgen_version(&crate_path, &crate_data, &lockfile)?;
+    let status = install::download_prebuilt_or_cargo_install(
+        Tool::WasmBindgen,
+        &cache,
+        &bindgen_version,
+        mode.install_permitted(),
+        &[],
+        None,
+        Some(&crate_path),
+    )?;
+    install::get_tool_path(&status, Tool::WasmBindgen)?;
+
+    if chrome {
+        let binary = crate_data
+            .browser_options("chrome")
+            .and_then(|options| options.binary.as_deref());
+        webdriver::get_or_install_chromedriver(
+            &cache,
+            mode,
+            crate_data.chromedriver_version(),
+            binary,
+        )?;
+    }
+    if firefox {
+        webdriver::get_or_install_geckodriver(&cache, mode, crate_data.geckodriver_version())?;
+    }
+    if edge {
+        webdriver::get_or_install_msedgedriver(&cache, mode, crate_data.msedgedriver_version())?;
+    }
+
+    PBAR.info("🧰  The tools are ready!");
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use anyhow::{bail, Error, Result};
 use std::fmt;
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
 
 /// Represents access level for the to-be publish package. Passed to `wasm-pack publish` as a flag, e.g. `--access=public`.
@@ -18,8 +20,8 @@ impl FromStr for Access {
         match s {
       "public" => Ok(Access::Public),
       "restricted" => Ok(Access::Restricted),
-      "private" => Ok(Access::Restricted),
-      _ => bail!("{} is not a supported access level. See https://docs.npmjs.com/cli/access for more information on npm package access levels.", s),
+      "private" => bail!("private is not a supported access level, npm calls it restricted: pass `--access restricted` to publish a package only you or your organization can see."),
+      _ => bail!("{} is not a supported access level, expected public or restricted. See https://docs.npmjs.com/cli/access for more information on npm package access levels.", s),
     }
     }
 }
@@ -33,3 +35,22 @@ impl fmt::Display for Access {
         write!(f, "{}", printable)
     }
 }
+
+/// A hint about `--access` for a failed publish of the package in
+/// `pkg_directory`, if it is scoped and nothing says which access it gets.
+///
+/// npm publishes scoped packages as restricted by default, which fails for
+/// accounts that can't publish private packages.
+pub fn hint(pkg_directory: &Path) -> Option<String> {
+    let package: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(pkg_directory.join("package.json")).ok()?).ok()?;
+    let name = package["name"].as_str()?;
+    if !name.starts_with('@') || package["publishConfig"]["access"].is_string() {
+        return None;
+    }
+    Some(format!(
+        "{} is a scoped package, which npm publishes as restricted unless told otherwise. To \
+         publish it publicly, pass `--access public`, or set `publishConfig.access`.",
+        name
+    ))
+}
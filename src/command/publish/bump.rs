@@ -0,0 +1,179 @@
+use anyhow::{bail, Context, Error, Result};
+use semver::{Prerelease, Version};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use toml_edit::{Document, Item, Value};
+
+/// The part of the version that `wasm-pack publish --bump` and `wasm-pack
+/// version` increment.
+#[derive(Clone, Copy, Debug)]
+pub enum Bump {
+    /// `1.2.3` becomes `1.2.4`.
+    Patch,
+    /// `1.2.3` becomes `1.3.0`.
+    Minor,
+    /// `1.2.3` becomes `2.0.0`.
+    Major,
+    /// `1.2.3` becomes `1.2.4-0`, and `1.2.4-beta.1` becomes `1.2.4-beta.2`,
+    /// like `npm version prerelease`.
+    Prerelease,
+}
+
+impl FromStr for Bump {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "patch" => Ok(Bump::Patch),
+            "minor" => Ok(Bump::Minor),
+            "major" => Ok(Bump::Major),
+            "prerelease" => Ok(Bump::Prerelease),
+            _ => bail!(
+                "{} is not a supported version bump, expected one of: patch, minor, major, \
+                 prerelease",
+                s
+            ),
+        }
+    }
+}
+
+impl Bump {
+    /// The version after bumping `version`. A pre-release or build suffix is
+    /// dropped, except by `Prerelease`, which increments the pre-release.
+    pub fn apply(self, version: &Version) -> Version {
+        match self {
+            Bump::Patch => Version::new(version.major, version.minor, version.patch + 1),
+            Bump::Minor => Version::new(version.major, version.minor + 1, 0),
+            Bump::Major => Version::new(version.major + 1, 0, 0),
+            Bump::Prerelease if version.pre.is_empty() => {
+                let mut bumped = Version::new(version.major, version.minor, version.patch + 1);
+                bumped.pre = Prerelease::new("0").unwrap();
+                bumped
+            }
+            Bump::Prerelease => {
+                let mut parts: Vec<String> =
+                    version.pre.as_str().split('.').map(String::from).collect();
+                match parts.last().and_then(|last| last.parse::<u64>().ok()) {
+                    Some(n) => *parts.last_mut().unwrap() = (n + 1).to_string(),
+                    None => parts.push("0".to_string()),
+                }
+                let mut bumped = Version::new(version.major, version.minor, version.patch);
+                bumped.pre = Prerelease::new(&parts.join(".")).unwrap();
+                bumped
+            }
+        }
+    }
+}
+
+/// The version `wasm-pack version` sets: a bump of the current one, or one
+/// given as it is.
+#[derive(Clone, Debug)]
+pub enum NewVersion {
+    /// Bump the current version.
+    Bump(Bump),
+    /// Set this version.
+    Exact(Version),
+}
+
+impl FromStr for NewVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(bump) = s.parse() {
+            return Ok(NewVersion::Bump(bump));
+        }
+        match Version::parse(s.strip_prefix('v').unwrap_or(s)) {
+            Ok(version) => Ok(NewVersion::Exact(version)),
+            Err(_) => bail!(
+                "{} is neither a version bump nor a version, expected one of: patch, minor, \
+                 major, prerelease, or a version like 1.2.3",
+                s
+            ),
+        }
+    }
+}
+
+impl NewVersion {
+    /// The version that replaces `version`.
+    pub fn apply(&self, version: &Version) -> Version {
+        match self {
+            NewVersion::Bump(bump) => bump.apply(version),
+            NewVersion::Exact(exact) => exact.clone(),
+        }
+    }
+}
+
+/// Bump the version in the crate's `Cargo.toml` and in the `package.json`
+/// built from it, and return the new version.
+pub fn bump_version(crate_path: &Path, pkg_directory: &Path, bump: Bump) -> Result<Version> {
+    let manifest_path = crate_path.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    let (manifest, version) = set_manifest_version(&manifest, bump)?;
+    fs::write(&manifest_path, manifest)
+        .with_context(|| format!("failed to write {}", manifest_path.display()))?;
+
+    set_package_json_version(pkg_directory, &version)?;
+    Ok(version)
+}
+
+/// Set the `version` of the `package.json` in `pkg_directory`.
+pub fn set_package_json_version(pkg_directory: &Path, version: &Version) -> Result<()> {
+    let package_json = pkg_directory.join("package.json");
+    let mut package: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(&package_json)
+            .with_context(|| format!("failed to read {}", package_json.display()))?,
+    )
+    .with_context(|| format!("failed to parse {}", package_json.display()))?;
+    package["version"] = version.to_string().into();
+    fs::write(&package_json, serde_json::to_string_pretty(&package)?)
+        .with_context(|| format!("failed to write {}", package_json.display()))?;
+    Ok(())
+}
+
+/// Bump the `version` in the `[package]` table of the `Cargo.toml` contents
+/// in `manifest`, leaving the rest of the file as it is.
+pub fn set_manifest_version(manifest: &str, bump: Bump) -> Result<(String, Version)> {
+    change_manifest_version(manifest, &["package"], &NewVersion::Bump(bump))
+        .map(|(manifest, _, version)| (manifest, version))
+}
+
+/// Change the `version` in the table at `table` of the `Cargo.toml` contents
+/// in `manifest`, such as `["package"]` or `["workspace", "package"]`,
+/// leaving the rest of the file, comments and all, as it is. Returns the new
+/// contents, and the old and new versions.
+pub fn change_manifest_version(
+    manifest: &str,
+    table: &[&str],
+    change: &NewVersion,
+) -> Result<(String, Version, Version)> {
+    let mut document: Document = manifest.parse().context("failed to parse the Cargo.toml")?;
+    let name = format!("[{}]", table.join("."));
+    let mut item = document.as_item_mut();
+    for key in table {
+        item = match item.get_mut(key) {
+            Some(item) => item,
+            None => bail!("There is no `{}` table in the Cargo.toml", name),
+        };
+    }
+    let value = match item.get_mut("version") {
+        Some(Item::Value(value)) if value.is_str() => value,
+        Some(_) => bail!(
+            "The version is not set in the `{}` table, e.g. because it is inherited from the \
+             workspace, so it can't be bumped. Bump it where it is set.",
+            name
+        ),
+        None => bail!(
+            "There is no version in the `{}` table of the Cargo.toml",
+            name
+        ),
+    };
+    let current = value.as_str().unwrap();
+    let old = Version::parse(current).with_context(|| format!("invalid version `{}`", current))?;
+    let new = change.apply(&old);
+    let decor = value.decor().clone();
+    *value = Value::from(new.to_string());
+    *value.decor_mut() = decor;
+    Ok((document.to_string(), old, new))
+}
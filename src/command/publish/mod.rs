@@ -1,83 +1,380 @@
 /// Data structure to represent published package access level.
 pub mod access;
+/// Bumping the crate's version before publishing.
+pub mod bump;
 
 use self::access::Access;
-use crate::command::build::{Build, BuildOptions, Target};
-use crate::command::utils::{find_pkg_directory, get_crate_path};
-use crate::npm;
+use self::bump::Bump;
+use crate::command::build::Target;
+use crate::command::utils::{built_pkg_directory, get_crate_path, report_members};
+use crate::manifest;
+use crate::npm::{self, PackageManager, PublishedVersion, Release, TarballContents};
+use crate::npmrc;
 use crate::PBAR;
 use anyhow::{anyhow, bail, Result};
-use dialoguer::{Confirm, Input, Select};
+use clap::Args;
+use dialoguer::Confirm;
 use log::info;
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Args)]
+/// Everything required to configure the `wasm-pack publish` command.
+pub struct PublishOptions {
+    #[clap(long = "target", short = 't')]
+    /// The target to build the package for if it hasn't been built yet. A
+    /// package that is out of date is rebuilt the way it was built instead.
+    /// Defaults to bundler.
+    /// [possible values: bundler, nodejs, web, no-modules, deno]
+    pub target: Option<Target>,
+
+    #[clap(long = "no-build")]
+    /// Publish the pkg directory as it is, without building it when it is
+    /// missing or older than the crate's sources.
+    pub no_build: bool,
+
+    /// The access level for the package to be published. Scoped packages
+    /// are restricted unless this says otherwise. [possible values: public,
+    /// restricted]
+    #[clap(long = "access", short = 'a')]
+    pub access: Option<Access>,
+
+    /// The distribution tag being used for publishing, `latest` by default.
+    /// See https://docs.npmjs.com/cli/dist-tag
+    #[clap(long = "tag", value_parser = npm::parse_dist_tag)]
+    pub tag: Option<String>,
+
+    #[clap(
+        long = "pkg-dir",
+        short = 'd',
+        value_name = "DIR",
+        conflicts_with_all = ["workspace", "packages"]
+    )]
+    /// The directory with the package.json of the npm package to publish,
+    /// relative to the crate given as the path if it is there, and to the
+    /// current directory otherwise. Defaults to the `pkg` directory in the
+    /// crate.
+    pub pkg_directory: Option<PathBuf>,
+
+    #[clap(long = "bump")]
+    /// Bump the version in Cargo.toml and package.json before publishing.
+    /// [possible values: patch, minor, major, prerelease]
+    pub bump: Option<Bump>,
+
+    #[clap(long = "otp", value_name = "CODE")]
+    /// The one-time password of an account with two-factor authentication.
+    /// Without it, wasm-pack asks for one if the registry wants it.
+    pub otp: Option<String>,
+
+    #[clap(long = "dry-run", conflicts_with = "bump")]
+    /// Go through the whole publish, but only pass `--dry-run` to the
+    /// package manager, so that nothing is uploaded.
+    pub dry_run: bool,
+
+    #[clap(long = "skip-existing", conflicts_with = "bump")]
+    /// Succeed without publishing if the version is already on the registry,
+    /// such as when a CI job that published it is retried.
+    pub skip_existing: bool,
+
+    #[clap(long = "yes", short = 'y')]
+    /// Publish without asking for confirmation after listing the contents of
+    /// the tarball. There is nothing to confirm without a terminal either.
+    pub yes: bool,
+
+    #[clap(long = "provenance")]
+    /// Publish with a provenance statement that links the package to the CI
+    /// run and commit it was built from. Needs npm 9.5.0 or later, on a CI
+    /// service that npm supports for it.
+    pub provenance: bool,
+
+    #[clap(
+        long = "npm-arg",
+        value_name = "ARG",
+        allow_hyphen_values = true,
+        value_parser = npm::parse_npm_arg
+    )]
+    /// Pass `ARG` on to the package manager's publish, such as a flag
+    /// wasm-pack doesn't know about. Can be given several times. Flags that
+    /// wasm-pack sets itself, like `--tag`, are refused.
+    pub npm_args: Vec<String>,
+
+    #[clap(long = "registry", value_name = "URL", value_parser = npm::parse_registry)]
+    /// The registry to publish to. Defaults to `publishConfig.registry` in the
+    /// package.json, and then to the registry configured for npm.
+    pub registry: Option<String>,
+
+    #[clap(
+        long = "package-manager",
+        alias = "pkg-manager",
+        value_name = "npm|yarn|pnpm"
+    )]
+    /// The package manager to publish with. Defaults to the `package-manager`
+    /// in `[package.metadata.wasm-pack]`, then to the one whose lockfile is
+    /// closest to the pkg directory, or npm.
+    pub package_manager: Option<PackageManager>,
+
+    #[clap(long = "package", short = 'p', value_name = "MEMBER")]
+    /// Publish the workspace member named `MEMBER`. Can be given several
+    /// times, and the members are published in the order of their
+    /// dependencies.
+    pub packages: Vec<String>,
+
+    #[clap(long = "workspace", conflicts_with = "packages")]
+    /// Publish every `cdylib` member of the workspace, each one after the
+    /// members it depends on.
+    pub workspace: bool,
+
+    #[clap(long = "fail-fast")]
+    /// With `--workspace` or `--package`, stop at the first member that fails
+    /// to publish, instead of going on with the others.
+    pub fail_fast: bool,
+
+    /// The path to the Rust crate. If not set, searches up the path from the current directory.
+    #[clap()]
+    pub path: Option<PathBuf>,
+}
+
+/// What publishing a package came to.
+enum Outcome {
+    /// The release was published, or would have been by a dry run.
+    Published(Release),
+    /// The version was already published, and `--skip-existing` was given.
+    Skipped(PublishedVersion),
+}
 
 /// Creates a tarball from a 'pkg' directory
 /// and publishes it to the NPM registry
-pub fn publish(
-    _target: &str,
-    path: Option<PathBuf>,
-    access: Option<Access>,
-    tag: Option<String>,
-    pkg_directory: PathBuf,
-) -> Result<()> {
+pub fn publish(opts: PublishOptions) -> Result<()> {
+    publish_package(opts).map(drop)
+}
+
+/// Publish the workspace members selected with `--package`, or every `cdylib`
+/// member with `--workspace`, each one after the members it depends on.
+///
+/// A member that fails to publish doesn't stop the others, unless
+/// `--fail-fast` is given. Each member's outcome is reported at the end.
+pub fn publish_workspace(mut opts: PublishOptions) -> Result<()> {
+    let root = get_crate_path(opts.path.take())?;
+    let members = manifest::workspace_members(&root)?;
+    for name in &opts.packages {
+        if !members.iter().any(|member| &member.name == name) {
+            bail!(
+                "package `{}` is not a member of the workspace at {}",
+                name,
+                root.display()
+            )
+        }
+    }
+    let members: Vec<_> = members
+        .into_iter()
+        .filter(|member| {
+            if opts.workspace {
+                member.is_cdylib
+            } else {
+                opts.packages.contains(&member.name)
+            }
+        })
+        .collect();
+    if members.is_empty() {
+        bail!(
+            "None of the members of the workspace at {} are `cdylib` crates that wasm-pack can \
+             publish.",
+            root.display()
+        )
+    }
+    let members = manifest::dependency_order(members)?;
+
+    let mut outcomes = Vec::new();
+    for member in members {
+        PBAR.info(&format!("Publishing {}...", member.name));
+        let member_opts = PublishOptions {
+            path: Some(member.path.clone()),
+            packages: Vec::new(),
+            workspace: false,
+            ..opts.clone()
+        };
+        let outcome = match publish_package(member_opts) {
+            Ok(Outcome::Published(release)) if opts.dry_run => Some(format!(
+                "[dry run] would publish {}@{}",
+                release.name, release.version
+            )),
+            Ok(Outcome::Published(release)) => {
+                Some(format!("published {}@{}", release.name, release.version))
+            }
+            Ok(Outcome::Skipped(published)) => Some(format!("skipped, {}", published)),
+            Err(error) if opts.fail_fast => {
+                return Err(anyhow!("Failed to publish {}: {:#}", member.name, error));
+            }
+            Err(error) => {
+                PBAR.error(&format!("Failed to publish {}: {:#}", member.name, error));
+                None
+            }
+        };
+        outcomes.push((member.name, outcome));
+    }
+
+    let failed = report_members(&outcomes);
+    if !failed.is_empty() {
+        bail!("Failed to publish {}.", failed.join(", "))
+    }
+    Ok(())
+}
+
+fn publish_package(opts: PublishOptions) -> Result<Outcome> {
+    let PublishOptions {
+        target,
+        no_build,
+        access,
+        tag,
+        pkg_directory,
+        bump,
+        otp,
+        dry_run,
+        skip_existing,
+        yes,
+        provenance,
+        npm_args,
+        registry,
+        package_manager,
+        packages: _,
+        workspace: _,
+        fail_fast: _,
+        path,
+    } = opts;
+    let crate_path_given = path.is_some();
     let crate_path = get_crate_path(path)?;
+    if dry_run {
+        PBAR.info("[dry run] Going through the publish without publishing anything.");
+    }
 
     info!("Publishing the npm package...");
     info!("npm info located in the npm debug log");
 
-    let pkg_directory = match find_pkg_directory(&crate_path, &pkg_directory) {
-        Some(path) => Ok(path),
-        None => {
-            // while `wasm-pack publish`, if the pkg directory cannot be found,
-            // then try to `wasm-pack build`
-            if Confirm::new()
-                .with_prompt("Your package hasn't been built, build it?")
-                .interact()?
-            {
-                let out_dir = Input::new()
-                    .with_prompt("out_dir[default: pkg]")
-                    .default(".".to_string())
-                    .show_default(false)
-                    .interact()?;
-                let out_dir = format!("{}/pkg", out_dir);
-                let target = Select::new()
-                    .with_prompt("target[default: bundler]")
-                    .items(&["bundler", "nodejs", "web", "no-modules"])
-                    .default(0)
-                    .interact()?
-                    .to_string();
-                let target = Target::from_str(&target)?;
-                let build_opts = BuildOptions {
-                    path: Some(crate_path.clone()),
-                    target,
-                    out_dir: out_dir.clone(),
-                    ..Default::default()
-                };
-                Build::try_from_opts(build_opts)
-                    .and_then(|mut build| build.run())
-                    .map(|()| crate_path.join(out_dir))
-                    .map_err(|_| {
-                        anyhow!(
-                            "Unable to find the pkg directory at path '{:#?}',\
-                             or in a child directory of '{:#?}'",
-                            &crate_path,
-                            &crate_path
-                        )
-                    })
-            } else {
-                bail!(
-                    "Unable to find the pkg directory at path '{:#?}',\
-                     or in a child directory of '{:#?}'",
-                    &crate_path,
-                    &crate_path
-                )
+    let pkg_directory = built_pkg_directory(
+        &crate_path,
+        crate_path_given,
+        pkg_directory.as_deref(),
+        target,
+        no_build,
+    )?;
+    if let Some(bump) = bump {
+        let version = bump::bump_version(&crate_path, &pkg_directory, bump)?;
+        PBAR.info(&format!("Bumped the version to {}", version));
+    }
+    let registry = registry.or_else(|| npm::publish_config_registry(&pkg_directory));
+    if skip_existing {
+        let published =
+            npm::find_published_version(&pkg_directory, &crate_path, registry.as_deref())?;
+        if let Some(published) = published {
+            PBAR.info(&format!("{}, skipping the publish.", published));
+            return Ok(Outcome::Skipped(published));
+        }
+    } else {
+        npm::check_version_unpublished(&pkg_directory, &crate_path, registry.as_deref())?;
+    }
+    // npm may still find credentials that wasm-pack doesn't know about, and
+    // the dry run doesn't talk to the registry, so this only warns.
+    if let Err(e) = npmrc::check_registry_auth(&pkg_directory, &crate_path, registry.as_deref()) {
+        let prefix = if dry_run { "[dry run] " } else { "" };
+        PBAR.warn(&format!("{}{:#}", prefix, e));
+    }
+    let package_manager = PackageManager::choose(package_manager, &crate_path, &pkg_directory)?;
+    info!("Publishing with {}", package_manager);
+    let dist_tag = tag.clone().unwrap_or_else(|| "latest".to_string());
+    let release = Release::of_package(&pkg_directory, &crate_path, registry.as_deref())?;
+    review_tarball(&pkg_directory, package_manager, &release, dry_run, yes)?;
+    let args = npm::publish_args(access.as_ref(), tag.as_deref(), provenance, &npm_args);
+    npm::npm_publish(
+        &pkg_directory.to_string_lossy(),
+        package_manager,
+        registry.as_deref(),
+        &args,
+        otp,
+        dry_run,
+    )?;
+    if dry_run {
+        info!("The dry run of publishing your package succeeded.");
+        PBAR.info(&format!(
+            "[dry run] Your package would be published under the `{}` tag. Nothing was published.",
+            dist_tag
+        ));
+        return Ok(Outcome::Published(release));
+    }
+    info!("Published your package under the `{}` tag!", dist_tag);
+
+    PBAR.info(&format!(
+        "💥  published your package under the `{}` tag!",
+        dist_tag
+    ));
+    Ok(Outcome::Published(release))
+}
+
+/// Print what the tarball of the package in `pkg_directory` contains, warn
+/// about the files its users need that it leaves out, and ask whether to
+/// publish it as `release` with `package_manager`.
+///
+/// The contents are only listed when npm publishes, since the other package
+/// managers can pick other files than npm's dry run. Nothing is asked with
+/// `yes`, for a dry run, or without a terminal to ask on.
+fn review_tarball(
+    pkg_directory: &Path,
+    package_manager: PackageManager,
+    release: &Release,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let prefix = if dry_run { "[dry run] " } else { "" };
+    let contents = match package_manager {
+        PackageManager::Npm => TarballContents::of_package(pkg_directory),
+        _ => Err(anyhow!(
+            "wasm-pack can only list them with npm, and {} may pick other files",
+            package_manager
+        )),
+    };
+    match contents {
+        Ok(contents) => {
+            PBAR.info(&format!(
+                "{}The tarball of {}@{} contains {} files, {} unpacked:",
+                prefix,
+                release.name,
+                release.version,
+                contents.files.len(),
+                npm::format_size(contents.unpacked_size)
+            ));
+            for (file, size) in &contents.files {
+                PBAR.info(&format!(
+                    "{}  {:>9}  {}",
+                    prefix,
+                    npm::format_size(*size),
+                    file
+                ));
+            }
+            for missing in contents.missing_artifacts(pkg_directory)? {
+                PBAR.warn(&format!(
+                    "The tarball doesn't contain {}. Check the `files` in the package.json and \
+                     the `.npmignore`.",
+                    missing
+                ));
             }
         }
-    }?;
-    npm::npm_publish(&pkg_directory.to_string_lossy(), access, tag)?;
-    info!("Published your package!");
+        Err(error) => PBAR.warn(&format!(
+            "Could not list the contents of the tarball: {:#}",
+            error
+        )),
+    }
 
-    PBAR.info("💥  published your package!");
+    if yes || dry_run || !io::stdin().is_terminal() {
+        return Ok(());
+    }
+    let confirmed = Confirm::new()
+        .with_prompt(format!(
+            "Publish {}@{} to {}?",
+            release.name, release.version, release.registry
+        ))
+        .default(false)
+        .interact()?;
+    if !confirmed {
+        bail!("Not publishing {}@{}.", release.name, release.version)
+    }
     Ok(())
 }
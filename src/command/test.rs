@@ -2,21 +2,26 @@
 
 use crate::build;
 use crate::cache;
-use crate::command::utils::get_crate_path;
+use crate::child;
+use crate::command::utils::{get_crate_path, report_members};
 use crate::install::{self, InstallMode, Tool};
-use crate::lockfile::Lockfile;
+use crate::lockfile::{self, Lockfile};
 use crate::manifest;
-use crate::test::{self, webdriver};
-use anyhow::{bail, Result};
+use crate::pinned;
+use crate::test::{self, detect, detect::Environment, junit, webdriver, webdriver::WebDriver};
+use crate::PBAR;
+use anyhow::{bail, Context, Result};
 use binary_install::Cache;
 use clap::Args;
-use console::style;
 use log::info;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Instant;
+use url::Url;
 
-#[derive(Debug, Default, Args)]
+#[derive(Clone, Debug, Default, Args)]
 #[command(allow_hyphen_values = true, trailing_var_arg = true)]
 /// Everything required to configure the `wasm-pack test` command.
 pub struct TestOptions {
@@ -24,6 +29,19 @@ pub struct TestOptions {
     /// Run the tests in Node.js.
     pub node: bool,
 
+    #[clap(long = "node-arg", value_name = "ARG", allow_hyphen_values = true)]
+    /// An extra command line argument for node, such as
+    /// `--max-old-space-size=4096`, added to the `args` in
+    /// `[package.metadata.wasm-pack.test.node]` and passed in `NODE_OPTIONS`.
+    /// Can be given several times.
+    pub node_args: Vec<String>,
+
+    #[clap(long = "node-env", value_name = "KEY=VALUE", value_parser = test::parse_env_var)]
+    /// An extra environment variable for the Node.js tests, added to the
+    /// `env` in `[package.metadata.wasm-pack.test.node]`. Can be given
+    /// several times.
+    pub node_env: Vec<(String, String)>,
+
     #[clap(long = "firefox")]
     /// Run the tests in Firefox. This machine must have a Firefox installation.
     /// If the `geckodriver` WebDriver client is not on the `$PATH`, and not
@@ -33,9 +51,21 @@ pub struct TestOptions {
 
     #[clap(long = "geckodriver")]
     /// The path to the `geckodriver` WebDriver client for testing in
-    /// Firefox. Implies `--firefox`.
+    /// Firefox, used instead of `$GECKODRIVER`. Implies `--firefox`.
     pub geckodriver: Option<PathBuf>,
 
+    #[clap(long = "geckodriver-version")]
+    /// The version of `geckodriver` to download, e.g. `0.34.0`, instead of the
+    /// latest release. Overrides `geckodriver` in
+    /// `[package.metadata.wasm-pack]`.
+    pub geckodriver_version: Option<String>,
+
+    #[clap(long = "firefox-binary")]
+    /// The Firefox binary to test in, such as Firefox Nightly, instead of the
+    /// one `geckodriver` finds. Overrides `binary` in
+    /// `[package.metadata.wasm-pack.test.firefox]`. Implies `--firefox`.
+    pub firefox_binary: Option<PathBuf>,
+
     #[clap(long = "chrome")]
     /// Run the tests in Chrome. This machine must have a Chrome installation.
     /// If the `chromedriver` WebDriver client is not on the `$PATH`, and not
@@ -45,9 +75,16 @@ pub struct TestOptions {
 
     #[clap(long = "chromedriver")]
     /// The path to the `chromedriver` WebDriver client for testing in
-    /// Chrome. Implies `--chrome`.
+    /// Chrome, used instead of `$CHROMEDRIVER`. Implies `--chrome`.
     pub chromedriver: Option<PathBuf>,
 
+    #[clap(long = "chrome-binary")]
+    /// The Chrome binary to test in, such as Chrome Beta, instead of the
+    /// installed one. The downloaded `chromedriver` matches its version.
+    /// Overrides `binary` in `[package.metadata.wasm-pack.test.chrome]`.
+    /// Implies `--chrome`.
+    pub chrome_binary: Option<PathBuf>,
+
     #[clap(long = "safari")]
     /// Run the tests in Safari. This machine must have a Safari installation,
     /// and the `safaridriver` WebDriver client must either be on the `$PATH` or
@@ -57,9 +94,68 @@ pub struct TestOptions {
 
     #[clap(long = "safaridriver")]
     /// The path to the `safaridriver` WebDriver client for testing in
-    /// Safari. Implies `--safari`.
+    /// Safari, used instead of `$SAFARIDRIVER`. Implies `--safari`.
     pub safaridriver: Option<PathBuf>,
 
+    #[clap(long = "edge")]
+    /// Run the tests in Microsoft Edge. This machine must have an Edge
+    /// installation. If the `msedgedriver` WebDriver client is not on the
+    /// `$PATH`, and not specified with `--msedgedriver`, then `wasm-pack` will
+    /// download the one matching the installed Edge, on Windows and macOS.
+    pub edge: bool,
+
+    #[clap(long = "msedgedriver")]
+    /// The path to the `msedgedriver` WebDriver client for testing in
+    /// Edge, used instead of `$MSEDGEDRIVER`. Implies `--edge`.
+    pub msedgedriver: Option<PathBuf>,
+
+    #[clap(
+        long = "webdriver-url",
+        conflicts_with_all = ["geckodriver", "chromedriver", "safaridriver", "msedgedriver"]
+    )]
+    /// The URL of an already running WebDriver server, such as a Selenium
+    /// Grid, to run the browser tests with instead of a local WebDriver client.
+    /// Credentials go in the URL, or in `$WASM_PACK_WEBDRIVER_USER` and
+    /// `$WASM_PACK_WEBDRIVER_PASSWORD`.
+    pub webdriver_url: Option<String>,
+
+    #[clap(long = "keep-driver-alive", conflicts_with = "webdriver_url")]
+    /// Keep the WebDriver clients running after the tests, and run the tests
+    /// with the ones kept by an earlier run if they still answer, instead of
+    /// starting new ones each time.
+    pub keep_driver_alive: bool,
+
+    #[clap(long = "shutdown-driver")]
+    /// Stop the WebDriver clients kept running by `--keep-driver-alive`,
+    /// without running any tests.
+    pub shutdown_driver: bool,
+
+    #[clap(long = "webdriver-capabilities")]
+    /// A JSON file with the desired capabilities of the browser sessions,
+    /// overriding `webdriver-capabilities` in `[package.metadata.wasm-pack]`.
+    pub webdriver_capabilities: Option<PathBuf>,
+
+    #[clap(long = "browser-arg", allow_hyphen_values = true)]
+    /// An extra command line argument for Chrome, Firefox and Edge, such as
+    /// `--no-sandbox`, added to the `args` in
+    /// `[package.metadata.wasm-pack.test.<browser>]`. Can be given several
+    /// times.
+    pub browser_args: Vec<String>,
+
+    #[clap(long = "browser-capabilities", value_name = "BROWSER=FILE")]
+    /// A JSON file with capabilities for the sessions of a single browser,
+    /// e.g. `chrome=proxy.json`, merged into the other capabilities. Can be
+    /// given once per browser.
+    pub browser_capabilities: Vec<String>,
+
+    #[clap(
+        long = "auto",
+        conflicts_with_all = ["node", "firefox", "chrome", "safari", "edge"]
+    )]
+    /// Run the tests in every environment found on this machine: Node.js,
+    /// Chrome, Firefox, Safari and Edge.
+    pub auto: bool,
+
     #[clap(long = "headless")]
     /// When running browser tests, run the browser in headless mode without any
     /// UI or windows.
@@ -73,9 +169,104 @@ pub struct TestOptions {
     /// Build with the release profile.
     pub release: bool,
 
-    /// Path to the Rust crate, and extra options to pass to `cargo test`.
+    #[clap(long = "package", short = 'p', value_name = "MEMBER")]
+    /// Test the workspace member named `MEMBER`, from anywhere in the
+    /// workspace.
+    pub package: Option<String>,
+
+    #[clap(long = "workspace", conflicts_with = "package")]
+    /// Test every member of the workspace with a `wasm-bindgen-test`
+    /// dev-dependency, one after the other.
+    pub workspace: bool,
+
+    #[clap(long = "features", short = 'F')]
+    /// Space or comma separated list of features to enable, as for `cargo
+    /// test`. Can be given several times.
+    pub features: Vec<String>,
+
+    #[clap(long = "no-default-features")]
+    /// Do not enable the `default` feature.
+    pub no_default_features: bool,
+
+    #[clap(long = "all-features")]
+    /// Enable all the features of the crate.
+    pub all_features: bool,
+
+    #[clap(long = "timeout", value_name = "SECONDS", value_parser = parse_timeout)]
+    /// How many seconds the test runner waits for each test to finish before
+    /// failing it. This is a timeout per test, not for the whole suite. Sets
+    /// `WASM_BINDGEN_TEST_TIMEOUT` for the runner, and defaults to `timeout`
+    /// in `[package.metadata.wasm-pack.test]`.
+    pub timeout: Option<u64>,
+
+    #[clap(long = "startup-retries", value_name = "N", default_value_t = 2)]
+    /// How many more times to try when the WebDriver or the browser fails to
+    /// start, before giving up on the browser. Failing tests aren't retried.
+    pub startup_retries: u32,
+
+    #[clap(long = "junit", value_name = "PATH")]
+    /// Write the results of the tests to `PATH` as JUnit XML, with a test
+    /// suite for each environment the tests ran in.
+    pub junit: Option<PathBuf>,
+
+    #[clap(long = "fail-fast")]
+    /// Stop at the first environment whose tests fail, instead of running
+    /// the tests in the others too.
+    pub fail_fast: bool,
+
+    #[clap(long = "exact")]
+    /// Only run the tests whose names are exactly the given filters.
+    pub exact: bool,
+
+    #[clap(long = "ignored")]
+    /// Only run the tests marked `#[ignore]`, like `cargo test -- --ignored`.
+    /// Combines with test name filters. Needs wasm-bindgen 0.2.100 or later.
+    pub ignored: bool,
+
+    #[clap(long = "include-ignored", conflicts_with = "ignored")]
+    /// Run the tests marked `#[ignore]` along with the others, like `cargo
+    /// test -- --include-ignored`. Combines with test name filters. Needs
+    /// wasm-bindgen 0.2.93 or later.
+    pub include_ignored: bool,
+
+    #[clap(long = "nocapture")]
+    /// Show what the tests log to the console as they run, instead of only
+    /// the output of the tests that fail. Needs wasm-bindgen 0.2.100 or
+    /// later.
+    pub nocapture: bool,
+
+    #[clap(long = "list")]
+    /// Build the tests and print their names instead of running them, like
+    /// `cargo test -- --list`. Lists the tests for Node.js if no environment
+    /// is given. Needs wasm-bindgen 0.2.100 or later.
+    pub list: bool,
+
+    #[clap(
+        long = "shard",
+        value_name = "INDEX/TOTAL",
+        value_parser = test::parse_shard,
+        conflicts_with = "list"
+    )]
+    /// Only run the `INDEX`th of `TOTAL` parts of the tests, like `2/4`, to
+    /// split them between several machines. Every machine picks the same
+    /// parts. Needs wasm-bindgen 0.2.100 or later.
+    pub shard: Option<test::Shard>,
+
+    #[clap(long = "skip", value_name = "PATTERN")]
+    /// Skip the tests whose names contain `PATTERN`. Can be given several
+    /// times.
+    pub skip: Vec<String>,
+
+    /// Path to the Rust crate, test name filters, and extra options to pass
+    /// to `cargo test`.
     ///
     /// If the path is not provided, this command searches up the path from the current directory.
+    /// The first argument is only taken as the path if it is a directory with a `Cargo.toml`.
+    ///
+    /// Arguments that aren't `cargo test` options, or their values, are test name filters: only
+    /// the tests whose names contain one of them run. They, and anything after a second `--`,
+    /// are passed to the test runner rather than to `cargo`, as in
+    /// `wasm-pack test --node -- --features f1 filter -- --nocapture`.
     ///
     /// This is a workaround to allow wasm pack to provide the same command line interface as `cargo`.
     /// See <https://github.com/rustwasm/wasm-pack/pull/851> for more information.
@@ -88,15 +279,35 @@ pub struct Test {
     crate_data: manifest::CrateData,
     cache: Cache,
     node: bool,
+    node_args: Vec<String>,
+    node_env: Vec<(String, String)>,
     mode: InstallMode,
     firefox: bool,
-    geckodriver: Option<PathBuf>,
+    geckodriver: Option<WebDriver>,
+    geckodriver_version: Option<String>,
     chrome: bool,
-    chromedriver: Option<PathBuf>,
+    chromedriver: Option<WebDriver>,
+    chrome_binary: Option<PathBuf>,
     safari: bool,
-    safaridriver: Option<PathBuf>,
+    safaridriver: Option<WebDriver>,
+    edge: bool,
+    msedgedriver: Option<WebDriver>,
+    webdriver_url: Option<Url>,
+    keep_driver_alive: bool,
+    capabilities: BTreeMap<&'static str, serde_json::Value>,
+    extends_webdriver_json: bool,
     headless: bool,
     release: bool,
+    fail_fast: bool,
+    timeout: Option<u64>,
+    startup_retries: u32,
+    runner_flags: Vec<&'static str>,
+    shard: Option<test::Shard>,
+    /// Whether the shard to run has none of the tests.
+    shard_is_empty: bool,
+    junit: Option<PathBuf>,
+    junit_suites: Vec<junit::Suite>,
+    runner_output: Option<child::Output>,
     test_runner_path: Option<PathBuf>,
     extra_options: Vec<String>,
 }
@@ -108,38 +319,253 @@ impl Test {
     pub fn try_from_opts(test_opts: TestOptions) -> Result<Self> {
         let TestOptions {
             node,
+            node_args,
+            node_env,
             mode,
             headless,
             release,
+            features,
+            no_default_features,
+            all_features,
+            fail_fast,
+            timeout,
+            startup_retries,
+            junit,
             chrome,
             chromedriver,
+            chrome_binary,
             firefox,
             geckodriver,
+            geckodriver_version,
+            firefox_binary,
             safari,
             safaridriver,
-            mut path_and_extra_options,
+            edge,
+            msedgedriver,
+            webdriver_url,
+            keep_driver_alive,
+            shutdown_driver: _,
+            webdriver_capabilities,
+            browser_args,
+            browser_capabilities,
+            auto,
+            exact,
+            ignored,
+            include_ignored,
+            nocapture,
+            list,
+            shard,
+            skip,
+            package,
+            workspace: _,
+            path_and_extra_options,
         } = test_opts;
 
-        let first_arg_is_path = path_and_extra_options
-            .get(0)
-            .map(|first_arg| !first_arg.starts_with("-"))
-            .unwrap_or(false);
+        let (path, extra_options) = split_path_and_extra_options(path_and_extra_options)?;
+        let extra_options: Vec<String> =
+            test::cargo_feature_args(&features, no_default_features, all_features)
+                .into_iter()
+                .chain(extra_options)
+                .collect();
+        let runner_flags: Vec<&'static str> = [
+            ("--exact", exact),
+            ("--ignored", ignored),
+            ("--include-ignored", include_ignored),
+            ("--nocapture", nocapture),
+            ("--list", list),
+        ]
+        .iter()
+        .filter(|(_, given)| *given)
+        .map(|(flag, _)| *flag)
+        .collect();
+        let extra_options = test::cargo_test_args(&extra_options, &runner_flags, &skip);
+
+        let crate_path = get_crate_path(path)?;
+        let crate_path = match &package {
+            Some(name) => manifest::workspace_member(&crate_path, name)?,
+            None => crate_path,
+        };
+        lockfile::generate_if_missing(&crate_path, &extra_options)?;
+        let crate_data = manifest::CrateData::new(&crate_path, None)?;
 
-        let (path, extra_options) = if first_arg_is_path {
-            let path = PathBuf::from_str(&path_and_extra_options.remove(0))?;
-            let extra_options = path_and_extra_options;
+        let timeout = match (timeout, crate_data.test_timeout()) {
+            (Some(timeout), _) => Some(timeout),
+            (None, Some(0)) => bail!(
+                "invalid `timeout = 0` in `[package.metadata.wasm-pack.test]`, expected a \
+                 positive number of seconds"
+            ),
+            (None, timeout) => timeout,
+        };
 
-            (Some(path), extra_options)
+        let (node, chrome, firefox, safari, edge) = if auto {
+            let found = detect::detect_environments();
+            if found.is_empty() {
+                bail!(
+                    "`--auto` found none of Node.js, Chrome, Firefox, Safari or Edge on this \
+                     machine."
+                )
+            }
+            PBAR.info(&format!(
+                "Found {} on this machine.",
+                found
+                    .iter()
+                    .map(|environment| environment.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            (
+                found.contains(&Environment::Node),
+                found.contains(&Environment::Chrome),
+                found.contains(&Environment::Firefox),
+                found.contains(&Environment::Safari),
+                found.contains(&Environment::Edge),
+            )
         } else {
-            (None, path_and_extra_options)
+            (node, chrome, firefox, safari, edge)
         };
+        let chrome = chrome || chromedriver.is_some() || chrome_binary.is_some();
+        let firefox = firefox || geckodriver.is_some() || firefox_binary.is_some();
+        let safari = safari || safaridriver.is_some();
+        let edge = edge || msedgedriver.is_some();
+        let any_browser = chrome || firefox || safari || edge;
+        // Listing doesn't run the tests, so any environment lists them.
+        let node = node || (list && !any_browser);
 
-        let crate_path = get_crate_path(path)?;
-        let crate_data = manifest::CrateData::new(&crate_path, None)?;
-        let any_browser = chrome || firefox || safari;
+        let mut capabilities = match webdriver_capabilities {
+            Some(path) => Some(webdriver::read_capabilities(&path)?),
+            None => crate_data.webdriver_capabilities().cloned(),
+        }
+        .filter(|_| any_browser);
+
+        let takes_args = chrome || firefox || edge;
+        if !takes_args && !browser_args.is_empty() {
+            bail!("The `--browser-arg` flag only applies to Chrome, Firefox and Edge tests.")
+        }
+        let browsers: Vec<_> = [("chrome", chrome), ("firefox", firefox), ("edge", edge)]
+            .into_iter()
+            .filter(|(_, enabled)| *enabled)
+            .filter_map(|(browser, _)| {
+                let mut options = crate_data.browser_options(browser)?.clone();
+                let binary = match browser {
+                    "chrome" => chrome_binary.clone(),
+                    "firefox" => firefox_binary.clone(),
+                    _ => None,
+                };
+                options.binary = binary.or(options.binary);
+                Some((browser, options))
+            })
+            .collect();
+        if webdriver_url.is_none() {
+            for (browser, options) in &browsers {
+                if let Some(binary) = &options.binary {
+                    if !binary.is_file() {
+                        bail!(
+                            "Cannot test in {}: there is no browser binary at {}",
+                            browser,
+                            binary.display()
+                        )
+                    }
+                }
+            }
+        }
+        let chrome_binary = browsers
+            .iter()
+            .find(|(browser, _)| *browser == "chrome")
+            .and_then(|(_, options)| options.binary.clone())
+            .filter(|_| webdriver_url.is_none());
+        let mut extends_webdriver_json = webdriver::add_browser_options(
+            &mut capabilities,
+            &crate_path,
+            &browsers,
+            &browser_args,
+        )?;
+
+        let browser_capabilities = browser_capabilities
+            .iter()
+            .map(|arg| webdriver::read_browser_capabilities(arg))
+            .collect::<Result<Vec<_>>>()?;
+        let mut capabilities_per_browser = BTreeMap::new();
+        for (browser, enabled) in [
+            ("chrome", chrome),
+            ("firefox", firefox),
+            ("safari", safari),
+            ("edge", edge),
+        ] {
+            if !enabled {
+                continue;
+            }
+            let mut browser_caps = capabilities.clone();
+            for (_, extra) in browser_capabilities.iter().filter(|(b, _)| *b == browser) {
+                extends_webdriver_json |=
+                    webdriver::load_base_capabilities(&mut browser_caps, &crate_path)?;
+                webdriver::merge_capabilities(browser_caps.as_mut().unwrap(), extra.clone());
+            }
+            if let Some(browser_caps) = browser_caps {
+                log::debug!("WebDriver capabilities for {}: {}", browser, browser_caps);
+                capabilities_per_browser.insert(browser, browser_caps);
+            }
+        }
+
+        let webdriver_url = webdriver_url
+            .as_deref()
+            .map(webdriver::remote_webdriver_url)
+            .transpose()?;
+        let remote = webdriver_url.clone().map(WebDriver::Remote);
+        let remote = remote.as_ref();
+        let chromedriver =
+            given_driver(chrome, remote, "chromedriver", chromedriver, "CHROMEDRIVER")?;
+        let geckodriver = given_driver(firefox, remote, "geckodriver", geckodriver, "GECKODRIVER")?;
+        let safaridriver =
+            given_driver(safari, remote, "safaridriver", safaridriver, "SAFARIDRIVER")?;
+        let msedgedriver =
+            given_driver(edge, remote, "msedgedriver", msedgedriver, "MSEDGEDRIVER")?;
 
         if !node && !any_browser {
-            bail!("Must specify at least one of `--node`, `--chrome`, `--firefox`, or `--safari`")
+            let found = detect::detect_environments();
+            let found = if found.is_empty() {
+                "none of them were found on this machine".to_string()
+            } else {
+                format!(
+                    "this machine has {}",
+                    found
+                        .iter()
+                        .map(|environment| format!("{} (`{}`)", environment, environment.flag()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            bail!(
+                "Must specify at least one of `--node`, `--chrome`, `--firefox`, `--safari`, or \
+                 `--edge`, or `--auto` to run the tests in all of the ones installed; {}.",
+                found
+            )
+        }
+
+        if safari && safaridriver.is_none() {
+            webdriver::check_safari_host()?;
+        }
+
+        if edge && msedgedriver.is_none() {
+            webdriver::check_edge_host()?;
+        }
+
+        if !node && (!node_args.is_empty() || !node_env.is_empty()) {
+            bail!("The `--node-arg` and `--node-env` flags only apply to Node.js tests.")
+        }
+        let node_options = crate_data.node_options();
+        let node_args: Vec<String> = node_options.args.iter().cloned().chain(node_args).collect();
+        for arg in &node_args {
+            test::check_node_value("The node argument", arg)?;
+        }
+        let mut env = node_options.env.clone();
+        env.extend(node_env);
+        let node_env: Vec<(String, String)> = env.into_iter().collect();
+        for (key, value) in &node_env {
+            test::check_node_value("The environment variable", &format!("{}={}", key, value))?;
+        }
+
+        if keep_driver_alive && !any_browser {
+            bail!("The `--keep-driver-alive` flag only applies to browser tests.")
         }
 
         if headless && !any_browser {
@@ -154,15 +580,34 @@ impl Test {
             crate_path,
             crate_data,
             node,
+            node_args,
+            node_env,
             mode,
             chrome,
             chromedriver,
+            chrome_binary,
             firefox,
             geckodriver,
+            geckodriver_version,
             safari,
             safaridriver,
+            edge,
+            msedgedriver,
+            webdriver_url,
+            keep_driver_alive,
+            capabilities: capabilities_per_browser,
+            extends_webdriver_json,
             headless,
             release,
+            fail_fast,
+            timeout,
+            startup_retries,
+            runner_flags,
+            shard,
+            shard_is_empty: false,
+            junit,
+            junit_suites: Vec::new(),
+            runner_output: None,
             test_runner_path: None,
             extra_options,
         })
@@ -178,15 +623,113 @@ impl Test {
         let process_steps = self.get_process_steps();
 
         let started = Instant::now();
-        for (_, process_step) in process_steps {
-            process_step(&mut self)?;
+        // Each environment runs even if the tests failed in an earlier one,
+        // unless there are no others or `--fail-fast` was passed.
+        let fail_fast = self.fail_fast || self.environments().len() < 2;
+        let mut results: Vec<(Environment, Option<anyhow::Error>)> = Vec::new();
+        for (name, process_step) in process_steps {
+            if self.shard_is_empty {
+                break;
+            }
+            let environment = match step_environment(name) {
+                Some(environment) => environment,
+                None => {
+                    process_step(&mut self)?;
+                    continue;
+                }
+            };
+            if results.iter().any(|(failed, _)| *failed == environment) {
+                continue;
+            }
+            if self.junit.is_some() {
+                self.runner_output = Some(child::Output::default());
+            }
+            let step_started = Instant::now();
+            match process_step(&mut self) {
+                Ok(()) if name.starts_with("step_test_") => {
+                    self.report(environment, step_started, None);
+                    results.push((environment, None));
+                }
+                Ok(()) => {}
+                Err(error) if fail_fast => {
+                    self.report(environment, step_started, Some(&error));
+                    if let Err(report_error) = self.write_junit() {
+                        PBAR.warn(&format!("{:#}", report_error));
+                    }
+                    return Err(error);
+                }
+                Err(error) => {
+                    PBAR.error(&format!("The tests in {} failed: {:#}", environment, error));
+                    self.report(environment, step_started, Some(&error));
+                    results.push((environment, Some(error)));
+                }
+            }
         }
+        self.write_junit()?;
         let duration = crate::command::utils::elapsed(started.elapsed());
         info!("Done in {}.", &duration);
 
+        for (environment, error) in &results {
+            let outcome = if error.is_none() { "passed" } else { "failed" };
+            PBAR.info(&format!("{:<8} {}", environment.to_string(), outcome));
+        }
+        let failed: Vec<String> = results
+            .iter()
+            .filter(|(_, error)| error.is_some())
+            .map(|(environment, _)| environment.to_string())
+            .collect();
+        if !failed.is_empty() {
+            bail!("The tests failed in {}.", failed.join(", "))
+        }
+
         Ok(())
     }
 
+    /// Add the results of the tests in `environment`, which started running
+    /// at `started`, to the JUnit report, if one was asked for.
+    fn report(
+        &mut self,
+        environment: Environment,
+        started: Instant,
+        error: Option<&anyhow::Error>,
+    ) {
+        if self.junit.is_none() {
+            return;
+        }
+        let output = self.runner_output.take().unwrap_or_default();
+        self.junit_suites.push(junit::Suite::new(
+            &environment.to_string(),
+            &output.stdout,
+            &output.stderr,
+            started.elapsed(),
+            error,
+        ));
+    }
+
+    /// Write the JUnit report, if one was asked for.
+    fn write_junit(&self) -> Result<()> {
+        if let Some(path) = &self.junit {
+            junit::write(path, &self.junit_suites)?;
+            info!("Wrote the JUnit report to {}", path.display());
+        }
+        Ok(())
+    }
+
+    /// The environments that the tests run in.
+    fn environments(&self) -> Vec<Environment> {
+        Environment::ALL
+            .iter()
+            .copied()
+            .filter(|environment| match environment {
+                Environment::Node => self.node,
+                Environment::Chrome => self.chrome,
+                Environment::Firefox => self.firefox,
+                Environment::Safari => self.safari,
+                Environment::Edge => self.edge,
+            })
+            .collect()
+    }
+
     fn get_process_steps(&self) -> Vec<(&'static str, TestStep)> {
         macro_rules! steps {
             ($($name:ident $(if $e:expr)* ),+) => {
@@ -206,8 +749,10 @@ impl Test {
             InstallMode::Normal => steps![
                 step_check_rustc_version,
                 step_check_for_wasm_target,
+                step_check_webdriver_url if self.webdriver_url.is_some(),
                 step_build_tests,
                 step_install_wasm_bindgen,
+                step_select_shard if self.shard.is_some(),
                 step_test_node if self.node,
                 step_get_chromedriver if self.chrome && self.chromedriver.is_none(),
                 step_test_chrome if self.chrome,
@@ -215,11 +760,15 @@ impl Test {
                 step_test_firefox if self.firefox,
                 step_get_safaridriver if self.safari && self.safaridriver.is_none(),
                 step_test_safari if self.safari,
+                step_get_msedgedriver if self.edge && self.msedgedriver.is_none(),
+                step_test_edge if self.edge,
             ],
             InstallMode::Force => steps![
                 step_check_for_wasm_target,
+                step_check_webdriver_url if self.webdriver_url.is_some(),
                 step_build_tests,
                 step_install_wasm_bindgen,
+                step_select_shard if self.shard.is_some(),
                 step_test_node if self.node,
                 step_get_chromedriver if self.chrome && self.chromedriver.is_none(),
                 step_test_chrome if self.chrome,
@@ -227,10 +776,14 @@ impl Test {
                 step_test_firefox if self.firefox,
                 step_get_safaridriver if self.safari && self.safaridriver.is_none(),
                 step_test_safari if self.safari,
+                step_get_msedgedriver if self.edge && self.msedgedriver.is_none(),
+                step_test_edge if self.edge,
             ],
             InstallMode::Noinstall => steps![
+                step_check_webdriver_url if self.webdriver_url.is_some(),
                 step_build_tests,
                 step_install_wasm_bindgen,
+                step_select_shard if self.shard.is_some(),
                 step_test_node if self.node,
                 step_get_chromedriver if self.chrome && self.chromedriver.is_none(),
                 step_test_chrome if self.chrome,
@@ -238,6 +791,8 @@ impl Test {
                 step_test_firefox if self.firefox,
                 step_get_safaridriver if self.safari && self.safaridriver.is_none(),
                 step_test_safari if self.safari,
+                step_get_msedgedriver if self.edge && self.msedgedriver.is_none(),
+                step_test_edge if self.edge,
             ],
         }
     }
@@ -249,6 +804,17 @@ impl Test {
         Ok(())
     }
 
+    fn step_check_webdriver_url(&mut self) -> Result<()> {
+        let url = self.webdriver_url.as_ref().unwrap();
+        info!(
+            "Checking the WebDriver at {}...",
+            url.host_str().unwrap_or_default()
+        );
+        webdriver::check_remote_webdriver(url)?;
+        info!("The WebDriver is reachable.");
+        Ok(())
+    }
+
     fn step_check_for_wasm_target(&mut self) -> Result<()> {
         info!("Adding wasm-target...");
         build::wasm_target::check_for_wasm32_target()?;
@@ -275,21 +841,17 @@ impl Test {
 
     fn step_install_wasm_bindgen(&mut self) -> Result<()> {
         info!("Identifying wasm-bindgen dependency...");
-        let lockfile = Lockfile::new(&self.crate_data)?;
-        let bindgen_version = lockfile.require_wasm_bindgen()?;
-
-        // Unlike `wasm-bindgen` and `wasm-bindgen-cli`, `wasm-bindgen-test`
-        // will work with any semver compatible `wasm-bindgen-cli`, so just make
-        // sure that it is depended upon, so we can run tests on
-        // `wasm32-unkown-unknown`. Don't enforce that it is the same version as
-        // `wasm-bindgen`.
-        if lockfile.wasm_bindgen_test_version().is_none() {
-            bail!(
-                "Ensure that you have \"{}\" as a dependency in your Cargo.toml file:\n\
-                 [dev-dependencies]\n\
-                 wasm-bindgen-test = \"0.2\"",
-                style("wasm-bindgen-test").bold().dim(),
-            )
+        let lockfile = Lockfile::for_tests(&self.crate_data)?;
+        let bindgen_version =
+            pinned::wasm_bindgen_version(&self.crate_path, &self.crate_data, &lockfile)?;
+
+        // The test runner comes with the `wasm-bindgen` CLI, so make sure
+        // that it matches the `wasm-bindgen-test` harness the tests were
+        // built with before installing it.
+        lockfile.require_wasm_bindgen_test(&bindgen_version)?;
+        test::check_runner_flags(&bindgen_version, &self.runner_flags)?;
+        if self.shard.is_some() {
+            test::check_runner_flags(&bindgen_version, &["--list"])?;
         }
 
         let status = install::download_prebuilt_or_cargo_install(
@@ -297,6 +859,9 @@ impl Test {
             &self.cache,
             &bindgen_version,
             self.mode.install_permitted(),
+            &[],
+            None,
+            Some(&self.crate_path),
         )?;
 
         self.test_runner_path = match status {
@@ -308,20 +873,65 @@ impl Test {
         Ok(())
     }
 
+    fn step_select_shard(&mut self) -> Result<()> {
+        let shard = self.shard.unwrap();
+        info!("Listing the tests to pick shard {}...", shard);
+        let test_runner = self.test_runner_path.as_ref().unwrap();
+        let envs = [(
+            "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
+            test_runner.display().to_string(),
+        )];
+        let mut names =
+            test::cargo_test_list(&self.crate_path, self.release, envs, &self.extra_options)?;
+        // Every test binary lists its own tests, and may share names with
+        // the others.
+        names.sort();
+        names.dedup();
+        let selected = shard.select(&names);
+        PBAR.info(&format!(
+            "Running {} of the {} tests in shard {}",
+            selected.len(),
+            names.len(),
+            shard
+        ));
+        if selected.is_empty() {
+            self.shard_is_empty = true;
+            return Ok(());
+        }
+        self.extra_options = test::shard_args(&self.extra_options, &names, &selected);
+        Ok(())
+    }
+
     fn step_test_node(&mut self) -> Result<()> {
         assert!(self.node);
         info!("Running tests in node...");
+        let test_runner = self.test_runner_path.as_ref().unwrap();
+        let mut envs = vec![
+            (
+                "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER".to_string(),
+                test_runner.display().to_string(),
+            ),
+            ("WASM_BINDGEN_TEST_ONLY_NODE".to_string(), "1".to_string()),
+        ];
+        envs.extend(self.timeout_env());
+        envs.extend(self.node_env.iter().cloned());
+        let existing = env::var("NODE_OPTIONS").ok();
+        match test::node_options(existing.as_deref(), &self.node_args)? {
+            Some(node_options) => {
+                log::debug!(
+                    "The test runner runs `node` with NODE_OPTIONS={}",
+                    node_options
+                );
+                envs.push(("NODE_OPTIONS".to_string(), node_options));
+            }
+            None => log::debug!("The test runner runs `node` without extra arguments"),
+        }
         test::cargo_test_wasm(
             &self.crate_path,
             self.release,
-            vec![
-                (
-                    "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER",
-                    &**self.test_runner_path.as_ref().unwrap(),
-                ),
-                ("WASM_BINDGEN_TEST_ONLY_NODE", "1".as_ref()),
-            ],
+            envs,
             &self.extra_options,
+            self.runner_output.as_mut(),
         )?;
         info!("Finished running tests in node.");
         Ok(())
@@ -330,85 +940,321 @@ impl Test {
     fn step_get_chromedriver(&mut self) -> Result<()> {
         assert!(self.chrome && self.chromedriver.is_none());
 
-        self.chromedriver = Some(webdriver::get_or_install_chromedriver(
+        self.chromedriver = Some(WebDriver::Local(webdriver::get_or_install_chromedriver(
             &self.cache,
             self.mode,
-        )?);
+            self.crate_data.chromedriver_version(),
+            self.chrome_binary.as_deref(),
+        )?));
         Ok(())
     }
 
     fn step_test_chrome(&mut self) -> Result<()> {
-        let chromedriver = self.chromedriver.as_ref().unwrap().display().to_string();
-        let chromedriver = chromedriver.as_str();
-        info!(
-            "Running tests in Chrome with chromedriver at {}",
-            chromedriver
-        );
+        let chromedriver = self.driver(self.chromedriver.as_ref().unwrap(), "chromedriver")?;
+        info!("Running tests in Chrome with {}", chromedriver);
 
         let mut envs = self.webdriver_env();
-        envs.push(("CHROMEDRIVER", chromedriver));
+        envs.push(chromedriver.env("CHROMEDRIVER"));
+        let _capabilities = self.write_capabilities("chrome")?;
 
-        test::cargo_test_wasm(&self.crate_path, self.release, envs, &self.extra_options)?;
+        self.cargo_test_in_browser("Chrome", &envs)?;
         Ok(())
     }
 
     fn step_get_geckodriver(&mut self) -> Result<()> {
         assert!(self.firefox && self.geckodriver.is_none());
 
-        self.geckodriver = Some(webdriver::get_or_install_geckodriver(
+        let pinned_version = self
+            .geckodriver_version
+            .as_deref()
+            .or_else(|| self.crate_data.geckodriver_version());
+        self.geckodriver = Some(WebDriver::Local(webdriver::get_or_install_geckodriver(
             &self.cache,
             self.mode,
-        )?);
+            pinned_version,
+        )?));
         Ok(())
     }
 
     fn step_test_firefox(&mut self) -> Result<()> {
-        let geckodriver = self.geckodriver.as_ref().unwrap().display().to_string();
-        let geckodriver = geckodriver.as_str();
-        info!(
-            "Running tests in Firefox with geckodriver at {}",
-            geckodriver
-        );
+        let geckodriver = self.driver(self.geckodriver.as_ref().unwrap(), "geckodriver")?;
+        info!("Running tests in Firefox with {}", geckodriver);
 
         let mut envs = self.webdriver_env();
-        envs.push(("GECKODRIVER", geckodriver));
+        envs.push(geckodriver.env("GECKODRIVER"));
+        let _capabilities = self.write_capabilities("firefox")?;
 
-        test::cargo_test_wasm(&self.crate_path, self.release, envs, &self.extra_options)?;
+        self.cargo_test_in_browser("Firefox", &envs)?;
         Ok(())
     }
 
     fn step_get_safaridriver(&mut self) -> Result<()> {
         assert!(self.safari && self.safaridriver.is_none());
 
-        self.safaridriver = Some(webdriver::get_safaridriver()?);
+        self.safaridriver = Some(WebDriver::Local(webdriver::get_safaridriver()?));
         Ok(())
     }
 
     fn step_test_safari(&mut self) -> Result<()> {
-        let safaridriver = self.safaridriver.as_ref().unwrap().display().to_string();
-        let safaridriver = safaridriver.as_str();
-        info!(
-            "Running tests in Safari with safaridriver at {}",
-            safaridriver
+        let safaridriver = self.driver(self.safaridriver.as_ref().unwrap(), "safaridriver")?;
+        info!("Running tests in Safari with {}", safaridriver);
+
+        let mut envs = self.webdriver_env();
+        envs.push(safaridriver.env("SAFARIDRIVER"));
+        let _capabilities = self.write_capabilities("safari")?;
+
+        // Safari refuses to be automated until remote automation is enabled,
+        // which shows up as `safaridriver` failing to start a session, so its
+        // output is kept to tell that apart from failing tests.
+        let mut output = child::Output::default();
+        let kept = self.runner_output.as_mut().unwrap_or(&mut output);
+        let result = test::cargo_test_wasm_in_browser(
+            &self.crate_path,
+            self.release,
+            &envs,
+            &self.extra_options,
+            "Safari",
+            self.startup_retries,
+            Some(&mut *kept),
         );
+        match result {
+            Err(error) if webdriver::failed_to_start(&kept.stderr) => Err(error.context(format!(
+                "safaridriver failed to start Safari. If it has never been enabled on this \
+                 machine, run `{}` once and try again.",
+                webdriver::SAFARIDRIVER_ENABLE
+            ))),
+            result => result,
+        }
+    }
+
+    fn step_get_msedgedriver(&mut self) -> Result<()> {
+        assert!(self.edge && self.msedgedriver.is_none());
+
+        self.msedgedriver = Some(WebDriver::Local(webdriver::get_or_install_msedgedriver(
+            &self.cache,
+            self.mode,
+            self.crate_data.msedgedriver_version(),
+        )?));
+        Ok(())
+    }
+
+    fn step_test_edge(&mut self) -> Result<()> {
+        let msedgedriver = self.driver(self.msedgedriver.as_ref().unwrap(), "msedgedriver")?;
+        info!("Running tests in Edge with {}", msedgedriver);
 
         let mut envs = self.webdriver_env();
-        envs.push(("SAFARIDRIVER", safaridriver));
+        envs.push(msedgedriver.env("MSEDGEDRIVER"));
+        let _capabilities = self.write_capabilities("edge")?;
 
-        test::cargo_test_wasm(&self.crate_path, self.release, envs, &self.extra_options)?;
+        self.cargo_test_in_browser("Edge", &envs)?;
         Ok(())
     }
 
-    fn webdriver_env(&self) -> Vec<(&'static str, &str)> {
+    /// The WebDriver to run the browser tests with: `driver`, or with
+    /// `--keep-driver-alive` the `name` started from it that is kept running.
+    fn driver(&self, driver: &WebDriver, name: &str) -> Result<WebDriver> {
+        match driver {
+            WebDriver::Local(path) if self.keep_driver_alive => Ok(WebDriver::Remote(
+                webdriver::kept_driver(&self.cache, name, path)?,
+            )),
+            driver => Ok(driver.clone()),
+        }
+    }
+
+    /// Run the tests in `browser`, trying again if it doesn't start.
+    fn cargo_test_in_browser(&mut self, browser: &str, envs: &[(String, String)]) -> Result<()> {
+        test::cargo_test_wasm_in_browser(
+            &self.crate_path,
+            self.release,
+            envs,
+            &self.extra_options,
+            browser,
+            self.startup_retries,
+            self.runner_output.as_mut(),
+        )
+    }
+
+    /// Hand the capabilities of `browser` to the test runner until the
+    /// returned guard is dropped.
+    fn write_capabilities(&self, browser: &str) -> Result<Option<webdriver::Capabilities>> {
+        let capabilities = match self.capabilities.get(browser) {
+            Some(capabilities) => capabilities,
+            None => return Ok(None),
+        };
+        Ok(Some(if self.extends_webdriver_json {
+            webdriver::extend_capabilities(&self.crate_path, capabilities)?
+        } else {
+            webdriver::write_capabilities(&self.crate_path, capabilities)?
+        }))
+    }
+
+    fn webdriver_env(&self) -> Vec<(String, String)> {
         let test_runner = self.test_runner_path.as_ref().unwrap().to_str().unwrap();
         info!("Using wasm-bindgen test runner at {}", test_runner);
         let mut envs = vec![
-            ("CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER", test_runner),
-            ("WASM_BINDGEN_TEST_ONLY_WEB", "1"),
+            (
+                "CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER".to_string(),
+                test_runner.to_string(),
+            ),
+            ("WASM_BINDGEN_TEST_ONLY_WEB".to_string(), "1".to_string()),
         ];
         if !self.headless {
-            envs.push(("NO_HEADLESS", "1"));
+            envs.push(("NO_HEADLESS".to_string(), "1".to_string()));
         }
+        envs.extend(self.timeout_env());
         envs
     }
+
+    /// The environment giving the test runner the timeout per test, if any.
+    fn timeout_env(&self) -> Option<(String, String)> {
+        let timeout = self.timeout?;
+        info!("Using a timeout of {} seconds per test", timeout);
+        Some(("WASM_BINDGEN_TEST_TIMEOUT".to_string(), timeout.to_string()))
+    }
+}
+
+/// Stop the WebDriver clients kept running by `--keep-driver-alive`.
+pub fn shutdown_drivers() -> Result<()> {
+    let cache = cache::get_wasm_pack_cache()?;
+    match webdriver::shutdown_kept_drivers(&cache)? {
+        0 => PBAR.info("No WebDriver clients were kept running."),
+        1 => PBAR.info("Stopped the WebDriver client kept running."),
+        stopped => PBAR.info(&format!(
+            "Stopped the {} WebDriver clients kept running.",
+            stopped
+        )),
+    }
+    Ok(())
+}
+
+/// Test every member of the workspace with a `wasm-bindgen-test`
+/// dev-dependency, one after the other, as configured by its own manifest.
+///
+/// A member whose tests fail doesn't stop the others, unless `--fail-fast`
+/// was passed. Each member's outcome is reported at the end.
+pub fn test_workspace(test_opts: TestOptions) -> Result<()> {
+    let (path, extra_options) =
+        split_path_and_extra_options(test_opts.path_and_extra_options.clone())?;
+    let root = get_crate_path(path)?;
+    let members: Vec<_> = manifest::workspace_members(&root)?
+        .into_iter()
+        .filter(|member| member.has_wasm_bindgen_test)
+        .collect();
+    if members.is_empty() {
+        bail!(
+            "None of the members of the workspace at {} have a `wasm-bindgen-test` \
+             dev-dependency, so there are no tests to run.",
+            root.display()
+        )
+    }
+
+    let mut outcomes = Vec::new();
+    for member in members {
+        PBAR.info(&format!("Testing {}...", member.name));
+        let member_opts = TestOptions {
+            workspace: false,
+            junit: test_opts
+                .junit
+                .as_deref()
+                .map(|path| member_report_path(path, &member.name)),
+            path_and_extra_options: std::iter::once(member.path.display().to_string())
+                .chain(extra_options.iter().cloned())
+                .collect(),
+            ..test_opts.clone()
+        };
+        match Test::try_from_opts(member_opts).and_then(|t| t.run()) {
+            Ok(()) => outcomes.push((member.name, Some("passed".to_string()))),
+            Err(error) if test_opts.fail_fast => return Err(error),
+            Err(error) => {
+                PBAR.error(&format!("The tests of {} failed: {:#}", member.name, error));
+                outcomes.push((member.name, None));
+            }
+        }
+    }
+
+    let failed = report_members(&outcomes);
+    if !failed.is_empty() {
+        bail!("The tests of {} failed.", failed.join(", "))
+    }
+    Ok(())
+}
+
+/// Where the JUnit report of the workspace member `name` goes, e.g.
+/// `report-name.xml` for `--junit report.xml`.
+fn member_report_path(path: &Path, name: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, name, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, name),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Parse the `--timeout` of a positive number of seconds.
+fn parse_timeout(value: &str) -> Result<u64> {
+    match value.parse() {
+        Ok(0) | Err(_) => bail!("expected a positive number of seconds, not {:?}", value),
+        Ok(seconds) => Ok(seconds),
+    }
+}
+
+/// Split the crate path, if it was given, off the extra options. The first
+/// argument is only the path if it is a directory with a `Cargo.toml`.
+fn split_path_and_extra_options(
+    mut path_and_extra_options: Vec<String>,
+) -> Result<(Option<PathBuf>, Vec<String>)> {
+    let first_arg_is_path = path_and_extra_options
+        .get(0)
+        .map(|first_arg| {
+            !first_arg.starts_with("-") && Path::new(first_arg).join("Cargo.toml").is_file()
+        })
+        .unwrap_or(false);
+
+    if first_arg_is_path {
+        let path = PathBuf::from_str(&path_and_extra_options.remove(0))?;
+        Ok((Some(path), path_and_extra_options))
+    } else {
+        Ok((None, path_and_extra_options))
+    }
+}
+
+/// The environment that the step `name` belongs to, if it belongs to one.
+fn step_environment(name: &str) -> Option<Environment> {
+    match name {
+        "step_test_node" => Some(Environment::Node),
+        "step_get_chromedriver" | "step_test_chrome" => Some(Environment::Chrome),
+        "step_get_geckodriver" | "step_test_firefox" => Some(Environment::Firefox),
+        "step_get_safaridriver" | "step_test_safari" => Some(Environment::Safari),
+        "step_get_msedgedriver" | "step_test_edge" => Some(Environment::Edge),
+        _ => None,
+    }
+}
+
+/// Get the `remote` WebDriver if there is one, or else the `name` driver the
+/// user passed with its flag or, failing that, in the `env_var` environment
+/// variable, if the browser is tested at all.
+fn given_driver(
+    browser: bool,
+    remote: Option<&WebDriver>,
+    name: &str,
+    flag: Option<PathBuf>,
+    env_var: &str,
+) -> Result<Option<WebDriver>> {
+    if !browser {
+        return Ok(None);
+    }
+    if let Some(remote) = remote {
+        return Ok(Some(remote.clone()));
+    }
+    let (path, source) = match flag {
+        Some(path) => (path, format!("from `--{}`", name)),
+        None => match env::var_os(env_var) {
+            Some(path) if !path.is_empty() => (PathBuf::from(path), format!("from ${}", env_var)),
+            _ => return Ok(None),
+        },
+    };
+    webdriver::check_driver(name, &path)
+        .with_context(|| format!("Cannot use the `{}` {}", name, source))?;
+    webdriver::announce_driver(name, &path, &source);
+    Ok(Some(WebDriver::Local(path)))
 }
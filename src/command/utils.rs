@@ -1,7 +1,14 @@
 //! Utility functions for commands.
 #![allow(clippy::redundant_closure)]
 
-use anyhow::Result;
+use crate::command::build::{Build, BuildOptions, Target};
+use crate::command::Command;
+use crate::fingerprint;
+use crate::rollback;
+use crate::{Cli, PBAR};
+use anyhow::{bail, Result};
+use clap::Parser;
+use path_clean::PathClean;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
@@ -60,6 +67,232 @@ fn is_pkg_directory(path: &Path, pkg_directory: &Path) -> bool {
     path.exists() && path.is_dir() && path.ends_with(pkg_directory)
 }
 
+/// Locates the pkg directory that `pack` and `publish` work on, and says
+/// where it is.
+///
+/// That's `pkg_directory` relative to the crate, if `crate_path_given` and it
+/// is there, or else relative to the current directory, or `crate_path`
+/// itself, if it has a `package.json`. Otherwise it is searched for in the
+/// crate, and built first with `wasm-pack build --target <target>` if it
+/// hasn't been built.
+///
+/// A pkg directory left incomplete by a failed build is built again with the
+/// arguments of that build, and one older than the crate's sources is
+/// rebuilt with the arguments of the build that wrote it. If they weren't
+/// recorded, the former is refused and the latter only warned about. With
+/// `no_build`, the pkg directory is used as it is.
+pub fn built_pkg_directory(
+    crate_path: &Path,
+    crate_path_given: bool,
+    pkg_directory: Option<&Path>,
+    target: Option<Target>,
+    no_build: bool,
+) -> Result<PathBuf> {
+    let dir = match given_pkg_directory(crate_path, crate_path_given, pkg_directory)? {
+        Some(dir) => dir,
+        None => crate_pkg_directory(
+            crate_path,
+            pkg_directory.unwrap_or_else(|| Path::new("pkg")),
+            target,
+            no_build,
+        )?,
+    };
+    let dir = std::env::current_dir()?.join(dir).clean();
+    rollback::check_complete(&dir)?;
+    PBAR.info(&format!("📦  Using the package in {}", dir.display()));
+    Ok(dir)
+}
+
+/// The package directory given with `--pkg-dir`, or as the path, if it is
+/// one: a directory with a `package.json`.
+fn given_pkg_directory(
+    crate_path: &Path,
+    crate_path_given: bool,
+    pkg_directory: Option<&Path>,
+) -> Result<Option<PathBuf>> {
+    // With the crate given as the path, `--pkg-dir` is looked for in it first.
+    let in_crate = pkg_directory
+        .filter(|_| crate_path_given)
+        .map(|dir| crate_path.join(dir))
+        .filter(|dir| dir.is_dir());
+    let dir = match in_crate.as_deref().or(pkg_directory) {
+        Some(dir) if dir.is_dir() => dir,
+        _ if !crate_path.join("Cargo.toml").is_file()
+            && crate_path.join("package.json").is_file() =>
+        {
+            crate_path
+        }
+        _ => return Ok(None),
+    };
+    if !dir.join("package.json").is_file() {
+        // Maybe it is only the name of the pkg directory in the crate.
+        if pkg_directory.is_some_and(|given| given.components().count() == 1) {
+            return Ok(None);
+        }
+        bail!(
+            "{} has no package.json, so it isn't a package built by wasm-pack",
+            dir.display()
+        );
+    }
+    let has_wasm = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .any(|entry| entry.path().extension().is_some_and(|ext| ext == "wasm"));
+    if !has_wasm {
+        PBAR.warn(&format!(
+            "{} has no .wasm file, so it may not be a package built by wasm-pack",
+            dir.display()
+        ));
+    }
+    Ok(Some(dir.to_owned()))
+}
+
+/// Locates the pkg directory in the crate, building it first if needed.
+fn crate_pkg_directory(
+    crate_path: &Path,
+    pkg_directory: &Path,
+    target: Option<Target>,
+    no_build: bool,
+) -> Result<PathBuf> {
+    let is_crate = crate_path.join("Cargo.toml").is_file();
+    let default_dir = crate_path.join(pkg_directory);
+    if rollback::is_incomplete(&default_dir) && is_crate && !no_build {
+        if let Some(args) = fingerprint::saved_build_args(&default_dir) {
+            PBAR.info(&format!(
+                "🏗️  {} was left incomplete by a failed build, building it again with `wasm-pack build {}`",
+                default_dir.display(),
+                args.join(" ")
+            ));
+            return build_pkg_directory(crate_path, pkg_directory, &args);
+        }
+        // How the failed build was run isn't known, so it can't be repeated.
+        rollback::check_complete(&default_dir)?;
+    }
+    let found = find_pkg_directory(crate_path, pkg_directory)
+        .filter(|dir| dir.join("package.json").is_file());
+    let dir = match found {
+        Some(dir) => dir,
+        None if no_build || !is_crate => {
+            rollback::check_complete(&default_dir)?;
+            bail!(
+                "Unable to find the pkg directory at path {:#?}, or in a child directory of {:#?}",
+                crate_path,
+                crate_path
+            )
+        }
+        None => {
+            let target = target.unwrap_or_default();
+            PBAR.info(&format!(
+                "🏗️  {} hasn't been built yet, building it first with `wasm-pack build --target {}`",
+                crate_path.join(pkg_directory).display(),
+                target
+            ));
+            return build_pkg_directory(
+                crate_path,
+                pkg_directory,
+                &["--target".to_string(), target.to_string()],
+            );
+        }
+    };
+    if no_build || !is_crate {
+        return Ok(dir);
+    }
+    let source = match changed_source(crate_path, &dir)? {
+        Some(source) => source,
+        None => return Ok(dir),
+    };
+    match fingerprint::saved_build_args(&dir) {
+        Some(args) => {
+            PBAR.info(&format!(
+                "🏗️  {} changed since {} was built, rebuilding it with `wasm-pack build {}`",
+                source.display(),
+                dir.display(),
+                args.join(" ")
+            ));
+            let out_dir = dir.strip_prefix(crate_path).unwrap_or(&dir).to_owned();
+            build_pkg_directory(crate_path, &out_dir, &args)
+        }
+        None => {
+            PBAR.warn(&format!(
+                "{} changed since {} was built, so it may be out of date. Rebuild it with \
+                 `wasm-pack build`, since how it was built isn't known.",
+                source.display(),
+                dir.display()
+            ));
+            Ok(dir)
+        }
+    }
+}
+
+/// Build the crate at `crate_path` into `out_dir` with the arguments `args`
+/// of `wasm-pack build`.
+fn build_pkg_directory(crate_path: &Path, out_dir: &Path, args: &[String]) -> Result<PathBuf> {
+    let argv = ["wasm-pack", "build"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain(Some(crate_path.display().to_string()))
+        .chain(args.iter().cloned());
+    let build_opts = match Cli::try_parse_from(argv)?.cmd {
+        Command::Build(build_opts) => BuildOptions {
+            out_dir: out_dir.to_string_lossy().into_owned(),
+            ..build_opts
+        },
+        _ => unreachable!("`wasm-pack build` is the build command"),
+    };
+    Build::try_from_opts(build_opts).and_then(|mut build| build.run())?;
+    Ok(crate_path.join(out_dir))
+}
+
+/// The newest of the crate's sources changed since the `package.json` in
+/// `pkg_dir` was written, if any: its `Cargo.toml`, `Cargo.lock`, `build.rs`
+/// and everything in `src`.
+fn changed_source(crate_path: &Path, pkg_dir: &Path) -> Result<Option<PathBuf>> {
+    let built = fs::metadata(pkg_dir.join("package.json"))?.modified()?;
+    let sources = ["Cargo.toml", "Cargo.lock", "build.rs"]
+        .iter()
+        .map(|file| crate_path.join(file))
+        .chain(
+            WalkDir::new(crate_path.join("src"))
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path()),
+        );
+    let mut newest = None;
+    for source in sources {
+        let modified = match fs::metadata(&source).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(_) => continue,
+        };
+        if modified > built && newest.as_ref().is_none_or(|(_, newest)| modified > *newest) {
+            newest = Some((source, modified));
+        }
+    }
+    Ok(newest.map(|(source, _)| source))
+}
+
+/// Print a line for each member of a workspace with how it fared, its name
+/// padded so that the outcomes line up, and return the names of the members
+/// that failed.
+///
+/// `outcomes` holds the name of each member with its outcome, such as
+/// `passed`, or `None` if it failed.
+pub fn report_members(outcomes: &[(String, Option<String>)]) -> Vec<&str> {
+    let width = outcomes
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, outcome) in outcomes {
+        let outcome = outcome.as_deref().unwrap_or("failed");
+        PBAR.info(&format!("{:<width$} {}", name, outcome, width = width));
+    }
+    outcomes
+        .iter()
+        .filter(|(_, outcome)| outcome.is_none())
+        .map(|(name, _)| name.as_str())
+        .collect()
+}
+
 /// Render a `Duration` to a form suitable for display on a console
 pub fn elapsed(duration: Duration) -> String {
     let secs = duration.as_secs();
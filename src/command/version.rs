@@ -0,0 +1,126 @@
+//! Implementation of the `wasm-pack version` command.
+
+use crate::child;
+use crate::command::publish::bump::{self, NewVersion};
+use crate::command::utils::get_crate_path;
+use crate::manifest::CrateData;
+use crate::PBAR;
+use anyhow::{Context, Result};
+use clap::Args;
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Clone, Debug, Args)]
+/// Everything required to configure the `wasm-pack version` command.
+pub struct VersionOptions {
+    /// The new version: `major`, `minor`, `patch` or `prerelease` to bump the
+    /// current one, or a version like `1.2.3`.
+    #[clap(value_name = "major|minor|patch|prerelease|x.y.z")]
+    pub new_version: NewVersion,
+
+    #[clap(long = "dry-run")]
+    /// Show the change and the files it would update, without writing
+    /// anything.
+    pub dry_run: bool,
+
+    #[clap(long = "pkg-dir", short = 'd', value_name = "DIR")]
+    /// The directory with the package.json to keep in sync, relative to the
+    /// current directory. Defaults to the `pkg` directory in the crate.
+    pub pkg_directory: Option<PathBuf>,
+
+    /// The path to the Rust crate. If not set, searches up the path from the current directory.
+    #[clap()]
+    pub path: Option<PathBuf>,
+}
+
+/// Change the version of the crate in its Cargo.toml, or in the workspace's
+/// if the crate inherits it, and in `Cargo.lock` and the built package.json,
+/// so that they don't lag behind until the next build.
+pub fn version(opts: VersionOptions) -> Result<()> {
+    let VersionOptions {
+        new_version,
+        dry_run,
+        pkg_directory,
+        path,
+    } = opts;
+    let crate_path = get_crate_path(path)?;
+    let crate_data = CrateData::new(&crate_path, None)?;
+    let package = &crate_data.metadata()[crate_data.package_id()];
+    let name = package.name.clone();
+
+    let crate_manifest_path = crate_path.join("Cargo.toml");
+    let crate_manifest = fs::read_to_string(&crate_manifest_path)
+        .with_context(|| format!("failed to read {}", crate_manifest_path.display()))?;
+    let inherited = toml::from_str::<toml::Value>(&crate_manifest)
+        .ok()
+        .and_then(|manifest| {
+            manifest
+                .get("package")?
+                .get("version")?
+                .get("workspace")
+                .cloned()
+        })
+        .and_then(|workspace| workspace.as_bool())
+        .unwrap_or(false);
+    let (manifest_path, table) = if inherited {
+        let root_manifest = crate_data.workspace_root().join("Cargo.toml");
+        PBAR.info(&format!(
+            "The version of {} is inherited from the workspace, so it is changed in {}, for \
+             every member that inherits it.",
+            name,
+            root_manifest.display()
+        ));
+        (root_manifest, &["workspace", "package"][..])
+    } else {
+        (crate_manifest_path, &["package"][..])
+    };
+    let manifest = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    let (manifest, old, new) = bump::change_manifest_version(&manifest, table, &new_version)?;
+    if old == new {
+        PBAR.info(&format!("{} is already at version {}", name, new));
+        return Ok(());
+    }
+
+    let prefix = if dry_run { "[dry run] " } else { "" };
+    PBAR.info(&format!("{}{} {} → {}", prefix, name, old, new));
+    let report = |path: &Path| {
+        let verb = if dry_run { "Would update" } else { "Updated" };
+        PBAR.info(&format!("{}{} {}", prefix, verb, path.display()));
+    };
+
+    if !dry_run {
+        fs::write(&manifest_path, manifest)
+            .with_context(|| format!("failed to write {}", manifest_path.display()))?;
+    }
+    report(&manifest_path);
+
+    let lockfile = crate_data.workspace_root().join("Cargo.lock");
+    if lockfile.is_file() {
+        if !dry_run {
+            let mut cmd = Command::new("cargo");
+            cmd.current_dir(&crate_path)
+                .arg("update")
+                .arg("-p")
+                .arg(format!("{}@{}", name, old))
+                .arg("--precise")
+                .arg(new.to_string());
+            info!("Updating {} in {}", name, lockfile.display());
+            child::run(cmd, "cargo update")
+                .with_context(|| format!("failed to update {}", lockfile.display()))?;
+        }
+        report(&lockfile);
+    }
+
+    let pkg_directory = pkg_directory.unwrap_or_else(|| crate_path.join("pkg"));
+    let package_json = pkg_directory.join("package.json");
+    if package_json.is_file() {
+        if !dry_run {
+            bump::set_package_json_version(&pkg_directory, &new)?;
+        }
+        report(&package_json);
+    }
+    Ok(())
+}
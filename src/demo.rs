@@ -0,0 +1,170 @@
+//! Generating a demo `index.html` for the `web` and `no-modules` targets.
+//!
+//! Every generated file carries a checksum of its own contents, which lets
+//! later builds tell an untouched demo (safe to regenerate) apart from one
+//! the user has edited (left alone unless `--force` is passed).
+
+use crate::command::build::Target;
+use crate::PBAR;
+use anyhow::{bail, Context, Result};
+use siphasher::sip::SipHasher13;
+use std::fs;
+use std::hash::Hasher;
+use std::path::Path;
+
+const CHECKSUM_MARKER: &str = "wasm-pack demo checksum: ";
+
+/// Make sure that a demo can be generated for `target`.
+pub fn check_target(target: Target) -> Result<()> {
+    match target {
+        Target::Web | Target::NoModules => Ok(()),
+        _ => bail!(
+            "`--demo-html` is only supported for the `web` and `no-modules` targets, not `{}`",
+            target
+        ),
+    }
+}
+
+/// Write the demo files for `target` into `out_dir`, wired to the bindings
+/// named `name_prefix`.
+///
+/// Returns the names of the demo files, whether or not they were rewritten.
+pub fn write_demo_html(
+    out_dir: &Path,
+    name_prefix: &str,
+    target: Target,
+    force: bool,
+) -> Result<Vec<String>> {
+    check_target(target)?;
+    let files = match target {
+        Target::Web => vec![
+            ("index.html", web_html(name_prefix)),
+            ("init.js", web_init_js(name_prefix)),
+        ],
+        _ => vec![("index.html", no_modules_html(name_prefix))],
+    };
+
+    let mut names = Vec::new();
+    for (name, (comment_prefix, comment_suffix, body)) in files {
+        let path = out_dir.join(name);
+        if path.exists() && !force && !is_unmodified(&path)? {
+            PBAR.warn(&format!(
+                "{} has been modified since it was generated, not overwriting it. Pass `--force` to regenerate it.",
+                path.display()
+            ));
+        } else {
+            let contents = with_checksum(&body, comment_prefix, comment_suffix);
+            fs::write(&path, contents)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        names.push(name.to_string());
+    }
+    Ok(names)
+}
+
+/// Add the demo files to the `files` list of the `package.json` in `out_dir`.
+pub fn add_to_package_json(out_dir: &Path, names: &[String]) -> Result<()> {
+    let path = out_dir.join("package.json");
+    let mut package: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?,
+    )?;
+    if let Some(files) = package["files"].as_array_mut() {
+        for name in names {
+            files.push(name.clone().into());
+        }
+    }
+    fs::write(&path, serde_json::to_string_pretty(&package)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+type DemoFile = (&'static str, &'static str, String);
+
+fn no_modules_html(name_prefix: &str) -> DemoFile {
+    let body = format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>{name} demo</title>
+  </head>
+  <body>
+    <script src="./{name}.js"></script>
+    <script>
+      wasm_bindgen("./{name}_bg.wasm").then(() => {{
+        console.log("{name} is ready");
+      }});
+    </script>
+  </body>
+</html>
+"#,
+        name = name_prefix
+    );
+    ("<!-- ", " -->", body)
+}
+
+fn web_html(name_prefix: &str) -> DemoFile {
+    let body = format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>{name} demo</title>
+    <link rel="modulepreload" href="./{name}.js">
+  </head>
+  <body>
+    <script type="module" src="./init.js"></script>
+  </body>
+</html>
+"#,
+        name = name_prefix
+    );
+    ("<!-- ", " -->", body)
+}
+
+fn web_init_js(name_prefix: &str) -> DemoFile {
+    let body = format!(
+        r#"import init from "./{name}.js";
+
+init().then(() => {{
+  console.log("{name} is ready");
+}});
+"#,
+        name = name_prefix
+    );
+    ("// ", "", body)
+}
+
+fn checksum(body: &str) -> String {
+    let mut hasher = SipHasher13::new();
+    hasher.write(body.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Append a comment holding the checksum of `body` to it.
+fn with_checksum(body: &str, comment_prefix: &str, comment_suffix: &str) -> String {
+    format!(
+        "{}{}{}{}{}\n",
+        body,
+        comment_prefix,
+        CHECKSUM_MARKER,
+        checksum(body),
+        comment_suffix
+    )
+}
+
+/// Check whether the file at `path` still matches the checksum it was
+/// generated with.
+fn is_unmodified(path: &Path) -> Result<bool> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let trimmed = contents.strip_suffix('\n').unwrap_or(&contents);
+    let (body, last_line) = match trimmed.rfind('\n') {
+        Some(idx) => (&contents[..=idx], &trimmed[idx + 1..]),
+        None => return Ok(false),
+    };
+    let saved = match last_line.find(CHECKSUM_MARKER) {
+        Some(idx) => &last_line[idx + CHECKSUM_MARKER.len()..],
+        None => return Ok(false),
+    };
+    Ok(saved.starts_with(&checksum(body)))
+}
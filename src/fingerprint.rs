@@ -0,0 +1,114 @@
+//! Fingerprints of the inputs to `wasm-bindgen` and `wasm-opt`.
+//!
+//! After a successful build we record a hash of the compiled `.wasm`, the
+//! `wasm-bindgen` version and the options we ran with inside the output
+//! directory. If the next build produces the same fingerprint, regenerating
+//! the bindings would produce the same files, so it can be skipped.
+//!
+//! The arguments of the build are recorded next to it, so that `pack` and
+//! `publish` can rebuild an out of date package the way it was built.
+
+use anyhow::{Context, Result};
+use siphasher::sip128::{Hasher128, SipHasher13};
+use std::fs;
+use std::hash::Hasher;
+use std::path::Path;
+
+/// The name of the file in the output directory that holds the fingerprint of
+/// the last successful build.
+pub const FINGERPRINT_FILE: &str = ".wasm-pack-fingerprint";
+
+/// A hash of everything that determines the output of `wasm-bindgen` and
+/// `wasm-opt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint(String);
+
+impl Fingerprint {
+    /// Compute the fingerprint of the compiled `.wasm` at `wasm_path`, built
+    /// with `wasm-bindgen` at `bindgen_version` and the given `options`.
+    pub fn new(wasm_path: &Path, bindgen_version: &str, options: &[String]) -> Result<Fingerprint> {
+        let wasm = fs::read(wasm_path)
+            .with_context(|| format!("failed to read {}", wasm_path.display()))?;
+
+        let mut hasher = SipHasher13::new();
+        hasher.write(&wasm);
+        hasher.write(bindgen_version.as_bytes());
+        for option in options {
+            hasher.write(option.as_bytes());
+            hasher.write_u8(0);
+        }
+        let hash = hasher.finish128();
+        Ok(Fingerprint(format!("{:016x}{:016x}", hash.h1, hash.h2)))
+    }
+
+    /// Check whether `out_dir` holds the output of a build with this exact
+    /// fingerprint.
+    pub fn is_up_to_date(&self, out_dir: &Path) -> bool {
+        match read_saved(out_dir) {
+            Some(saved) => saved.hash == self.0,
+            None => false,
+        }
+    }
+
+    /// Record this fingerprint in `out_dir`, along with the `package.json`
+    /// that `wasm-bindgen` generated, if any.
+    pub fn save(&self, out_dir: &Path) -> Result<()> {
+        let saved = SavedFingerprint {
+            hash: self.0.clone(),
+            package_json: fs::read_to_string(out_dir.join("package.json")).ok(),
+            build_args: None,
+        };
+        write_saved(out_dir, &saved)
+    }
+
+    /// Put back the `package.json` that `wasm-bindgen` generated during the
+    /// build recorded in `out_dir`, since it gets cleaned up before every
+    /// build but carries the npm dependencies of the bindings.
+    pub fn restore(out_dir: &Path) -> Result<()> {
+        if let Some(package_json) = read_saved(out_dir).and_then(|saved| saved.package_json) {
+            let path = out_dir.join("package.json");
+            fs::write(&path, package_json)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Forget any previously recorded fingerprint in `out_dir`.
+    pub fn clear(out_dir: &Path) {
+        let _ = fs::remove_file(out_dir.join(FINGERPRINT_FILE));
+    }
+}
+
+/// Record the arguments of the `wasm-pack build` that wrote `out_dir`, as
+/// given by `BuildOptions::args`.
+pub fn save_build_args(out_dir: &Path, build_args: &[String]) -> Result<()> {
+    let mut saved = read_saved(out_dir).unwrap_or_default();
+    saved.build_args = Some(build_args.to_vec());
+    write_saved(out_dir, &saved)
+}
+
+/// The arguments of the `wasm-pack build` that wrote `out_dir`, if they were
+/// recorded.
+pub fn saved_build_args(out_dir: &Path) -> Option<Vec<String>> {
+    read_saved(out_dir)?.build_args
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SavedFingerprint {
+    #[serde(default)]
+    hash: String,
+    package_json: Option<String>,
+    #[serde(default)]
+    build_args: Option<Vec<String>>,
+}
+
+fn read_saved(out_dir: &Path) -> Option<SavedFingerprint> {
+    let contents = fs::read_to_string(out_dir.join(FINGERPRINT_FILE)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_saved(out_dir: &Path, saved: &SavedFingerprint) -> Result<()> {
+    let path = out_dir.join(FINGERPRINT_FILE);
+    fs::write(&path, serde_json::to_string(saved)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
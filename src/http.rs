@@ -0,0 +1,253 @@
+//! The HTTP client wasm-pack downloads its tools and talks to web APIs with.
+
+use crate::cache;
+use anyhow::{anyhow, bail, Context, Result};
+use binary_install::{Cache, Download};
+use flate2::read::GzDecoder;
+use fs4::FileExt;
+use siphasher::sip::SipHasher13;
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The environment variable setting the network timeout, in seconds, like
+/// `--network-timeout`.
+pub const NETWORK_TIMEOUT_ENV: &str = "WASM_PACK_NETWORK_TIMEOUT";
+
+/// How long to wait for a connection to a server by default.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for a server to send more of its response by default.
+/// This isn't a limit on the whole download, so large tools still download
+/// over slow links, as long as they make progress.
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The network timeout in seconds, or 0 for the defaults.
+static NETWORK_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+
+const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
+
+/// Set how many seconds to wait for a server to accept a connection, and
+/// then for each part of its response. `None` restores the defaults.
+pub fn set_network_timeout(seconds: Option<u64>) {
+    NETWORK_TIMEOUT.store(seconds.unwrap_or(0), Ordering::SeqCst);
+}
+
+/// The network timeout in `$WASM_PACK_NETWORK_TIMEOUT`, if it is set.
+pub fn network_timeout_from_env() -> Result<Option<u64>> {
+    match env::var(NETWORK_TIMEOUT_ENV) {
+        Ok(value) if !value.trim().is_empty() => parse_network_timeout(value.trim())
+            .map(Some)
+            .map_err(|e| anyhow!("invalid ${}: {}", NETWORK_TIMEOUT_ENV, e)),
+        _ => Ok(None),
+    }
+}
+
+/// Parse a network timeout of a positive number of seconds.
+pub fn parse_network_timeout(value: &str) -> Result<u64> {
+    match value.parse() {
+        Ok(0) | Err(_) => bail!(
+            "expected a positive number of seconds for the network timeout, not {:?}",
+            value
+        ),
+        Ok(seconds) => Ok(seconds),
+    }
+}
+
+/// The connect and read timeouts of the requests.
+fn timeouts() -> (Duration, Duration) {
+    match NETWORK_TIMEOUT.load(Ordering::SeqCst) {
+        0 => (DEFAULT_CONNECT_TIMEOUT, DEFAULT_READ_TIMEOUT),
+        seconds => (Duration::from_secs(seconds), Duration::from_secs(seconds)),
+    }
+}
+
+/// An HTTP client with the network timeouts, going through the proxy in the
+/// environment, if any.
+pub fn agent() -> ureq::Agent {
+    let (connect, read) = timeouts();
+    ureq::builder()
+        .try_proxy_from_env(true)
+        .timeout_connect(connect)
+        .timeout_read(read)
+        .timeout_write(read)
+        .user_agent(&format!("wasm-pack/{}", VERSION.unwrap_or("unknown")))
+        .build()
+}
+
+/// Download the tarball or zip file at `url` and extract `binaries` from it
+/// into `cache`, returning where they are.
+///
+/// The entries in the cache are named and locked like binary-install's
+/// `Cache::download` does, so that several wasm-pack processes don't
+/// download the same tool at once, and earlier downloads are still found.
+/// The archive is streamed through `agent`, so that a server that stops
+/// sending it fails the download with the network timeouts instead of
+/// hanging.
+pub fn download(
+    cache: &Cache,
+    install_permitted: bool,
+    name: &str,
+    binaries: &[&str],
+    url: &str,
+) -> Result<Option<Download>> {
+    let dirname = hashed_dirname(url, name);
+    let destination = cache.join(Path::new(&dirname));
+    // Released when the file is closed, on return.
+    let lock = File::create(cache.join(Path::new(&format!(".{}.lock", dirname))))?;
+    lock.lock_exclusive()?;
+    if destination.exists() {
+        return Ok(Some(Download::at(&destination)));
+    }
+    if !install_permitted {
+        return Ok(None);
+    }
+
+    // Extract everything in a temporary directory, so that an interrupted
+    // download doesn't leave a broken entry behind.
+    let temp = cache.join(Path::new(&format!(".{}", dirname)));
+    drop(fs::remove_dir_all(&temp));
+    fs::create_dir_all(&temp)?;
+    download_into(url, &temp, binaries)
+        .map_err(timed_out)
+        .with_context(|| format!("failed to download from {}", url))?;
+    // Remember where it came from, so that `wasm-pack cache list` can tell
+    // which version this is.
+    fs::write(temp.join(cache::SOURCE_URL_FILE), url)
+        .with_context(|| format!("failed to write {} into the cache", name))?;
+    fs::rename(&temp, &destination)?;
+    Ok(Some(Download::at(&destination)))
+}
+
+/// The name of the entry in the cache for `name` downloaded from `url`, the
+/// same as binary-install gives it.
+fn hashed_dirname(url: &str, name: &str) -> String {
+    let mut hasher = SipHasher13::new();
+    url.hash(&mut hasher);
+    let hex: String = hasher
+        .finish()
+        .to_le_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}-{}", name, hex)
+}
+
+/// Download the archive at `url` and extract `binaries` from it into `dir`.
+fn download_into(url: &str, dir: &Path, binaries: &[&str]) -> Result<()> {
+    let response = match agent().get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, _)) => bail!(
+            "received a bad HTTP status code ({}) when requesting {}",
+            status,
+            url
+        ),
+        Err(e) => return Err(e.into()),
+    };
+    let mut binaries: HashSet<&str> = binaries.iter().copied().collect();
+    if url.ends_with(".tar.gz") {
+        let mut archive = tar::Archive::new(GzDecoder::new(response.into_reader()));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if let Some(dest) = binary_destination(&entry.path()?, dir, &mut binaries) {
+                fs::create_dir_all(dest.parent().unwrap())?;
+                entry.unpack(dest)?;
+            }
+        }
+    } else if url.ends_with(".zip") {
+        // A zip is read from its end, so it is downloaded in full first.
+        let zip_path = dir.join(".download.zip");
+        let mut zip_file = File::create(&zip_path)?;
+        io::copy(&mut response.into_reader(), &mut zip_file)?;
+        let mut archive = zip::ZipArchive::new(File::open(&zip_path)?)?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let path = match entry.enclosed_name() {
+                Some(path) => path,
+                None => continue,
+            };
+            if let Some(dest) = binary_destination(&path, dir, &mut binaries) {
+                fs::create_dir_all(dest.parent().unwrap())?;
+                io::copy(&mut entry, &mut executable(&dest)?)?;
+            }
+        }
+        fs::remove_file(&zip_path)?;
+    } else {
+        bail!("don't know how to extract {}", url)
+    }
+    if !binaries.is_empty() {
+        let mut missing: Vec<_> = binaries.into_iter().collect();
+        missing.sort();
+        bail!(
+            "the archive was missing expected executables: {}",
+            missing.join(", ")
+        )
+    }
+    Ok(())
+}
+
+/// Where to extract the file at `path` in an archive to, if it is one of
+/// `binaries`, which it is then taken off.
+fn binary_destination(path: &Path, dir: &Path, binaries: &mut HashSet<&str>) -> Option<PathBuf> {
+    let stem = path.file_stem()?;
+    let binary = *binaries
+        .iter()
+        .find(|binary| **binary == stem || (binary.contains('/') && path.ends_with(binary)))?;
+    binaries.remove(binary);
+    if binary.contains('/') {
+        Some(dir.join(binary))
+    } else {
+        Some(dir.join(path.file_name()?))
+    }
+}
+
+#[cfg(unix)]
+fn executable(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o755)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn executable(path: &Path) -> io::Result<File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+}
+
+/// Whether `error` is caused by a server not responding in time.
+fn is_timeout(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<io::Error>().is_some_and(|e| {
+            matches!(
+                e.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            )
+        }) || cause.to_string().contains("timed out")
+    })
+}
+
+/// Explain how to wait longer when `error` is a timeout.
+fn timed_out(error: anyhow::Error) -> anyhow::Error {
+    if !is_timeout(&error) {
+        return error;
+    }
+    let (connect, read) = timeouts();
+    error.context(format!(
+        "the server didn't respond within the network timeout ({}s to connect, {}s to read); \
+         wait longer with `--network-timeout <seconds>` or ${}",
+        connect.as_secs(),
+        read.as_secs(),
+        NETWORK_TIMEOUT_ENV
+    ))
+}
@@ -0,0 +1,171 @@
+//! Exporting the init function of the `web` target's glue under a name of
+//! the user's choosing, with `--init-name`.
+//!
+//! The glue only exports the function that instantiates the wasm as its
+//! default export, which doesn't suit consumers that only use named imports,
+//! so it is exported under the given name as well, in the `.d.ts` too.
+
+use crate::command::build::Target;
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Words that can't name a binding in a JS module.
+const RESERVED_WORDS: &[&str] = &[
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Make sure that `name` can be exported from the glue for `target`.
+pub fn check(target: Target, name: &str) -> Result<()> {
+    if !matches!(target, Target::Web) {
+        bail!(
+            "`--init-name` is only supported for the `web` target, not `{}`, whose glue has no \
+             init function to export",
+            target
+        )
+    }
+    let mut chars = name.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if !is_identifier || RESERVED_WORDS.contains(&name) {
+        bail!(
+            "`{}` can't be the name of the init function, it isn't a valid JS identifier",
+            name
+        )
+    }
+    Ok(())
+}
+
+/// Export the init function of the glue named `name_prefix` in `out_dir` as
+/// `name`, next to the default export, in its `.d.ts` too if there is one.
+pub fn export_init_as(out_dir: &Path, name_prefix: &str, name: &str) -> Result<()> {
+    let js = out_dir.join(format!("{}.js", name_prefix));
+    let dts = out_dir.join(format!("{}.d.ts", name_prefix));
+    export_default_as(&js, "export default ", name)?;
+    if dts.is_file() {
+        export_default_as(&dts, "export default function ", name)?;
+    }
+    Ok(())
+}
+
+/// Add an export of the binding that the `default_export` in the file at
+/// `path` exports as `name`.
+fn export_default_as(path: &Path, default_export: &str, name: &str) -> Result<()> {
+    let source =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    let init = source
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(default_export))
+        .map(|rest| {
+            rest.chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '$')
+                .collect::<String>()
+        })
+        .find(|init| !init.is_empty());
+    let init = match init {
+        Some(init) => init,
+        None => bail!(
+            "Could not find the init function that {} exports by default, so it can't be \
+             exported as `{}`",
+            path.display(),
+            name
+        ),
+    };
+    if init != name && exports(&source, name) {
+        bail!(
+            "{} already exports `{}`, so the init function can't be exported under that name",
+            path.display(),
+            name
+        )
+    }
+
+    let mut source = source;
+    if !source.ends_with('\n') {
+        source.push('\n');
+    }
+    source.push_str(&format!("export {{ {} as {} }};\n", init, name));
+    fs::write(path, source).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Whether `source` exports something called `name`.
+fn exports(source: &str, name: &str) -> bool {
+    let is_name = |rest: &str| {
+        rest.strip_prefix(name).is_some_and(|rest| {
+            !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        })
+    };
+    source.lines().map(str::trim).any(|line| {
+        [
+            "export function ",
+            "export async function ",
+            "export class ",
+            "export const ",
+            "export let ",
+            "export var ",
+            "export declare function ",
+            "export declare class ",
+            "export type ",
+            "export interface ",
+            "export enum ",
+        ]
+        .iter()
+        .any(|prefix| line.strip_prefix(prefix).is_some_and(is_name))
+            || line
+                .strip_prefix("export {")
+                .and_then(|rest| rest.split('}').next())
+                .is_some_and(|names| {
+                    names
+                        .split(',')
+                        .map(|binding| binding.split(" as ").last().unwrap_or_default().trim())
+                        .any(|exported| exported == name)
+                })
+    })
+}
@@ -1,7 +1,7 @@
+use crate::http;
 use crate::install::Tool;
 use anyhow::Result;
 use serde::Deserialize;
-const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 
 #[derive(Debug, Deserialize)]
 pub struct Krate {
@@ -17,15 +17,7 @@ pub struct KrateResponse {
 impl Krate {
     pub fn new(name: &Tool) -> Result<Krate> {
         let krate_address = format!("https://crates.io/api/v1/crates/{}", name);
-        let res = ureq::builder()
-            .try_proxy_from_env(true)
-            .build()
-            .get(&krate_address)
-            .set(
-                "user-agent",
-                &format!("wasm-pack/{}", VERSION.unwrap_or("unknown")),
-            )
-            .call()?;
+        let res = http::agent().get(&krate_address).call()?;
 
         let kr: KrateResponse = res.into_json()?;
         Ok(kr.krate)
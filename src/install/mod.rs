@@ -3,16 +3,20 @@
 use self::krate::Krate;
 use crate::child;
 use crate::emoji;
+use crate::http;
 use crate::install;
 use crate::PBAR;
 use anyhow::{anyhow, bail, Context, Result};
 use binary_install::{Cache, Download};
 use log::debug;
 use log::{info, warn};
+use serde::Serialize;
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use which::which;
 
 mod arch;
@@ -25,6 +29,126 @@ pub use self::mode::InstallMode;
 pub use self::os::Os;
 pub use self::tool::Tool;
 
+static KEEP_TMP: AtomicBool = AtomicBool::new(false);
+
+/// Keep the temporary directory of a failed `cargo install`, and report where
+/// it and a successful one end up, instead of cleaning up after a failure.
+pub fn set_keep_tmp(keep: bool) {
+    KEEP_TMP.store(keep, Ordering::SeqCst);
+}
+
+/// The environment variable with the base URL to download the prebuilt
+/// releases of wasm-bindgen from, like `--bindgen-downloads-base-url`.
+pub const BINDGEN_URL_BASE_ENV: &str = "WASM_PACK_BINDGEN_URL_BASE";
+
+/// Where the prebuilt releases of wasm-bindgen are downloaded from by
+/// default.
+pub const DEFAULT_BINDGEN_URL_BASE: &str =
+    "https://github.com/rustwasm/wasm-bindgen/releases/download";
+
+/// The base URL of the wasm-bindgen releases, if another one than the default
+/// was given.
+static BINDGEN_URL_BASE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Download the prebuilt releases of wasm-bindgen from below `url`, such as
+/// an internal mirror of the GitHub releases, instead of from GitHub. `None`
+/// restores the default.
+pub fn set_bindgen_url_base(url: Option<String>) {
+    *BINDGEN_URL_BASE.lock().unwrap() = url;
+}
+
+/// The base URL in `$WASM_PACK_BINDGEN_URL_BASE`, if it is set.
+pub fn bindgen_url_base_from_env() -> Result<Option<String>> {
+    match env::var(BINDGEN_URL_BASE_ENV) {
+        Ok(value) if !value.trim().is_empty() => parse_bindgen_url_base(value.trim())
+            .map(Some)
+            .map_err(|e| anyhow!("invalid ${}: {}", BINDGEN_URL_BASE_ENV, e)),
+        _ => Ok(None),
+    }
+}
+
+/// Parse the base URL of the wasm-bindgen releases, which needs an `http://`
+/// or `https://` scheme.
+pub fn parse_bindgen_url_base(url: &str) -> Result<String> {
+    match url::Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {
+            Ok(url.to_string())
+        }
+        _ => bail!(
+            "expected an `http://` or `https://` URL for the wasm-bindgen downloads, not {:?}",
+            url
+        ),
+    }
+}
+
+/// The environment variable with the crates.io mirror to `cargo install`
+/// from, like `--registry-mirror`.
+pub const REGISTRY_MIRROR_ENV: &str = "WASM_PACK_REGISTRY_MIRROR";
+
+/// The name of the source that crates.io is replaced with when a mirror is
+/// given.
+const MIRROR_SOURCE: &str = "wasm-pack-mirror";
+
+/// The index of the crates.io mirror to `cargo install` from, if one was
+/// given.
+static REGISTRY_MIRROR: Mutex<Option<String>> = Mutex::new(None);
+
+/// Replace crates.io with the registry whose index is at `index` when falling
+/// back to `cargo install`. `None` leaves the sources to cargo's
+/// configuration.
+pub fn set_registry_mirror(index: Option<String>) {
+    *REGISTRY_MIRROR.lock().unwrap() = index;
+}
+
+/// The mirror in `$WASM_PACK_REGISTRY_MIRROR`, if it is set.
+pub fn registry_mirror_from_env() -> Result<Option<String>> {
+    match env::var(REGISTRY_MIRROR_ENV) {
+        Ok(value) if !value.trim().is_empty() => parse_registry_mirror(value.trim())
+            .map(Some)
+            .map_err(|e| anyhow!("invalid ${}: {}", REGISTRY_MIRROR_ENV, e)),
+        _ => Ok(None),
+    }
+}
+
+/// Parse the index URL of a crates.io mirror, the way cargo takes it for a
+/// `registry` source: a git URL, or a sparse index as `sparse+https://...`.
+pub fn parse_registry_mirror(index: &str) -> Result<String> {
+    let url = index.strip_prefix("sparse+").unwrap_or(index);
+    match url::Url::parse(url) {
+        Ok(parsed) if ["http", "https", "ssh", "git", "file"].contains(&parsed.scheme()) => {
+            Ok(index.to_string())
+        }
+        _ => bail!(
+            "expected the index URL of a registry, such as `sparse+https://mirror.example/index/` \
+             or a git URL, not {:?}",
+            index
+        ),
+    }
+}
+
+/// Which copy of a tool was used and where it came from, as reported when it
+/// was found or installed.
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolSource {
+    /// The tool, such as `wasm-bindgen`.
+    pub tool: String,
+    /// Its version, or `local` for one given with `--bindgen-path`.
+    pub version: String,
+    /// Where it came from, such as `global` or `cargo install`.
+    pub source: String,
+    /// The resolved path of its binary.
+    pub path: PathBuf,
+}
+
+/// The sources of the tools reported since they were last taken.
+static REPORTED_SOURCES: Mutex<Vec<ToolSource>> = Mutex::new(Vec::new());
+
+/// Take the sources of the tools found or installed since the last call, in
+/// the order they were reported.
+pub fn take_reported_sources() -> Vec<ToolSource> {
+    std::mem::take(&mut *REPORTED_SOURCES.lock().unwrap())
+}
+
 /// Possible outcomes of attempting to find/install a tool
 pub enum Status {
     /// Couldn't install tool because downloads are forbidden by user
@@ -51,12 +175,18 @@ pub fn get_tool_path(status: &Status, tool: Tool) -> Result<&Download> {
 /// Prefers an existing local install, if any exists. Then checks if there is a
 /// global install on `$PATH` that fits the bill. Then attempts to download a
 /// tarball from the GitHub releases page, if this target has prebuilt
-/// binaries. Finally, falls back to `cargo install`.
+/// binaries. Finally, falls back to `cargo install`, enabling `features` and
+/// running at most `jobs` parallel jobs if given, with the `[source]` tables
+/// of the cargo configuration in `cargo_config_dir`, such as the crate's, if
+/// given.
 pub fn download_prebuilt_or_cargo_install(
     tool: Tool,
     cache: &Cache,
     version: &str,
     install_permitted: bool,
+    features: &[String],
+    jobs: Option<u32>,
+    cargo_config_dir: Option<&Path>,
 ) -> Result<Status> {
     // If the tool is installed globally and it has the right version, use
     // that. Assume that other tools are installed next to it.
@@ -67,6 +197,7 @@ pub fn download_prebuilt_or_cargo_install(
         debug!("found global {} binary at: {}", tool, path.display());
         if check_version(&tool, &path, version)? {
             let download = Download::at(path.parent().unwrap());
+            report_source(&tool.to_string(), version, "global", &download);
             return Ok(Status::Found(download));
         }
     }
@@ -76,7 +207,16 @@ pub fn download_prebuilt_or_cargo_install(
 
     let dl = download_prebuilt(&tool, cache, version, install_permitted);
     match dl {
-        Ok(dl) => return Ok(dl),
+        Ok(dl) => {
+            if let Status::Found(download) = &dl {
+                let source = match prebuilt_target(&tool, &Arch::get()?, &Os::get()?) {
+                    Ok(target) => format!("prebuilt for {}", target),
+                    Err(_) => "prebuilt".to_string(),
+                };
+                report_source(&tool.to_string(), version, &source, download);
+            }
+            return Ok(dl);
+        }
         Err(e) => {
             warn!(
                 "could not download pre-built `{}`: {}. Falling back to `cargo install`.",
@@ -85,7 +225,108 @@ pub fn download_prebuilt_or_cargo_install(
         }
     }
 
-    cargo_install(tool, cache, version, install_permitted)
+    let name = tool.to_string();
+    let status = cargo_install(
+        tool,
+        cache,
+        version,
+        install_permitted,
+        features,
+        jobs,
+        cargo_config_dir,
+    )?;
+    if let Status::Found(download) = &status {
+        report_source(&name, version, "cargo install", download);
+    }
+    Ok(status)
+}
+
+/// Use the `wasm-bindgen` at `path` instead of installing one.
+///
+/// `path` is either a `wasm-bindgen` binary, or a local checkout of
+/// wasm-bindgen (or of its `wasm-bindgen-cli` crate), which is built with
+/// `cargo build --release` first.
+pub fn local_wasm_bindgen(path: &Path) -> Result<Status> {
+    let tool = Tool::WasmBindgen.to_string();
+    if path.is_file() {
+        let expected = format!("{}{}", tool, env::consts::EXE_SUFFIX);
+        if path.file_name() != Some(expected.as_ref()) {
+            bail!(
+                "{} isn't a `{}` binary; pass the binary or a wasm-bindgen checkout",
+                path.display(),
+                expected
+            );
+        }
+        let download = Download::at(path.parent().unwrap_or_else(|| Path::new(".")));
+        report_source(&tool, "local", "--bindgen-path", &download);
+        return Ok(Status::Found(download));
+    }
+
+    // In a checkout of the whole repository the CLI is a crate of its own.
+    let cli_manifest = path.join("crates").join("cli").join("Cargo.toml");
+    let manifest = if cli_manifest.is_file() {
+        cli_manifest
+    } else {
+        path.join("Cargo.toml")
+    };
+    if !manifest.is_file() {
+        bail!(
+            "{} is neither a `{}` binary nor a wasm-bindgen checkout with a Cargo.toml",
+            path.display(),
+            tool
+        );
+    }
+
+    PBAR.info(&format!(
+        "{}Building {} from {}...",
+        emoji::WRENCH,
+        tool,
+        path.display()
+    ));
+    let mut cmd = Command::new("cargo");
+    cmd.arg("build")
+        .arg("--release")
+        .arg("--bin")
+        .arg(&tool)
+        .arg("--manifest-path")
+        .arg(&manifest);
+    child::run(cmd, "cargo build").context("Building the local wasm-bindgen failed")?;
+
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(&manifest)
+        .no_deps()
+        .exec()
+        .with_context(|| format!("failed to read the metadata of {}", manifest.display()))?;
+    let download = Download::at(metadata.target_directory.join("release").as_std_path());
+    report_source(
+        &tool,
+        "local",
+        &format!("built from {}", path.display()),
+        &download,
+    );
+    Ok(Status::Found(download))
+}
+
+/// Report which copy of `tool` is going to be used, naming where it came
+/// from and the resolved path of its binary, and record it for
+/// [`take_reported_sources`].
+fn report_source(tool: &str, version: &str, source: &str, download: &Download) {
+    let path = download.binary(tool).unwrap_or_else(|_| download.path());
+    let msg = format!(
+        "Using {} {} (source: {}, path: {})",
+        tool,
+        version,
+        source,
+        path.display()
+    );
+    info!("{}", msg);
+    PBAR.info(&msg);
+    REPORTED_SOURCES.lock().unwrap().push(ToolSource {
+        tool: tool.to_string(),
+        version: version.to_string(),
+        source: source.to_string(),
+        path,
+    });
 }
 
 /// Check if the tool dependency is locally satisfied.
@@ -117,6 +358,27 @@ pub fn get_cli_version(tool: &Tool, path: &Path) -> Result<String> {
     }
 }
 
+/// The extended attribute with which macOS marks downloaded files, so that
+/// Gatekeeper asks before running them.
+#[cfg(target_os = "macos")]
+pub const QUARANTINE_XATTR: &str = "com.apple.quarantine";
+
+/// Remove the quarantine from the downloaded binary at `path`, so that it
+/// runs without a Gatekeeper prompt. A binary without one is left as it is.
+#[cfg(target_os = "macos")]
+pub fn remove_quarantine(path: &Path) -> Result<()> {
+    let mut cmd = Command::new("xattr");
+    cmd.arg("-d").arg(QUARANTINE_XATTR).arg(path);
+    debug!("Running {:?}", cmd);
+    let output = cmd.output().context("failed to run `xattr`")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() || stderr.contains("No such xattr") {
+        Ok(())
+    } else {
+        bail!("`xattr -d {}` failed: {}", QUARANTINE_XATTR, stderr.trim())
+    }
+}
+
 /// Downloads a precompiled copy of the tool, if available.
 pub fn download_prebuilt(
     tool: &Tool,
@@ -135,14 +397,28 @@ pub fn download_prebuilt(
     match tool {
         Tool::WasmBindgen => {
             let binaries = &["wasm-bindgen", "wasm-bindgen-test-runner"];
-            match cache.download(install_permitted, "wasm-bindgen", binaries, &url)? {
-                Some(download) => Ok(Status::Found(download)),
+            match http::download(cache, install_permitted, "wasm-bindgen", binaries, &url)? {
+                Some(download) => {
+                    #[cfg(target_os = "macos")]
+                    for binary in binaries {
+                        let path = download.binary(binary)?;
+                        if let Err(e) = remove_quarantine(&path) {
+                            warn!(
+                                "could not remove the quarantine of {}, so macOS may refuse to \
+                                 run it: {:#}",
+                                path.display(),
+                                e
+                            );
+                        }
+                    }
+                    Ok(Status::Found(download))
+                }
                 None => bail!("wasm-bindgen v{} is not installed!", version),
             }
         }
         Tool::CargoGenerate => {
             let binaries = &["cargo-generate"];
-            match cache.download(install_permitted, "cargo-generate", binaries, &url)? {
+            match http::download(cache, install_permitted, "cargo-generate", binaries, &url)? {
                 Some(download) => Ok(Status::Found(download)),
                 None => bail!("cargo-generate v{} is not installed!", version),
             }
@@ -153,7 +429,7 @@ pub fn download_prebuilt(
                 Os::Linux => &["bin/wasm-opt"],
                 Os::Windows => &["bin/wasm-opt.exe"],
             };
-            match cache.download(install_permitted, "wasm-opt", binaries, &url)? {
+            match http::download(cache, install_permitted, "wasm-opt", binaries, &url)? {
                 Some(download) => Ok(Status::Found(download)),
                 // TODO(ag_dubs): why is this different? i forget...
                 None => Ok(Status::CannotInstall),
@@ -172,26 +448,12 @@ fn prebuilt_url(tool: &Tool, version: &str) -> Result<String> {
 
 /// Get the download URL for some tool at some version, architecture and operating system
 pub fn prebuilt_url_for(tool: &Tool, version: &str, arch: &Arch, os: &Os) -> Result<String> {
-    let target = match (os, arch, tool) {
-        (Os::Linux, Arch::AArch64, Tool::WasmOpt) => "aarch64-linux",
-        (Os::Linux, Arch::AArch64, _) => "aarch64-unknown-linux-gnu",
-        (Os::Linux, Arch::X86_64, Tool::WasmOpt) => "x86_64-linux",
-        (Os::Linux, Arch::X86_64, _) => "x86_64-unknown-linux-musl",
-        (Os::MacOS, Arch::X86_64, Tool::WasmOpt) => "x86_64-macos",
-        (Os::MacOS, Arch::X86_64, _) => "x86_64-apple-darwin",
-        (Os::MacOS, Arch::AArch64, Tool::CargoGenerate) => "aarch64-apple-darwin",
-        (Os::MacOS, Arch::AArch64, Tool::WasmOpt) => "arm64-macos",
-        (Os::Windows, Arch::X86_64, Tool::WasmOpt) => "x86_64-windows",
-        (Os::Windows, Arch::X86_64, _) => "x86_64-pc-windows-msvc",
-        _ => bail!("Unrecognized target!"),
-    };
+    let target = prebuilt_target(tool, arch, os)?;
     match tool {
         Tool::WasmBindgen => {
-            Ok(format!(
-                "https://github.com/rustwasm/wasm-bindgen/releases/download/{0}/wasm-bindgen-{0}-{1}.tar.gz",
-                version,
-                target
-            ))
+            let base = BINDGEN_URL_BASE.lock().unwrap().clone();
+            let base = base.as_deref().unwrap_or(DEFAULT_BINDGEN_URL_BASE);
+            wasm_bindgen_url(base, version, arch, os)
         },
         Tool::CargoGenerate => {
             Ok(format!(
@@ -210,20 +472,68 @@ pub fn prebuilt_url_for(tool: &Tool, version: &str, arch: &Arch, os: &Os) -> Res
     }
 }
 
+/// Get the download URL of wasm-bindgen `version` for `arch` and `os`, from
+/// the releases below `base`, which are laid out like the GitHub releases:
+/// `<base>/<version>/wasm-bindgen-<version>-<target>.tar.gz`.
+pub fn wasm_bindgen_url(base: &str, version: &str, arch: &Arch, os: &Os) -> Result<String> {
+    let target = prebuilt_target(&Tool::WasmBindgen, arch, os)?;
+    Ok(format!(
+        "{0}/{1}/wasm-bindgen-{1}-{2}.tar.gz",
+        base.trim_end_matches('/'),
+        version,
+        target
+    ))
+}
+
+/// The platform name that prebuilt binaries of `tool` are published under.
+fn prebuilt_target(tool: &Tool, arch: &Arch, os: &Os) -> Result<&'static str> {
+    let target = match (os, arch, tool) {
+        (Os::Linux, Arch::AArch64, Tool::WasmOpt) => "aarch64-linux",
+        (Os::Linux, Arch::AArch64, _) => "aarch64-unknown-linux-gnu",
+        (Os::Linux, Arch::X86_64, Tool::WasmOpt) => "x86_64-linux",
+        (Os::Linux, Arch::X86_64, _) => "x86_64-unknown-linux-musl",
+        (Os::MacOS, Arch::X86_64, Tool::WasmOpt) => "x86_64-macos",
+        (Os::MacOS, Arch::X86_64, _) => "x86_64-apple-darwin",
+        (Os::MacOS, Arch::AArch64, Tool::CargoGenerate) => "aarch64-apple-darwin",
+        (Os::MacOS, Arch::AArch64, Tool::WasmOpt) => "arm64-macos",
+        (Os::Windows, Arch::X86_64, Tool::WasmOpt) => "x86_64-windows",
+        (Os::Windows, Arch::X86_64, _) => "x86_64-pc-windows-msvc",
+        _ => bail!("Unrecognized target!"),
+    };
+    Ok(target)
+}
+
 /// Use `cargo install` to install the tool locally into the given
 /// crate.
+///
+/// `cargo install` doesn't read the configuration of the crate it installs
+/// for, so the `[source]` tables of the `.cargo/config.toml` in
+/// `cargo_config_dir`, if given, are passed to it with `--config`.
 pub fn cargo_install(
     tool: Tool,
     cache: &Cache,
     version: &str,
     install_permitted: bool,
+    features: &[String],
+    jobs: Option<u32>,
+    cargo_config_dir: Option<&Path>,
 ) -> Result<Status> {
     debug!(
         "Attempting to use a `cargo install`ed version of `{}={}`",
         tool, version,
     );
 
-    let dirname = format!("{}-cargo-install-{}", tool, version);
+    // Builds with different features must not share a cache entry.
+    let dirname = if features.is_empty() {
+        format!("{}-cargo-install-{}", tool, version)
+    } else {
+        format!(
+            "{}-cargo-install-{}-features-{}",
+            tool,
+            version,
+            features.join("-")
+        )
+    };
     let destination = cache.join(dirname.as_ref());
     if destination.exists() {
         debug!(
@@ -249,24 +559,43 @@ pub fn cargo_install(
     let context = format!("failed to create temp dir for `cargo install {}`", tool);
     fs::create_dir_all(&tmp).context(context)?;
 
-    let crate_name = match tool {
-        Tool::WasmBindgen => "wasm-bindgen-cli".to_string(),
-        _ => tool.to_string(),
+    let sources = match cargo_config_dir {
+        Some(dir) => source_config(dir)?,
+        None => Vec::new(),
     };
-    let mut cmd = Command::new("cargo");
-
-    cmd.arg("install")
-        .arg("--force")
-        .arg(crate_name)
-        .arg("--root")
-        .arg(&tmp);
-
-    if version != "latest" {
-        cmd.arg("--version").arg(version);
-    }
-
+    let cmd = cargo_install_command(&tool, version, &tmp, features, jobs, &sources);
     let context = format!("Installing {} with cargo", tool);
-    child::run(cmd, "cargo install").context(context)?;
+    // Building from source takes minutes, so show that it is still going.
+    // cargo's own output is captured while the spinner is drawn.
+    let heartbeat = PBAR.heartbeat(&format!(
+        "Installing {} {} with `cargo install`",
+        tool, version
+    ));
+    let result = if heartbeat.is_running() {
+        child::run_captured(cmd, "cargo install")
+    } else {
+        child::run(cmd, "cargo install")
+    };
+    let elapsed = heartbeat.finish();
+    let keep_tmp = KEEP_TMP.load(Ordering::SeqCst);
+    if let Err(error) = result {
+        if keep_tmp {
+            PBAR.info(&format!(
+                "Kept the `cargo install` of {} at {}",
+                tool,
+                tmp.display()
+            ));
+        } else {
+            drop(fs::remove_dir_all(&tmp));
+        }
+        return Err(error.context(context));
+    }
+    PBAR.info(&format!(
+        "Installed {} {} in {}s",
+        tool,
+        version,
+        elapsed.as_secs()
+    ));
 
     // `cargo install` will put the installed binaries in `$root/bin/*`, but we
     // just want them in `$root/*` directly (which matches how the tarballs are
@@ -294,9 +623,131 @@ pub fn cargo_install(
         })?;
     }
 
+    // A mirror or replaced source could serve another version than the one
+    // asked for, which would then sit in the cache under the wrong name.
+    if matches!(tool, Tool::WasmBindgen) && version != "latest" {
+        let binary = tmp
+            .join("wasm-bindgen")
+            .with_extension(env::consts::EXE_EXTENSION);
+        let installed = get_cli_version(&tool, &binary)?;
+        if installed != version {
+            drop(fs::remove_dir_all(&tmp));
+            bail!(
+                "`cargo install` installed {} {} instead of {}; check that the configured \
+                 registry source or mirror has that version",
+                tool,
+                installed,
+                version
+            )
+        }
+    }
+
     // Finally, move the `tmp` directory into our binary cache.
     fs::rename(&tmp, &destination)?;
+    if keep_tmp {
+        PBAR.info(&format!(
+            "Kept the `cargo install` of {} at {}",
+            tool,
+            destination.display()
+        ));
+    }
 
     let download = Download::at(&destination);
     Ok(Status::Found(download))
 }
+
+/// Build the `cargo install` command that installs `version` of `tool` into
+/// `root` with `features` enabled, running at most `jobs` parallel jobs if
+/// given, with the `--config` values in `sources`, as read by
+/// `source_config`.
+///
+/// With a mirror set by `set_registry_mirror`, crates.io is replaced by it
+/// through `--config`, which takes precedence over the `.cargo/config.toml`
+/// files and `sources`.
+pub fn cargo_install_command(
+    tool: &Tool,
+    version: &str,
+    root: &Path,
+    features: &[String],
+    jobs: Option<u32>,
+    sources: &[String],
+) -> Command {
+    let crate_name = match tool {
+        Tool::WasmBindgen => "wasm-bindgen-cli".to_string(),
+        _ => tool.to_string(),
+    };
+    let mut cmd = Command::new("cargo");
+
+    cmd.arg("install")
+        .arg("--force")
+        .arg(crate_name)
+        .arg("--root")
+        .arg(root);
+
+    if version != "latest" {
+        cmd.arg("--version").arg(version);
+    }
+
+    if !features.is_empty() {
+        cmd.arg("--features").arg(features.join(","));
+    }
+
+    if let Some(jobs) = jobs {
+        cmd.arg("--jobs").arg(jobs.to_string());
+    }
+
+    for source in sources {
+        cmd.arg("--config").arg(source);
+    }
+
+    if let Some(index) = REGISTRY_MIRROR.lock().unwrap().as_deref() {
+        cmd.arg("--config")
+            .arg(format!("source.crates-io.replace-with={:?}", MIRROR_SOURCE))
+            .arg("--config")
+            .arg(format!("source.{}.registry={:?}", MIRROR_SOURCE, index));
+    }
+
+    cmd
+}
+
+/// The `[source]` tables of the `.cargo/config.toml`, or `.cargo/config`, in
+/// `dir`, as `--config` values for `cargo install`, such as
+/// `source.crates-io.replace-with="vendored-sources"`. The relative paths of
+/// `directory` and `local-registry` sources are made absolute, since cargo
+/// reads them relative to `dir`.
+pub fn source_config(dir: &Path) -> Result<Vec<String>> {
+    let config = match ["config.toml", "config"]
+        .iter()
+        .map(|name| dir.join(".cargo").join(name))
+        .find(|path| path.is_file())
+    {
+        Some(config) => config,
+        None => return Ok(Vec::new()),
+    };
+    let contents = fs::read_to_string(&config)
+        .with_context(|| format!("failed to read {}", config.display()))?;
+    let parsed: toml::Table = toml::from_str(&contents)
+        .with_context(|| format!("failed to parse {}", config.display()))?;
+    let sources = match parsed.get("source").and_then(toml::Value::as_table) {
+        Some(sources) => sources,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut values = Vec::new();
+    for (name, source) in sources {
+        let source = match source.as_table() {
+            Some(source) => source,
+            None => continue,
+        };
+        for (key, value) in source {
+            let value = match value.as_str() {
+                Some(path) if key == "directory" || key == "local-registry" => {
+                    toml::Value::String(dir.join(path).display().to_string())
+                }
+                _ => value.clone(),
+            };
+            values.push(format!("source.{}.{}={}", name, key, value));
+        }
+    }
+    Ok(values)
+}
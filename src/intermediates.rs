@@ -0,0 +1,58 @@
+//! Keeping the intermediate artifacts of a build around for debugging, with
+//! `wasm-pack build --keep-tmp`.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// The directory below the target directory that the intermediate artifacts
+/// of the crate named `name_prefix` are kept in.
+pub fn keep_dir(target_dir: &Path, name_prefix: &str) -> PathBuf {
+    target_dir.join("wasm-pack-tmp").join(name_prefix)
+}
+
+/// Empty `dir` of the artifacts kept by an earlier build.
+pub fn reset(dir: &Path) -> Result<()> {
+    drop(fs::remove_dir_all(dir));
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))
+}
+
+/// Keep a copy of the file at `path` in `dir`, returning where it is.
+pub fn keep_file(path: &Path, dir: &Path) -> Result<PathBuf> {
+    let kept = dir.join(path.file_name().unwrap());
+    fs::copy(path, &kept)
+        .with_context(|| format!("failed to copy {} to {}", path.display(), kept.display()))?;
+    Ok(kept)
+}
+
+/// Keep a copy of what `wasm-bindgen` wrote for `name_prefix` into `out_dir`
+/// in `dir`, before anything else changes it, returning where it is.
+pub fn keep_bindgen_output(out_dir: &Path, name_prefix: &str, dir: &Path) -> Result<PathBuf> {
+    let kept = dir.join("wasm-bindgen");
+    for entry in WalkDir::new(out_dir).min_depth(1) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(out_dir)?;
+        let generated = relative.starts_with("snippets")
+            || relative
+                .to_str()
+                .is_some_and(|name| name.starts_with(name_prefix));
+        if !generated {
+            continue;
+        }
+        let destination = kept.join(relative);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)?;
+        } else {
+            fs::create_dir_all(destination.parent().unwrap())?;
+            fs::copy(entry.path(), &destination).with_context(|| {
+                format!(
+                    "failed to copy {} to {}",
+                    entry.path().display(),
+                    destination.display()
+                )
+            })?;
+        }
+    }
+    Ok(kept)
+}
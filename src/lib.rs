@@ -16,34 +16,52 @@ extern crate serde_derive;
 extern crate binary_install;
 extern crate chrono;
 extern crate dialoguer;
+extern crate env_logger;
 extern crate log;
 extern crate serde_ignored;
 extern crate serde_json;
+extern crate siphasher;
 extern crate toml;
 extern crate walkdir;
 
+pub mod argfile;
 pub mod bindgen;
 pub mod build;
 pub mod cache;
+pub mod cdn;
 pub mod child;
 pub mod command;
+pub mod demo;
 pub mod emoji;
+pub mod fingerprint;
 pub mod generate;
+pub mod http;
+pub mod init_name;
 pub mod install;
+pub mod intermediates;
 pub mod license;
 pub mod lockfile;
+pub mod logger;
 pub mod manifest;
 pub mod npm;
+pub mod npmrc;
+pub mod pinned;
 pub mod progressbar;
 pub mod readme;
+pub mod rollback;
 pub mod stamps;
+pub mod tarball;
 pub mod target;
 pub mod test;
+pub mod timings;
+pub mod transform;
+pub mod wasm_features;
 pub mod wasm_opt;
 
-use crate::progressbar::{LogLevel, ProgressOutput};
+use crate::progressbar::{ColorChoice, LogLevel, ProgressOutput};
 use clap::builder::ArgAction;
 use clap::Parser;
+use std::path::PathBuf;
 
 /// The global progress bar and user-facing message output.
 pub static PBAR: ProgressOutput = ProgressOutput::new();
@@ -56,7 +74,8 @@ pub struct Cli {
     #[clap(subcommand)] // Note that we mark a field as a subcommand
     pub cmd: command::Command,
 
-    /// Log verbosity is based off the number of v used
+    /// Log verbosity is based off the number of v used. `-vvv` implies
+    /// `--verbose-child`.
     #[clap(long = "verbose", short = 'v', action = ArgAction::Count)]
     pub verbosity: u8,
 
@@ -67,4 +86,53 @@ pub struct Cli {
     #[clap(long = "log-level", default_value = "info")]
     /// The maximum level of messages that should be logged by wasm-pack. [possible values: info, warn, error]
     pub log_level: LogLevel,
+
+    #[clap(long = "color", default_value = "auto")]
+    /// Whether to use colors in the output. [possible values: always, never, auto]
+    pub color: ColorChoice,
+
+    #[clap(long = "fail-on-warnings", alias = "deny-warnings")]
+    /// Treat the warnings wasm-pack emits as errors, and exit with a non-zero
+    /// status if there were any
+    pub fail_on_warnings: bool,
+
+    #[clap(long = "verbose-child")]
+    /// Print the command line of every tool wasm-pack runs, such as cargo,
+    /// wasm-bindgen, wasm-opt and npm, before running it
+    pub verbose_child: bool,
+
+    #[clap(long = "network-timeout", value_name = "SECONDS", value_parser = http::parse_network_timeout)]
+    /// How many seconds to wait for a server to accept a connection, and then
+    /// for each part of its response, when downloading tools such as
+    /// wasm-bindgen and the WebDriver clients. Also set with
+    /// $WASM_PACK_NETWORK_TIMEOUT [default: 10 to connect, 30 to read]
+    pub network_timeout: Option<u64>,
+
+    #[clap(
+        long = "bindgen-downloads-base-url",
+        value_name = "URL",
+        value_parser = install::parse_bindgen_url_base
+    )]
+    /// Download the prebuilt releases of wasm-bindgen from below `URL`, such
+    /// as an internal mirror of the GitHub releases, as
+    /// `URL/<version>/wasm-bindgen-<version>-<target>.tar.gz`. Also set with
+    /// $WASM_PACK_BINDGEN_URL_BASE
+    pub bindgen_downloads_base_url: Option<String>,
+
+    #[clap(
+        long = "registry-mirror",
+        value_name = "INDEX",
+        value_parser = install::parse_registry_mirror
+    )]
+    /// When falling back to `cargo install` for wasm-bindgen, install from
+    /// the crates.io mirror whose index is at `INDEX`, such as
+    /// `sparse+https://mirror.example/index/`, instead of the sources in the
+    /// cargo configuration. Also set with $WASM_PACK_REGISTRY_MIRROR
+    pub registry_mirror: Option<String>,
+
+    #[clap(long = "log-file", value_name = "PATH")]
+    /// Also write what wasm-pack logs, down to debug level and including the
+    /// command line and duration of every tool it runs, to `PATH` as JSON
+    /// lines, whatever `RUST_LOG` is
+    pub log_file: Option<PathBuf>,
 }
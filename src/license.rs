@@ -48,6 +48,11 @@ pub fn copy_from_crate(crate_data: &CrateData, path: &Path, out_dir: &Path) -> R
         "crate's pkg directory should exist"
     );
 
+    if crate_data.is_unlicensed() {
+        // There's no license to give to the users of a proprietary package.
+        return Ok(());
+    }
+
     match (crate_data.crate_license(), crate_data.crate_license_file()) {
         (Some(_), _) => {
             let license_files = glob_license_files(path);
@@ -1,19 +1,46 @@
-//! Reading Cargo.lock lock file.
+//! Finding the versions of the crates a crate is built against.
+//!
+//! The resolved dependency graph comes from the `cargo metadata` snapshot in
+//! `CrateData`, so that changes to cargo's lock file format don't affect us.
+//! Parsing `Cargo.lock` directly is only a fallback for when that snapshot
+//! has no resolve graph.
 
 #![allow(clippy::new_ret_no_self)]
 
+use std::collections::{HashSet, VecDeque};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
+use crate::child;
+use crate::emoji;
 use crate::manifest::CrateData;
+use crate::PBAR;
 use anyhow::{anyhow, bail, Context, Result};
+use cargo_metadata::DependencyKind;
 use console::style;
 use toml;
 
-/// This struct represents the contents of `Cargo.lock`.
+/// The resolved packages of a crate's dependency graph, as in `Cargo.lock`.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Lockfile {
+    /// Every package in the resolved graph of the workspace.
     package: Vec<Package>,
+
+    /// The packages reachable from the crate being built, along with how many
+    /// dependency edges away from it they are, closest first.
+    #[serde(skip)]
+    reachable: Vec<(usize, Package)>,
+
+    /// The dependencies the crate being built declares itself, with the
+    /// table each is declared in.
+    #[serde(skip)]
+    direct: Vec<(DependencyKind, Package)>,
+
+    /// Whether the crate is built for its tests, which link against its
+    /// `[dev-dependencies]` as well.
+    #[serde(skip)]
+    for_tests: bool,
 }
 
 /// This struct represents a single package entry in `Cargo.lock`
@@ -21,13 +48,71 @@ pub struct Lockfile {
 struct Package {
     name: String,
     version: String,
+    /// The package's own dependencies, as `name` or `name version`.
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+impl Package {
+    fn from_metadata(
+        metadata: &cargo_metadata::Metadata,
+        pkg: &cargo_metadata::Package,
+    ) -> Package {
+        let dependencies = metadata
+            .resolve
+            .iter()
+            .flat_map(|resolve| &resolve.nodes)
+            .filter(|node| node.id == pkg.id)
+            .flat_map(|node| &node.dependencies)
+            .filter_map(|id| metadata.packages.iter().find(|dep| &dep.id == id))
+            .map(|dep| format!("{} {}", dep.name, dep.version))
+            .collect();
+        Package {
+            name: pkg.name.clone(),
+            version: pkg.version.to_string(),
+            dependencies,
+        }
+    }
 }
 
 impl Lockfile {
-    /// Read the `Cargo.lock` file for the crate at the given path.
+    /// Get the resolved dependency graph of the crate at the given path from
+    /// its `cargo metadata`, falling back to reading its `Cargo.lock` if the
+    /// metadata has no resolve graph.
     pub fn new(crate_data: &CrateData) -> Result<Lockfile> {
-        let lock_path = get_lockfile_path(crate_data)?;
-        let lockfile = fs::read_to_string(&lock_path)
+        let metadata = crate_data.metadata();
+        if metadata.resolve.is_none() {
+            return Lockfile::from_cargo_lock(&get_lockfile_path(crate_data)?);
+        }
+
+        let package = metadata
+            .packages
+            .iter()
+            .map(|pkg| Package::from_metadata(metadata, pkg))
+            .collect();
+        Ok(Lockfile {
+            package,
+            reachable: reachable_packages(crate_data),
+            direct: direct_dependencies(crate_data),
+            for_tests: false,
+        })
+    }
+
+    /// Like `new`, for building the crate's tests, so that a `wasm-bindgen`
+    /// in its `[dev-dependencies]` counts too.
+    pub fn for_tests(crate_data: &CrateData) -> Result<Lockfile> {
+        Ok(Lockfile {
+            for_tests: true,
+            ..Lockfile::new(crate_data)?
+        })
+    }
+
+    /// Read the packages listed in the `Cargo.lock` at `lock_path`.
+    ///
+    /// Without a dependency graph there is no telling which packages the
+    /// crate itself depends on, so every package in the workspace counts.
+    pub fn from_cargo_lock(lock_path: &Path) -> Result<Lockfile> {
+        let lockfile = fs::read_to_string(lock_path)
             .with_context(|| anyhow!("failed to read: {}", lock_path.display()))?;
         let lockfile = toml::from_str(&lockfile)
             .with_context(|| anyhow!("failed to parse: {}", lock_path.display()))?;
@@ -40,16 +125,51 @@ impl Lockfile {
     }
 
     /// Like `wasm_bindgen_version`, except it returns an error instead of
-    /// `None`.
+    /// `None`, or when the crate can reach several versions of `wasm-bindgen`
+    /// and it isn't clear which one it links against.
+    ///
+    /// When `wasm-bindgen` is only pulled in through another crate, its
+    /// version is still used but the user is nudged to depend on it directly.
     pub fn require_wasm_bindgen(&self) -> Result<&str> {
-        self.wasm_bindgen_version().ok_or_else(|| {
-            anyhow!(
+        let direct = self.direct_versions("wasm-bindgen")?;
+        let candidates = if !direct.is_empty() {
+            direct.clone()
+        } else if !self.reachable.is_empty() {
+            self.versions_within("wasm-bindgen", usize::MAX)
+        } else {
+            dedup(self.package.iter().filter(|p| p.name == "wasm-bindgen"))
+        };
+
+        match candidates[..] {
+            [] => bail!(
                 "Ensure that you have \"{}\" as a dependency in your Cargo.toml file:\n\
                  [dependencies]\n\
                  wasm-bindgen = \"0.2\"",
                 style("wasm-bindgen").bold().dim(),
-            )
-        })
+            ),
+            [version] => {
+                if direct.is_empty() {
+                    PBAR.warn(&format!(
+                        "`wasm-bindgen` is not a direct dependency of this crate, using version {} \
+                         from its dependencies. Consider adding `wasm-bindgen = \"{}\"` to the \
+                         [dependencies] in your Cargo.toml file.",
+                        version, version
+                    ));
+                }
+                Ok(version)
+            }
+            _ => bail!(
+                "Found several versions of `wasm-bindgen` in the dependencies of this crate: {}. \
+                 The wasm-bindgen CLI has to match the version the crate links against, so either \
+                 depend on that version directly in your Cargo.toml file, e.g. \
+                 `wasm-bindgen = \"={}\"`, or pin the CLI version in wasm-pack.toml:\n\
+                 [wasm-bindgen]\n\
+                 version = \"{}\"",
+                candidates.join(", "),
+                candidates[0],
+                candidates[0],
+            ),
+        }
     }
 
     /// Get the version of `wasm-bindgen` dependency used in the `Cargo.lock`.
@@ -57,22 +177,281 @@ impl Lockfile {
         self.get_package_version("wasm-bindgen-test")
     }
 
-    fn get_package_version(&self, package: &str) -> Option<&str> {
-        self.package
+    /// Like `wasm_bindgen_test_version`, except it returns an error instead of
+    /// `None`, or when `wasm-bindgen-test` was built against a different
+    /// `wasm-bindgen` than `cli_version`, whose test runner will be used.
+    ///
+    /// The runner and the test harness compiled into the tests have to agree
+    /// on a schema that changes even between patch releases of
+    /// `wasm-bindgen`, so any difference ends in an obscure error once the
+    /// tests run. That holds for a version pinned in `wasm-pack.toml` too.
+    pub fn require_wasm_bindgen_test(&self, cli_version: &str) -> Result<&str> {
+        let test_version = match self.wasm_bindgen_test_version() {
+            Some(version) => version,
+            None => bail!(
+                "Add \"{}\" to the [dev-dependencies] in your Cargo.toml file to run tests:\n\
+                 [dev-dependencies]\n\
+                 wasm-bindgen-test = \"0.3\"",
+                style("wasm-bindgen-test").bold().dim(),
+            ),
+        };
+
+        if let Some(bindgen_version) = self.dependency_version("wasm-bindgen-test", "wasm-bindgen")
+        {
+            if bindgen_version != cli_version {
+                bail!(
+                    "wasm-bindgen-test {} is built against wasm-bindgen {}, but the test runner \
+                     comes from wasm-bindgen {}. Update both crates to the same release, e.g. with \
+                     `cargo update -p wasm-bindgen -p wasm-bindgen-test`, so that the test harness \
+                     and the runner are compatible.",
+                    test_version,
+                    bindgen_version,
+                    cli_version,
+                );
+            }
+        }
+        Ok(test_version)
+    }
+
+    fn get_package(&self, package: &str) -> Option<&Package> {
+        self.reachable
             .iter()
+            .map(|(_, p)| p)
+            .chain(&self.package)
             .find(|p| p.name == package)
-            .map(|p| &p.version[..])
     }
+
+    fn get_package_version(&self, package: &str) -> Option<&str> {
+        self.get_package(package).map(|p| &p.version[..])
+    }
+
+    /// The version of `dependency` that `package` was resolved against.
+    fn dependency_version(&self, package: &str, dependency: &str) -> Option<&str> {
+        let entry = self
+            .get_package(package)?
+            .dependencies
+            .iter()
+            .find(|entry| entry.split(' ').next() == Some(dependency))?;
+        match entry.split(' ').nth(1) {
+            Some(version) => Some(version),
+            // `Cargo.lock` leaves the version out when there is only one.
+            None => self.get_package_version(dependency),
+        }
+    }
+
+    /// The distinct versions of `package` that the crate declares in the
+    /// tables it is built with: `[dependencies]`, and `[dev-dependencies]`
+    /// for tests. `[build-dependencies]` are built for the host, not for
+    /// wasm, so they don't count.
+    ///
+    /// Without a resolve graph that tells the tables apart, every package one
+    /// edge away counts.
+    fn direct_versions(&self, package: &str) -> Result<Vec<&str>> {
+        if self.direct.is_empty() {
+            return Ok(self.versions_within(package, 1));
+        }
+        let declared: Vec<_> = self
+            .direct
+            .iter()
+            .filter(|(kind, p)| {
+                p.name == package
+                    && match kind {
+                        DependencyKind::Normal => true,
+                        DependencyKind::Development => self.for_tests,
+                        _ => false,
+                    }
+            })
+            .collect();
+        let versions = dedup(declared.iter().map(|(_, p)| p));
+        if versions.len() > 1 {
+            let tables: Vec<String> = declared
+                .iter()
+                .map(|(kind, p)| format!("{} in {}", p.version, table_name(*kind)))
+                .collect();
+            bail!(
+                "The crate declares different versions of `{}`: {}. The tests are built with \
+                 both tables, so declare the same version in each of them.",
+                package,
+                tables.join(", ")
+            )
+        }
+        Ok(versions)
+    }
+
+    /// The distinct versions of `package` at most `depth` dependency edges
+    /// away from the crate being built, closest first.
+    fn versions_within(&self, package: &str, depth: usize) -> Vec<&str> {
+        dedup(
+            self.reachable
+                .iter()
+                .filter(|(d, p)| *d <= depth && p.name == package)
+                .map(|(_, p)| p),
+        )
+    }
+}
+
+fn dedup<'a>(packages: impl Iterator<Item = &'a Package>) -> Vec<&'a str> {
+    let mut versions = Vec::new();
+    for package in packages {
+        if !versions.contains(&&package.version[..]) {
+            versions.push(&package.version[..]);
+        }
+    }
+    versions
+}
+
+/// The name of the manifest table that declares dependencies of `kind`.
+fn table_name(kind: DependencyKind) -> &'static str {
+    match kind {
+        DependencyKind::Development => "[dev-dependencies]",
+        DependencyKind::Build => "[build-dependencies]",
+        _ => "[dependencies]",
+    }
+}
+
+/// The dependencies the crate being built declares itself, once for each
+/// table that declares them.
+fn direct_dependencies(crate_data: &CrateData) -> Vec<(DependencyKind, Package)> {
+    let metadata = crate_data.metadata();
+    let node = match metadata.resolve.as_ref().and_then(|resolve| {
+        resolve
+            .nodes
+            .iter()
+            .find(|node| &node.id == crate_data.package_id())
+    }) {
+        Some(node) => node,
+        None => return vec![],
+    };
+    let mut direct = Vec::new();
+    for dep in &node.deps {
+        let pkg = match metadata.packages.iter().find(|pkg| pkg.id == dep.pkg) {
+            Some(pkg) => pkg,
+            None => continue,
+        };
+        for info in &dep.dep_kinds {
+            direct.push((info.kind, Package::from_metadata(metadata, pkg)));
+        }
+    }
+    direct
+}
+
+/// Walk the resolved dependency graph from the crate being built, breadth
+/// first, so that the versions it actually links against are preferred over
+/// those used elsewhere in the workspace.
+fn reachable_packages(crate_data: &CrateData) -> Vec<(usize, Package)> {
+    let metadata = crate_data.metadata();
+    let resolve = match &metadata.resolve {
+        Some(resolve) => resolve,
+        None => return vec![],
+    };
+
+    let mut packages = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    queue.push_back((0, crate_data.package_id()));
+    while let Some((depth, id)) = queue.pop_front() {
+        if !seen.insert(id) {
+            continue;
+        }
+        if let Some(pkg) = metadata.packages.iter().find(|pkg| &pkg.id == id) {
+            packages.push((depth, Package::from_metadata(metadata, pkg)));
+        }
+        if let Some(node) = resolve.nodes.iter().find(|node| &node.id == id) {
+            queue.extend(node.dependencies.iter().map(|dep| (depth + 1, dep)));
+        }
+    }
+    packages
+}
+
+/// Run `cargo generate-lockfile` if the workspace of the crate at
+/// `crate_path` has no `Cargo.lock` yet, as is common for freshly cloned
+/// libraries that don't check theirs in.
+///
+/// This has to run before `CrateData::new`, since `cargo metadata` writes a
+/// missing lock file itself, ignoring `--locked`.
+///
+/// `extra_options` are the options passed through to cargo: with `--locked`
+/// or `--frozen` a missing lock file stays an error, and `--offline` is
+/// forwarded to cargo.
+pub fn generate_if_missing(crate_path: &Path, extra_options: &[String]) -> Result<()> {
+    // Leave a missing manifest to `CrateData::new`, which explains it.
+    if !crate_path.join("Cargo.toml").is_file() {
+        return Ok(());
+    }
+    // The common case, a crate that is its own workspace, doesn't need cargo
+    // to find its lock file. Only a member's is looked for at the root.
+    if crate_path.join("Cargo.lock").is_file() {
+        return Ok(());
+    }
+    let workspace_root = locate_workspace_root(crate_path)?;
+    if workspace_root.join("Cargo.lock").is_file() {
+        return Ok(());
+    }
+    let has_option = |name: &str| extra_options.iter().any(|option| option == name);
+    if has_option("--locked") || has_option("--frozen") {
+        lockfile_path_in(&workspace_root)?;
+    }
+
+    PBAR.info(&format!("{}Generating Cargo.lock...", emoji::MEMO));
+    let offline = has_option("--offline") || has_option("--frozen");
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&workspace_root)
+        .arg("generate-lockfile")
+        .arg("--manifest-path")
+        .arg(workspace_root.join("Cargo.toml"));
+    if offline {
+        cmd.arg("--offline");
+    }
+    child::run(cmd, "cargo generate-lockfile").with_context(|| {
+        if offline {
+            anyhow!(
+                "Cargo.lock is missing and could not be generated offline. Run `cargo \
+                 generate-lockfile` in {:?} with network access first.",
+                workspace_root
+            )
+        } else {
+            anyhow!("Generating Cargo.lock in {:?} failed", workspace_root)
+        }
+    })
+}
+
+/// The root of the workspace of the crate at `crate_path`, found with `cargo
+/// locate-project`, which unlike `cargo metadata` doesn't resolve, or lock,
+/// the dependencies.
+fn locate_workspace_root(crate_path: &Path) -> Result<PathBuf> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format")
+        .arg("plain")
+        .arg("--manifest-path")
+        .arg(crate_path.join("Cargo.toml"));
+    let manifest = child::run_capture_stdout(cmd, &"cargo locate-project")?;
+    Ok(Path::new(manifest.trim())
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default())
 }
 
 /// Given the path to the crate that we are building, return a `PathBuf`
 /// containing the location of the lock file, by finding the workspace root.
+///
+/// Cargo keeps a single lock file at the root of the workspace, so crates
+/// that are workspace members never have one next to their own manifest.
 fn get_lockfile_path(crate_data: &CrateData) -> Result<PathBuf> {
-    // Check that a lock file can be found in the directory. Return an error
-    // if it cannot, otherwise return the path buffer.
-    let lockfile_path = crate_data.workspace_root().join("Cargo.lock");
+    lockfile_path_in(crate_data.workspace_root())
+}
+
+/// The lock file of the workspace at `workspace_root`, or an error saying how
+/// to create it if there is none.
+fn lockfile_path_in(workspace_root: &Path) -> Result<PathBuf> {
+    let lockfile_path = workspace_root.join("Cargo.lock");
     if !lockfile_path.is_file() {
-        bail!("Could not find lockfile at {:?}", lockfile_path)
+        bail!(
+            "Could not find lockfile at {:?}. Run `cargo generate-lockfile` in {:?} to create it.",
+            lockfile_path,
+            workspace_root
+        )
     } else {
         Ok(lockfile_path)
     }
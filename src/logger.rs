@@ -0,0 +1,88 @@
+//! Setting up the `log` logger: `RUST_LOG` decides what goes to the terminal,
+//! and with `--log-file` everything wasm-pack logs at debug level or above
+//! is also written to a file, one JSON object per line.
+
+use anyhow::{Context, Result};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Install the logger, also writing to `log_file` if one is given.
+pub fn init(log_file: Option<&Path>) -> Result<()> {
+    let terminal = env_logger::Builder::from_default_env().build();
+    let file = match log_file {
+        Some(path) => Some(open(path)?),
+        None => None,
+    };
+    let max_level = match file {
+        Some(_) => terminal.filter().max(LevelFilter::Debug),
+        None => terminal.filter(),
+    };
+    log::set_boxed_logger(Box::new(Logger { terminal, file }))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+fn open(path: &Path) -> Result<Mutex<File>> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let file = File::create(path)
+        .with_context(|| format!("failed to create the log file {}", path.display()))?;
+    Ok(Mutex::new(file))
+}
+
+struct Logger {
+    terminal: env_logger::Logger,
+    file: Option<Mutex<File>>,
+}
+
+impl Logger {
+    /// Whether a record goes to the log file: wasm-pack's own down to debug
+    /// level, and those of the crates it uses down to info level.
+    fn logs_to_file(&self, metadata: &Metadata) -> bool {
+        self.file.is_some()
+            && (metadata.level() <= Level::Info
+                || metadata.level() <= Level::Debug && metadata.target().starts_with("wasm_pack"))
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.terminal.enabled(metadata) || self.logs_to_file(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.terminal.matches(record) {
+            self.terminal.log(record);
+        }
+        if !self.logs_to_file(record.metadata()) {
+            return;
+        }
+        let entry = serde_json::json!({
+            "time": chrono::Utc::now().to_rfc3339(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        });
+        let mut line = entry.to_string();
+        line.push('\n');
+        if let Some(file) = &self.file {
+            // A failure to log isn't worth failing the command for.
+            let _ = file.lock().unwrap().write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        self.terminal.flush();
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
@@ -2,7 +2,6 @@
 
 extern crate anyhow;
 extern crate clap;
-extern crate env_logger;
 extern crate human_panic;
 extern crate log;
 extern crate wasm_pack;
@@ -15,9 +14,11 @@ use std::panic;
 use std::sync::mpsc;
 use std::thread;
 use wasm_pack::{
+    argfile,
     build::{self, WasmPackVersion},
+    child,
     command::run_wasm_pack,
-    Cli, PBAR,
+    http, install, Cli, PBAR,
 };
 
 mod installer;
@@ -44,8 +45,6 @@ fn background_check_for_updates() -> mpsc::Receiver<Result<WasmPackVersion>> {
 }
 
 fn main() {
-    env_logger::init();
-
     setup_panic_hooks();
 
     if let Err(e) = run() {
@@ -78,15 +77,33 @@ fn run() -> Result<()> {
         }
     }
 
-    let args = Cli::parse();
+    let args = Cli::parse_from(argfile::expand(env::args_os())?);
+
+    wasm_pack::logger::init(args.log_file.as_deref())?;
 
     PBAR.set_log_level(args.log_level);
+    PBAR.set_color(args.color);
+    PBAR.set_deny_warnings(args.fail_on_warnings);
+    child::set_echo_commands(args.verbose_child || args.verbosity >= 3);
+    http::set_network_timeout(match args.network_timeout {
+        Some(seconds) => Some(seconds),
+        None => http::network_timeout_from_env()?,
+    });
+    install::set_bindgen_url_base(match args.bindgen_downloads_base_url {
+        Some(url) => Some(url),
+        None => install::bindgen_url_base_from_env()?,
+    });
+    install::set_registry_mirror(match args.registry_mirror {
+        Some(index) => Some(index),
+        None => install::registry_mirror_from_env()?,
+    });
 
     if args.quiet {
         PBAR.set_quiet(true);
     }
 
     run_wasm_pack(args.cmd)?;
+    PBAR.check_warnings()?;
 
     if let Ok(wasm_pack_version) = wasm_pack_version.try_recv() {
         match wasm_pack_version {
@@ -0,0 +1,15 @@
+/// The `deno.json` written for the `deno` target, which lets the generated
+/// module be imported by name and published to a Deno registry.
+#[derive(Serialize)]
+pub struct DenoConfig {
+    pub name: String,
+    pub version: String,
+    pub exports: String,
+    pub publish: DenoPublish,
+}
+
+/// The files that make up the published Deno module.
+#[derive(Serialize)]
+pub struct DenoPublish {
+    pub include: Vec<String>,
+}
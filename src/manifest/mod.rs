@@ -7,22 +7,24 @@
 )]
 
 use anyhow::{anyhow, bail, Context, Result};
+mod deno;
 mod npm;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{collections::HashMap, fs};
 
+use self::deno::{DenoConfig, DenoPublish};
 use self::npm::{
     repository::Repository, CommonJSPackage, ESModulesPackage, NoModulesPackage, NpmPackage,
 };
-use crate::command::build::{BuildProfile, Target};
+use crate::command::build::{BuildProfile, ModuleType, Target};
 use crate::PBAR;
 use cargo_metadata::Metadata;
 use chrono::offset;
 use chrono::DateTime;
 use serde::{self, Deserialize};
 use serde_json;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
 use std::io::Write;
 use strsim::levenshtein;
@@ -33,17 +35,33 @@ const WASM_PACK_VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION")
 const WASM_PACK_REPO_URL: &str = "https://github.com/rustwasm/wasm-pack";
 
 /// Store for metadata learned about a crate
+///
+/// `cargo metadata` is run exactly once, when the `CrateData` is constructed,
+/// and every later query is answered from that snapshot.
 pub struct CrateData {
     data: Metadata,
     current_idx: usize,
     manifest: CargoManifest,
     out_name: Option<String>,
+    crate_name: Option<String>,
+    module_type: Option<ModuleType>,
+    bin_target: Option<String>,
+    no_license: bool,
 }
 
 #[doc(hidden)]
 #[derive(Deserialize)]
 pub struct CargoManifest {
     package: CargoPackage,
+
+    #[serde(default)]
+    lib: CargoLib,
+}
+
+#[derive(Default, Deserialize)]
+struct CargoLib {
+    #[serde(default, rename = "required-features")]
+    required_features: Vec<String>,
 }
 
 #[derive(Deserialize)]
@@ -64,6 +82,105 @@ struct CargoMetadata {
 struct CargoWasmPack {
     #[serde(default)]
     profile: CargoWasmPackProfiles,
+
+    #[serde(default, rename = "chromedriver-version")]
+    chromedriver_version: Option<String>,
+
+    #[serde(default, rename = "msedgedriver-version")]
+    msedgedriver_version: Option<String>,
+
+    #[serde(default)]
+    geckodriver: Option<String>,
+
+    #[serde(default, rename = "webdriver-capabilities")]
+    webdriver_capabilities: Option<serde_json::Value>,
+
+    #[serde(default, rename = "package-manager")]
+    package_manager: Option<String>,
+
+    #[serde(default)]
+    test: CargoWasmPackTest,
+}
+
+#[derive(Default, Deserialize)]
+struct CargoWasmPackTest {
+    #[serde(default)]
+    chrome: BrowserOptions,
+
+    #[serde(default)]
+    firefox: BrowserOptions,
+
+    #[serde(default)]
+    edge: BrowserOptions,
+
+    #[serde(default)]
+    node: NodeOptions,
+
+    timeout: Option<u64>,
+
+    chromedriver: Option<String>,
+
+    geckodriver: Option<String>,
+}
+
+impl CargoWasmPack {
+    /// The driver pins set with the deprecated keys directly in
+    /// `[package.metadata.wasm-pack]`, as the deprecated key, the key in
+    /// `[package.metadata.wasm-pack.test]` replacing it, and whether that one
+    /// is set too.
+    fn deprecated_driver_pins(&self) -> Vec<(&'static str, &'static str, bool)> {
+        let mut pins = Vec::new();
+        if self.chromedriver_version.is_some() {
+            pins.push((
+                "chromedriver-version",
+                "chromedriver",
+                self.test.chromedriver.is_some(),
+            ));
+        }
+        if self.geckodriver.is_some() {
+            pins.push((
+                "geckodriver",
+                "geckodriver",
+                self.test.geckodriver.is_some(),
+            ));
+        }
+        pins
+    }
+}
+
+/// How to run node for `wasm-pack test --node`, from the
+/// `[package.metadata.wasm-pack.test.node]` table.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NodeOptions {
+    /// Extra command line arguments for node, such as
+    /// `--experimental-vm-modules`.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Extra environment variables for the tests.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// How to launch a browser for `wasm-pack test`, from a
+/// `[package.metadata.wasm-pack.test.<browser>]` table.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct BrowserOptions {
+    /// Extra command line arguments for the browser.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Browser preferences, such as Firefox's `about:config` settings.
+    #[serde(default)]
+    pub prefs: serde_json::Map<String, serde_json::Value>,
+
+    /// The size of the browser window, as `WIDTHxHEIGHT`.
+    #[serde(default, rename = "window-size")]
+    pub window_size: Option<String>,
+
+    /// The browser binary to launch instead of the installed one.
+    #[serde(default)]
+    pub binary: Option<PathBuf>,
 }
 
 #[derive(Deserialize)]
@@ -265,13 +382,43 @@ impl Crate {
     }
 }
 
-#[derive(Clone, Deserialize)]
-#[serde(untagged)]
+#[derive(Clone)]
 enum CargoWasmPackProfileWasmOpt {
     Enabled(bool),
     ExplicitArgs(Vec<String>),
 }
 
+impl<'de> Deserialize<'de> for CargoWasmPackProfileWasmOpt {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct WasmOptVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for WasmOptVisitor {
+            type Value = CargoWasmPackProfileWasmOpt;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a boolean or an array of `wasm-opt` arguments")
+            }
+
+            fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<Self::Value, E> {
+                Ok(CargoWasmPackProfileWasmOpt::Enabled(value))
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                Deserialize::deserialize(serde::de::value::SeqAccessDeserializer::new(seq))
+                    .map(CargoWasmPackProfileWasmOpt::ExplicitArgs)
+            }
+        }
+
+        deserializer.deserialize_any(WasmOptVisitor)
+    }
+}
+
 impl Default for CargoWasmPackProfileWasmOpt {
     fn default() -> Self {
         CargoWasmPackProfileWasmOpt::Enabled(false)
@@ -428,6 +575,146 @@ struct NpmData {
     keywords: Option<Vec<String>>, // https://docs.npmjs.com/files/package.json#keywords
 }
 
+/// A member crate of a cargo workspace.
+pub struct WorkspaceMember {
+    /// The name of the member's package.
+    pub name: String,
+    /// The directory containing the member's `Cargo.toml`.
+    pub path: PathBuf,
+    /// Whether the member has a `wasm-bindgen-test` dev-dependency, so that
+    /// `wasm-pack test` can run its tests.
+    pub has_wasm_bindgen_test: bool,
+    /// Whether the member's lib target is a `cdylib`, so that `wasm-pack
+    /// build` can package it.
+    pub is_cdylib: bool,
+    /// The names of the packages the member depends on, leaving out its
+    /// dev-dependencies.
+    pub dependencies: Vec<String>,
+}
+
+/// Find the members of the workspace whose root, or one of whose members, is
+/// at `path`, in the order cargo lists them.
+pub fn workspace_members(path: &Path) -> Result<Vec<WorkspaceMember>> {
+    let manifest_path = path.join("Cargo.toml");
+    if !manifest_path.is_file() {
+        bail!(
+            "crate directory is missing a `Cargo.toml` file; is `{}` the \
+             wrong directory?",
+            path.display()
+        )
+    }
+    let data = cargo_metadata::MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()?;
+    Ok(data
+        .workspace_packages()
+        .into_iter()
+        .map(|pkg| WorkspaceMember {
+            name: pkg.name.clone(),
+            path: pkg
+                .manifest_path
+                .parent()
+                .map(|dir| dir.as_std_path().to_path_buf())
+                .unwrap_or_default(),
+            has_wasm_bindgen_test: pkg.dependencies.iter().any(|dep| {
+                dep.name == "wasm-bindgen-test"
+                    && dep.kind == cargo_metadata::DependencyKind::Development
+            }),
+            is_cdylib: pkg
+                .targets
+                .iter()
+                .any(|target| target.kind.iter().any(|kind| kind == "cdylib")),
+            dependencies: pkg
+                .dependencies
+                .iter()
+                .filter(|dep| dep.kind != cargo_metadata::DependencyKind::Development)
+                .map(|dep| dep.name.clone())
+                .collect(),
+        })
+        .collect())
+}
+
+/// Order `members` so that each one comes after the others of `members` it
+/// depends on, keeping cargo's order otherwise.
+pub fn dependency_order(members: Vec<WorkspaceMember>) -> Result<Vec<WorkspaceMember>> {
+    let names: Vec<String> = members.iter().map(|member| member.name.clone()).collect();
+    let mut remaining = members;
+    let mut ordered: Vec<WorkspaceMember> = Vec::new();
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|member| {
+            member
+                .dependencies
+                .iter()
+                .all(|dep| !names.contains(dep) || ordered.iter().any(|done| &done.name == dep))
+        });
+        match ready {
+            Some(index) => ordered.push(remaining.remove(index)),
+            None => bail!(
+                "the workspace members {} depend on each other in a cycle",
+                remaining
+                    .iter()
+                    .map(|member| format!("`{}`", member.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+    Ok(ordered)
+}
+
+/// Find the directory of the workspace member named `name`, in the workspace
+/// at `path`.
+pub fn workspace_member(path: &Path, name: &str) -> Result<PathBuf> {
+    let members = workspace_members(path)?;
+    match members.iter().find(|member| member.name == name) {
+        Some(member) => Ok(member.path.clone()),
+        None => bail!(
+            "package `{}` is not a member of the workspace at {}; its members are: {}",
+            name,
+            path.display(),
+            members
+                .iter()
+                .map(|member| format!("`{}`", member.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Make sure the entry points the `package.json` in `out_dir` names in
+/// `main`, `module`, `browser` and `types` are files in `out_dir`, since a
+/// bundler resolving the package would fail on a missing one.
+pub fn check_package_entries(out_dir: &Path) -> Result<()> {
+    let package_json = out_dir.join("package.json");
+    let package: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(&package_json)
+            .with_context(|| format!("failed to read {}", package_json.display()))?,
+    )
+    .with_context(|| format!("failed to parse {}", package_json.display()))?;
+    let missing: Vec<String> = ["main", "module", "browser", "types"]
+        .iter()
+        .filter_map(|field| Some((field, package[field].as_str()?)))
+        .filter(|(_, entry)| !out_dir.join(entry).is_file())
+        .map(|(field, entry)| format!("`{}` ({})", field, entry))
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "The package.json in {} names entry points that aren't in the package: {}",
+            out_dir.display(),
+            missing.join(", ")
+        )
+    }
+    Ok(())
+}
+
+impl CargoManifest {
+    /// Get the package manager set in `[package.metadata.wasm-pack]`, if any.
+    pub fn package_manager(&self) -> Option<&str> {
+        self.package.metadata.wasm_pack.package_manager.as_deref()
+    }
+}
+
 #[doc(hidden)]
 pub struct ManifestAndUnusedKeys {
     pub manifest: CargoManifest,
@@ -437,6 +724,8 @@ pub struct ManifestAndUnusedKeys {
 impl CrateData {
     /// Reads all metadata for the crate whose manifest is inside the directory
     /// specified by `path`.
+    ///
+    /// This is the only place where `cargo metadata` gets invoked.
     pub fn new(crate_path: &Path, out_name: Option<String>) -> Result<CrateData> {
         let manifest_path = crate_path.join("Cargo.toml");
         if !manifest_path.is_file() {
@@ -447,13 +736,14 @@ impl CrateData {
             )
         }
 
+        let manifest_and_keys = CrateData::parse_crate_data(&manifest_path)?;
+        CrateData::warn_for_unused_keys(&manifest_and_keys);
+        CrateData::warn_for_deprecated_keys(&manifest_and_keys);
+
         let data = cargo_metadata::MetadataCommand::new()
             .manifest_path(&manifest_path)
             .exec()?;
 
-        let manifest_and_keys = CrateData::parse_crate_data(&manifest_path)?;
-        CrateData::warn_for_unused_keys(&manifest_and_keys);
-
         let manifest = manifest_and_keys.manifest;
         let current_idx = data
             .packages
@@ -469,6 +759,10 @@ impl CrateData {
             manifest,
             current_idx,
             out_name,
+            crate_name: None,
+            module_type: None,
+            no_license: false,
+            bin_target: None,
         })
     }
 
@@ -486,27 +780,71 @@ impl CrateData {
     /// and a `BTreeSet<String>` containing the unused keys from the parsed file.
     ///
     /// # Errors
-    /// Will return Err if the file (manifest_path) couldn't be read or
-    /// if deserialize to `CargoManifest` fails.
+    /// Will return Err if the file (manifest_path) couldn't be read, if
+    /// deserialize to `CargoManifest` fails, if the
+    /// `[package.metadata.wasm-pack]` table contains unknown keys, or if a
+    /// driver is pinned with both its deprecated and its current key.
     pub fn parse_crate_data(manifest_path: &Path) -> Result<ManifestAndUnusedKeys> {
-        let manifest = fs::read_to_string(&manifest_path)
+        let contents = fs::read_to_string(manifest_path)
             .with_context(|| anyhow!("failed to read: {}", manifest_path.display()))?;
-        let manifest = toml::Deserializer::new(&manifest);
+        let manifest = toml::Deserializer::new(&contents);
 
         let mut unused_keys = BTreeSet::new();
+        let mut unknown_keys = Vec::new();
         let levenshtein_threshold = 1;
 
         let manifest: CargoManifest = serde_ignored::deserialize(manifest, |path| {
-            let path_string = path.to_string();
+            // `Option`s show up as `?` segments in the path, which only get in
+            // the way of pointing at the offending key.
+            let path_string = path.to_string().replace(".?", "");
 
-            if path_string.starts_with("package.metadata")
+            if path_string.starts_with(&format!("{}.", WASM_PACK_METADATA_KEY)) {
+                unknown_keys.push(path_string);
+            } else if path_string.starts_with("package.metadata")
                 && (path_string.contains("wasm-pack")
                     || levenshtein(WASM_PACK_METADATA_KEY, &path_string) <= levenshtein_threshold)
             {
                 unused_keys.insert(path_string);
             }
         })
-        .with_context(|| anyhow!("failed to parse manifest: {}", manifest_path.display()))?;
+        .map_err(|e| {
+            anyhow!(
+                "failed to parse manifest: {}\n{}",
+                manifest_path.display(),
+                describe_toml_error(&contents, &e)
+            )
+        })?;
+
+        if !unknown_keys.is_empty() {
+            let messages = unknown_keys
+                .iter()
+                .map(|key| describe_unknown_key(key))
+                .collect::<Vec<_>>();
+            bail!(
+                "failed to parse manifest: {}\n{}",
+                manifest_path.display(),
+                messages.join("\n")
+            );
+        }
+
+        let wasm_pack = &manifest.package.metadata.wasm_pack;
+        if let Some((deprecated, key, _)) = wasm_pack
+            .deprecated_driver_pins()
+            .into_iter()
+            .find(|(_, _, both)| *both)
+        {
+            bail!(
+                "failed to parse manifest: {}\n`{}.{}` and `{}.test.{}` both pin the same \
+                 driver; remove the deprecated `{}.{}`",
+                manifest_path.display(),
+                WASM_PACK_METADATA_KEY,
+                deprecated,
+                WASM_PACK_METADATA_KEY,
+                key,
+                WASM_PACK_METADATA_KEY,
+                deprecated
+            );
+        }
 
         Ok(ManifestAndUnusedKeys {
             manifest,
@@ -525,6 +863,18 @@ impl CrateData {
         });
     }
 
+    /// Output a warning for each driver pinned with a deprecated key directly
+    /// in `[package.metadata.wasm-pack]`.
+    pub fn warn_for_deprecated_keys(manifest_and_keys: &ManifestAndUnusedKeys) {
+        let wasm_pack = &manifest_and_keys.manifest.package.metadata.wasm_pack;
+        for (deprecated, key, _) in wasm_pack.deprecated_driver_pins() {
+            PBAR.warn(&format!(
+                "\"{}.{}\" is deprecated, use \"{}.test.{}\" instead.",
+                WASM_PACK_METADATA_KEY, deprecated, WASM_PACK_METADATA_KEY, key
+            ));
+        }
+    }
+
     /// Get the configured profile.
     pub fn configured_profile(&self, profile: BuildProfile) -> &CargoWasmPackProfile {
         match profile {
@@ -542,7 +892,11 @@ impl CrateData {
     }
 
     fn check_crate_type(&self) -> Result<()> {
-        let pkg = &self.data.packages[self.current_idx];
+        // A bin target is built as it is, without a lib.
+        if self.bin_target.is_some() {
+            return Ok(());
+        }
+        let pkg = self.pkg();
         let any_cdylib = pkg
             .targets
             .iter()
@@ -563,19 +917,168 @@ impl CrateData {
         &self.data.packages[self.current_idx]
     }
 
+    /// Returns the `cargo metadata` snapshot taken when this `CrateData` was
+    /// constructed.
+    pub fn metadata(&self) -> &Metadata {
+        &self.data
+    }
+
+    /// Get the `cargo metadata` package id of the crate at the given path.
+    pub fn package_id(&self) -> &cargo_metadata::PackageId {
+        &self.pkg().id
+    }
+
+    /// Override the name of the crate's lib target, for setups where it
+    /// can't be told from the manifest.
+    pub fn set_crate_name(&mut self, crate_name: Option<String>) {
+        self.crate_name = crate_name;
+    }
+
+    /// Choose which target to build: the bin target named `bin`, or the lib
+    /// if `lib` is set or `bin` isn't.
+    ///
+    /// A crate with both a `cdylib` and bin targets has to be told which one
+    /// is meant, rather than the lib being built without a word.
+    pub fn select_target(&mut self, lib: bool, bin: Option<String>) -> Result<()> {
+        let pkg = self.pkg();
+        let bins: Vec<&str> = pkg
+            .targets
+            .iter()
+            .filter(|t| t.kind.iter().any(|k| k == "bin"))
+            .map(|t| t.name.as_str())
+            .collect();
+        let cdylib = pkg
+            .targets
+            .iter()
+            .find(|t| t.kind.iter().any(|k| k == "cdylib"))
+            .map(|t| t.name.as_str());
+        let available = || {
+            let mut available: Vec<String> =
+                bins.iter().map(|bin| format!("  --bin {}", bin)).collect();
+            if let Some(lib) = cdylib {
+                available.insert(0, format!("  --lib    (the `cdylib` {})", lib));
+            }
+            available.join("\n")
+        };
+        match bin {
+            Some(bin) if bins.contains(&bin.as_str()) => {
+                self.bin_target = Some(bin);
+                Ok(())
+            }
+            Some(bin) => bail!(
+                "The crate {} has no bin target named `{}`. Its targets are:\n{}",
+                pkg.name,
+                bin,
+                available()
+            ),
+            None if !lib && cdylib.is_some() && !bins.is_empty() => bail!(
+                "The crate {} has both a `cdylib` and bin targets, so choose which one to build:\n{}",
+                pkg.name,
+                available()
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// The bin target chosen to be built instead of the lib, if any.
+    pub fn bin_target(&self) -> Option<&str> {
+        self.bin_target.as_deref()
+    }
+
+    /// Override the `type` field of the generated `package.json`.
+    pub fn set_module_type(&mut self, module_type: Option<ModuleType>) {
+        self.module_type = module_type;
+    }
+
+    /// Leave the license out of the package: no `license` field in the
+    /// generated `package.json`, and no warning that the crate has none.
+    pub fn set_no_license(&mut self, no_license: bool) {
+        self.no_license = no_license;
+    }
+
+    /// Whether the license is left out of the package.
+    pub fn no_license(&self) -> bool {
+        self.no_license
+    }
+
+    /// Whether the crate declares itself proprietary, with a `license` of
+    /// `UNLICENSED`, as npm spells it, or `proprietary`.
+    pub fn is_unlicensed(&self) -> bool {
+        self.crate_license().as_deref().is_some_and(|license| {
+            license.eq_ignore_ascii_case("UNLICENSED")
+                || license.eq_ignore_ascii_case("proprietary")
+                || license.eq_ignore_ascii_case("LicenseRef-Proprietary")
+        })
+    }
+
+    /// The `type` field of the generated `package.json` for `target`.
+    fn module_type(&self, target: Target) -> Option<String> {
+        self.module_type
+            .or_else(|| ModuleType::for_target(target))
+            .map(|ty| ty.to_string())
+    }
+
     /// Get the crate name for the crate at the given path.
+    ///
+    /// This is the name of the lib target, which is what the compiled `.wasm`
+    /// is named after: the `--crate-name` override if there is one, then the
+    /// `cdylib` target, then any other lib target, then the package name. A
+    /// chosen bin target is used as it is instead.
     pub fn crate_name(&self) -> String {
+        if let Some(bin) = &self.bin_target {
+            return bin.clone();
+        }
+        if let Some(name) = &self.crate_name {
+            return name.replace("-", "_");
+        }
         let pkg = self.pkg();
+        let is_lib = |k: &String| ["lib", "rlib", "dylib", "staticlib"].contains(&k.as_str());
         match pkg
             .targets
             .iter()
             .find(|t| t.kind.iter().any(|k| k == "cdylib"))
+            .or_else(|| pkg.targets.iter().find(|t| t.kind.iter().any(is_lib)))
         {
             Some(lib) => lib.name.replace("-", "_"),
             None => pkg.name.replace("-", "_"),
         }
     }
 
+    /// Get the path to the root source file of the lib target, usually
+    /// `src/lib.rs`.
+    pub fn lib_src_path(&self) -> Option<PathBuf> {
+        self.pkg()
+            .targets
+            .iter()
+            .find(|t| t.kind.iter().any(|k| k == "lib" || k == "cdylib"))
+            .map(|t| t.src_path.clone().into_std_path_buf())
+    }
+
+    /// Get the features the lib target lists in `required-features`.
+    ///
+    /// `cargo metadata` only reports `required-features` for binaries,
+    /// examples, tests and benches, so the manifest is consulted as well.
+    pub fn lib_required_features(&self) -> Vec<String> {
+        let mut features: Vec<String> = self
+            .pkg()
+            .targets
+            .iter()
+            .filter(|t| t.kind.iter().any(|k| k == "lib" || k == "cdylib"))
+            .flat_map(|t| t.required_features.iter().cloned())
+            .collect();
+        for feature in &self.manifest.lib.required_features {
+            if !features.contains(feature) {
+                features.push(feature.clone());
+            }
+        }
+        features
+    }
+
+    /// Get the features declared in the crate's `[features]` table.
+    pub fn crate_features(&self) -> &HashMap<String, Vec<String>> {
+        &self.pkg().features
+    }
+
     /// Get the prefix for output file names
     pub fn name_prefix(&self) -> String {
         match &self.out_name {
@@ -584,6 +1087,76 @@ impl CrateData {
         }
     }
 
+    /// Get the `chromedriver` version pinned in
+    /// `[package.metadata.wasm-pack.test]`, or else with the deprecated
+    /// `chromedriver-version` in `[package.metadata.wasm-pack]`, if any.
+    pub fn chromedriver_version(&self) -> Option<&str> {
+        let wasm_pack = &self.manifest.package.metadata.wasm_pack;
+        wasm_pack
+            .test
+            .chromedriver
+            .as_deref()
+            .or(wasm_pack.chromedriver_version.as_deref())
+    }
+
+    /// Get the `msedgedriver` version pinned in
+    /// `[package.metadata.wasm-pack]`, if any.
+    pub fn msedgedriver_version(&self) -> Option<&str> {
+        self.manifest
+            .package
+            .metadata
+            .wasm_pack
+            .msedgedriver_version
+            .as_deref()
+    }
+
+    /// Get the `geckodriver` version pinned in
+    /// `[package.metadata.wasm-pack.test]`, or else with the deprecated
+    /// `geckodriver` in `[package.metadata.wasm-pack]`, if any.
+    pub fn geckodriver_version(&self) -> Option<&str> {
+        let wasm_pack = &self.manifest.package.metadata.wasm_pack;
+        wasm_pack
+            .test
+            .geckodriver
+            .as_deref()
+            .or(wasm_pack.geckodriver.as_deref())
+    }
+
+    /// Get the desired capabilities of browser test sessions from the
+    /// `[package.metadata.wasm-pack.webdriver-capabilities]` table, if any.
+    pub fn webdriver_capabilities(&self) -> Option<&serde_json::Value> {
+        self.manifest
+            .package
+            .metadata
+            .wasm_pack
+            .webdriver_capabilities
+            .as_ref()
+    }
+
+    /// Get the options for launching `browser` (`chrome`, `firefox` or
+    /// `edge`) from `[package.metadata.wasm-pack.test.<browser>]`.
+    pub fn browser_options(&self, browser: &str) -> Option<&BrowserOptions> {
+        let test = &self.manifest.package.metadata.wasm_pack.test;
+        match browser {
+            "chrome" => Some(&test.chrome),
+            "firefox" => Some(&test.firefox),
+            "edge" => Some(&test.edge),
+            _ => None,
+        }
+    }
+
+    /// Get the options for running node from
+    /// `[package.metadata.wasm-pack.test.node]`.
+    pub fn node_options(&self) -> &NodeOptions {
+        &self.manifest.package.metadata.wasm_pack.test.node
+    }
+
+    /// Get the default number of seconds the test runner waits for each test,
+    /// from `[package.metadata.wasm-pack.test] timeout`.
+    pub fn test_timeout(&self) -> Option<u64> {
+        self.manifest.package.metadata.wasm_pack.test.timeout
+    }
+
     /// Gets the optional path to the readme, or None if disabled.
     pub fn crate_readme(&self) -> Option<String> {
         self.pkg()
@@ -616,7 +1189,8 @@ impl CrateData {
         Path::new(&self.data.workspace_root)
     }
 
-    /// Generate a package.json file inside in `./pkg`.
+    /// Generate a package.json file inside in `./pkg`, or a deno.json file
+    /// when targeting Deno.
     pub fn write_package_json(
         &self,
         out_dir: &Path,
@@ -624,6 +1198,10 @@ impl CrateData {
         disable_dts: bool,
         target: Target,
     ) -> Result<()> {
+        if let Target::Deno = target {
+            return self.write_deno_json(out_dir, scope, disable_dts);
+        }
+
         let pkg_file_path = out_dir.join("package.json");
         // Check if a `package.json` was already generated by wasm-bindgen, if so
         // we merge the NPM dependencies already specified in it.
@@ -640,8 +1218,7 @@ impl CrateData {
             Target::NoModules => self.to_nomodules(scope, disable_dts, existing_deps, out_dir),
             Target::Bundler => self.to_esmodules(scope, disable_dts, existing_deps, out_dir),
             Target::Web => self.to_web(scope, disable_dts, existing_deps, out_dir),
-            // Deno does not need package.json
-            Target::Deno => return Ok(()),
+            Target::Deno => unreachable!("deno.json is written by `write_deno_json`"),
         };
 
         let npm_json = serde_json::to_string_pretty(&npm_data)?;
@@ -651,6 +1228,29 @@ impl CrateData {
         Ok(())
     }
 
+    fn write_deno_json(
+        &self,
+        out_dir: &Path,
+        scope: &Option<String>,
+        disable_dts: bool,
+    ) -> Result<()> {
+        let data = self.npm_data(scope, false, disable_dts, out_dir);
+        let deno_data = DenoConfig {
+            name: data.name,
+            version: self.pkg().version.to_string(),
+            exports: format!("./{}", data.main),
+            publish: DenoPublish {
+                include: data.files,
+            },
+        };
+
+        let deno_file_path = out_dir.join("deno.json");
+        let deno_json = serde_json::to_string_pretty(&deno_data)?;
+        fs::write(&deno_file_path, deno_json)
+            .with_context(|| anyhow!("failed to write: {}", deno_file_path.display()))?;
+        Ok(())
+    }
+
     fn npm_data(
         &self,
         scope: &Option<String>,
@@ -669,7 +1269,7 @@ impl CrateData {
             files.push(js_bg_file);
         }
 
-        let pkg = &self.data.packages[self.current_idx];
+        let pkg = self.pkg();
         let npm_name = match scope {
             Some(s) => format!("@{}/{}", s, pkg.name),
             None => pkg.name.clone(),
@@ -678,6 +1278,12 @@ impl CrateData {
         let dts_file = if !disable_dts {
             let file = format!("{}.d.ts", name_prefix);
             files.push(file.to_string());
+            // The declarations of the `_bg.wasm` module, which the `.d.ts` of
+            // some targets refers to.
+            let wasm_dts = format!("{}_bg.wasm.d.ts", name_prefix);
+            if out_dir.join(&wasm_dts).is_file() {
+                files.push(wasm_dts);
+            }
             Some(file)
         } else {
             None
@@ -712,6 +1318,12 @@ impl CrateData {
     }
 
     fn license(&self) -> Option<String> {
+        if self.no_license {
+            return None;
+        }
+        if self.is_unlicensed() {
+            return Some("UNLICENSED".to_string());
+        }
         self.crate_license().clone().or_else(|| {
             self.crate_license_file().clone().map(|file| {
                 // When license is written in file: https://docs.npmjs.com/files/package.json#license
@@ -720,6 +1332,15 @@ impl CrateData {
         })
     }
 
+    /// Keep npm from publishing a proprietary package by accident.
+    fn private(&self) -> Option<bool> {
+        if !self.no_license && self.is_unlicensed() {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
     fn to_commonjs(
         &self,
         scope: &Option<String>,
@@ -728,16 +1349,18 @@ impl CrateData {
         out_dir: &Path,
     ) -> NpmPackage {
         let data = self.npm_data(scope, false, disable_dts, out_dir);
-        let pkg = &self.data.packages[self.current_idx];
+        let pkg = self.pkg();
 
         self.check_optional_fields();
 
         NpmPackage::CommonJSPackage(CommonJSPackage {
             name: data.name,
+            ty: self.module_type(Target::Nodejs),
             collaborators: pkg.authors.clone(),
             description: self.pkg().description.clone(),
             version: pkg.version.to_string(),
             license: self.license(),
+            private: self.private(),
             repository: self.pkg().repository.clone().map(|repo_url| Repository {
                 ty: "git".to_string(),
                 url: repo_url,
@@ -759,23 +1382,26 @@ impl CrateData {
         out_dir: &Path,
     ) -> NpmPackage {
         let data = self.npm_data(scope, true, disable_dts, out_dir);
-        let pkg = &self.data.packages[self.current_idx];
+        let pkg = self.pkg();
 
         self.check_optional_fields();
 
         NpmPackage::ESModulesPackage(ESModulesPackage {
             name: data.name,
-            ty: "module".into(),
+            ty: self.module_type(Target::Bundler),
             collaborators: pkg.authors.clone(),
             description: self.pkg().description.clone(),
             version: pkg.version.to_string(),
             license: self.license(),
+            private: self.private(),
             repository: self.pkg().repository.clone().map(|repo_url| Repository {
                 ty: "git".to_string(),
                 url: repo_url,
             }),
             files: data.files,
             main: data.main.clone(),
+            // Bundlers look for the ES module entry in `module`.
+            module: Some(data.main.clone()),
             homepage: data.homepage,
             types: data.dts_file,
             side_effects: vec![format!("./{}", data.main), "./snippets/*".to_owned()],
@@ -792,23 +1418,25 @@ impl CrateData {
         out_dir: &Path,
     ) -> NpmPackage {
         let data = self.npm_data(scope, false, disable_dts, out_dir);
-        let pkg = &self.data.packages[self.current_idx];
+        let pkg = self.pkg();
 
         self.check_optional_fields();
 
         NpmPackage::ESModulesPackage(ESModulesPackage {
             name: data.name,
-            ty: "module".into(),
+            ty: self.module_type(Target::Web),
             collaborators: pkg.authors.clone(),
             description: self.pkg().description.clone(),
             version: pkg.version.to_string(),
             license: self.license(),
+            private: self.private(),
             repository: self.pkg().repository.clone().map(|repo_url| Repository {
                 ty: "git".to_string(),
                 url: repo_url,
             }),
             files: data.files,
             main: data.main,
+            module: None,
             homepage: data.homepage,
             types: data.dts_file,
             side_effects: vec!["./snippets/*".to_owned()],
@@ -825,16 +1453,18 @@ impl CrateData {
         out_dir: &Path,
     ) -> NpmPackage {
         let data = self.npm_data(scope, false, disable_dts, out_dir);
-        let pkg = &self.data.packages[self.current_idx];
+        let pkg = self.pkg();
 
         self.check_optional_fields();
 
         NpmPackage::NoModulesPackage(NoModulesPackage {
             name: data.name,
+            ty: self.module_type(Target::NoModules),
             collaborators: pkg.authors.clone(),
             description: self.pkg().description.clone(),
             version: pkg.version.to_string(),
             license: self.license(),
+            private: self.private(),
             repository: self.pkg().repository.clone().map(|repo_url| Repository {
                 ty: "git".to_string(),
                 url: repo_url,
@@ -856,7 +1486,7 @@ impl CrateData {
         if self.pkg().repository.is_none() {
             messages.push("repository");
         }
-        if self.pkg().license.is_none() && self.pkg().license_file.is_none() {
+        if !self.no_license && self.pkg().license.is_none() && self.pkg().license_file.is_none() {
             messages.push("license");
         }
 
@@ -868,3 +1498,115 @@ impl CrateData {
         };
     }
 }
+
+/// The keys that are valid directly below the given `[package.metadata.wasm-pack]`
+/// table, identified by its path relative to that table.
+fn known_metadata_keys(table: &[&str]) -> &'static [&'static str] {
+    match table {
+        [] => &[
+            "profile",
+            "chromedriver-version",
+            "msedgedriver-version",
+            "geckodriver",
+            "webdriver-capabilities",
+            "package-manager",
+            "test",
+        ],
+        ["test"] => &[
+            "chrome",
+            "firefox",
+            "edge",
+            "node",
+            "timeout",
+            "chromedriver",
+            "geckodriver",
+        ],
+        ["test", "node"] => &["args", "env"],
+        ["test", _] => &["args", "prefs", "window-size", "binary"],
+        ["profile"] => &["dev", "release", "profiling", "custom"],
+        ["profile", _] => &["wasm-bindgen", "wasm-opt"],
+        ["profile", _, "wasm-bindgen"] => &[
+            "debug-js-glue",
+            "demangle-name-section",
+            "dwarf-debug-info",
+            "omit-default-module-path",
+            "split-linked-modules",
+        ],
+        _ => &[],
+    }
+}
+
+/// Explain an unknown key inside the `[package.metadata.wasm-pack]` table,
+/// suggesting the closest valid key if there is one.
+fn describe_unknown_key(path: &str) -> String {
+    let relative = &path[WASM_PACK_METADATA_KEY.len() + 1..];
+    let segments: Vec<&str> = relative.split('.').collect();
+    let (key, table) = segments.split_last().unwrap();
+    let known = known_metadata_keys(table);
+
+    let suggestion = known
+        .iter()
+        .map(|candidate| (levenshtein(key, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance);
+
+    match suggestion {
+        Some((_, candidate)) => format!("unknown key `{}`, did you mean `{}`?", path, candidate),
+        None if known.is_empty() => format!("unknown key `{}`", path),
+        None => format!(
+            "unknown key `{}`, expected one of: {}",
+            path,
+            known
+                .iter()
+                .map(|k| format!("`{}`", k))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Turn a TOML deserialization error into a message that names the offending
+/// key and its line, rather than only a byte span.
+fn describe_toml_error(contents: &str, error: &toml::de::Error) -> String {
+    let span = match error.span() {
+        Some(span) => span,
+        None => return error.message().to_string(),
+    };
+    let line = contents[..span.start].matches('\n').count() + 1;
+    match key_path_at(contents, span.start) {
+        Some(key) => format!(
+            "invalid value for `{}` on line {}: {}",
+            key,
+            line,
+            error.message()
+        ),
+        None => format!("on line {}: {}", line, error.message()),
+    }
+}
+
+/// Reconstruct the dotted key path of the `key = value` pair that contains
+/// `offset`, by combining the key with the closest preceding table header.
+fn key_path_at(contents: &str, offset: usize) -> Option<String> {
+    let line_start = contents[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = contents[line_start..].lines().next()?;
+    let key = line.split('=').next().filter(|_| line.contains('='))?;
+
+    let header = contents[..line_start].lines().rev().find_map(|line| {
+        let line = line.trim();
+        line.strip_prefix("[[")
+            .and_then(|l| l.strip_suffix("]]"))
+            .or_else(|| line.strip_prefix('[').and_then(|l| l.strip_suffix(']')))
+    });
+
+    let normalize = |key: &str| {
+        key.split('.')
+            .map(|segment| segment.trim().trim_matches('"').trim_matches('\''))
+            .collect::<Vec<_>>()
+            .join(".")
+    };
+
+    Some(match header {
+        Some(header) => format!("{}.{}", normalize(header), normalize(key)),
+        None => normalize(key),
+    })
+}
@@ -5,6 +5,8 @@ use crate::manifest::npm::repository::Repository;
 #[derive(Serialize)]
 pub struct CommonJSPackage {
     pub name: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub collaborators: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -13,6 +15,8 @@ pub struct CommonJSPackage {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub license: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub private: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub repository: Option<Repository>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub files: Vec<String>,
@@ -1,52 +1,1001 @@
 //! Functionality related to publishing to npm.
 
 use crate::child;
-use crate::command::publish::access::Access;
-use anyhow::{bail, Context, Result};
+use crate::command::publish::access::{self, Access};
+use crate::http;
+use crate::manifest::CrateData;
+use crate::npmrc::Npmrc;
+use crate::PBAR;
+use anyhow::{bail, Context, Error, Result};
+use dialoguer::Input;
 use log::info;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use url::Url;
 
 /// The default npm registry used when we aren't working with a custom registry.
 pub const DEFAULT_NPM_REGISTRY: &str = "https://registry.npmjs.org/";
 
-/// Run the `npm pack` command.
-pub fn npm_pack(path: &str) -> Result<()> {
-    let mut cmd = child::new_command("npm");
+/// The package manager that packs and publishes the npm package.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageManager {
+    /// `npm`, the default.
+    Npm,
+    /// `yarn`, either Yarn 1 or a later version.
+    Yarn,
+    /// `pnpm`.
+    Pnpm,
+}
+
+impl PackageManager {
+    /// The package manager whose lockfile is in `dir` or the closest of its
+    /// parents with one, or npm if there's none.
+    pub fn detect(dir: &Path) -> PackageManager {
+        for dir in dir.ancestors() {
+            if dir.join("pnpm-lock.yaml").is_file() {
+                return PackageManager::Pnpm;
+            }
+            if dir.join("yarn.lock").is_file() {
+                return PackageManager::Yarn;
+            }
+            if dir.join("package-lock.json").is_file() || dir.join("npm-shrinkwrap.json").is_file()
+            {
+                return PackageManager::Npm;
+            }
+        }
+        PackageManager::Npm
+    }
+
+    /// The package manager to use: the one `chosen` on the command line, then
+    /// the `package-manager` in `[package.metadata.wasm-pack]` of the crate at
+    /// `crate_path`, and then the one detected from the lockfiles around
+    /// `dir`. It has to be on the `$PATH`.
+    pub fn choose(
+        chosen: Option<PackageManager>,
+        crate_path: &Path,
+        dir: &Path,
+    ) -> Result<PackageManager> {
+        let package_manager = match chosen {
+            Some(package_manager) => package_manager,
+            None => match PackageManager::configured(crate_path)? {
+                Some(package_manager) => package_manager,
+                None => PackageManager::detect(dir),
+            },
+        };
+        package_manager.check_installed()?;
+        Ok(package_manager)
+    }
+
+    /// The package manager set in the `Cargo.toml` of the crate at
+    /// `crate_path`, if there's a crate there and it sets one. A manifest that
+    /// isn't a crate's, such as that of a virtual workspace, sets none.
+    fn configured(crate_path: &Path) -> Result<Option<PackageManager>> {
+        let manifest_path = crate_path.join("Cargo.toml");
+        if !manifest_path.is_file() {
+            return Ok(None);
+        }
+        let manifest = match CrateData::parse_crate_data(&manifest_path) {
+            Ok(data) => data.manifest,
+            Err(e) => {
+                info!(
+                    "Not reading a package manager from {}: {:#}",
+                    manifest_path.display(),
+                    e
+                );
+                return Ok(None);
+            }
+        };
+        manifest
+            .package_manager()
+            .map(|name| {
+                name.parse().with_context(|| {
+                    format!(
+                        "invalid `package-manager` in [package.metadata.wasm-pack] of {}",
+                        manifest_path.display()
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    /// Make sure the package manager is on the `$PATH`, explaining how to get
+    /// it if it isn't.
+    fn check_installed(self) -> Result<()> {
+        self.executable().map(drop)
+    }
+
+    /// The executable of the package manager on the `$PATH`.
+    pub fn executable(self) -> Result<PathBuf> {
+        if let Some(executable) = find_executable(self.program(), env::var_os("PATH")) {
+            return Ok(executable);
+        }
+        let install = match self {
+            PackageManager::Npm => {
+                "Install Node.js, which comes with npm, from https://nodejs.org/. If you use Yarn \
+                 or pnpm instead, pass `--package-manager yarn` or `--package-manager pnpm`."
+            }
+            PackageManager::Yarn => {
+                "Enable it with `corepack enable`, which comes with Node.js from \
+                 https://nodejs.org/, or see https://yarnpkg.com/getting-started/install"
+            }
+            PackageManager::Pnpm => {
+                "Enable it with `corepack enable`, which comes with Node.js from \
+                 https://nodejs.org/, or see https://pnpm.io/installation"
+            }
+        };
+        bail!("{} isn't on the $PATH. {}", self, install)
+    }
+
+    /// A command running the package manager.
+    fn command(self) -> Result<Command> {
+        Ok(Command::new(self.executable()?))
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Pnpm => "pnpm",
+        }
+    }
+
+    /// Whether this is Yarn 2 or later, which publishes with `yarn npm
+    /// publish` instead of `yarn publish`.
+    fn is_modern_yarn(self, path: &str) -> bool {
+        if self != PackageManager::Yarn {
+            return false;
+        }
+        self.version(path)
+            .is_some_and(|version| !version.starts_with("1."))
+    }
+
+    /// The version the package manager reports, run in `path`.
+    fn version(self, path: &str) -> Option<String> {
+        let mut cmd = self.command().ok()?;
+        cmd.current_dir(path).arg("--version");
+        info!("Running {:?}", cmd);
+        match cmd.output() {
+            Ok(output) if output.status.success() => {
+                Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Find the executable of `program` in `paths`, a `$PATH`.
+///
+/// On Windows, npm, Yarn and pnpm are installed as `.cmd` scripts next to
+/// shell scripts of the same name that can't be run, so `program` is looked
+/// for as is, then with `.cmd` and then with `.exe`.
+pub fn find_executable(program: &str, paths: Option<OsString>) -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    [
+        program.to_string(),
+        format!("{}.cmd", program),
+        format!("{}.exe", program),
+    ]
+    .iter()
+    .find_map(|name| which::which_in(name, paths.clone(), &cwd).ok())
+}
+
+impl FromStr for PackageManager {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "npm" => Ok(PackageManager::Npm),
+            "yarn" => Ok(PackageManager::Yarn),
+            "pnpm" => Ok(PackageManager::Pnpm),
+            _ => bail!(
+                "{} is not a supported package manager, expected npm, yarn or pnpm",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+/// Parse a dist-tag to publish under, rejecting the ones npm would refuse:
+/// those that need escaping in a URL, and those that look like a version or
+/// a version range, which npm would confuse with versions when installing.
+pub fn parse_dist_tag(tag: &str) -> Result<String> {
+    if tag.is_empty() {
+        bail!("the dist-tag can't be empty")
+    }
+    let unprefixed = tag.trim_start_matches(['v', '=']);
+    if semver::VersionReq::parse(unprefixed).is_ok() || matches!(unprefixed, "x" | "X") {
+        bail!(
+            "the dist-tag {:?} looks like a version, which npm doesn't allow; use a name like \
+             `next` or `beta`",
+            tag
+        )
+    }
+    if let Some(c) = tag
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && !"-_.!~*'()".contains(*c))
+    {
+        bail!(
+            "the dist-tag {:?} can't contain {:?}; use letters, digits, `-`, `_` or `.`",
+            tag,
+            c
+        )
+    }
+    Ok(tag.to_string())
+}
+
+/// Parse the URL of a registry given with `--registry`, so that one without
+/// a scheme, like `localhost:4873`, is caught before npm sees it.
+pub fn parse_registry(registry: &str) -> Result<String> {
+    match Url::parse(registry) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(registry.to_string()),
+        Ok(_) | Err(url::ParseError::RelativeUrlWithoutBase) => bail!(
+            "the registry {:?} needs an `http://` or `https://` scheme, like `https://{}`",
+            registry,
+            registry
+        ),
+        Err(error) => bail!("the registry {:?} isn't a valid URL: {}", registry, error),
+    }
+}
+
+/// The flags of `npm publish` that wasm-pack passes itself. `wasm-pack
+/// publish` has an option of the same name for each.
+const MANAGED_PUBLISH_FLAGS: &[&str] = &[
+    "--tag",
+    "--access",
+    "--registry",
+    "--otp",
+    "--dry-run",
+    "--provenance",
+];
+
+/// Parse an argument of `--npm-arg`, refusing the flags that wasm-pack
+/// passes itself, which would conflict with its own.
+pub fn parse_npm_arg(arg: &str) -> Result<String> {
+    let flag = arg.split('=').next().unwrap_or_default();
+    if MANAGED_PUBLISH_FLAGS.contains(&flag) {
+        bail!(
+            "wasm-pack passes `{}` to the package manager itself; use `wasm-pack publish {}` \
+             instead of `--npm-arg {}`",
+            flag,
+            flag,
+            arg
+        )
+    }
+    Ok(arg.to_string())
+}
+
+/// The first version of npm that knows `--provenance`.
+pub const MIN_PROVENANCE_NPM: &str = "9.5.0";
+
+/// The registry that `publishConfig.registry` in the `package.json` in
+/// `pkg_directory` sets, if any.
+pub fn publish_config_registry(pkg_directory: &Path) -> Option<String> {
+    let package_json = fs::read_to_string(pkg_directory.join("package.json")).ok()?;
+    let package: serde_json::Value = serde_json::from_str(&package_json).ok()?;
+    package["publishConfig"]["registry"]
+        .as_str()
+        .map(String::from)
+}
+
+/// Point `cmd` of `package_manager` at `registry`. Yarn 2 and later don't
+/// take `--registry`, and read the setting named by `yarn_env` from the
+/// environment instead.
+fn set_registry(
+    cmd: &mut Command,
+    package_manager: PackageManager,
+    path: &str,
+    registry: &str,
+    yarn_env: &str,
+) {
+    if package_manager.is_modern_yarn(path) {
+        cmd.env(yarn_env, registry);
+    } else {
+        cmd.arg("--registry").arg(registry);
+    }
+}
+
+/// Run the `pack` command of `package_manager`, against `registry` if one is
+/// given.
+pub fn npm_pack(path: &str, package_manager: PackageManager, registry: Option<&str>) -> Result<()> {
+    let mut cmd = package_manager.command()?;
     cmd.current_dir(path).arg("pack");
-    child::run(cmd, "npm pack").context("Packaging up your code failed")?;
+    if let Some(registry) = registry {
+        set_registry(
+            &mut cmd,
+            package_manager,
+            path,
+            registry,
+            "YARN_NPM_REGISTRY_SERVER",
+        );
+    }
+    child::run(cmd, &format!("{} pack", package_manager))
+        .context("Packaging up your code failed")?;
     Ok(())
 }
 
-/// Run the `npm publish` command.
-pub fn npm_publish(path: &str, access: Option<Access>, tag: Option<String>) -> Result<()> {
-    let mut cmd = child::new_command("npm");
-    match access {
-        Some(a) => cmd.current_dir(path).arg("publish").arg(&a.to_string()),
-        None => cmd.current_dir(path).arg("publish"),
-    };
+/// Why a `publish` of the package manager failed, as far as can be told from
+/// what it wrote to stderr.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PublishFailure {
+    /// The account has two-factor authentication, and a one-time password
+    /// is needed (`EOTP`).
+    OneTimePassword,
+    /// There are no credentials for the registry, or they were rejected
+    /// (`ENEEDAUTH`, `E401`). The registry is the one npm named, if it did.
+    Unauthorized {
+        /// The registry that wants credentials.
+        registry: Option<String>,
+    },
+    /// The credentials aren't allowed to publish this package (`E403`).
+    Forbidden {
+        /// What to do about the access of a scoped package, if nothing set it.
+        access_hint: Option<String>,
+    },
+    /// The registry wants a paid account to publish this package, which npm
+    /// does for private packages (`E402`).
+    PaymentRequired {
+        /// What to do about the access of a scoped package, if nothing set it.
+        access_hint: Option<String>,
+    },
+    /// The version is already published (`EPUBLISHCONFLICT`, or an `E403`
+    /// for publishing over it).
+    AlreadyPublished,
+    /// The package manager doesn't know `--provenance`.
+    ProvenanceUnsupported,
+    /// The registry couldn't be reached (`ENOTFOUND`, `ECONNREFUSED`,
+    /// `ETIMEDOUT`, ...). The registry is the origin of the request that
+    /// failed, if npm named it.
+    Unreachable {
+        /// The registry that couldn't be reached.
+        registry: Option<String>,
+    },
+}
+
+/// The error codes of npm for a registry that can't be reached.
+const NETWORK_ERROR_CODES: &[&str] = &[
+    "ENOTFOUND",
+    "EAI_AGAIN",
+    "ECONNREFUSED",
+    "ECONNRESET",
+    "ETIMEDOUT",
+    "EHOSTUNREACH",
+    "ENETUNREACH",
+];
+
+impl PublishFailure {
+    /// Classify the failure from the stderr of the package manager.
+    pub fn classify(stderr: &str) -> Option<PublishFailure> {
+        let has_code = |code: &str| {
+            stderr.lines().any(|line| {
+                line.contains(&format!("code {}", code)) || line.contains(&format!("{}:", code))
+            })
+        };
+        if has_code("EOTP") || stderr.contains("one-time password") {
+            Some(PublishFailure::OneTimePassword)
+        } else if stderr.lines().any(|line| {
+            let line = line.to_lowercase();
+            line.contains("--provenance")
+                && (line.contains("unknown") || line.contains("not a valid"))
+        }) {
+            Some(PublishFailure::ProvenanceUnsupported)
+        } else if has_code("EPUBLISHCONFLICT")
+            || stderr.to_lowercase().contains("cannot publish over")
+        {
+            Some(PublishFailure::AlreadyPublished)
+        } else if has_code("ENEEDAUTH") || has_code("E401") {
+            Some(PublishFailure::Unauthorized {
+                registry: word_after(stderr, "logged in to "),
+            })
+        } else if has_code("E402") || stderr.contains("You must sign up for private packages") {
+            Some(PublishFailure::PaymentRequired { access_hint: None })
+        } else if has_code("E403") {
+            Some(PublishFailure::Forbidden { access_hint: None })
+        } else if NETWORK_ERROR_CODES.iter().any(|code| has_code(code)) {
+            let registry = word_after(stderr, "request to ")
+                .and_then(|url| Url::parse(&url).ok())
+                .map(|url| format!("{}/", url.origin().ascii_serialization()));
+            Some(PublishFailure::Unreachable { registry })
+        } else {
+            None
+        }
+    }
+
+    /// Fill in `registry` as the registry of the failure if npm didn't name
+    /// one.
+    fn or_registry(self, registry: Option<&str>) -> PublishFailure {
+        match self {
+            PublishFailure::Unauthorized { registry: None } => PublishFailure::Unauthorized {
+                registry: registry.map(String::from),
+            },
+            PublishFailure::Unreachable { registry: None } => PublishFailure::Unreachable {
+                registry: registry.map(String::from),
+            },
+            failure => failure,
+        }
+    }
+
+    /// Fill in `access_hint` for the failures that a scoped package without
+    /// an access level runs into.
+    fn or_access_hint(self, access_hint: Option<String>) -> PublishFailure {
+        match self {
+            PublishFailure::Forbidden { access_hint: None } => {
+                PublishFailure::Forbidden { access_hint }
+            }
+            PublishFailure::PaymentRequired { access_hint: None } => {
+                PublishFailure::PaymentRequired { access_hint }
+            }
+            failure => failure,
+        }
+    }
+}
+
+/// The word following the first `marker` in `text`.
+fn word_after(text: &str, marker: &str) -> Option<String> {
+    let (_, rest) = text.split_once(marker)?;
+    rest.split_whitespace().next().map(String::from)
+}
+
+impl fmt::Display for PublishFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PublishFailure::OneTimePassword => f.write_str(
+                "The registry asked for a one-time password. Pass it with `--otp <code>`, or \
+                 publish with an automation token, which doesn't need one.",
+            ),
+            PublishFailure::Unauthorized {
+                registry: Some(registry),
+            } => write!(
+                f,
+                "The registry {} didn't accept your credentials, or has none for you. Log in \
+                 with `wasm-pack login --registry {}` and try again.",
+                registry, registry
+            ),
+            PublishFailure::Unauthorized { registry: None } => f.write_str(
+                "The registry didn't accept your credentials. Log in with `wasm-pack login` \
+                 and try again.",
+            ),
+            PublishFailure::Forbidden { access_hint } => {
+                f.write_str(
+                    "Your account isn't allowed to publish this package, or this version of it.",
+                )?;
+                if let Some(hint) = access_hint {
+                    write!(f, " {}", hint)?;
+                }
+                Ok(())
+            }
+            PublishFailure::PaymentRequired { access_hint } => {
+                f.write_str(
+                    "The registry only publishes this package for a paid account, as npm does \
+                     for private packages.",
+                )?;
+                if let Some(hint) = access_hint {
+                    write!(f, " {}", hint)?;
+                }
+                Ok(())
+            }
+            PublishFailure::ProvenanceUnsupported => write!(
+                f,
+                "This version of the package manager doesn't know `--provenance`, which needs \
+                 npm {} or later. Update npm with `npm install -g npm`, or publish without \
+                 `--provenance`.",
+                MIN_PROVENANCE_NPM
+            ),
+            PublishFailure::AlreadyPublished => f.write_str(
+                "This version is already published. Bump the version in Cargo.toml, e.g. with \
+                 `wasm-pack publish --bump patch`, and try again, or pass `--skip-existing` to \
+                 leave it as it is.",
+            ),
+            PublishFailure::Unreachable { registry } => write!(
+                f,
+                "Could not reach the registry{}. Check the network connection and the proxy \
+                 settings, and that the registry from `--registry`, `publishConfig.registry` or \
+                 the .npmrc is right.",
+                registry
+                    .as_deref()
+                    .map(|registry| format!(" {}", registry))
+                    .unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// The arguments of a publish that are passed on to the package manager as
+/// they are: `--access`, `--tag` and `--provenance` if given, and then the
+/// `npm_args`.
+pub fn publish_args(
+    access: Option<&Access>,
+    tag: Option<&str>,
+    provenance: bool,
+    npm_args: &[String],
+) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(access) = access {
+        args.push(access.to_string());
+    }
     if let Some(tag) = tag {
-        cmd.arg("--tag").arg(tag);
+        args.push("--tag".to_string());
+        args.push(tag.to_string());
+    }
+    if provenance {
+        args.push("--provenance".to_string());
+    }
+    args.extend(npm_args.iter().cloned());
+    args
+}
+
+/// Make sure `package_manager` can publish with `--provenance`, so that an
+/// npm too old for it is reported before anything is uploaded.
+fn check_provenance_support(package_manager: PackageManager, path: &str) -> Result<()> {
+    match package_manager {
+        PackageManager::Yarn if !package_manager.is_modern_yarn(path) => {
+            bail!("Yarn 1 can't publish with `--provenance`; use `--package-manager npm`")
+        }
+        PackageManager::Npm => {
+            let min = semver::Version::parse(MIN_PROVENANCE_NPM).unwrap();
+            let version = package_manager.version(path);
+            match version.as_deref().map(semver::Version::parse) {
+                Some(Ok(version)) if version < min => bail!(
+                    "Publishing with `--provenance` needs npm {} or later, but npm {} is \
+                     installed. Update npm with `npm install -g npm`.",
+                    MIN_PROVENANCE_NPM,
+                    version
+                ),
+                _ => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Run the `publish` command of `package_manager`, to `registry` if one is
+/// given, with `args` from `publish_args`, and only as a dry run that uploads
+/// nothing if `dry_run` is set.
+///
+/// If the registry asks for a one-time password that `otp` doesn't give, the
+/// user is asked for one when there's a terminal to ask on, and the publish
+/// is tried again with it.
+pub fn npm_publish(
+    path: &str,
+    package_manager: PackageManager,
+    registry: Option<&str>,
+    args: &[String],
+    otp: Option<String>,
+    dry_run: bool,
+) -> Result<()> {
+    if args.iter().any(|arg| arg == "--provenance") {
+        check_provenance_support(package_manager, path)?;
+    }
+    let publish_command = |otp: Option<&str>| -> Result<(Command, &'static str)> {
+        let mut cmd = package_manager.command()?;
+        cmd.current_dir(path);
+        let command_name = match package_manager {
+            PackageManager::Npm => {
+                cmd.arg("publish");
+                "npm publish"
+            }
+            PackageManager::Yarn if package_manager.is_modern_yarn(path) => {
+                cmd.arg("npm").arg("publish");
+                "yarn npm publish"
+            }
+            PackageManager::Yarn => {
+                if dry_run {
+                    bail!(
+                        "Yarn 1 can't do a dry run of `yarn publish`; use `--package-manager npm`"
+                    )
+                }
+                // Yarn 1 asks for the new version unless told not to.
+                cmd.arg("publish").arg("--non-interactive");
+                "yarn publish"
+            }
+            PackageManager::Pnpm => {
+                // The package directory is build output, which is usually
+                // ignored by git, so pnpm's checks of the git checkout don't
+                // apply.
+                cmd.arg("publish").arg("--no-git-checks");
+                "pnpm publish"
+            }
+        };
+        if let Some(registry) = registry {
+            set_registry(
+                &mut cmd,
+                package_manager,
+                path,
+                registry,
+                "YARN_NPM_PUBLISH_REGISTRY",
+            );
+        }
+        cmd.args(args);
+        if let Some(otp) = otp {
+            cmd.arg("--otp").arg(otp);
+        }
+        Ok((cmd, command_name))
     };
 
-    child::run(cmd, "npm publish").context("Publishing to npm failed")?;
+    let (mut cmd, command_name) = publish_command(otp.as_deref())?;
+    if !dry_run {
+        let (result, output) = child::run_keeping_output(cmd, command_name, false);
+        let error = match result {
+            Ok(()) => return Ok(()),
+            Err(error) => error,
+        };
+        // `--npm-arg` can't set the access, so only `--access` does.
+        let access_hint = if args.iter().any(|arg| arg.starts_with("--access")) {
+            None
+        } else {
+            access::hint(Path::new(path))
+        };
+        let failure = PublishFailure::classify(&output.stderr)
+            .map(|failure| failure.or_registry(registry).or_access_hint(access_hint));
+        return match failure {
+            Some(PublishFailure::OneTimePassword) if otp.is_none() => {
+                let otp = ask_for_otp()?;
+                let (cmd, command_name) = publish_command(Some(&otp))?;
+                child::run(cmd, command_name).context("Publishing to npm failed")
+            }
+            Some(PublishFailure::OneTimePassword) => Err(error
+                .context("The registry didn't accept the one-time password from `--otp`.")
+                .context("Publishing to npm failed")),
+            Some(failure) => Err(error
+                .context(failure.to_string())
+                .context("Publishing to npm failed")),
+            None => Err(error.context("Publishing to npm failed")),
+        };
+    }
+    cmd.arg("--dry-run");
+    child::run(cmd, command_name).context("The dry run of publishing to npm failed")
+}
+
+/// Ask for the one-time password the registry wants, if there's a terminal
+/// to ask on.
+fn ask_for_otp() -> Result<String> {
+    if !io::stdin().is_terminal() {
+        bail!(
+            "{} There's no terminal to ask for it on.",
+            PublishFailure::OneTimePassword
+        )
+    }
+    let otp: String = Input::new()
+        .with_prompt("The registry asked for a one-time password")
+        .interact_text()?;
+    Ok(otp.trim().to_string())
+}
+
+/// What packing a package would put in its tarball.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TarballContents {
+    /// The paths of the files, with their sizes in bytes.
+    pub files: Vec<(String, u64)>,
+    /// The size of all of the files together.
+    pub unpacked_size: u64,
+}
+
+impl TarballContents {
+    /// List what packing the package in `pkg_directory` with npm would put in
+    /// its tarball, with `npm pack --dry-run`.
+    pub fn of_package(pkg_directory: &Path) -> Result<TarballContents> {
+        let mut cmd = PackageManager::Npm.command()?;
+        cmd.current_dir(pkg_directory)
+            .arg("pack")
+            .arg("--dry-run")
+            .arg("--json");
+        let stdout = child::run_capture_stdout(cmd, &"npm pack --dry-run")?;
+        TarballContents::parse(&stdout)
+            .context("failed to read the output of `npm pack --dry-run --json`")
+    }
+
+    /// Parse the report of `npm pack --dry-run --json`, or of `npm publish
+    /// --dry-run --json`, which newer versions of npm key by the package name.
+    pub fn parse(json: &str) -> Result<TarballContents> {
+        let report: serde_json::Value = serde_json::from_str(json)?;
+        let report = match &report {
+            serde_json::Value::Array(packages) => {
+                packages.first().context("it names no package")?
+            }
+            report if report.get("files").is_some() => report,
+            report => report
+                .as_object()
+                .and_then(|packages| packages.values().next())
+                .context("it names no package")?,
+        };
+        let files: Vec<(String, u64)> = report["files"]
+            .as_array()
+            .context("it has no list of files")?
+            .iter()
+            .filter_map(|file| {
+                let path = file["path"].as_str()?;
+                Some((path.to_string(), file["size"].as_u64().unwrap_or(0)))
+            })
+            .collect();
+        let unpacked_size = report["unpackedSize"]
+            .as_u64()
+            .unwrap_or_else(|| files.iter().map(|(_, size)| size).sum());
+        Ok(TarballContents {
+            files,
+            unpacked_size,
+        })
+    }
+
+    /// The files of the package in `pkg_directory` that its users need but
+    /// the tarball leaves out: the `_bg.wasm` file, unless the `files` in its
+    /// package.json leave it out on purpose like `--no-bundle-wasm` does, and
+    /// the JS entries named by `main` and `module` in its package.json.
+    pub fn missing_artifacts(&self, pkg_directory: &Path) -> Result<Vec<String>> {
+        let contains = |path: &str| self.files.iter().any(|(file, _)| file == path);
+        let mut missing = Vec::new();
+        let package = read_package_json(pkg_directory)?;
+        let wasm_left_out = package["files"].as_array().is_some_and(|files| {
+            !files
+                .iter()
+                .any(|file| file.as_str().is_some_and(|file| file.ends_with(".wasm")))
+        });
+        if !wasm_left_out
+            && !self
+                .files
+                .iter()
+                .any(|(file, _)| file.ends_with("_bg.wasm"))
+        {
+            missing.push("the `_bg.wasm` file".to_string());
+        }
+        for field in ["main", "module"] {
+            if let Some(entry) = package[field].as_str() {
+                let path = entry.trim_start_matches("./");
+                if !contains(path) {
+                    missing.push(format!("`{}`, the `{}` entry", path, field));
+                }
+            }
+        }
+        Ok(missing)
+    }
+}
+
+/// Format a size in bytes like npm does, such as `1.2 kB`.
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=999 => format!("{} B", bytes),
+        1_000..=999_999 => format!("{:.1} kB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
+
+/// A version of a package that is already on a registry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublishedVersion {
+    /// The name of the package.
+    pub name: String,
+    /// The version that is published.
+    pub version: String,
+    /// The registry it is published to.
+    pub registry: String,
+}
+
+impl fmt::Display for PublishedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}@{} is already published to {}",
+            self.name, self.version, self.registry
+        )
+    }
+}
+
+/// A version of a package, and the registry it is published to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    /// The name of the package.
+    pub name: String,
+    /// The version of the package.
+    pub version: String,
+    /// The registry it is published to.
+    pub registry: String,
+}
+
+impl Release {
+    /// The version of the package in `pkg_directory`, published to `registry`
+    /// if one is given, then to `publishConfig.registry` in its package.json,
+    /// and then to the registry the `.npmrc` files set for its scope.
+    pub fn of_package(
+        pkg_directory: &Path,
+        crate_path: &Path,
+        registry: Option<&str>,
+    ) -> Result<Release> {
+        let package = read_package_json(pkg_directory)?;
+        let name = package["name"].as_str().unwrap_or_default();
+        let version = package["version"].as_str().unwrap_or_default();
+        let publish_registry = registry.or_else(|| package["publishConfig"]["registry"].as_str());
+        let npmrc = Npmrc::load(pkg_directory, crate_path)?;
+        Ok(Release {
+            name: name.to_string(),
+            version: version.to_string(),
+            registry: npmrc.registry_for(name, publish_registry),
+        })
+    }
+}
+
+fn read_package_json(pkg_directory: &Path) -> Result<serde_json::Value> {
+    let package_json = pkg_directory.join("package.json");
+    serde_json::from_str(
+        &fs::read_to_string(&package_json)
+            .with_context(|| format!("failed to read {}", package_json.display()))?,
+    )
+    .with_context(|| format!("failed to parse {}", package_json.display()))
+}
+
+/// Find out whether the version of the package in `pkg_directory` is already
+/// published to its registry, or to `registry` if one is given.
+///
+/// If the registry can't be asked, this only warns and returns `None`, leaving
+/// it to the publish.
+pub fn find_published_version(
+    pkg_directory: &Path,
+    crate_path: &Path,
+    registry: Option<&str>,
+) -> Result<Option<PublishedVersion>> {
+    let Release {
+        name,
+        version,
+        registry,
+    } = Release::of_package(pkg_directory, crate_path, registry)?;
+    let versions = match published_versions(&registry, &name) {
+        Ok(versions) => versions,
+        Err(error) => {
+            PBAR.warn(&format!(
+                "Could not check whether {}@{} is already published to {}: {}",
+                name, version, registry, error
+            ));
+            return Ok(None);
+        }
+    };
+    if !versions.contains(&version) {
+        return Ok(None);
+    }
+    Ok(Some(PublishedVersion {
+        name,
+        version,
+        registry,
+    }))
+}
+
+/// Make sure the version of the package in `pkg_directory` hasn't been
+/// published to its registry, or to `registry` if one is given, yet, since
+/// npm would refuse it anyway, and only after the upload.
+///
+/// If the registry can't be asked, this only warns and leaves it to npm.
+pub fn check_version_unpublished(
+    pkg_directory: &Path,
+    crate_path: &Path,
+    registry: Option<&str>,
+) -> Result<()> {
+    if let Some(published) = find_published_version(pkg_directory, crate_path, registry)? {
+        bail!(
+            "{}. Bump the version in Cargo.toml, e.g. with `wasm-pack publish --bump patch`, \
+             and try again, or pass `--skip-existing` to leave it as it is.",
+            published
+        )
+    }
     Ok(())
 }
 
-/// Run the `npm login` command.
-pub fn npm_login(registry: &str, scope: &Option<String>, auth_type: &Option<String>) -> Result<()> {
-    let mut args = vec!["login".to_string(), format!("--registry={}", registry)];
+/// The versions of the package `name` on `registry`, from its packument.
+/// A package that was never published has none.
+fn published_versions(registry: &str, name: &str) -> Result<Vec<String>> {
+    // Scoped names are requested as `@scope%2fname`.
+    let url = format!(
+        "{}/{}",
+        registry.trim_end_matches('/'),
+        name.replacen('/', "%2f", 1)
+    );
+    let packument: serde_json::Value = match http::agent()
+        .get(&url)
+        .set("Accept", "application/vnd.npm.install-v1+json")
+        .call()
+    {
+        Ok(response) => response.into_json()?,
+        Err(ureq::Error::Status(404, _)) => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+    Ok(packument["versions"]
+        .as_object()
+        .map(|versions| versions.keys().cloned().collect())
+        .unwrap_or_default())
+}
 
-    if let Some(scope) = scope {
-        args.push(format!("--scope={}", scope));
+/// Ask `registry` which user `token` belongs to, which also makes sure the
+/// token works before it is saved.
+pub fn whoami(registry: &str, token: &str) -> Result<String> {
+    let url = format!("{}/-/whoami", registry.trim_end_matches('/'));
+    let response = match http::agent()
+        .get(&url)
+        .set("Authorization", &format!("Bearer {}", token))
+        .call()
+    {
+        Ok(response) => response,
+        Err(ureq::Error::Status(401, _)) | Err(ureq::Error::Status(403, _)) => bail!(
+            "The registry {} didn't accept the token. Check that it hasn't expired or been \
+             revoked, and that it was made for this registry.",
+            registry
+        ),
+        Err(error) => {
+            return Err(Error::from(error))
+                .with_context(|| format!("failed to check the token with {}", registry))
+        }
+    };
+    let body: serde_json::Value = response
+        .into_json()
+        .with_context(|| format!("failed to read the answer of {}", url))?;
+    match body["username"].as_str() {
+        Some(username) => Ok(username.to_string()),
+        None => bail!("{} didn't say which user the token belongs to", url),
     }
+}
 
-    if let Some(auth_type) = auth_type {
-        args.push(format!("--auth_type={}", auth_type));
+/// Build the command that logs in to `registry` with `package_manager`,
+/// for `scope` if one is given.
+///
+/// Yarn 2 and later log in with `yarn npm login`, and read the registry from
+/// the environment. Neither Yarn takes an `auth_type`, and Yarn 1 logs in to
+/// one registry for all scopes.
+pub fn login_command(
+    package_manager: PackageManager,
+    registry: &str,
+    scope: &Option<String>,
+    auth_type: &Option<String>,
+) -> Result<Command> {
+    let mut cmd = package_manager.command()?;
+    match package_manager {
+        PackageManager::Npm | PackageManager::Pnpm => {
+            cmd.arg("login").arg(format!("--registry={}", registry));
+            if let Some(scope) = scope {
+                cmd.arg(format!("--scope={}", scope));
+            }
+            if let Some(auth_type) = auth_type {
+                cmd.arg(format!("--auth_type={}", auth_type));
+            }
+            return Ok(cmd);
+        }
+        PackageManager::Yarn if auth_type.is_some() => {
+            bail!("Yarn has no `--auth-type`; log in with `--package-manager npm` instead")
+        }
+        PackageManager::Yarn if package_manager.is_modern_yarn(".") => {
+            cmd.arg("npm").arg("login");
+            cmd.env("YARN_NPM_REGISTRY_SERVER", registry);
+            if let Some(scope) = scope {
+                cmd.arg("--scope").arg(scope);
+            }
+        }
+        PackageManager::Yarn => {
+            if scope.is_some() {
+                bail!(
+                    "Yarn 1 can't log in for a scope; log in with `--package-manager npm` instead"
+                )
+            }
+            cmd.arg("login").arg("--registry").arg(registry);
+        }
     }
+    Ok(cmd)
+}
 
+/// Log in to `registry` with `package_manager`.
+pub fn npm_login(
+    package_manager: PackageManager,
+    registry: &str,
+    scope: &Option<String>,
+    auth_type: &Option<String>,
+) -> Result<()> {
     // Interactively ask user for npm login info.
     //  (child::run does not support interactive input)
-    let mut cmd = child::new_command("npm");
-    cmd.args(args);
+    let mut cmd = login_command(package_manager, registry, scope, auth_type)?;
 
     info!("Running {:?}", cmd);
     if cmd.status()?.success() {
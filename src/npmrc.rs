@@ -0,0 +1,340 @@
+//! Reading registry and auth settings from `.npmrc` files, and saving auth
+//! tokens to them.
+
+use crate::npm::DEFAULT_NPM_REGISTRY;
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The settings found in `npm_config_*` environment variables and in the
+/// project, user and global `.npmrc` files, in order of precedence.
+pub struct Npmrc {
+    env: Vec<(String, String)>,
+    files: Vec<(PathBuf, Vec<(String, String)>)>,
+}
+
+impl Npmrc {
+    /// Load the `.npmrc` files that apply when publishing the package in
+    /// `pkg_directory` of the crate at `crate_path`.
+    pub fn load(pkg_directory: &Path, crate_path: &Path) -> Result<Npmrc> {
+        let mut paths = vec![pkg_directory.join(".npmrc")];
+        if crate_path != pkg_directory {
+            paths.push(crate_path.join(".npmrc"));
+        }
+        if let Some(user) = user_npmrc() {
+            paths.push(user);
+        }
+        if let Some(global) = global_npmrc() {
+            paths.push(global);
+        }
+
+        let mut files = Vec::new();
+        for path in paths {
+            if !path.is_file() {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            files.push((path, parse(&contents)));
+        }
+        Ok(Npmrc {
+            env: env_config(),
+            files,
+        })
+    }
+
+    /// The paths of the `.npmrc` files that were found.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// The value of `key`, with the environment variables it refers to filled
+    /// in. `None` if it isn't set, or refers to a variable that isn't.
+    fn get(&self, key: &str) -> Option<String> {
+        if let Some((_, value)) = self.env.iter().find(|(k, _)| k == key) {
+            return Some(value.clone());
+        }
+        self.files
+            .iter()
+            .flat_map(|(_, entries)| entries)
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| interpolate(v))
+    }
+
+    /// Resolve the registry a package named `name` is published to, the way
+    /// npm does: `publishConfig.registry`, then the scope's registry, then the
+    /// configured default registry.
+    pub fn registry_for(&self, name: &str, publish_registry: Option<&str>) -> String {
+        if let Some(registry) = publish_registry {
+            return registry.to_string();
+        }
+        let scoped = name
+            .strip_prefix('@')
+            .and_then(|rest| rest.split('/').next())
+            .and_then(|scope| self.get(&format!("@{}:registry", scope)));
+        scoped
+            .or_else(|| self.get("registry"))
+            .unwrap_or_else(|| DEFAULT_NPM_REGISTRY.to_string())
+    }
+
+    /// Check whether there are credentials for `registry`, either for its
+    /// exact URL or for one of its parent paths: an `_authToken`, an `_auth`,
+    /// or a `username` together with a `_password`.
+    pub fn has_auth_for(&self, registry: &str) -> bool {
+        let mut nerfed = nerf_dart(registry);
+        loop {
+            let has = |field: &str| {
+                self.get(&format!("{}:{}", nerfed, field))
+                    .is_some_and(|value| !value.is_empty())
+            };
+            if has("_authToken") || has("_auth") || (has("username") && has("_password")) {
+                return true;
+            }
+            // Walk up from `//host/a/b/` to `//host/a/` and on to `//host/`.
+            let trimmed = &nerfed[..nerfed.len() - 1];
+            match trimmed.rfind('/') {
+                Some(idx) if idx > 1 => nerfed = trimmed[..=idx].to_string(),
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// Check that the registry the package in `pkg_directory` is published to, or
+/// `registry` if one is given, has credentials in an `.npmrc` or the
+/// environment, so that a likely missing login can be pointed out before
+/// `npm publish` runs.
+///
+/// npm has other ways to authenticate, such as client certificates, so the
+/// error is only meant to be shown as a warning. Only custom registries are
+/// checked, since npm's own error for the public registry already tells the
+/// user to log in.
+pub fn check_registry_auth(
+    pkg_directory: &Path,
+    crate_path: &Path,
+    registry: Option<&str>,
+) -> Result<()> {
+    let package_json = pkg_directory.join("package.json");
+    let package: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(&package_json)
+            .with_context(|| format!("failed to read {}", package_json.display()))?,
+    )
+    .with_context(|| format!("failed to parse {}", package_json.display()))?;
+    let name = package["name"].as_str().unwrap_or_default();
+    let publish_registry = registry.or_else(|| package["publishConfig"]["registry"].as_str());
+
+    let npmrc = Npmrc::load(pkg_directory, crate_path)?;
+    let registry = npmrc.registry_for(name, publish_registry);
+    if nerf_dart(&registry) == nerf_dart(DEFAULT_NPM_REGISTRY) || npmrc.has_auth_for(&registry) {
+        return Ok(());
+    }
+
+    let searched = npmrc
+        .paths()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    let searched = if searched.is_empty() {
+        "no `.npmrc` file was found".to_string()
+    } else {
+        format!("searched {}", searched.join(", "))
+    };
+    bail!(
+        "No auth token found for the registry {} ({}), so publishing may fail.\nIf it does, run \
+         `npm login --registry={}` and then try publishing again.",
+        registry,
+        searched,
+        registry
+    )
+}
+
+/// Save `token` as the `_authToken` for `registry` in the `.npmrc` at `path`,
+/// and `registry` as the registry of `scope` if one is given.
+///
+/// Entries already there for the same keys are replaced rather than added
+/// to, so logging in again doesn't leave stale tokens behind. A new file is
+/// only readable by the user, since it holds a secret.
+pub fn set_auth_token(path: &Path, registry: &str, scope: Option<&str>, token: &str) -> Result<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let mut entries = vec![(
+        format!("{}:_authToken", nerf_dart(registry)),
+        token.to_string(),
+    )];
+    if let Some(scope) = scope {
+        entries.push((
+            format!("@{}:registry", scope.trim_start_matches('@')),
+            registry.to_string(),
+        ));
+    }
+    let updated = set_entries(contents.as_deref().unwrap_or_default(), &entries);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(path, updated).with_context(|| format!("failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    if contents.is_none() {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("failed to set the permissions of {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Set `entries` in the contents of an `.npmrc`. The first line with each key
+/// gets the new value and later lines with the same key are dropped. Keys
+/// that weren't there yet are appended.
+pub fn set_entries(contents: &str, entries: &[(String, String)]) -> String {
+    let mut set = vec![false; entries.len()];
+    let mut lines = Vec::new();
+    for line in contents.lines() {
+        let key = line
+            .split_once('=')
+            .map(|(key, _)| key.trim())
+            .filter(|key| !key.starts_with(';') && !key.starts_with('#'));
+        match key.and_then(|key| entries.iter().position(|(k, _)| k == key)) {
+            Some(idx) if set[idx] => {}
+            Some(idx) => {
+                set[idx] = true;
+                lines.push(format!("{}={}", entries[idx].0, entries[idx].1));
+            }
+            None => lines.push(line.to_string()),
+        }
+    }
+    for ((key, value), set) in entries.iter().zip(set) {
+        if !set {
+            lines.push(format!("{}={}", key, value));
+        }
+    }
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    updated
+}
+
+/// The user-level `.npmrc`, honoring `NPM_CONFIG_USERCONFIG`.
+pub(crate) fn user_npmrc() -> Option<PathBuf> {
+    if let Some(path) =
+        env::var_os("NPM_CONFIG_USERCONFIG").or_else(|| env::var_os("npm_config_userconfig"))
+    {
+        return Some(PathBuf::from(path));
+    }
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".npmrc"))
+}
+
+/// The global `npmrc`, honoring `NPM_CONFIG_GLOBALCONFIG` and then
+/// `NPM_CONFIG_PREFIX`, and otherwise below the prefix of the `node` on the
+/// `$PATH`, like npm's default prefix.
+fn global_npmrc() -> Option<PathBuf> {
+    if let Some(path) =
+        env::var_os("NPM_CONFIG_GLOBALCONFIG").or_else(|| env::var_os("npm_config_globalconfig"))
+    {
+        return Some(PathBuf::from(path));
+    }
+    let prefix = match env::var_os("NPM_CONFIG_PREFIX").or_else(|| env::var_os("npm_config_prefix"))
+    {
+        Some(prefix) => PathBuf::from(prefix),
+        None => {
+            let node = which::which("node").ok()?;
+            let bin = node.parent()?;
+            if cfg!(windows) {
+                bin.to_path_buf()
+            } else {
+                bin.parent()?.to_path_buf()
+            }
+        }
+    };
+    Some(prefix.join("etc").join("npmrc"))
+}
+
+/// The settings given as `npm_config_*` environment variables, in either
+/// case, named the way npm names them: keys starting with `//` as they are,
+/// and others lowercased, with a `-` for every `_` after the first character.
+fn env_config() -> Vec<(String, String)> {
+    const PREFIX: &str = "npm_config_";
+    env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, value)| {
+            !value.is_empty()
+                && name
+                    .get(..PREFIX.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(PREFIX))
+        })
+        .map(|(name, value)| {
+            let key = &name[PREFIX.len()..];
+            let key = if key.starts_with("//") {
+                key.to_string()
+            } else {
+                let mut chars = key.chars();
+                let first = chars.next().map(String::from).unwrap_or_default();
+                (first + &chars.as_str().replace('_', "-")).to_lowercase()
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// Fill the environment variables that an `.npmrc` value refers to as
+/// `${NAME}` into it, like npm. `None` if one of them isn't set, which npm
+/// refuses, unless it is written as `${NAME?}`, which is empty then.
+fn interpolate(value: &str) -> Option<String> {
+    let mut interpolated = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        interpolated.push_str(&rest[..start]);
+        let name = &rest[start + 2..end];
+        match name.strip_suffix('?') {
+            Some(name) => interpolated.push_str(&env::var(name).unwrap_or_default()),
+            None => interpolated.push_str(&env::var(name).ok()?),
+        }
+        rest = &rest[end + 1..];
+    }
+    interpolated.push_str(rest);
+    Some(interpolated)
+}
+
+/// Parse the `key = value` lines of an `.npmrc`, skipping comments.
+fn parse(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(';') && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// Strip the protocol off a registry URL and make sure it ends in a slash,
+/// which is how npm keys per-registry settings: `//host/path/`.
+fn nerf_dart(registry: &str) -> String {
+    let without_protocol = match registry.find("//") {
+        Some(idx) => &registry[idx..],
+        None => registry,
+    };
+    let without_protocol = without_protocol
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+    if without_protocol.ends_with('/') {
+        without_protocol.to_string()
+    } else {
+        format!("{}/", without_protocol)
+    }
+}
@@ -0,0 +1,88 @@
+//! Reading tool versions pinned in a `wasm-pack.toml`.
+//!
+//! A team can check in a `wasm-pack.toml` next to the crate's `Cargo.toml`,
+//! or at the root of its workspace, to make every developer use the same
+//! `wasm-bindgen` CLI regardless of the library version in `Cargo.lock`:
+//!
+//! ```toml
+//! [wasm-bindgen]
+//! version = "0.2.95"
+//! ```
+
+use crate::lockfile::Lockfile;
+use crate::manifest::CrateData;
+use crate::PBAR;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the file holding pinned tool versions.
+pub const PINNED_FILE: &str = "wasm-pack.toml";
+
+/// The contents of a `wasm-pack.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pinned {
+    #[serde(default, rename = "wasm-bindgen")]
+    wasm_bindgen: Option<PinnedTool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PinnedTool {
+    version: String,
+}
+
+impl Pinned {
+    /// Read the `wasm-pack.toml` for the crate at `crate_path`, looking next
+    /// to the crate first and then at the root of its workspace.
+    pub fn load(crate_path: &Path, crate_data: &CrateData) -> Result<Option<(PathBuf, Pinned)>> {
+        for dir in [crate_path, crate_data.workspace_root()] {
+            let path = dir.join(PINNED_FILE);
+            if !path.is_file() {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let pinned = toml::from_str(&contents)
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+            return Ok(Some((path, pinned)));
+        }
+        Ok(None)
+    }
+
+    /// The pinned `wasm-bindgen` CLI version, if any.
+    pub fn wasm_bindgen_version(&self) -> Option<&str> {
+        self.wasm_bindgen.as_ref().map(|tool| &tool.version[..])
+    }
+}
+
+/// Pick the `wasm-bindgen` CLI version to install: the one pinned in
+/// `wasm-pack.toml` if there is one, otherwise the one from `lockfile`.
+pub fn wasm_bindgen_version(
+    crate_path: &Path,
+    crate_data: &CrateData,
+    lockfile: &Lockfile,
+) -> Result<String> {
+    let pinned = Pinned::load(crate_path, crate_data)?;
+    let (path, version) = match &pinned {
+        Some((path, pinned)) => match pinned.wasm_bindgen_version() {
+            Some(version) => (path, version),
+            None => return Ok(lockfile.require_wasm_bindgen()?.to_string()),
+        },
+        None => return Ok(lockfile.require_wasm_bindgen()?.to_string()),
+    };
+    if let Some(lockfile_version) = lockfile.wasm_bindgen_version() {
+        if version != lockfile_version {
+            PBAR.warn(&format!(
+                "Using wasm-bindgen CLI {} pinned in {}, while the crate uses wasm-bindgen {}. \
+                 The CLI has to match the library exactly, so the generated bindings may not \
+                 work with the crate.",
+                version,
+                path.display(),
+                lockfile_version
+            ));
+        }
+    }
+    Ok(version.to_string())
+}
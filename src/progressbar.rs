@@ -2,8 +2,12 @@
 
 use crate::emoji;
 use anyhow::{bail, Error, Result};
-use console::style;
-use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use console::{style, Term};
+use std::env;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy)]
@@ -31,10 +35,56 @@ impl std::str::FromStr for LogLevel {
     }
 }
 
+/// Whether wasm-pack should emit ANSI colors in its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Always emit colors, even when not writing to a terminal.
+    Always,
+    /// Never emit colors.
+    Never,
+    /// Emit colors only when writing to a terminal, honoring `CARGO_TERM_COLOR`
+    /// and `NO_COLOR`.
+    Auto,
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            "auto" => Ok(ColorChoice::Auto),
+            _ => bail!("Unknown color choice: {}", s),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Resolve `Auto` against the `CARGO_TERM_COLOR` and `NO_COLOR`
+    /// environment variables. Returns `Auto` if neither of them decides.
+    pub fn resolve(self) -> ColorChoice {
+        if self != ColorChoice::Auto {
+            return self;
+        }
+        if let Ok(choice) = env::var("CARGO_TERM_COLOR") {
+            match choice.parse() {
+                Ok(ColorChoice::Auto) | Err(_) => {}
+                Ok(choice) => return choice,
+            }
+        }
+        match env::var_os("NO_COLOR") {
+            Some(value) if !value.is_empty() => ColorChoice::Never,
+            _ => ColorChoice::Auto,
+        }
+    }
+}
+
 /// Synchronized progress bar and status message printing.
 pub struct ProgressOutput {
     quiet: AtomicBool,
     log_level: AtomicU8,
+    deny_warnings: AtomicBool,
+    warnings: AtomicUsize,
 }
 
 impl ProgressOutput {
@@ -43,6 +93,8 @@ impl ProgressOutput {
         Self {
             quiet: AtomicBool::new(false),
             log_level: AtomicU8::new(LogLevel::Info as u8),
+            deny_warnings: AtomicBool::new(false),
+            warnings: AtomicUsize::new(0),
         }
     }
 
@@ -71,6 +123,45 @@ impl ProgressOutput {
         self.log_level.store(log_level as u8, Ordering::SeqCst);
     }
 
+    /// Causes warnings to be reported as errors, and `check_warnings` to fail
+    /// if there were any.
+    pub fn set_deny_warnings(&self, deny_warnings: bool) {
+        self.deny_warnings.store(deny_warnings, Ordering::SeqCst);
+    }
+
+    /// Returns how many warnings have been emitted so far, including those
+    /// that were not printed because of `--quiet` or `--log-level`.
+    pub fn warning_count(&self) -> usize {
+        self.warnings.load(Ordering::SeqCst)
+    }
+
+    /// Fail if warnings are denied and any were emitted.
+    pub fn check_warnings(&self) -> Result<()> {
+        let count = self.warning_count();
+        if self.deny_warnings.load(Ordering::SeqCst) && count > 0 {
+            bail!(
+                "wasm-pack emitted {} warning{}, which are errors because of `--fail-on-warnings`",
+                count,
+                if count == 1 { "" } else { "s" }
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets whether colors are used for wasm-pack's own output. An explicit
+    /// choice is also exported as `CARGO_TERM_COLOR` so that the `cargo`
+    /// processes we spawn agree with it.
+    pub fn set_color(&self, color: ColorChoice) {
+        let enabled = match color.resolve() {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => return,
+        };
+        console::set_colors_enabled(enabled);
+        console::set_colors_enabled_stderr(enabled);
+        env::set_var("CARGO_TERM_COLOR", if enabled { "always" } else { "never" });
+    }
+
     /// Add an informational message.
     pub fn info(&self, message: &str) {
         if !self.quiet() && self.is_log_enabled(LogLevel::Info) {
@@ -79,9 +170,13 @@ impl ProgressOutput {
         }
     }
 
-    /// Add a warning message.
+    /// Add a warning message. With `--fail-on-warnings` it is reported as an
+    /// error instead.
     pub fn warn(&self, message: &str) {
-        if !self.quiet() && self.is_log_enabled(LogLevel::Warn) {
+        self.warnings.fetch_add(1, Ordering::SeqCst);
+        if self.deny_warnings.load(Ordering::SeqCst) {
+            self.error(message);
+        } else if !self.quiet() && self.is_log_enabled(LogLevel::Warn) {
             let warn = format!(
                 "{}: {} {}",
                 style("[WARN]").bold().dim(),
@@ -92,6 +187,54 @@ impl ProgressOutput {
         }
     }
 
+    /// Whether a heartbeat is drawn: only when stderr is a terminal and info
+    /// messages are shown.
+    pub fn draws_heartbeat(&self) -> bool {
+        !self.quiet() && self.is_log_enabled(LogLevel::Info) && console::user_attended_stderr()
+    }
+
+    /// Start a spinner showing `message` and how long it has been running,
+    /// for steps that can take minutes without any output of their own.
+    ///
+    /// Nothing is drawn if `draws_heartbeat` is false, but the returned
+    /// `Heartbeat` still measures the elapsed time.
+    pub fn heartbeat(&self, message: &str) -> Heartbeat {
+        let started = Instant::now();
+        if !self.draws_heartbeat() {
+            return Heartbeat {
+                started,
+                ticker: None,
+            };
+        }
+
+        let (stop, stopped) = mpsc::channel();
+        let message = message.to_string();
+        let thread = thread::spawn(move || {
+            let term = Term::stderr();
+            let frames = ['|', '/', '-', '\\'];
+            for frame in frames.iter().cycle() {
+                let line = format!(
+                    "{}: {} {} ({}s)",
+                    style("[INFO]").bold().dim(),
+                    frame,
+                    message,
+                    started.elapsed().as_secs()
+                );
+                let _ = term.clear_line();
+                let _ = term.write_str(&line);
+                match stopped.recv_timeout(Duration::from_millis(100)) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => break,
+                }
+            }
+            let _ = term.clear_line();
+        });
+        Heartbeat {
+            started,
+            ticker: Some((stop, thread)),
+        }
+    }
+
     /// Add an error message.
     pub fn error(&self, message: &str) {
         if self.is_log_enabled(LogLevel::Error) {
@@ -106,6 +249,38 @@ impl ProgressOutput {
     }
 }
 
+/// A running spinner started by `ProgressOutput::heartbeat`.
+pub struct Heartbeat {
+    started: Instant,
+    ticker: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+impl Heartbeat {
+    /// Whether the spinner is being drawn.
+    pub fn is_running(&self) -> bool {
+        self.ticker.is_some()
+    }
+
+    /// Stop the spinner, clearing its line, and return how long it ran.
+    pub fn finish(mut self) -> Duration {
+        self.stop();
+        self.started.elapsed()
+    }
+
+    fn stop(&mut self) {
+        if let Some((stop, thread)) = self.ticker.take() {
+            let _ = stop.send(());
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
 impl Default for ProgressOutput {
     fn default() -> Self {
         ProgressOutput::new()
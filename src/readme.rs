@@ -7,6 +7,109 @@ use std::path::Path;
 use crate::manifest::CrateData;
 use crate::PBAR;
 
+/// Whether the crate has a README to copy into the `pkg` directory.
+pub fn crate_has_readme(crate_data: &CrateData, path: &Path) -> bool {
+    crate_data
+        .crate_readme()
+        .is_some_and(|readme_path| path.join(readme_path).exists())
+}
+
+/// Write the crate-level docs of the lib target, its leading `//!` and `/*!
+/// */` comments, to the README of the `pkg` directory.
+///
+/// The Markdown is written as it is, so intra-doc links such as
+/// ``[`Foo`]`` are left for the reader to resolve.
+pub fn write_from_docs(crate_data: &CrateData, out_dir: &Path) -> Result<()> {
+    let lib_path = match crate_data.lib_src_path() {
+        Some(lib_path) => lib_path,
+        None => {
+            PBAR.warn("origin crate has no README and no lib target to take its docs from");
+            return Ok(());
+        }
+    };
+    let source = fs::read_to_string(&lib_path)
+        .with_context(|| format!("failed to read {}", lib_path.display()))?;
+    let docs = crate_docs(&source);
+    if docs.is_empty() {
+        PBAR.warn(&format!(
+            "origin crate has no README, and {} has no crate-level docs",
+            lib_path.display()
+        ));
+        return Ok(());
+    }
+    fs::write(out_dir.join("README.md"), docs + "\n")
+        .context("failed to write README from the crate docs")?;
+    Ok(())
+}
+
+/// The leading inner doc comments of `source`, without their comment markers.
+fn crate_docs(source: &str) -> String {
+    let mut docs = Vec::new();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if let Some(doc) = trimmed.strip_prefix("//!") {
+            docs.push(doc.to_string());
+        } else if let Some(rest) = trimmed.strip_prefix("/*!") {
+            let mut block = Vec::new();
+            let mut rest = rest.to_string();
+            loop {
+                if let Some(end) = rest.find("*/") {
+                    block.push(rest[..end].to_string());
+                    break;
+                }
+                block.push(rest);
+                match lines.next() {
+                    Some(next) => rest = next.to_string(),
+                    None => break,
+                }
+            }
+            docs.extend(strip_block_stars(block));
+        } else if trimmed.is_empty()
+            || trimmed.starts_with("#![")
+            || (trimmed.starts_with("//") && !trimmed.starts_with("///"))
+        {
+            continue;
+        } else {
+            break;
+        }
+    }
+
+    let indent = docs
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    docs.iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+/// Strip the `*` in front of every line of a `/*! */` comment, if they all
+/// have one.
+fn strip_block_stars(block: Vec<String>) -> Vec<String> {
+    let starred = block
+        .iter()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.trim_start().starts_with('*'));
+    if !starred {
+        return block;
+    }
+    block
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| match line.trim_start().strip_prefix('*') {
+            Some(rest) if i > 0 => rest.to_string(),
+            _ => line,
+        })
+        .collect()
+}
+
 /// Copy the crate's README into the `pkg` directory.
 pub fn copy_from_crate(crate_data: &CrateData, path: &Path, out_dir: &Path) -> Result<()> {
     assert!(
@@ -0,0 +1,161 @@
+//! Undoing what a failed build wrote to the output directory, so that it
+//! isn't left looking like a complete package.
+//!
+//! Before the build steps run, the output directory is copied below the
+//! target directory. If a step fails, the output directory is put back as it
+//! was from that copy, or removed if there was none before the build. With
+//! `wasm-pack build --no-rollback` it is kept as the failed build left it
+//! instead, with an `.incomplete` marker in it.
+
+use anyhow::{bail, Context, Result};
+use siphasher::sip::SipHasher13;
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// The file marking an output directory left behind by a failed build.
+pub const INCOMPLETE_MARKER: &str = ".incomplete";
+
+/// The directory below the target directory that the output directory
+/// `out_dir` of the crate named `name_prefix` is copied to before a build.
+///
+/// It is named after a hash of `out_dir` too, so that builds of the same
+/// crate into different output directories don't share it.
+pub fn backup_dir(target_dir: &Path, name_prefix: &str, out_dir: &Path) -> PathBuf {
+    let mut hasher = SipHasher13::new();
+    hasher.write(out_dir.to_string_lossy().as_bytes());
+    target_dir
+        .join("wasm-pack-rollback")
+        .join(format!("{}-{:016x}", name_prefix, hasher.finish()))
+}
+
+/// The output directory as it was before a build.
+pub struct Snapshot {
+    out_dir: PathBuf,
+    /// Where the output directory was copied to, if it existed.
+    backup: Option<PathBuf>,
+}
+
+impl Snapshot {
+    /// Copy `out_dir`, if it exists, to `backup`.
+    pub fn take(out_dir: &Path, backup: &Path) -> Result<Snapshot> {
+        drop(fs::remove_dir_all(backup));
+        let backup = if out_dir.is_dir() {
+            copy_dir(out_dir, backup)?;
+            Some(backup.to_path_buf())
+        } else {
+            None
+        };
+        Ok(Snapshot {
+            out_dir: out_dir.to_path_buf(),
+            backup,
+        })
+    }
+
+    /// Put the output directory back as it was when the snapshot was taken.
+    pub fn restore(self) -> Result<()> {
+        if self.out_dir.exists() {
+            fs::remove_dir_all(&self.out_dir)
+                .with_context(|| format!("failed to remove {}", self.out_dir.display()))?;
+        }
+        if let Some(backup) = &self.backup {
+            copy_dir(backup, &self.out_dir)?;
+        }
+        self.discard();
+        Ok(())
+    }
+
+    /// Remove the copy, once the build succeeded.
+    pub fn discard(self) {
+        if let Some(backup) = self.backup {
+            drop(fs::remove_dir_all(backup));
+        }
+    }
+}
+
+/// Mark `out_dir` as left behind by a build that failed with `error`.
+pub fn mark_incomplete(out_dir: &Path, error: &anyhow::Error) -> Result<()> {
+    let marker = out_dir.join(INCOMPLETE_MARKER);
+    fs::write(
+        &marker,
+        format!(
+            "The build writing this package failed, so it is incomplete:\n\n{:?}\n",
+            error
+        ),
+    )
+    .with_context(|| format!("failed to write {}", marker.display()))
+}
+
+/// Remove the marker a failed build left in `out_dir`, if any.
+pub fn clear_incomplete(out_dir: &Path) -> Result<()> {
+    let marker = out_dir.join(INCOMPLETE_MARKER);
+    match fs::remove_file(&marker) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("failed to remove {}", marker.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether `out_dir` was left behind by a failed build.
+pub fn is_incomplete(out_dir: &Path) -> bool {
+    out_dir.join(INCOMPLETE_MARKER).is_file()
+}
+
+/// Refuse `out_dir` if it was left behind by a failed build.
+pub fn check_complete(out_dir: &Path) -> Result<()> {
+    if is_incomplete(out_dir) {
+        bail!(
+            "{} was left incomplete by a failed build, see {} in it. Build it again with \
+             `wasm-pack build`.",
+            out_dir.display(),
+            INCOMPLETE_MARKER
+        );
+    }
+    Ok(())
+}
+
+/// Copy the directory `from` to `to`, recreating the symlinks in it rather
+/// than copying what they point to.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let destination = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)
+                .with_context(|| format!("failed to create {}", destination.display()))?;
+        } else if entry.path_is_symlink() {
+            copy_symlink(entry.path(), &destination)?;
+        } else {
+            fs::copy(entry.path(), &destination).with_context(|| {
+                format!(
+                    "failed to copy {} to {}",
+                    entry.path().display(),
+                    destination.display()
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    let target =
+        fs::read_link(from).with_context(|| format!("failed to read {}", from.display()))?;
+    std::os::unix::fs::symlink(&target, to)
+        .with_context(|| format!("failed to create {}", to.display()))
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    let target =
+        fs::read_link(from).with_context(|| format!("failed to read {}", from.display()))?;
+    let created = if from.is_dir() {
+        std::os::windows::fs::symlink_dir(&target, to)
+    } else {
+        std::os::windows::fs::symlink_file(&target, to)
+    };
+    created.with_context(|| format!("failed to create {}", to.display()))
+}
@@ -0,0 +1,52 @@
+//! Writing the built package to stdout as a tarball, with `--out-dir -`, for
+//! pipelines that hand it straight to another tool.
+//!
+//! The package is assembled in a directory under the target directory as
+//! usual, then archived and removed.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use walkdir::WalkDir;
+
+/// The `--out-dir` that writes the package to stdout instead.
+pub const STDOUT: &str = "-";
+
+/// The directory in `target_dir` that the package named `name_prefix` is
+/// assembled in before it's written to stdout.
+pub fn staging_dir(target_dir: &Path, name_prefix: &str) -> PathBuf {
+    target_dir
+        .join("wasm-pack")
+        .join(format!("{}-stdout-{}", name_prefix, process::id()))
+}
+
+/// Write the package in `dir` to `writer` as a tar archive, with its files
+/// under `package/`, like in the tarballs of `npm pack`.
+///
+/// The files starting with a `.`, which wasm-pack keeps for itself, are left
+/// out. The archive is the same for the same files, whenever it's made.
+pub fn write_package<W: Write>(dir: &Path, writer: W) -> Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.mode(tar::HeaderMode::Deterministic);
+    let is_hidden = |entry: &walkdir::DirEntry| {
+        entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.')
+    };
+    for entry in WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| !is_hidden(entry))
+    {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let name = Path::new("package").join(entry.path().strip_prefix(dir)?);
+        builder
+            .append_path_with_name(entry.path(), &name)
+            .with_context(|| format!("failed to add {} to the tarball", entry.path().display()))?;
+    }
+    let mut writer = builder.into_inner()?;
+    writer.flush()?;
+    Ok(writer)
+}
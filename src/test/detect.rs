@@ -0,0 +1,120 @@
+//! Finding the environments on this machine that the tests can run in.
+
+use crate::target;
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+/// An environment that `wasm-pack test` can run the tests in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Environment {
+    /// Node.js.
+    Node,
+    /// Mozilla Firefox.
+    Firefox,
+    /// Google Chrome or Chromium.
+    Chrome,
+    /// Apple Safari.
+    Safari,
+    /// Microsoft Edge.
+    Edge,
+}
+
+impl Environment {
+    /// Every environment, in the order the tests run in them.
+    pub const ALL: [Environment; 5] = [
+        Environment::Node,
+        Environment::Chrome,
+        Environment::Firefox,
+        Environment::Safari,
+        Environment::Edge,
+    ];
+
+    /// The `wasm-pack test` flag selecting this environment.
+    pub fn flag(self) -> &'static str {
+        match self {
+            Environment::Node => "--node",
+            Environment::Firefox => "--firefox",
+            Environment::Chrome => "--chrome",
+            Environment::Safari => "--safari",
+            Environment::Edge => "--edge",
+        }
+    }
+
+    /// Whether this environment is installed on this machine, looking on the
+    /// `$PATH` and where the installers put it.
+    pub fn is_installed(self) -> bool {
+        let (commands, locations): (&[&str], Vec<PathBuf>) = match self {
+            Environment::Node => (&["node"], Vec::new()),
+            Environment::Firefox => (
+                &["firefox", "firefox-esr"],
+                app_locations(
+                    "Firefox.app/Contents/MacOS/firefox",
+                    r"Mozilla Firefox\firefox.exe",
+                ),
+            ),
+            Environment::Chrome => (
+                &[
+                    "google-chrome",
+                    "google-chrome-stable",
+                    "chromium",
+                    "chromium-browser",
+                ],
+                app_locations(
+                    "Google Chrome.app/Contents/MacOS/Google Chrome",
+                    r"Google\Chrome\Application\chrome.exe",
+                ),
+            ),
+            Environment::Safari => (&[], app_locations("Safari.app/Contents/MacOS/Safari", "")),
+            // `msedgedriver` is only downloaded for Windows and macOS.
+            Environment::Edge if target::LINUX => (&[], Vec::new()),
+            Environment::Edge => (
+                &["msedge"],
+                app_locations(
+                    "Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+                    r"Microsoft\Edge\Application\msedge.exe",
+                ),
+            ),
+        };
+        commands.iter().any(|command| which::which(command).is_ok())
+            || locations.iter().any(|location| location.is_file())
+    }
+}
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Environment::Node => "Node.js",
+            Environment::Firefox => "Firefox",
+            Environment::Chrome => "Chrome",
+            Environment::Safari => "Safari",
+            Environment::Edge => "Edge",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Find the environments installed on this machine.
+pub fn detect_environments() -> Vec<Environment> {
+    Environment::ALL
+        .iter()
+        .copied()
+        .filter(|environment| environment.is_installed())
+        .collect()
+}
+
+/// Where the installer puts an app: `macos` below `/Applications` on macOS,
+/// or `windows` below the program files directories on Windows.
+fn app_locations(macos: &str, windows: &str) -> Vec<PathBuf> {
+    if target::MACOS {
+        vec![PathBuf::from("/Applications").join(macos)]
+    } else if target::WINDOWS && !windows.is_empty() {
+        ["ProgramFiles", "ProgramFiles(x86)", "LOCALAPPDATA"]
+            .iter()
+            .filter_map(env::var_os)
+            .map(|dir| PathBuf::from(dir).join(windows))
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
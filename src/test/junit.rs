@@ -0,0 +1,285 @@
+//! Reporting the results of `wasm-pack test` as JUnit XML, for CI systems
+//! that render test results from it.
+//!
+//! The test runner only prints its results as text, in the same format as
+//! `cargo test`, so that is what the reports are made from.
+
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// The name of the test case standing for a run that failed without any of
+/// its tests failing, or whose output was cut short.
+const ERROR_CASE: &str = "wasm-pack";
+
+/// How a test ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The test passed.
+    Passed,
+    /// The test failed, with what it printed.
+    Failed(String),
+    /// The test was ignored.
+    Ignored,
+}
+
+/// A test that the runner ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    /// The path of the test, e.g. `tests::it_works`.
+    pub name: String,
+    /// How it ended.
+    pub outcome: Outcome,
+}
+
+/// The tests that ran in one environment.
+#[derive(Debug, Clone)]
+pub struct Suite {
+    name: String,
+    time: Duration,
+    cases: Vec<TestCase>,
+    error: Option<String>,
+    stdout: String,
+    stderr: String,
+}
+
+impl Suite {
+    /// The suite of the tests in the environment `name`, from what the
+    /// runner printed to `stdout` and `stderr` in `time`, and the error the
+    /// run failed with, if any.
+    ///
+    /// A run that failed without a failing test, or whose output ended
+    /// before its results, gets a test case with an error, so that the
+    /// failure shows up in the report. Everything that was printed, such as
+    /// the console output of the tests with `--nocapture`, is kept in the
+    /// suite's `system-out` and `system-err`.
+    pub fn new(
+        name: &str,
+        stdout: &str,
+        stderr: &str,
+        time: Duration,
+        error: Option<&anyhow::Error>,
+    ) -> Suite {
+        let (cases, complete) = parse_runner_output(stdout);
+        let any_failed = cases
+            .iter()
+            .any(|case| matches!(case.outcome, Outcome::Failed(_)));
+        let error = match error {
+            Some(error) if !any_failed || !complete => Some(format!("{:#}", error)),
+            None if !complete => {
+                Some("the output of the test runner ended before its results".to_string())
+            }
+            _ => None,
+        };
+        Suite {
+            name: name.to_string(),
+            time,
+            cases,
+            error,
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    fn count(&self, outcome: fn(&Outcome) -> bool) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| outcome(&case.outcome))
+            .count()
+    }
+
+    fn tests(&self) -> usize {
+        self.cases.len() + self.errors()
+    }
+
+    fn failures(&self) -> usize {
+        self.count(|outcome| matches!(outcome, Outcome::Failed(_)))
+    }
+
+    fn errors(&self) -> usize {
+        usize::from(self.error.is_some())
+    }
+}
+
+/// Parse the results of the tests out of what the test runner printed, and
+/// whether every test binary that started printed its results too.
+pub fn parse_runner_output(output: &str) -> (Vec<TestCase>, bool) {
+    let mut cases = Vec::new();
+    let mut started = 0;
+    let mut finished = 0;
+    let mut failure_output: Option<(String, String)> = None;
+    let mut failure_outputs = Vec::new();
+
+    for line in output.lines() {
+        let trimmed = line.trim_end();
+        if let Some(name) = failure_header(trimmed) {
+            failure_outputs.extend(failure_output.take());
+            failure_output = Some((name.to_string(), String::new()));
+            continue;
+        }
+        if trimmed == "failures:" || trimmed.starts_with("test result: ") {
+            failure_outputs.extend(failure_output.take());
+        }
+        if let Some((_, text)) = &mut failure_output {
+            text.push_str(trimmed);
+            text.push('\n');
+            continue;
+        }
+
+        if trimmed.starts_with("running ")
+            && (trimmed.ends_with(" tests") || trimmed.ends_with(" test"))
+        {
+            started += 1;
+        } else if trimmed.starts_with("test result: ") {
+            finished += 1;
+        } else if let Some(case) = test_line(trimmed) {
+            cases.push(case);
+        }
+    }
+    failure_outputs.extend(failure_output);
+
+    for (name, text) in failure_outputs {
+        let case = cases
+            .iter_mut()
+            .find(|case| case.name == name && matches!(case.outcome, Outcome::Failed(_)));
+        if let Some(case) = case {
+            case.outcome = Outcome::Failed(text.trim_end().to_string());
+        }
+    }
+    let complete = started == finished && (started > 0 || cases.is_empty());
+    (cases, complete)
+}
+
+/// The name in a line like `---- tests::it_fails output ----`.
+fn failure_header(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("---- ")?;
+    rest.strip_suffix(" output ----")
+        .or_else(|| rest.strip_suffix(" stdout ----"))
+}
+
+/// The result in a line like `test tests::it_works ... ok`.
+fn test_line(line: &str) -> Option<TestCase> {
+    let (name, result) = line.strip_prefix("test ")?.split_once(" ... ")?;
+    let outcome = if result.starts_with("ok") {
+        Outcome::Passed
+    } else if result.starts_with("FAIL") {
+        Outcome::Failed(String::new())
+    } else if result.starts_with("ignored") {
+        Outcome::Ignored
+    } else {
+        return None;
+    };
+    Some(TestCase {
+        name: name.trim().to_string(),
+        outcome,
+    })
+}
+
+/// Write `suites` to `path` as a JUnit XML report.
+pub fn write(path: &Path, suites: &[Suite]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(path, to_xml(suites))
+        .with_context(|| format!("failed to write the JUnit report to {}", path.display()))
+}
+
+/// `suites` as a JUnit XML document.
+pub fn to_xml(suites: &[Suite]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let tests: usize = suites.iter().map(Suite::tests).sum();
+    let failures: usize = suites.iter().map(Suite::failures).sum();
+    let errors: usize = suites.iter().map(Suite::errors).sum();
+    let time: Duration = suites.iter().map(|suite| suite.time).sum();
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"wasm-pack test\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+        tests,
+        failures,
+        errors,
+        time.as_secs_f64()
+    );
+    for suite in suites {
+        let name = escape(&suite.name);
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            name,
+            suite.tests(),
+            suite.failures(),
+            suite.errors(),
+            suite.count(|outcome| *outcome == Outcome::Ignored),
+            suite.time.as_secs_f64()
+        );
+        for case in &suite.cases {
+            let open = format!(
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                escape(&case.name),
+                name
+            );
+            match &case.outcome {
+                Outcome::Passed => {
+                    let _ = writeln!(xml, "{}/>", open);
+                }
+                Outcome::Ignored => {
+                    let _ = writeln!(xml, "{}>\n      <skipped/>\n    </testcase>", open);
+                }
+                Outcome::Failed(output) => {
+                    let message = output
+                        .lines()
+                        .map(str::trim)
+                        .find(|line| !line.is_empty() && !line.ends_with(':'))
+                        .unwrap_or("test failed");
+                    let _ = writeln!(
+                        xml,
+                        "{}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                        open,
+                        escape(message),
+                        escape(output)
+                    );
+                }
+            }
+        }
+        if let Some(error) = &suite.error {
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\">\n      <error message=\"{}\">{}</error>\n    </testcase>",
+                ERROR_CASE,
+                name,
+                escape(error.lines().next().unwrap_or_default()),
+                escape(error)
+            );
+        }
+        for (element, output) in [("system-out", &suite.stdout), ("system-err", &suite.stderr)] {
+            if !output.trim().is_empty() {
+                let _ = writeln!(xml, "    <{0}>{1}</{0}>", element, escape(output));
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Escape `text` for an attribute or the text of an element, leaving out the
+/// control characters XML can't contain, such as those of ANSI colors.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\t' => escaped.push(c),
+            '\r' => escaped.push_str("&#13;"),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
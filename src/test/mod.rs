@@ -1,22 +1,514 @@
 //! Testing a Rust crate compiled to wasm.
 
+pub mod detect;
+pub mod junit;
 pub mod webdriver;
 
 use crate::child;
 use crate::PBAR;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
 use std::ffi::OsStr;
+use std::fmt;
 use std::path::Path;
 use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// The flags of the test runner that `wasm-pack test` passes on, and the
+/// first release of `wasm-bindgen` whose runner takes each. The harnesses of
+/// earlier releases panic on flags they don't know.
+const RUNNER_FLAG_MIN_VERSIONS: &[(&str, &str)] = &[
+    ("--include-ignored", "0.2.93"),
+    ("--ignored", "0.2.100"),
+    ("--exact", "0.2.100"),
+    ("--nocapture", "0.2.100"),
+    ("--list", "0.2.100"),
+];
+
+/// The `cargo test` options that take a value, so that the value isn't
+/// mistaken for a test name filter.
+const CARGO_TEST_VALUE_OPTIONS: &[&str] = &[
+    "-p",
+    "--package",
+    "--exclude",
+    "--test",
+    "--bench",
+    "--example",
+    "--bin",
+    "-F",
+    "--features",
+    "-j",
+    "--jobs",
+    "--profile",
+    "--target",
+    "--target-dir",
+    "--manifest-path",
+    "--message-format",
+    "--color",
+    "--config",
+    "-Z",
+];
+
+/// The `cargo` arguments selecting the features of the crate to test.
+pub fn cargo_feature_args(
+    features: &[String],
+    no_default_features: bool,
+    all_features: bool,
+) -> Vec<String> {
+    let mut args = Vec::new();
+    for list in features {
+        args.push("--features".to_string());
+        args.push(list.clone());
+    }
+    if no_default_features {
+        args.push("--no-default-features".to_string());
+    }
+    if all_features {
+        args.push("--all-features".to_string());
+    }
+    args
+}
+
+/// Turn the extra options of `wasm-pack test` into the arguments of `cargo
+/// test`.
+///
+/// The options for `cargo` stay in front. The test name filters among them,
+/// the arguments after `--`, the `runner_flags`, such as `--exact`, and
+/// `--skip <pattern>` if given, all go after a single `--`, to the test
+/// runner.
+pub fn cargo_test_args(
+    extra_options: &[String],
+    runner_flags: &[&str],
+    skip: &[String],
+) -> Vec<String> {
+    let (options, runner_args) = match extra_options.iter().position(|arg| arg == "--") {
+        Some(index) => (&extra_options[..index], &extra_options[index + 1..]),
+        None => (extra_options, &[][..]),
+    };
+
+    let mut cargo_args = Vec::new();
+    let mut filters = Vec::new();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        if option.starts_with('-') {
+            cargo_args.push(option.clone());
+            if CARGO_TEST_VALUE_OPTIONS.contains(&option.as_str()) {
+                cargo_args.extend(options.next().cloned());
+            }
+        } else {
+            filters.push(option.clone());
+        }
+    }
+
+    let mut runner_args: Vec<String> = filters
+        .into_iter()
+        .chain(runner_args.iter().cloned())
+        .collect();
+    runner_args.extend(runner_flags.iter().map(|flag| flag.to_string()));
+    for pattern in skip {
+        runner_args.push("--skip".to_string());
+        runner_args.push(pattern.clone());
+    }
+    if !runner_args.is_empty() {
+        cargo_args.push("--".to_string());
+        cargo_args.extend(runner_args);
+    }
+    cargo_args
+}
+
+/// One of the parts the tests are split into with `--shard <index>/<total>`,
+/// to run them on several machines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard {
+    /// Which part this is, from 1.
+    pub index: usize,
+    /// How many parts there are.
+    pub total: usize,
+}
+
+impl Shard {
+    /// The names of the tests in this shard, out of all of `names`, which
+    /// are sorted and free of duplicates.
+    ///
+    /// The names are split into `total` consecutive runs that differ in size
+    /// by one test at most, so that every machine and every run picks the
+    /// same tests, and the tests of a module mostly end up together.
+    pub fn select(&self, names: &[String]) -> Vec<String> {
+        let start = (self.index - 1) * names.len() / self.total;
+        let end = self.index * names.len() / self.total;
+        names[start..end].to_vec()
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.total)
+    }
+}
+
+/// Parse the `--shard` of the `<index>/<total>` part of the tests to run.
+pub fn parse_shard(value: &str) -> Result<Shard> {
+    let shard = value
+        .split_once('/')
+        .and_then(|(index, total)| Some((index.parse().ok()?, total.parse().ok()?)));
+    match shard {
+        Some((index, total)) if 1 <= index && index <= total => Ok(Shard { index, total }),
+        _ => bail!(
+            "expected `<index>/<total>` with 1 <= index <= total, like `1/4`, not {:?}",
+            value
+        ),
+    }
+}
+
+/// The names of the tests in the `--list` that the test runner printed.
+pub fn parse_test_list(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|line| line.strip_suffix(": test"))
+        .map(String::from)
+        .collect()
+}
+
+/// Turn the `cargo test` arguments `cargo_args` into ones that run exactly
+/// the tests `selected`, out of all of the listed `names`.
+///
+/// The test name filters among the runner's arguments are replaced by ones
+/// for `selected`, and `--skip` is left out, since the tests it skips weren't
+/// listed. The runner's other flags are kept.
+///
+/// Passing every name could exceed the length of a command line with a large
+/// suite, so where all the tests of a module are selected, its path is
+/// passed instead. If some test can't be told apart from one that isn't
+/// selected that way, every name is passed with `--exact`.
+pub fn shard_args(cargo_args: &[String], names: &[String], selected: &[String]) -> Vec<String> {
+    let (options, runner_args) = match cargo_args.iter().position(|arg| arg == "--") {
+        Some(index) => (&cargo_args[..index], &cargo_args[index + 1..]),
+        None => (cargo_args, &[][..]),
+    };
+    let mut flags = Vec::new();
+    let mut runner_args = runner_args.iter();
+    while let Some(arg) = runner_args.next() {
+        if arg == "--skip" {
+            runner_args.next();
+        } else if arg.starts_with('-') && arg != "--exact" {
+            flags.push(arg.clone());
+        }
+    }
+    let filters = match shard_filters(names, selected) {
+        Some(filters) => filters,
+        None => {
+            flags.push("--exact".to_string());
+            selected.to_vec()
+        }
+    };
+    options
+        .iter()
+        .cloned()
+        .chain(std::iter::once("--".to_string()))
+        .chain(filters)
+        .chain(flags)
+        .collect()
+}
+
+/// The fewest substring filters, out of module paths like `a::b::` and whole
+/// test names, that match all of `selected` and none of the other `names`,
+/// or `None` if a test can't be matched that way.
+fn shard_filters(names: &[String], selected: &[String]) -> Option<Vec<String>> {
+    let selected_names: HashSet<&str> = selected.iter().map(|name| &name[..]).collect();
+    let only_selected = |filter: &str| {
+        names
+            .iter()
+            .filter(|name| name.contains(filter))
+            .all(|name| selected_names.contains(&name[..]))
+    };
+    let mut filters: Vec<String> = Vec::new();
+    for name in selected {
+        if filters.iter().any(|filter| name.contains(&filter[..])) {
+            continue;
+        }
+        let filter = name
+            .match_indices("::")
+            .map(|(i, _)| &name[..i + 2])
+            .chain(std::iter::once(&name[..]))
+            .find(|filter| only_selected(filter))?;
+        filters.push(filter.to_string());
+    }
+    Some(filters)
+}
+
+/// Make sure that `value`, an argument for node or an environment variable
+/// for the tests, is one that can be passed on.
+pub fn check_node_value(what: &str, value: &str) -> Result<()> {
+    if value.contains(['\0', '\n', '\r']) {
+        bail!(
+            "{} {:?} contains a NUL byte or a line break, which can't be passed to node",
+            what,
+            value
+        );
+    }
+    Ok(())
+}
+
+/// Parse a `KEY=VALUE` environment variable for the tests.
+pub fn parse_env_var(value: &str) -> Result<(String, String)> {
+    let (key, val) = match value.split_once('=') {
+        Some((key, val)) if !key.is_empty() => (key, val),
+        _ => bail!("expected KEY=VALUE, not {:?}", value),
+    };
+    check_node_value("The environment variable", value)?;
+    Ok((key.to_string(), val.to_string()))
+}
+
+/// The `NODE_OPTIONS` that make the node the test runner starts take `args`
+/// as well as the `existing` options, or `None` if there are no `args`.
+///
+/// Arguments with spaces, quotes or backslashes are quoted the way node
+/// splits `NODE_OPTIONS`.
+pub fn node_options(existing: Option<&str>, args: &[String]) -> Result<Option<String>> {
+    if args.is_empty() {
+        return Ok(None);
+    }
+    let mut options: Vec<String> = existing
+        .filter(|existing| !existing.trim().is_empty())
+        .map(|existing| existing.trim().to_string())
+        .into_iter()
+        .collect();
+    for arg in args {
+        check_node_value("The node argument", arg)?;
+        if arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+            options.push(format!(
+                "\"{}\"",
+                arg.replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+        } else {
+            options.push(arg.clone());
+        }
+    }
+    Ok(Some(options.join(" ")))
+}
+
+/// Make sure that the test runner of `wasm-bindgen` `cli_version` takes
+/// the `runner_flags`.
+pub fn check_runner_flags(cli_version: &str, runner_flags: &[&str]) -> Result<()> {
+    let cli_version = match semver::Version::parse(cli_version) {
+        Ok(version) => version,
+        Err(_) => return Ok(()),
+    };
+    for flag in runner_flags {
+        let min_version = RUNNER_FLAG_MIN_VERSIONS
+            .iter()
+            .find(|(known, _)| known == flag)
+            .map(|(_, min_version)| semver::Version::parse(min_version).unwrap());
+        match min_version {
+            Some(min_version) if cli_version < min_version => bail!(
+                "`{}` needs the test runner of wasm-bindgen {} or later, but this crate uses \
+                 wasm-bindgen {}. Update `wasm-bindgen` and `wasm-bindgen-test`, or leave out \
+                 `{}`.",
+                flag,
+                min_version,
+                cli_version,
+                flag
+            ),
+            _ => {}
+        }
+    }
+    Ok(())
+}
 
 /// Run `cargo test` with the `nightly` toolchain and targeting
 /// `wasm32-unknown-unknown`.
+///
+/// If `kept` is given, what the tests print is kept in it too.
 pub fn cargo_test_wasm<I, K, V>(
     path: &Path,
     release: bool,
     envs: I,
     extra_options: &[String],
+    kept: Option<&mut child::Output>,
 ) -> Result<()>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    let cmd = cargo_test_command(path, release, envs, extra_options);
+    let result = match kept {
+        Some(kept) => {
+            let (result, output) = child::run_keeping_output(cmd, "cargo test", true);
+            *kept = output;
+            result
+        }
+        None => child::run(cmd, "cargo test"),
+    };
+    result.context("Running Wasm tests with wasm-bindgen-test failed")?;
+
+    // NB: `child::run` took care of ensuring that test output gets printed.
+    Ok(())
+}
+
+/// Run `cargo test` like `cargo_test_wasm`, for tests in `browser` driven
+/// by a WebDriver.
+///
+/// When the runner fails because the driver or the browser didn't start,
+/// the tests are run again, up to `retries` more times, waiting a little
+/// longer before each attempt. Every attempt launches a new runner, which
+/// starts the driver on a new free port. Failing tests, and a driver that
+/// doesn't match the browser or can't be found, aren't retried.
+///
+/// If `kept` is given, what the tests print on the last attempt is kept in
+/// it too.
+pub fn cargo_test_wasm_in_browser(
+    path: &Path,
+    release: bool,
+    envs: &[(String, String)],
+    extra_options: &[String],
+    browser: &str,
+    retries: u32,
+    mut kept: Option<&mut child::Output>,
+) -> Result<()> {
+    let mut failed_starts = Vec::new();
+    loop {
+        let cmd = cargo_test_command(path, release, envs.iter().cloned(), extra_options);
+        // Stdout is kept too, to tell whether the tests started running.
+        let (result, output) = child::run_keeping_output(cmd, "cargo test", true);
+        let failed_start = match &result {
+            Ok(()) => None,
+            Err(_) => startup_failure(&output),
+        };
+        if let Some(kept) = kept.as_deref_mut() {
+            *kept = output;
+        }
+        let error = match result {
+            Ok(()) => return Ok(()),
+            Err(error) => error,
+        };
+        match failed_start {
+            Some(reason) => failed_starts.push(reason),
+            None => return Err(error).context("Running Wasm tests with wasm-bindgen-test failed"),
+        }
+
+        let attempts = failed_starts.len() as u32;
+        if attempts > retries {
+            let reasons = failed_starts
+                .iter()
+                .enumerate()
+                .map(|(i, reason)| format!("  attempt {}: {}", i + 1, reason))
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Err(error)
+                .context(format!("{} didn't start, giving up:\n{}", browser, reasons));
+        }
+        let backoff = STARTUP_RETRY_BACKOFF * attempts;
+        PBAR.warn(&format!(
+            "{} didn't start ({}), trying again in {}ms (attempt {} of {})",
+            browser,
+            failed_starts.last().unwrap(),
+            backoff.as_millis(),
+            attempts + 1,
+            retries + 1
+        ));
+        thread::sleep(backoff);
+    }
+}
+
+/// How long to wait before starting a browser again, multiplied by the
+/// number of attempts so far.
+const STARTUP_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// What the test runner and the drivers print when a driver or a browser
+/// failed to start for reasons that may not happen again.
+const TRANSIENT_STARTUP_ERRORS: &[&str] = &[
+    "driver failed to bind port during startup",
+    "Address already in use",
+    "Connection refused",
+    "connection reset",
+    "chrome not reachable",
+    "DevToolsActivePort file doesn't exist",
+    "Chrome failed to start",
+    "Failed to start browser",
+    "failed to start the browser",
+];
+
+/// What the test runner and the drivers print when they are misconfigured,
+/// which trying again doesn't fix.
+const MISCONFIGURED_ERRORS: &[&str] = &[
+    "only supports Chrome version",
+    "only supports Microsoft Edge version",
+    "only supports Firefox version",
+    "failed to spawn",
+    "cannot find Chrome binary",
+    "unable to find binary",
+    "No such file or directory",
+];
+
+/// The line of the runner's `stderr` explaining that the driver or the
+/// browser failed to start, if that is why the tests failed, and it is worth
+/// trying again.
+///
+/// Only what the runner printed before it started running the tests is
+/// looked at, since by then the browser started, and what failing tests
+/// print can look like a failed start.
+fn startup_failure(output: &child::Output) -> Option<String> {
+    if output.stdout.lines().any(is_running_tests) {
+        return None;
+    }
+    let lines = || {
+        output
+            .stderr
+            .lines()
+            .map(str::trim)
+            .take_while(|line| !is_running_tests(line))
+    };
+    if lines().any(|line| MISCONFIGURED_ERRORS.iter().any(|e| line.contains(e))) {
+        return None;
+    }
+    lines()
+        .find(|line| TRANSIENT_STARTUP_ERRORS.iter().any(|e| line.contains(e)))
+        .map(|line| line.trim_start_matches("Error: ").to_string())
+}
+
+/// Whether `line` is the runner's `running 3 tests`, printed once the tests
+/// start running.
+fn is_running_tests(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    words.next() == Some("running")
+        && words
+            .next()
+            .is_some_and(|count| count.parse::<u32>().is_ok())
+        && matches!(words.next(), Some("test" | "tests"))
+}
+
+/// List the names of the tests that `cargo test` with `extra_options` would
+/// run, with the `--list` of the test runner, which doesn't run them.
+pub fn cargo_test_list<I, K, V>(
+    path: &Path,
+    release: bool,
+    envs: I,
+    extra_options: &[String],
+) -> Result<Vec<String>>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    let mut extra_options = extra_options.to_vec();
+    if !extra_options.iter().any(|arg| arg == "--") {
+        extra_options.push("--".to_string());
+    }
+    extra_options.push("--list".to_string());
+    let cmd = cargo_test_command(path, release, envs, &extra_options);
+    let stdout = child::run_capture_stdout(cmd, &"cargo test -- --list")
+        .context("Listing the Wasm tests failed")?;
+    Ok(parse_test_list(&stdout))
+}
+
+fn cargo_test_command<I, K, V>(
+    path: &Path,
+    release: bool,
+    envs: I,
+    extra_options: &[String],
+) -> Command
 where
     I: IntoIterator<Item = (K, V)>,
     K: AsRef<OsStr>,
@@ -38,9 +530,5 @@ where
     cmd.arg("--target").arg("wasm32-unknown-unknown");
 
     cmd.args(extra_options);
-
-    child::run(cmd, "cargo test").context("Running Wasm tests with wasm-bindgen-test failed")?;
-
-    // NB: `child::run` took care of ensuring that test output gets printed.
-    Ok(())
+    cmd
 }
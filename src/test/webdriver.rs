@@ -1,20 +1,98 @@
 //! Getting WebDriver client binaries.
 
+mod capabilities;
 mod chromedriver;
 mod geckodriver;
+mod kept;
+mod msedgedriver;
+mod remote;
 mod safaridriver;
 
+use crate::http;
 use crate::PBAR;
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Error, Result};
 use binary_install::Cache;
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use url::Url;
 
 pub use self::{
+    capabilities::{
+        add_browser_options, load_base_capabilities, merge_capabilities, read_browser_capabilities,
+    },
     chromedriver::{get_or_install_chromedriver, install_chromedriver},
     geckodriver::{get_or_install_geckodriver, install_geckodriver},
-    safaridriver::get_safaridriver,
+    kept::{kept_driver, shutdown_kept_drivers, state_file, KeptDriver, KEPT_DRIVERS_DIR},
+    msedgedriver::{check_edge_host, get_or_install_msedgedriver, install_msedgedriver},
+    remote::{
+        check_remote_webdriver, extend_capabilities, read_capabilities, remote_webdriver_url,
+        write_capabilities, Capabilities, WEBDRIVER_PASSWORD, WEBDRIVER_USER,
+    },
+    safaridriver::{check_safari_host, failed_to_start, get_safaridriver, SAFARIDRIVER_ENABLE},
 };
 
+/// How the browser tests reach a WebDriver.
+#[derive(Clone, Debug)]
+pub enum WebDriver {
+    /// Let `wasm-bindgen-test-runner` spawn the WebDriver client at this path.
+    Local(PathBuf),
+    /// Use the WebDriver server that is already running at this URL, such as
+    /// a Selenium Grid.
+    Remote(Url),
+}
+
+impl WebDriver {
+    /// The environment variable telling `wasm-bindgen-test-runner` to use this
+    /// WebDriver, given the one for a local client, such as `CHROMEDRIVER`.
+    pub fn env(&self, local_var: &str) -> (String, String) {
+        match self {
+            WebDriver::Local(path) => (local_var.to_string(), path.display().to_string()),
+            WebDriver::Remote(url) => (format!("{}_REMOTE", local_var), url.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for WebDriver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WebDriver::Local(path) => write!(f, "{}", path.display()),
+            WebDriver::Remote(url) => write!(f, "the WebDriver at {}", remote::redact(url)),
+        }
+    }
+}
+
+/// Make sure that the `name` binary the user gave at `path` exists and
+/// responds to `--version`, since it is used as is.
+pub fn check_driver(name: &str, path: &Path) -> Result<()> {
+    if !path.is_file() {
+        bail!("there is no `{}` at {}", name, path.display())
+    }
+    let output = Command::new(path)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("cannot run the `{}` at {}", name, path.display()))?;
+    if !output.status.success() {
+        bail!(
+            "`{} --version` exited with {}, is it a `{}`?",
+            path.display(),
+            output.status,
+            name
+        )
+    }
+    log::info!(
+        "[{}] {}",
+        name,
+        String::from_utf8_lossy(&output.stdout).trim()
+    );
+    Ok(())
+}
+
+/// Tell the user which `name` binary is used, and where it came from.
+pub fn announce_driver(name: &str, path: &Path, source: &str) {
+    PBAR.info(&format!("Using {} {}: {}", name, source, path.display()));
+}
+
 // ------ driver helpers  ------
 
 fn get_and_notify(
@@ -23,14 +101,63 @@ fn get_and_notify(
     name: &str,
     url: &str,
 ) -> Result<Option<PathBuf>> {
-    if let Some(dl) = cache.download(false, name, &[name], url)? {
+    if let Some(dl) = http::download(cache, false, name, &[name], url)? {
         return Ok(Some(dl.binary(name)?));
     }
     if installation_allowed {
         PBAR.info(&format!("Getting {}...", name));
     }
-    match cache.download(installation_allowed, name, &[name], url)? {
+    match http::download(cache, installation_allowed, name, &[name], url)? {
         Some(dl) => Ok(Some(dl.binary(name)?)),
         None => Ok(None),
     }
 }
+
+/// The error for a driver that isn't published upstream for this OS and
+/// architecture, rather than silently downloading one for another.
+fn unavailable_on_this_host(name: &str) -> Error {
+    anyhow!(
+        "`{name}` binaries are not published for {os} on {arch}, so wasm-pack can't download \
+         one. Install `{name}` yourself and pass it with `--{name}`.",
+        name = name,
+        os = std::env::consts::OS,
+        arch = std::env::consts::ARCH,
+    )
+}
+
+/// The context for a failed download of the pinned `version` of the driver
+/// `name`, most likely because that version isn't published for `target`.
+fn pinned_download_failed(name: &str, version: &str, target: &str) -> String {
+    format!(
+        "failed to download the pinned {} {} for {}. Check that this version is published \
+         for this platform.",
+        name, version, target
+    )
+}
+
+/// Ask an installed browser for its version, trying each of `commands` in
+/// turn: a browser binary to run with `--version`, or `reg query` on Windows.
+fn installed_browser_version(commands: &[(&str, &[&str])]) -> Result<String> {
+    for (program, args) in commands {
+        let output = match Command::new(program).args(*args).output() {
+            Ok(output) if output.status.success() => output,
+            _ => continue,
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if let Some(version) = parse_browser_version(&stdout) {
+            return Ok(version.to_string());
+        }
+    }
+    bail!("could not find an installed browser to ask for its version")
+}
+
+/// Find the version in the output of `<browser> --version`, `<driver>
+/// --version` or `reg query`, e.g. `Google Chrome 120.0.6099.109`.
+///
+/// This is the first dotted number in it, since some builds add the version
+/// of their OS after it, e.g. `Chromium 119.0.6045.159 built on Debian 12.2`.
+pub fn parse_browser_version(output: &str) -> Option<&str> {
+    output
+        .split_whitespace()
+        .find(|word| word.contains('.') && word.split('.').all(|part| part.parse::<u32>().is_ok()))
+}
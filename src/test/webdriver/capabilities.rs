@@ -0,0 +1,184 @@
+//! Turning the browser launch options into WebDriver capabilities.
+
+use super::remote::{read_capabilities, WEBDRIVER_JSON};
+use crate::manifest::BrowserOptions;
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Add the launch options of each of `browsers`, and the extra `args` for
+/// all of them, to `capabilities`.
+///
+/// Without other capabilities, the ones in the crate's `webdriver.json` are
+/// extended, if it exists. Returns whether that happened, so that the file can
+/// be restored after the tests.
+pub fn add_browser_options(
+    capabilities: &mut Option<Value>,
+    crate_path: &Path,
+    browsers: &[(&str, BrowserOptions)],
+    args: &[String],
+) -> Result<bool> {
+    let mut extends_webdriver_json = false;
+    for (browser, options) in browsers {
+        let window_size = options
+            .window_size
+            .as_deref()
+            .map(|size| parse_window_size(browser, size))
+            .transpose()?;
+        if options.args.is_empty()
+            && options.prefs.is_empty()
+            && options.binary.is_none()
+            && window_size.is_none()
+            && args.is_empty()
+        {
+            continue;
+        }
+
+        extends_webdriver_json |= load_base_capabilities(capabilities, crate_path)?;
+        let key = options_key(browser);
+        let launch = match capabilities.as_mut().unwrap() {
+            Value::Object(capabilities) => capabilities
+                .entry(key)
+                .or_insert_with(|| Value::Object(Map::new())),
+            _ => bail!("the WebDriver capabilities must be a JSON object"),
+        };
+        let launch = match launch {
+            Value::Object(launch) => launch,
+            _ => bail!("`{}` in the WebDriver capabilities must be an object", key),
+        };
+
+        let window_args = match window_size {
+            Some((width, height)) if *browser == "firefox" => {
+                vec![format!("--width={}", width), format!("--height={}", height)]
+            }
+            Some((width, height)) => vec![format!("--window-size={},{}", width, height)],
+            None => Vec::new(),
+        };
+        let new_args: Vec<&String> = options
+            .args
+            .iter()
+            .chain(&window_args)
+            .chain(args)
+            .collect();
+        if !new_args.is_empty() {
+            match launch
+                .entry("args")
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                Value::Array(existing) => {
+                    existing.extend(new_args.into_iter().map(|arg| Value::from(arg.as_str())))
+                }
+                _ => bail!(
+                    "`{}.args` in the WebDriver capabilities must be an array",
+                    key
+                ),
+            }
+        }
+
+        if !options.prefs.is_empty() {
+            match launch
+                .entry("prefs")
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                Value::Object(existing) => existing.extend(options.prefs.clone()),
+                _ => bail!(
+                    "`{}.prefs` in the WebDriver capabilities must be an object",
+                    key
+                ),
+            }
+        }
+
+        if let Some(binary) = &options.binary {
+            launch.insert(
+                "binary".to_string(),
+                Value::from(binary.display().to_string()),
+            );
+        }
+    }
+    Ok(extends_webdriver_json)
+}
+
+/// Without `capabilities` yet, start from the ones in the crate's
+/// `webdriver.json` if it exists, or from none. Returns whether the file was
+/// loaded.
+pub fn load_base_capabilities(capabilities: &mut Option<Value>, crate_path: &Path) -> Result<bool> {
+    if capabilities.is_some() {
+        return Ok(false);
+    }
+    let path = crate_path.join(WEBDRIVER_JSON);
+    let loaded = path.exists();
+    *capabilities = Some(if loaded {
+        read_capabilities(&path)?
+    } else {
+        Value::Object(Map::new())
+    });
+    Ok(loaded)
+}
+
+/// Read the capabilities of a single browser from a `BROWSER=FILE` argument
+/// of `--browser-capabilities`.
+pub fn read_browser_capabilities(arg: &str) -> Result<(&'static str, Value)> {
+    let (browser, path) = arg.split_once('=').ok_or_else(|| {
+        anyhow!(
+            "expected `BROWSER=FILE` for `--browser-capabilities`, not {:?}",
+            arg
+        )
+    })?;
+    let browser = match browser {
+        "chrome" => "chrome",
+        "firefox" => "firefox",
+        "safari" => "safari",
+        "edge" => "edge",
+        _ => bail!(
+            "unknown browser `{}` in `--browser-capabilities`, expected one of: `chrome`, \
+             `firefox`, `safari`, `edge`",
+            browser
+        ),
+    };
+    Ok((browser, read_capabilities(Path::new(path))?))
+}
+
+/// Merge `extra` into `capabilities`: objects are merged key by key, arrays
+/// such as `args` are appended to, and anything else is replaced.
+pub fn merge_capabilities(capabilities: &mut Value, extra: Value) {
+    match (capabilities, extra) {
+        (Value::Object(capabilities), Value::Object(extra)) => {
+            for (key, value) in extra {
+                match capabilities.get_mut(&key) {
+                    Some(existing) => merge_capabilities(existing, value),
+                    None => {
+                        capabilities.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(capabilities), Value::Array(extra)) => capabilities.extend(extra),
+        (capabilities, extra) => *capabilities = extra,
+    }
+}
+
+/// The capability holding the launch options of `browser`.
+fn options_key(browser: &str) -> &'static str {
+    match browser {
+        "chrome" => "goog:chromeOptions",
+        "firefox" => "moz:firefoxOptions",
+        "edge" => "ms:edgeOptions",
+        _ => unreachable!("no launch options for {}", browser),
+    }
+}
+
+/// Parse the `WIDTHxHEIGHT` window size of `browser`.
+fn parse_window_size(browser: &str, size: &str) -> Result<(u32, u32)> {
+    let parsed = size.split_once('x').and_then(|(width, height)| {
+        Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+    });
+    match parsed {
+        Some((width, height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => bail!(
+            "invalid `window-size` {:?} in `[package.metadata.wasm-pack.test.{}]`, expected \
+             `WIDTHxHEIGHT`, e.g. `1280x720`",
+            size,
+            browser
+        ),
+    }
+}
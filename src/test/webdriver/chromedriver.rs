@@ -1,31 +1,89 @@
-use super::get_and_notify;
+use super::{
+    announce_driver, get_and_notify, installed_browser_version, pinned_download_failed,
+    unavailable_on_this_host,
+};
+use crate::http;
 use crate::install::InstallMode;
 use crate::stamps;
 use crate::target;
+use crate::PBAR;
 use anyhow::{bail, Context, Result};
 use binary_install::Cache;
 use chrono::DateTime;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Keep it up to date with each `wasm-pack` release.
-// https://chromedriver.storage.googleapis.com/LATEST_RELEASE
-const DEFAULT_CHROMEDRIVER_VERSION: &str = "114.0.5735.90";
+// https://googlechromelabs.github.io/chrome-for-testing/last-known-good-versions.json
+const DEFAULT_CHROMEDRIVER_VERSION: &str = "129.0.6668.100";
 
 const CHROMEDRIVER_LAST_UPDATED_STAMP: &str = "chromedriver_last_updated";
 const CHROMEDRIVER_VERSION_STAMP: &str = "chromedriver_version";
 
 /// Get the path to an existing `chromedriver`, or install it if no existing
 /// binary is found or if there is a new binary version.
-pub fn get_or_install_chromedriver(cache: &Cache, mode: InstallMode) -> Result<PathBuf> {
-    if let Ok(path) = which::which("chromedriver") {
-        return Ok(path);
+///
+/// The `chromedriver` on the `$PATH` is skipped if it is for another major
+/// version than the installed Chrome, if there is a `chrome_binary` other
+/// than the installed Chrome, since it may not match that one, or if there is
+/// a `pinned_version`.
+pub fn get_or_install_chromedriver(
+    cache: &Cache,
+    mode: InstallMode,
+    pinned_version: Option<&str>,
+    chrome_binary: Option<&Path>,
+) -> Result<PathBuf> {
+    if chrome_binary.is_none() && pinned_version.is_none() {
+        if let Ok(path) = which::which("chromedriver") {
+            if matches_installed_chrome(&path) {
+                announce_driver("chromedriver", &path, "found on the $PATH");
+                return Ok(path);
+            }
+        }
+    }
+    let path = install_chromedriver(
+        cache,
+        mode.install_permitted(),
+        pinned_version,
+        chrome_binary,
+    )?;
+    announce_driver("chromedriver", &path, "downloaded by wasm-pack");
+    Ok(path)
+}
+
+/// Whether the `chromedriver` at `path` drives the installed Chrome, assuming
+/// it does if either version is unknown.
+fn matches_installed_chrome(path: &Path) -> bool {
+    let chrome_version = match installed_chrome_version() {
+        Ok(version) => version,
+        Err(_) => return true,
+    };
+    let chromedriver_version =
+        match installed_browser_version(&[(&path.to_string_lossy(), &["--version"])]) {
+            Ok(version) => version,
+            Err(_) => return true,
+        };
+    let chrome_major = chrome_version.split('.').next();
+    if chromedriver_version.split('.').next() == chrome_major {
+        return true;
     }
-    install_chromedriver(cache, mode.install_permitted())
+    PBAR.warn(&format!(
+        "The chromedriver {} on the $PATH doesn't drive the installed Chrome {}, getting a \
+         matching chromedriver instead.",
+        chromedriver_version, chrome_version
+    ));
+    false
 }
 
-/// Download and install a pre-built `chromedriver` binary.
-pub fn install_chromedriver(cache: &Cache, installation_allowed: bool) -> Result<PathBuf> {
+/// Download and install a pre-built `chromedriver` binary: `pinned_version`
+/// if given, otherwise the one matching `chrome_binary` or the installed
+/// Chrome if it can be found, and the latest stable one otherwise.
+pub fn install_chromedriver(
+    cache: &Cache,
+    installation_allowed: bool,
+    pinned_version: Option<&str>,
+    chrome_binary: Option<&Path>,
+) -> Result<PathBuf> {
     let target = if target::LINUX && target::x86_64 {
         "linux64"
     } else if target::MACOS && target::x86_64 {
@@ -35,12 +93,22 @@ pub fn install_chromedriver(cache: &Cache, installation_allowed: bool) -> Result
     } else if target::WINDOWS {
         "win32"
     } else {
-        bail!("chromedriver binaries are unavailable for this target")
+        return Err(unavailable_on_this_host("chromedriver"));
     };
 
-    let url = get_chromedriver_url(target);
+    let url = match pinned_version {
+        Some(version) => assemble_chromedriver_url(version, target),
+        None => get_chromedriver_url(target, chrome_binary)?,
+    };
 
-    match get_and_notify(cache, installation_allowed, "chromedriver", &url)? {
+    let downloaded = get_and_notify(cache, installation_allowed, "chromedriver", &url);
+    let downloaded = match pinned_version {
+        Some(version) => {
+            downloaded.with_context(|| pinned_download_failed("chromedriver", version, target))
+        }
+        None => downloaded,
+    };
+    match downloaded? {
         Some(path) => Ok(path),
         None => bail!(
             "No cached `chromedriver` binary found, and could not find a global \
@@ -52,61 +120,163 @@ pub fn install_chromedriver(cache: &Cache, installation_allowed: bool) -> Result
 
 /// Get `chromedriver` download URL.
 ///
-/// _Algorithm_:
-/// 1. Try to open `*.stamps` file and deserialize its content to JSON object.
-/// 2. Try to compare current time with the saved one.
-/// 3. If the saved time is older than 1 day or something failed
-///    => fetch a new version and save version & time.
-/// 4. If everything failed, use the default version.
-/// 5. Return URL.
-///
-/// _Notes:_
+/// Chrome refuses to talk to a `chromedriver` from another milestone, so the
+/// driver has to match the installed Chrome's major version.
 ///
-/// It returns the latest one without checking the installed `Chrome` version
-/// because it's not easy to find out `Chrome` version on `Windows` -
-/// https://bugs.chromium.org/p/chromium/issues/detail?id=158372
+/// _Algorithm_:
+/// 1. Ask `chrome_binary`, or the installed Chrome, for its version. If that works, look up the
+///    latest `chromedriver` of its major version, using the one saved in the
+///    `*.stamps` file if it is less than 1 day old, and fail if there is none.
+/// 2. Otherwise use the latest stable `chromedriver`, again saved for a day,
+///    or the default version if everything failed, warning if `chrome_binary`
+///    didn't say its version.
+/// 3. Return URL.
 ///
-/// The official algorithm for `chromedriver` version selection:
-/// https://chromedriver.chromium.org/downloads/version-selection
-fn get_chromedriver_url(target: &str) -> String {
-    let fetch_and_save_version =
-        || fetch_chromedriver_version().and_then(save_chromedriver_version);
-
-    let chromedriver_version = match stamps::read_stamps_file_to_json() {
-        Ok(json) => {
-            if should_load_chromedriver_version_from_stamp(&json) {
-                stamps::get_stamp_value(CHROMEDRIVER_VERSION_STAMP, &json)
-            } else {
-                fetch_and_save_version()
+/// The `chromedriver` versions come from the Chrome for Testing JSON
+/// endpoints, since the old `chromedriver.storage` ones stop at 114:
+/// https://github.com/GoogleChromeLabs/chrome-for-testing#json-api-endpoints
+fn get_chromedriver_url(target: &str, chrome_binary: Option<&Path>) -> Result<String> {
+    let chrome_version = match chrome_binary {
+        Some(binary) => match chrome_binary_version(binary) {
+            Ok(version) => Some(version),
+            Err(error) => {
+                PBAR.warn(&format!(
+                    "{:#}, so using the latest stable chromedriver instead, which may not \
+                     drive it.",
+                    error
+                ));
+                None
+            }
+        },
+        None => installed_chrome_version().ok(),
+    };
+    if let Some(chrome_version) = chrome_version {
+        let major = chrome_version.split('.').next().unwrap_or_default();
+        let chromedriver_version =
+            load_or_fetch(&format!("{}_{}", CHROMEDRIVER_VERSION_STAMP, major), || {
+                fetch_chromedriver_version_for_milestone(major)
+            });
+        match chromedriver_version {
+            Ok(Some(version)) => return Ok(assemble_chromedriver_url(&version, target)),
+            Ok(None) => {
+                let latest = load_or_fetch(CHROMEDRIVER_VERSION_STAMP, || {
+                    fetch_chromedriver_version().map(Some)
+                })
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| DEFAULT_CHROMEDRIVER_VERSION.to_owned());
+                bail!(
+                    "Chrome {} is installed, but there is no chromedriver for Chrome {}; the \
+                     latest stable chromedriver is {}. Update Chrome, or pass `--chromedriver` \
+                     with a compatible chromedriver.",
+                    chrome_version,
+                    major,
+                    latest
+                )
             }
+            Err(error) => log::warn!(
+                "Cannot load or fetch the chromedriver version for Chrome {}. Error: {}",
+                chrome_version,
+                error
+            ),
         }
-        Err(_) => fetch_and_save_version(),
     }
-    .unwrap_or_else(|error| {
+
+    let chromedriver_version = load_or_fetch(CHROMEDRIVER_VERSION_STAMP, || {
+        fetch_chromedriver_version().map(Some)
+    })
+    .ok()
+    .flatten()
+    .unwrap_or_else(|| {
         log::warn!(
             "Cannot load or fetch chromedriver's latest version data, \
-             the default version {} will be used. Error: {}",
+             the default version {} will be used.",
             DEFAULT_CHROMEDRIVER_VERSION,
-            error
         );
         DEFAULT_CHROMEDRIVER_VERSION.to_owned()
     });
-    assemble_chromedriver_url(&chromedriver_version, target)
+    Ok(assemble_chromedriver_url(&chromedriver_version, target))
+}
+
+/// Ask the Chrome at `binary` for its version.
+fn chrome_binary_version(binary: &Path) -> Result<String> {
+    installed_browser_version(&[(&binary.to_string_lossy(), &["--version"])]).with_context(|| {
+        format!(
+            "failed to get the version of Chrome at {}",
+            binary.display()
+        )
+    })
+}
+
+/// Ask the installed Chrome for its version.
+fn installed_chrome_version() -> Result<String> {
+    if target::WINDOWS {
+        installed_browser_version(&[(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Google\Chrome\BLBeacon",
+                "/v",
+                "version",
+            ],
+        )])
+    } else if target::MACOS {
+        installed_browser_version(&[(
+            "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+            &["--version"],
+        )])
+    } else {
+        installed_browser_version(&[
+            ("google-chrome", &["--version"]),
+            ("google-chrome-stable", &["--version"]),
+            ("chromium", &["--version"]),
+            ("chromium-browser", &["--version"]),
+        ])
+    }
+}
+
+/// Load the version saved under `stamp` if it is less than 1 day old, or
+/// `fetch` it and save it. `None` is not saved, so that it is fetched again.
+fn load_or_fetch(
+    stamp: &str,
+    fetch: impl FnOnce() -> Result<Option<String>>,
+) -> Result<Option<String>> {
+    if let Ok(json) = stamps::read_stamps_file_to_json() {
+        if should_load_chromedriver_version_from_stamp(stamp, &json) {
+            if let Ok(version) = stamps::get_stamp_value(stamp, &json) {
+                return Ok(Some(version));
+            }
+        }
+    }
+    let version = fetch()?;
+    if let Some(version) = &version {
+        if let Err(error) = save_chromedriver_version(stamp, version) {
+            log::warn!("Cannot save the chromedriver version. Error: {}", error);
+        }
+    }
+    Ok(version)
 }
 
 // ------ `get_chromedriver_url` helpers ------
 
-fn save_chromedriver_version(version: String) -> Result<String> {
-    stamps::save_stamp_value(CHROMEDRIVER_VERSION_STAMP, &version)?;
+fn save_chromedriver_version(stamp: &str, version: &str) -> Result<()> {
+    stamps::save_stamp_value(stamp, version)?;
 
     let current_time = chrono::offset::Local::now().to_rfc3339();
-    stamps::save_stamp_value(CHROMEDRIVER_LAST_UPDATED_STAMP, current_time)?;
+    stamps::save_stamp_value(last_updated_stamp(stamp), current_time)
+}
 
-    Ok(version)
+/// The stamp holding when the version under `stamp` was saved.
+fn last_updated_stamp(stamp: &str) -> String {
+    stamp.replacen(
+        CHROMEDRIVER_VERSION_STAMP,
+        CHROMEDRIVER_LAST_UPDATED_STAMP,
+        1,
+    )
 }
 
-fn should_load_chromedriver_version_from_stamp(json: &serde_json::Value) -> bool {
-    let last_updated = stamps::get_stamp_value(CHROMEDRIVER_LAST_UPDATED_STAMP, json)
+fn should_load_chromedriver_version_from_stamp(stamp: &str, json: &serde_json::Value) -> bool {
+    let last_updated = stamps::get_stamp_value(last_updated_stamp(stamp), json)
         .ok()
         .and_then(|last_updated| DateTime::parse_from_rfc3339(&last_updated).ok());
 
@@ -131,12 +301,39 @@ struct GoodLatestVersions {
     channels: HashMap<String, ChannelInfo>,
 }
 
+/// A milestone from the chromedriver per-milestone endpoint.
+#[derive(Deserialize)]
+struct MilestoneInfo {
+    version: String,
+}
+
+/// The response from the chromedriver per-milestone endpoint.
+#[derive(Deserialize)]
+struct LatestVersionsPerMilestone {
+    milestones: HashMap<String, MilestoneInfo>,
+}
+
+/// Retrieve the latest version of chromedriver for Chrome's `milestone`, its
+/// major version, from the json endpoints. Returns `None` if there is no
+/// chromedriver for it.
+fn fetch_chromedriver_version_for_milestone(milestone: &str) -> Result<Option<String>> {
+    let info: LatestVersionsPerMilestone = http::agent()
+        .get("https://googlechromelabs.github.io/chrome-for-testing/latest-versions-per-milestone.json")
+        .call()
+        .context("fetching of chromedriver's latest versions per milestone failed")?
+        .into_json()
+        .context("converting chromedriver milestones response to LatestVersionsPerMilestone failed")?;
+
+    Ok(info
+        .milestones
+        .get(milestone)
+        .map(|milestone| milestone.version.clone()))
+}
+
 /// Retrieve the latest version of chromedriver from the json endpoints.
 /// See: <https://github.com/GoogleChromeLabs/chrome-for-testing#json-api-endpoints>
 fn fetch_chromedriver_version() -> Result<String> {
-    let info: GoodLatestVersions = ureq::builder()
-        .try_proxy_from_env(true)
-        .build()
+    let info: GoodLatestVersions = http::agent()
         .get("https://googlechromelabs.github.io/chrome-for-testing/last-known-good-versions.json")
         .call()
         .context("fetching of chromedriver's LATEST_RELEASE failed")?
@@ -155,7 +352,26 @@ fn fetch_chromedriver_version() -> Result<String> {
     Ok(version)
 }
 
+/// Versions before 115 are only published at the old `chromedriver.storage`
+/// endpoint, under other names for the targets.
 fn assemble_chromedriver_url(chromedriver_version: &str, target: &str) -> String {
+    let major: u32 = chromedriver_version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse().ok())
+        .unwrap_or(u32::MAX);
+    if major < 115 {
+        let target = match target {
+            "mac-x64" => "mac64",
+            "mac-arm64" => "mac_arm64",
+            target => target,
+        };
+        return format!(
+            "https://chromedriver.storage.googleapis.com/{version}/chromedriver_{target}.zip",
+            version = chromedriver_version,
+            target = target,
+        );
+    }
     format!(
         "https://storage.googleapis.com/chrome-for-testing-public/{version}/{target}/chromedriver-{target}.zip",
         version = chromedriver_version,
@@ -1,7 +1,9 @@
-use super::get_and_notify;
+use super::{announce_driver, get_and_notify, pinned_download_failed, unavailable_on_this_host};
+use crate::http;
 use crate::install::InstallMode;
 use crate::stamps;
 use crate::target;
+use crate::PBAR;
 use anyhow::{anyhow, bail, Context, Result};
 use binary_install::Cache;
 use chrono::DateTime;
@@ -17,7 +19,11 @@ const GECKODRIVER_VERSION_STAMP: &str = "geckodriver_version";
 
 /// Get the path to an existing `geckodriver`, or install it if no existing
 /// binary is found or if there is a new binary version.
-pub fn get_or_install_geckodriver(cache: &Cache, mode: InstallMode) -> Result<PathBuf> {
+pub fn get_or_install_geckodriver(
+    cache: &Cache,
+    mode: InstallMode,
+    pinned_version: Option<&str>,
+) -> Result<PathBuf> {
     // geckodriver Windows binaries >v0.24.0 have an additional
     // runtime dependency that we cannot be sure is present on the
     // user's machine
@@ -25,36 +31,52 @@ pub fn get_or_install_geckodriver(cache: &Cache, mode: InstallMode) -> Result<Pa
     // https://github.com/mozilla/geckodriver/issues/1617
     //
     // until this is resolved, always install v0.24.0 on windows
-    if !target::WINDOWS {
+    if !target::WINDOWS && pinned_version.is_none() {
         if let Ok(path) = which::which("geckodriver") {
-            log::info!("[geckodriver] Found geckodriver at {:?}", path);
+            announce_driver("geckodriver", &path, "found on the $PATH");
             return Ok(path);
         }
     }
-    install_geckodriver(cache, mode.install_permitted())
+    let path = install_geckodriver(cache, mode.install_permitted(), pinned_version)?;
+    announce_driver("geckodriver", &path, "downloaded by wasm-pack");
+    Ok(path)
 }
 
-/// Download and install a pre-built `geckodriver` binary.
-pub fn install_geckodriver(cache: &Cache, installation_allowed: bool) -> Result<PathBuf> {
+/// Download and install a pre-built `geckodriver` binary, of `pinned_version`
+/// if given and of the latest release otherwise.
+pub fn install_geckodriver(
+    cache: &Cache,
+    installation_allowed: bool,
+    pinned_version: Option<&str>,
+) -> Result<PathBuf> {
     let (target, ext) = if target::LINUX && target::x86 {
         ("linux32", "tar.gz")
     } else if target::LINUX && target::x86_64 {
         ("linux64", "tar.gz")
     } else if target::LINUX && target::aarch64 {
         ("linux-aarch64", "tar.gz")
-    } else if target::MACOS {
+    } else if target::MACOS && target::x86_64 {
         ("macos", "tar.gz")
+    } else if target::MACOS && target::aarch64 {
+        ("macos-aarch64", "tar.gz")
     } else if target::WINDOWS && target::x86 {
         ("win32", "zip")
     } else if target::WINDOWS && target::x86_64 {
         ("win64", "zip")
     } else {
-        bail!("geckodriver binaries are unavailable for this target")
+        return Err(unavailable_on_this_host("geckodriver"));
     };
 
-    let url = get_geckodriver_url(target, ext);
+    let url = get_geckodriver_url(target, ext, pinned_version);
 
-    match get_and_notify(cache, installation_allowed, "geckodriver", &url)? {
+    let downloaded = get_and_notify(cache, installation_allowed, "geckodriver", &url);
+    let downloaded = match pinned_version {
+        Some(version) => {
+            downloaded.with_context(|| pinned_download_failed("geckodriver", version, target))
+        }
+        None => downloaded,
+    };
+    match downloaded? {
         Some(path) => Ok(path),
         None => bail!(
             "No cached `geckodriver` binary found, and could not find a global `geckodriver` \
@@ -66,23 +88,29 @@ pub fn install_geckodriver(cache: &Cache, installation_allowed: bool) -> Result<
 /// Get `geckodriver` download URL.
 ///
 /// _Algorithm_:
-/// 1. Try to open `*.stamps` file and deserialize its content to JSON object.
-/// 2. Try to compare current time with the saved one.
-/// 3. If the saved time is older than 1 day or something failed
+/// 1. If there is a `pinned_version`, use it.
+/// 2. Try to open `*.stamps` file and deserialize its content to JSON object.
+/// 3. Try to compare current time with the saved one.
+/// 4. If the saved time is older than 1 day or something failed
 ///    => fetch a new version and save version & time.
-/// 4. If everything failed, use the default version.
-/// 5. Return URL.
+/// 5. If fetching failed, e.g. because of GitHub's rate limit or no network,
+///    warn and use the saved version however old it is.
+/// 6. If everything failed, use the default version.
+/// 7. Return URL.
 ///
 /// _Notes:_
 ///
 /// It returns the latest one without checking the installed `Firefox` version
 /// - it should be relatively safe because each `geckodriver` supports many `Firefox` versions:
 /// https://firefox-source-docs.mozilla.org/testing/geckodriver/Support.html#supported-platforms
-fn get_geckodriver_url(target: &str, ext: &str) -> String {
-    let fetch_and_save_version =
-        || fetch_latest_geckodriver_tag_json().and_then(save_geckodriver_version);
-
-    let geckodriver_version = if target::WINDOWS {
+fn get_geckodriver_url(target: &str, ext: &str, pinned_version: Option<&str>) -> String {
+    let geckodriver_version = if let Some(version) = pinned_version {
+        log::info!(
+            "[geckodriver] Using the pinned geckodriver version {}",
+            version
+        );
+        normalize_geckodriver_tag(version)
+    } else if target::WINDOWS {
         log::info!(
             "[geckodriver] Windows detected, holding geckodriver version to {}",
             DEFAULT_WINDOWS_GECKODRIVER_VERSION
@@ -90,23 +118,34 @@ fn get_geckodriver_url(target: &str, ext: &str) -> String {
         DEFAULT_WINDOWS_GECKODRIVER_VERSION.to_owned()
     } else {
         log::info!("[geckodriver] Looking up latest version of geckodriver...");
-        match stamps::read_stamps_file_to_json() {
-            Ok(json) => {
-                if should_load_geckodriver_version_from_stamp(&json) {
-                    stamps::get_stamp_value(GECKODRIVER_VERSION_STAMP, &json)
-                } else {
-                    fetch_and_save_version()
-                }
+        let stamps = stamps::read_stamps_file_to_json().ok();
+        let saved_version = stamps
+            .as_ref()
+            .and_then(|json| stamps::get_stamp_value(GECKODRIVER_VERSION_STAMP, json).ok());
+        match (saved_version, stamps) {
+            (Some(version), Some(json)) if should_load_geckodriver_version_from_stamp(&json) => {
+                Ok(version)
             }
-            Err(_) => fetch_and_save_version(),
+            (saved_version, _) => fetch_latest_geckodriver_tag_json()
+                .and_then(save_geckodriver_version)
+                .or_else(|error| match saved_version {
+                    Some(version) => {
+                        PBAR.warn(&format!(
+                            "Cannot fetch geckodriver's latest version, using the previously \
+                             found {} instead: {}",
+                            version, error
+                        ));
+                        Ok(version)
+                    }
+                    None => Err(error),
+                }),
         }
         .unwrap_or_else(|error| {
-            log::warn!(
-                "[geckodriver] Cannot load or fetch geckodriver's latest version data, \
-                 the default version {} will be used. Error: {}",
-                DEFAULT_GECKODRIVER_VERSION,
-                error
-            );
+            PBAR.warn(&format!(
+                "Cannot load or fetch geckodriver's latest version, the default version {} \
+                 will be used: {}",
+                DEFAULT_GECKODRIVER_VERSION, error
+            ));
             DEFAULT_GECKODRIVER_VERSION.to_owned()
         })
     };
@@ -117,6 +156,15 @@ fn get_geckodriver_url(target: &str, ext: &str) -> String {
 
 // ------ `get_geckodriver_url` helpers  ------
 
+/// Release tags start with a `v`, which a pinned version may leave out.
+fn normalize_geckodriver_tag(version: &str) -> String {
+    if version.starts_with('v') {
+        version.to_owned()
+    } else {
+        format!("v{}", version)
+    }
+}
+
 fn save_geckodriver_version(version: String) -> Result<String> {
     stamps::save_stamp_value(GECKODRIVER_VERSION_STAMP, &version)?;
 
@@ -140,19 +188,31 @@ fn should_load_geckodriver_version_from_stamp(json: &serde_json::Value) -> bool
     }
 }
 
+/// Ask the GitHub releases API for the latest `geckodriver` release.
 fn fetch_latest_geckodriver_tag_json() -> Result<String> {
-    let content: serde_json::Value = ureq::builder()
-        .try_proxy_from_env(true)
-        .build()
-        .get("https://github.com/mozilla/geckodriver/releases/latest")
-        .set("Accept", "application/json")
-        .call()
-        .context("fetching of geckodriver's latest release data failed")?
-        .into_json()?;
+    let response = http::agent()
+        .get("https://api.github.com/repos/mozilla/geckodriver/releases/latest")
+        .set("Accept", "application/vnd.github+json")
+        .call();
+    let content: serde_json::Value = match response {
+        Ok(response) => response.into_json()?,
+        Err(ureq::Error::Status(status, response)) if is_rate_limited(status, &response) => {
+            bail!("GitHub's API rate limit was exceeded")
+        }
+        Err(error) => {
+            return Err(error).context("fetching of geckodriver's latest release data failed")
+        }
+    };
 
     get_version_from_json(content)
 }
 
+/// GitHub answers 403 or 429 once the API rate limit is used up.
+/// See: <https://docs.github.com/en/rest/using-the-rest-api/rate-limits-for-the-rest-api>
+fn is_rate_limited(status: u16, response: &ureq::Response) -> bool {
+    status == 429 || (status == 403 && response.header("x-ratelimit-remaining") == Some("0"))
+}
+
 /// JSON example: `{"id":15227534,"tag_name":"v0.24.0","update_url":"/mozzila...`
 fn get_version_from_json(json: serde_json::Value) -> Result<String> {
     json.get("tag_name")
@@ -0,0 +1,238 @@
+//! WebDriver clients kept running between test runs with
+//! `wasm-pack test --keep-driver-alive`.
+//!
+//! Each kept driver is recorded in a state file in the cache, so that later
+//! runs can find it, check that it is still answering, and run the tests with
+//! it instead of starting a new one.
+
+use crate::http;
+use crate::PBAR;
+use anyhow::{bail, Context, Result};
+use binary_install::Cache;
+use std::fs;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// The directory in the cache with the state files of the kept drivers.
+pub const KEPT_DRIVERS_DIR: &str = "kept-drivers";
+
+/// How long a new driver gets to start answering.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many ports to try a new driver on, in case another process takes the
+/// free port that was picked before the driver binds to it.
+const START_ATTEMPTS: usize = 3;
+
+/// What is recorded about a kept driver.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeptDriver {
+    /// The driver binary that was started.
+    pub driver: PathBuf,
+    /// The port on `127.0.0.1` it listens on.
+    pub port: u16,
+    /// Its process id.
+    pub pid: u32,
+}
+
+impl KeptDriver {
+    /// The URL of the driver.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://127.0.0.1:{}/", self.port)).unwrap()
+    }
+}
+
+/// The state file of the kept driver called `name`, such as `chromedriver`.
+pub fn state_file(cache: &Cache, name: &str) -> PathBuf {
+    cache
+        .join(Path::new(KEPT_DRIVERS_DIR))
+        .join(format!("{}.json", name))
+}
+
+/// Get the URL of a running `name` started from `driver`, reusing the one
+/// from an earlier run if it is still answering, and starting one otherwise.
+pub fn kept_driver(cache: &Cache, name: &str, driver: &Path) -> Result<Url> {
+    let state_file = state_file(cache, name);
+    if let Some(kept) = read_state(&state_file) {
+        let answering = is_answering(&kept.url());
+        if kept.driver == driver && answering {
+            PBAR.info(&format!(
+                "Reusing the {} kept running at {}",
+                name,
+                kept.url()
+            ));
+            return Ok(kept.url());
+        }
+        if answering {
+            // Started from another binary, such as an older version of the
+            // driver, which would otherwise be left running for good.
+            PBAR.info(&format!(
+                "Stopping the {} kept running from {}, since {} is used now.",
+                name,
+                kept.driver.display(),
+                driver.display()
+            ));
+            if let Err(e) = kill(kept.pid) {
+                PBAR.warn(&format!(
+                    "Failed to stop the {} with process id {}: {:#}",
+                    name, kept.pid, e
+                ));
+            }
+        } else {
+            log::info!(
+                "The {} recorded in {} isn't answering anymore, starting a new one.",
+                name,
+                state_file.display()
+            );
+        }
+        let _ = fs::remove_file(&state_file);
+    }
+
+    let kept = start(name, driver)?;
+    if let Some(parent) = state_file.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(&state_file, serde_json::to_string_pretty(&kept)?)
+        .with_context(|| format!("failed to write {}", state_file.display()))?;
+    PBAR.info(&format!(
+        "Started {} at {}, kept running for the next test runs. Stop it with \
+         `wasm-pack test --shutdown-driver`.",
+        name,
+        kept.url()
+    ));
+    Ok(kept.url())
+}
+
+/// Stop the drivers kept running, returning how many were stopped. State
+/// files of drivers that are gone are removed too.
+///
+/// A driver is only stopped if it still answers on its port, so that a
+/// process that has since been given the same id is left alone.
+pub fn shutdown_kept_drivers(cache: &Cache) -> Result<usize> {
+    let dir = cache.join(Path::new(KEPT_DRIVERS_DIR));
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", dir.display())),
+    };
+    let mut stopped = 0;
+    for entry in entries {
+        let path = entry?.path();
+        if let Some(kept) = read_state(&path) {
+            if is_answering(&kept.url()) {
+                kill(kept.pid).with_context(|| {
+                    format!(
+                        "failed to stop the driver {} with process id {}",
+                        kept.driver.display(),
+                        kept.pid
+                    )
+                })?;
+                stopped += 1;
+            }
+        }
+        fs::remove_file(&path).with_context(|| format!("failed to remove {}", path.display()))?;
+    }
+    Ok(stopped)
+}
+
+fn read_state(path: &Path) -> Option<KeptDriver> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Whether a WebDriver answers at `url`. Anything else that took over the
+/// port wouldn't answer `/status` with a WebDriver response.
+fn is_answering(url: &Url) -> bool {
+    let status = match url.join("status") {
+        Ok(status) => status,
+        Err(_) => return false,
+    };
+    let response = match http::agent()
+        .get(status.as_str())
+        .timeout(Duration::from_secs(2))
+        .call()
+    {
+        Ok(response) => response,
+        Err(_) => return false,
+    };
+    response
+        .into_json::<serde_json::Value>()
+        .map(|json| json.get("value").is_some())
+        .unwrap_or(false)
+}
+
+/// Start `driver` on a free port, detached from this process, and wait for
+/// it to answer.
+fn start(name: &str, driver: &Path) -> Result<KeptDriver> {
+    let mut errors = Vec::new();
+    for _ in 0..START_ATTEMPTS {
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let mut command = Command::new(driver);
+        command
+            .arg(format!("--port={}", port))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Keep a Ctrl-C meant for this run from stopping the driver too.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("failed to start {}", driver.display()))?;
+        let kept = KeptDriver {
+            driver: driver.to_path_buf(),
+            port,
+            pid: child.id(),
+        };
+        let started = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                // Most likely something else took the port first.
+                errors.push(format!("on port {} it exited with {}", port, status));
+                break;
+            }
+            if is_answering(&kept.url()) {
+                return Ok(kept);
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                let _ = child.kill();
+                errors.push(format!(
+                    "on port {} it didn't answer within {} seconds",
+                    port,
+                    STARTUP_TIMEOUT.as_secs()
+                ));
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+    bail!(
+        "failed to start {} to keep it running: {}",
+        name,
+        errors.join("; ")
+    )
+}
+
+#[cfg(unix)]
+fn kill(pid: u32) -> Result<()> {
+    let status = Command::new("kill").arg(pid.to_string()).status()?;
+    if !status.success() {
+        bail!("`kill` exited with {}", status)
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn kill(pid: u32) -> Result<()> {
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/F"])
+        .status()?;
+    if !status.success() {
+        bail!("`taskkill` exited with {}", status)
+    }
+    Ok(())
+}
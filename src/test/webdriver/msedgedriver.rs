@@ -0,0 +1,149 @@
+use super::{announce_driver, get_and_notify, installed_browser_version, parse_browser_version};
+use crate::http;
+use crate::install::InstallMode;
+use crate::target;
+use anyhow::{bail, Context, Result};
+use binary_install::Cache;
+use std::io::Read;
+use std::path::PathBuf;
+
+// Keep it up to date with each `wasm-pack` release.
+// https://msedgedriver.azureedge.net/LATEST_STABLE
+const DEFAULT_MSEDGEDRIVER_VERSION: &str = "129.0.2792.79";
+
+/// Make sure that Edge tests can run on this host. `msedgedriver` is only
+/// downloaded for the Windows and macOS builds of Edge.
+pub fn check_edge_host() -> Result<()> {
+    msedgedriver_target().map(|_| ())
+}
+
+/// Get the path to an existing `msedgedriver`, or install the one matching
+/// the installed Edge, or `pinned_version` if there is one.
+pub fn get_or_install_msedgedriver(
+    cache: &Cache,
+    mode: InstallMode,
+    pinned_version: Option<&str>,
+) -> Result<PathBuf> {
+    if pinned_version.is_none() {
+        if let Ok(path) = which::which("msedgedriver") {
+            announce_driver("msedgedriver", &path, "found on the $PATH");
+            return Ok(path);
+        }
+    }
+    let path = install_msedgedriver(cache, mode.install_permitted(), pinned_version)?;
+    announce_driver("msedgedriver", &path, "downloaded by wasm-pack");
+    Ok(path)
+}
+
+/// Download and install a pre-built `msedgedriver` binary.
+///
+/// The version is `pinned_version` if given, otherwise that of the installed
+/// Edge, since each `msedgedriver` only drives the Edge release it shipped
+/// with.
+pub fn install_msedgedriver(
+    cache: &Cache,
+    installation_allowed: bool,
+    pinned_version: Option<&str>,
+) -> Result<PathBuf> {
+    let target = msedgedriver_target()?;
+    let version = match pinned_version {
+        Some(version) => version.to_string(),
+        None => installed_edge_version()
+            .or_else(|_| fetch_msedgedriver_version())
+            .unwrap_or_else(|error| {
+                log::warn!(
+                    "Cannot find the installed Edge version or msedgedriver's latest version, \
+                     the default version {} will be used. Error: {}",
+                    DEFAULT_MSEDGEDRIVER_VERSION,
+                    error
+                );
+                DEFAULT_MSEDGEDRIVER_VERSION.to_owned()
+            }),
+    };
+
+    let url = assemble_msedgedriver_url(&version, target);
+
+    match get_and_notify(cache, installation_allowed, "msedgedriver", &url)? {
+        Some(path) => Ok(path),
+        None => bail!(
+            "No cached `msedgedriver` binary found, and could not find a global \
+             `msedgedriver` on the `$PATH`. Not installing `msedgedriver` because of noinstall \
+             mode."
+        ),
+    }
+}
+
+fn msedgedriver_target() -> Result<&'static str> {
+    if target::WINDOWS && target::x86 {
+        Ok("win32")
+    } else if target::WINDOWS && target::x86_64 {
+        Ok("win64")
+    } else if target::WINDOWS && target::aarch64 {
+        Ok("arm64")
+    } else if target::MACOS && target::x86_64 {
+        Ok("mac64")
+    } else if target::MACOS && target::aarch64 {
+        Ok("mac64_m1")
+    } else {
+        bail!(
+            "Edge tests are only supported on Windows and macOS, where wasm-pack can download \
+             `msedgedriver`. Pass `--msedgedriver` to use a `msedgedriver` elsewhere."
+        )
+    }
+}
+
+/// Ask the installed Edge for its version.
+fn installed_edge_version() -> Result<String> {
+    if target::WINDOWS {
+        installed_browser_version(&[(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Edge\BLBeacon",
+                "/v",
+                "version",
+            ],
+        )])
+    } else {
+        installed_browser_version(&[(
+            "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+            &["--version"],
+        )])
+    }
+}
+
+/// Retrieve the latest stable version of msedgedriver.
+fn fetch_msedgedriver_version() -> Result<String> {
+    let response = http::agent()
+        .get("https://msedgedriver.azureedge.net/LATEST_STABLE")
+        .call()
+        .context("fetching of msedgedriver's LATEST_STABLE failed")?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .context("reading msedgedriver's LATEST_STABLE failed")?;
+
+    // The file is UTF-16 with a byte order mark.
+    let text = if bytes.starts_with(&[0xff, 0xfe]) {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+    match parse_browser_version(&text) {
+        Some(version) => Ok(version.to_string()),
+        None => bail!("unexpected msedgedriver LATEST_STABLE: {:?}", text),
+    }
+}
+
+fn assemble_msedgedriver_url(msedgedriver_version: &str, target: &str) -> String {
+    format!(
+        "https://msedgedriver.azureedge.net/{version}/edgedriver_{target}.zip",
+        version = msedgedriver_version,
+        target = target,
+    )
+}
@@ -0,0 +1,152 @@
+use crate::http;
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// The environment variable with the user name for a remote WebDriver, used
+/// when its URL doesn't contain one.
+pub const WEBDRIVER_USER: &str = "WASM_PACK_WEBDRIVER_USER";
+/// The environment variable with the password that goes with
+/// `WEBDRIVER_USER`.
+pub const WEBDRIVER_PASSWORD: &str = "WASM_PACK_WEBDRIVER_PASSWORD";
+
+/// The file in the crate's root that `wasm-bindgen-test-runner` reads the
+/// desired capabilities of the browser session from.
+pub(super) const WEBDRIVER_JSON: &str = "webdriver.json";
+
+/// Parse the `url` of a remote WebDriver, such as a Selenium Grid, adding the
+/// credentials from the environment if it has none.
+pub fn remote_webdriver_url(url: &str) -> Result<Url> {
+    let mut url = Url::parse(url).with_context(|| format!("invalid WebDriver URL `{}`", url))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!(
+            "the WebDriver URL `{}` must start with `http://` or `https://`",
+            url
+        )
+    }
+    if url.username().is_empty() {
+        if let Ok(user) = env::var(WEBDRIVER_USER) {
+            let password = env::var(WEBDRIVER_PASSWORD).ok();
+            if url.set_username(&user).is_err() || url.set_password(password.as_deref()).is_err() {
+                bail!("cannot add credentials to the WebDriver URL `{}`", url)
+            }
+        }
+    }
+    Ok(url)
+}
+
+/// Make sure that the remote WebDriver at `url` is up, so that failing to
+/// reach it isn't reported as failing tests.
+pub fn check_remote_webdriver(url: &Url) -> Result<()> {
+    let mut status = url.clone();
+    status.set_path(&format!("{}/status", url.path().trim_end_matches('/')));
+    match http::agent().get(status.as_str()).call() {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(401, _)) | Err(ureq::Error::Status(403, _)) => bail!(
+            "The WebDriver at {} rejected the credentials. Put them in the URL, or set `{}` \
+             and `{}`.",
+            redact(url),
+            WEBDRIVER_USER,
+            WEBDRIVER_PASSWORD
+        ),
+        // Some servers don't implement `/status`, but they did answer.
+        Err(ureq::Error::Status(_, _)) => Ok(()),
+        // The error itself would show the password in the URL.
+        Err(ureq::Error::Transport(transport)) => bail!(
+            "Cannot reach the WebDriver at {}: {}",
+            redact(url),
+            std::error::Error::source(&transport)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| transport.kind().to_string())
+        ),
+    }
+}
+
+/// `url` without its password, to show to the user.
+pub fn redact(url: &Url) -> Url {
+    let mut url = url.clone();
+    if url.password().is_some() {
+        let _ = url.set_password(Some("***"));
+    }
+    url
+}
+
+/// Read the desired capabilities of the browser session from the JSON file
+/// at `path`.
+pub fn read_capabilities(path: &Path) -> Result<serde_json::Value> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("failed to read the capabilities in {}", path.display()))?;
+    let capabilities: serde_json::Value = serde_json::from_str(&json)
+        .with_context(|| format!("{} doesn't contain valid JSON", path.display()))?;
+    if !capabilities.is_object() {
+        bail!(
+            "the capabilities in {} must be a JSON object",
+            path.display()
+        )
+    }
+    Ok(capabilities)
+}
+
+/// A `webdriver.json` written into the crate for a test run, and removed or
+/// restored again when this is dropped.
+pub struct Capabilities {
+    path: Option<PathBuf>,
+    original: Option<Vec<u8>>,
+}
+
+/// Hand `capabilities` to `wasm-bindgen-test-runner` by writing them to the
+/// `webdriver.json` in `crate_path`, unless it already holds them.
+pub fn write_capabilities(
+    crate_path: &Path,
+    capabilities: &serde_json::Value,
+) -> Result<Capabilities> {
+    let path = crate_path.join(WEBDRIVER_JSON);
+    if path.exists() {
+        if read_capabilities(&path).ok().as_ref() == Some(capabilities) {
+            return Ok(Capabilities {
+                path: None,
+                original: None,
+            });
+        }
+        bail!(
+            "{} already exists with other capabilities. Remove it, or put the capabilities in it \
+             instead.",
+            path.display()
+        )
+    }
+    fs::write(&path, serde_json::to_string_pretty(capabilities)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(Capabilities {
+        path: Some(path),
+        original: None,
+    })
+}
+
+/// Replace the `webdriver.json` in `crate_path` with `capabilities`, which
+/// extend the ones in it, until the returned guard is dropped.
+pub fn extend_capabilities(
+    crate_path: &Path,
+    capabilities: &serde_json::Value,
+) -> Result<Capabilities> {
+    let path = crate_path.join(WEBDRIVER_JSON);
+    let original = fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    fs::write(&path, serde_json::to_string_pretty(capabilities)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(Capabilities {
+        path: Some(path),
+        original: Some(original),
+    })
+}
+
+impl Drop for Capabilities {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = match &self.original {
+                Some(original) => fs::write(path, original),
+                None => fs::remove_file(path),
+            };
+        }
+    }
+}
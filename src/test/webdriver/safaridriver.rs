@@ -1,5 +1,39 @@
 use anyhow::{bail, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Where macOS installs `safaridriver`, for when it isn't on the `$PATH`.
+const SYSTEM_SAFARIDRIVER: &str = "/usr/bin/safaridriver";
+
+/// The command that allows `safaridriver` to automate Safari. It only has to
+/// be run once per machine.
+pub const SAFARIDRIVER_ENABLE: &str = "safaridriver --enable";
+
+/// What `safaridriver` prints when it refuses to start a session, such as
+/// before remote automation is allowed with [`SAFARIDRIVER_ENABLE`].
+const SESSION_REFUSED_ERRORS: &[&str] = &[
+    "allow remote automation",
+    "session not created",
+    "could not create a session",
+];
+
+/// Whether the runner's `stderr` shows that `safaridriver` failed to start a
+/// session, rather than the tests failing.
+pub fn failed_to_start(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    SESSION_REFUSED_ERRORS.iter().any(|e| stderr.contains(e))
+}
+
+/// Make sure that Safari tests can run on this host, which is only the case
+/// on macOS.
+pub fn check_safari_host() -> Result<()> {
+    if !cfg!(target_os = "macos") {
+        bail!(
+            "Safari tests can only run on macOS, where `safaridriver` ships with the OS. \
+             Pass `--safaridriver` to use a `safaridriver` elsewhere."
+        )
+    }
+    Ok(())
+}
 
 /// Get the path to an existing `safaridriver`.
 ///
@@ -7,8 +41,18 @@ use std::path::PathBuf;
 /// Apple does not provide pre-built binaries. However, `safaridriver` *should*
 /// be present by default.
 pub fn get_safaridriver() -> Result<PathBuf> {
-    match which::which("safaridriver") {
-        Ok(p) => Ok(p),
-        Err(_) => bail!("could not find `safaridriver` on the `$PATH`"),
+    check_safari_host()?;
+    if let Ok(p) = which::which("safaridriver") {
+        return Ok(p);
+    }
+    let system = Path::new(SYSTEM_SAFARIDRIVER);
+    if system.is_file() {
+        return Ok(system.to_path_buf());
     }
+    bail!(
+        "could not find `safaridriver` on the `$PATH` or at {}. It ships with Safari; \
+         once it is installed, run `{}` to allow it to automate Safari.",
+        SYSTEM_SAFARIDRIVER,
+        SAFARIDRIVER_ENABLE
+    )
 }
@@ -0,0 +1,172 @@
+//! How long each phase of a build took, and how much memory the tools it ran
+//! used, for the summary at the end of `wasm-pack build` and the one written
+//! with `--summary-json`.
+
+use crate::command::utils::elapsed;
+use crate::install::ToolSource;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// The phase of a build that the build step called `step` belongs to.
+pub fn phase(step: &str) -> &'static str {
+    match step {
+        "step_build_wasm" => "cargo build",
+        "step_check_wasm" => "cargo check",
+        "step_check_wasm_opt" | "step_install_wasm_bindgen" => "installing tools",
+        "step_run_wasm_bindgen" => "wasm-bindgen",
+        "step_run_wasm_opt" => "wasm-opt",
+        "step_create_dir"
+        | "step_check_built_package"
+        | "step_create_json"
+        | "step_write_demo_html"
+        | "step_copy_readme"
+        | "step_copy_license"
+        | "step_save_fingerprint" => "packaging",
+        _ => "checks",
+    }
+}
+
+/// The time spent in each phase of a build, in the order the phases started.
+#[derive(Debug, Default)]
+pub struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    /// Add `duration` to the time spent in `phase`.
+    pub fn record(&mut self, phase: &'static str, duration: Duration) {
+        match self.phases.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, total)) => *total += duration,
+            None => self.phases.push((phase, duration)),
+        }
+    }
+
+    /// The phases and the time spent in them, as printed after a build, e.g.
+    /// `cargo build 3.21s, wasm-bindgen 0.40s`.
+    pub fn describe(&self) -> String {
+        self.phases
+            .iter()
+            .map(|(name, duration)| format!("{} {}", name, elapsed(*duration)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The summary of a build of the crate `name` that took `duration`, used
+    /// the `tools` and produced a wasm needing the `wasm_features`.
+    ///
+    /// The peak memory is left to the caller, since it covers everything the
+    /// process ran rather than this build alone.
+    pub fn summary(
+        &self,
+        name: &str,
+        success: bool,
+        duration: Duration,
+        tools: Vec<ToolSource>,
+        wasm_features: Vec<&'static str>,
+    ) -> Summary {
+        Summary {
+            name: name.to_string(),
+            success,
+            duration_secs: duration.as_secs_f64(),
+            phases: self
+                .phases
+                .iter()
+                .map(|(name, duration)| PhaseTime {
+                    name,
+                    secs: duration.as_secs_f64(),
+                })
+                .collect(),
+            tools,
+            wasm_features,
+            peak_child_memory_bytes: None,
+        }
+    }
+}
+
+/// What `--summary-json` writes about a build.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    /// The name of the crate.
+    #[serde(rename = "crate")]
+    pub name: String,
+    /// Whether the build succeeded.
+    pub success: bool,
+    /// How long the whole build took.
+    pub duration_secs: f64,
+    /// The time spent in each phase.
+    pub phases: Vec<PhaseTime>,
+    /// Which copy of each tool was used and where it came from.
+    pub tools: Vec<ToolSource>,
+    /// The WebAssembly features the built wasm needs, such as `simd128`.
+    pub wasm_features: Vec<&'static str>,
+    /// The most memory any of the tools run by this process used at once,
+    /// where the platform reports it. Left out of the summaries of the
+    /// members of a workspace, whose [`WorkspaceSummary`] has it instead.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_child_memory_bytes: Option<u64>,
+}
+
+/// What `--summary-json` writes about a `--workspace` build.
+#[derive(Debug, Serialize)]
+pub struct WorkspaceSummary {
+    /// The summary of each member that was built.
+    pub members: Vec<Summary>,
+    /// The most memory any of the tools run for any member used at once,
+    /// where the platform reports it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_child_memory_bytes: Option<u64>,
+}
+
+/// The time spent in a phase of a build.
+#[derive(Debug, Serialize)]
+pub struct PhaseTime {
+    /// The phase, such as `cargo build`.
+    pub name: &'static str,
+    /// The time spent in it.
+    pub secs: f64,
+}
+
+/// Write `summary`, a [`Summary`] or [`WorkspaceSummary`], to `path` as
+/// JSON.
+pub fn write_summary(path: &Path, summary: &impl Serialize) -> Result<()> {
+    let json = serde_json::to_string_pretty(summary)?;
+    fs::write(path, json + "\n").with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Format `bytes` of memory for the summary, e.g. `412 MiB`.
+pub fn format_memory(bytes: u64) -> String {
+    format!("{} MiB", (bytes + (1 << 19)) >> 20)
+}
+
+/// The largest resident set of the child processes waited for so far,
+/// including theirs, in bytes.
+///
+/// The operating system only reports this for all the children of the
+/// process together, so it covers every tool run so far rather than those of
+/// one build.
+#[cfg(unix)]
+pub fn peak_child_memory() -> Option<u64> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    // SAFETY: `getrusage` only writes to the `rusage` it's given.
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, usage.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: `getrusage` succeeded, so it filled in `usage`.
+    let max_rss = unsafe { usage.assume_init() }.ru_maxrss;
+    // macOS reports it in bytes, the others in kilobytes.
+    let unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    match u64::try_from(max_rss) {
+        Ok(0) | Err(_) => None,
+        Ok(max_rss) => Some(max_rss * unit),
+    }
+}
+
+/// The largest resident set of the child processes waited for so far, which
+/// isn't reported on this platform.
+#[cfg(not(unix))]
+pub fn peak_child_memory() -> Option<u64> {
+    None
+}
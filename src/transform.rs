@@ -0,0 +1,64 @@
+//! Running a user's codemod over the JS glue that `wasm-bindgen` generates,
+//! with `--transform-js`.
+
+use crate::child;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// Pipe each JS file generated for `name_prefix` in `out_dir` through the
+/// shell command `script`, and replace it with what the command prints.
+pub fn transform_js(out_dir: &Path, name_prefix: &str, script: &str) -> Result<()> {
+    for path in generated_js(out_dir, name_prefix) {
+        let input =
+            fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let permissions = fs::metadata(&path)?.permissions();
+
+        let output = child::run_filter(shell_command(script), script, input)
+            .with_context(|| format!("Transforming {} with `{}` failed", path.display(), script))?;
+
+        fs::write(&path, output).with_context(|| format!("failed to write {}", path.display()))?;
+        fs::set_permissions(&path, permissions)?;
+    }
+    Ok(())
+}
+
+/// The glue itself, e.g. `{name_prefix}.js` and `{name_prefix}_bg.js`, and the
+/// JS snippets copied next to it.
+fn generated_js(out_dir: &Path, name_prefix: &str) -> Vec<PathBuf> {
+    let is_js = |path: &Path| path.extension().is_some_and(|ext| ext == "js");
+    let glue = fs::read_dir(out_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && is_js(path)
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(name_prefix))
+        });
+    let snippets = WalkDir::new(out_dir.join("snippets"))
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && is_js(path));
+    let mut paths: Vec<PathBuf> = glue.chain(snippets).collect();
+    paths.sort();
+    paths
+}
+
+fn shell_command(script: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/c").arg(script);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+}
@@ -0,0 +1,245 @@
+//! Finding the post-MVP WebAssembly features a `.wasm` needs, so that its
+//! consumers can pick an engine that supports them.
+//!
+//! The module is read with `wasmparser` for its types, segments and
+//! instructions, which is where every feature we look for shows up.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use wasmparser::{
+    DataKind, Element, ElementKind, ImportSectionEntryType, MemoryType, Operator, OperatorsReader,
+    Parser, Payload, TableType, Type, TypeDef, TypeOrFuncType,
+};
+
+/// The file the features are written to with `--target-features`.
+pub const FEATURES_JSON: &str = "features.json";
+
+/// The post-MVP features a module uses, by the names `rustc` and LLVM give
+/// them as target features.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Features {
+    bulk_memory: bool,
+    reference_types: bool,
+    simd128: bool,
+    threads: bool,
+}
+
+impl Features {
+    /// The names of the features that are used.
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.bulk_memory, "bulk-memory"),
+            (self.reference_types, "reference-types"),
+            (self.simd128, "simd128"),
+            (self.threads, "threads"),
+        ]
+        .iter()
+        .filter(|(used, _)| *used)
+        .map(|(_, name)| *name)
+        .collect()
+    }
+}
+
+/// Find the features used by the `.wasm` at `path`.
+pub fn scan_file(path: &Path) -> Result<Features> {
+    let wasm = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    scan(&wasm).with_context(|| format!("failed to decode {}", path.display()))
+}
+
+/// Find the features used by the module in `wasm`.
+pub fn scan(wasm: &[u8]) -> Result<Features> {
+    let mut scanner = Scanner::default();
+    for payload in Parser::new(0).parse_all(wasm) {
+        scanner.payload(wasm, payload?)?;
+    }
+    if scanner.tables > 1 {
+        scanner.features.reference_types = true;
+    }
+    Ok(scanner.features)
+}
+
+/// Write the `features` to `features.json` in `out_dir`.
+pub fn write_features_json(out_dir: &Path, features: &Features) -> Result<()> {
+    let path = out_dir.join(FEATURES_JSON);
+    let json = serde_json::json!({ "features": features.names() });
+    fs::write(&path, serde_json::to_string_pretty(&json)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// List `features.json` in the `files` of the `package.json` in `out_dir`.
+pub fn add_to_package_json(out_dir: &Path) -> Result<()> {
+    let path = out_dir.join("package.json");
+    let mut package: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?,
+    )?;
+    if let Some(files) = package["files"].as_array_mut() {
+        files.push(FEATURES_JSON.into());
+    }
+    fs::write(&path, serde_json::to_string_pretty(&package)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[derive(Default)]
+struct Scanner {
+    features: Features,
+    tables: usize,
+}
+
+impl Scanner {
+    fn payload(&mut self, wasm: &[u8], payload: Payload) -> Result<()> {
+        match payload {
+            Payload::TypeSection(types) => {
+                for ty in types {
+                    if let TypeDef::Func(func) = ty? {
+                        func.params.iter().for_each(|ty| self.value_type(*ty));
+                        func.returns.iter().for_each(|ty| self.value_type(*ty));
+                    }
+                }
+            }
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    match import?.ty {
+                        ImportSectionEntryType::Table(table) => self.table_type(table),
+                        ImportSectionEntryType::Memory(memory) => self.memory_type(memory),
+                        ImportSectionEntryType::Global(global) => {
+                            self.value_type(global.content_type)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Payload::TableSection(tables) => {
+                for table in tables {
+                    self.table_type(table?);
+                }
+            }
+            Payload::MemorySection(memories) => {
+                for memory in memories {
+                    self.memory_type(memory?);
+                }
+            }
+            Payload::GlobalSection(globals) => {
+                for global in globals {
+                    let global = global?;
+                    self.value_type(global.ty.content_type);
+                    self.operators(wasm, global.init_expr.get_operators_reader())?;
+                }
+            }
+            Payload::ElementSection(elements) => {
+                for element in elements {
+                    self.element(wasm, element?)?;
+                }
+            }
+            Payload::DataCountSection { .. } => self.features.bulk_memory = true,
+            Payload::DataSection(data) => {
+                for data in data {
+                    match data?.kind {
+                        DataKind::Passive => self.features.bulk_memory = true,
+                        DataKind::Active { init_expr, .. } => {
+                            self.operators(wasm, init_expr.get_operators_reader())?
+                        }
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                for local in body.get_locals_reader()? {
+                    self.value_type(local?.1);
+                }
+                self.operators(wasm, body.get_operators_reader()?)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn value_type(&mut self, ty: Type) {
+        match ty {
+            Type::V128 => self.features.simd128 = true,
+            Type::FuncRef | Type::ExternRef => self.features.reference_types = true,
+            _ => {}
+        }
+    }
+
+    fn table_type(&mut self, table: TableType) {
+        self.tables += 1;
+        if table.element_type == Type::ExternRef {
+            self.features.reference_types = true;
+        }
+    }
+
+    fn memory_type(&mut self, memory: MemoryType) {
+        match memory {
+            MemoryType::M32 { shared, .. } | MemoryType::M64 { shared, .. } => {
+                if shared {
+                    self.features.threads = true;
+                }
+            }
+        }
+    }
+
+    fn element(&mut self, wasm: &[u8], element: Element) -> Result<()> {
+        // Passive and declarative segments came with bulk memory, while an
+        // explicit table or expressions as elements came with reference
+        // types.
+        match element.kind {
+            ElementKind::Passive | ElementKind::Declared => self.features.bulk_memory = true,
+            ElementKind::Active {
+                table_index,
+                init_expr,
+            } => {
+                if table_index != 0 {
+                    self.features.reference_types = true;
+                }
+                self.operators(wasm, init_expr.get_operators_reader())?;
+            }
+        }
+        if element.items.get_items_reader()?.uses_exprs() {
+            self.features.reference_types = true;
+        }
+        Ok(())
+    }
+
+    /// Look at every instruction of a function body or constant expression.
+    fn operators(&mut self, wasm: &[u8], mut reader: OperatorsReader) -> Result<()> {
+        while !reader.eof() {
+            // SIMD and atomic instructions are told apart by their prefix.
+            match wasm.get(reader.original_position()) {
+                Some(0xfd) => self.features.simd128 = true,
+                Some(0xfe) => self.features.threads = true,
+                _ => {}
+            }
+            match reader.read()? {
+                Operator::Block { ty } | Operator::Loop { ty } | Operator::If { ty } => {
+                    if let TypeOrFuncType::Type(ty) = ty {
+                        self.value_type(ty);
+                    }
+                }
+                Operator::MemoryInit { .. }
+                | Operator::DataDrop { .. }
+                | Operator::MemoryCopy { .. }
+                | Operator::MemoryFill { .. }
+                | Operator::TableInit { .. }
+                | Operator::ElemDrop { .. }
+                | Operator::TableCopy { .. } => self.features.bulk_memory = true,
+                Operator::RefNull { .. }
+                | Operator::RefIsNull
+                | Operator::RefFunc { .. }
+                | Operator::TableGet { .. }
+                | Operator::TableSet { .. }
+                | Operator::TableGrow { .. }
+                | Operator::TableSize { .. }
+                | Operator::TableFill { .. }
+                | Operator::TypedSelect { .. } => self.features.reference_types = true,
+                Operator::CallIndirect { table_index, .. }
+                | Operator::ReturnCallIndirect { table_index, .. }
+                    if table_index != 0 =>
+                {
+                    self.features.reference_types = true
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
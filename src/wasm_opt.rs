@@ -5,19 +5,14 @@ use crate::install;
 use crate::PBAR;
 use anyhow::Result;
 use binary_install::Cache;
+use log::info;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
 
-/// Execute `wasm-opt` over wasm binaries found in `out_dir`, downloading if
-/// necessary into `cache`. Passes `args` to each invocation of `wasm-opt`.
-pub fn run(cache: &Cache, out_dir: &Path, args: &[String], install_permitted: bool) -> Result<()> {
-    let wasm_opt_path = match find_wasm_opt(cache, install_permitted)? {
-        Some(path) => path,
-        // `find_wasm_opt` will have already logged a message about this, so we don't need to here.
-        None => return Ok(()),
-    };
-
+/// Execute the `wasm-opt` at `wasm_opt_path` over wasm binaries found in
+/// `out_dir`. Passes `args` to each invocation of `wasm-opt`.
+pub fn run(wasm_opt_path: &Path, out_dir: &Path, args: &[String]) -> Result<()> {
     PBAR.info("Optimizing wasm binaries with `wasm-opt`...");
 
     for file in out_dir.read_dir()? {
@@ -28,7 +23,7 @@ pub fn run(cache: &Cache, out_dir: &Path, args: &[String], install_permitted: bo
         }
 
         let tmp = path.with_extension("wasm-opt.wasm");
-        let mut cmd = Command::new(&wasm_opt_path);
+        let mut cmd = Command::new(wasm_opt_path);
         cmd.arg(&path).arg("-o").arg(&tmp).args(args);
         child::run(cmd, "wasm-opt")?;
         std::fs::rename(&tmp, &path)?;
@@ -37,13 +32,13 @@ pub fn run(cache: &Cache, out_dir: &Path, args: &[String], install_permitted: bo
     Ok(())
 }
 
-/// Attempts to find `wasm-opt` in `PATH` locally, or failing that downloads a
-/// precompiled binary.
+/// Attempts to find `wasm-opt` in `PATH` locally, or failing that in `cache`,
+/// downloading a precompiled binary into it if `install_permitted`.
 ///
-/// Returns `Some` if a binary was found or it was successfully downloaded.
-/// Returns `None` if a binary wasn't found in `PATH` and this platform doesn't
-/// have precompiled binaries. Returns an error if we failed to download the
-/// binary.
+/// Returns `None`, after warning that the wasm won't be optimized, if it
+/// isn't in `PATH` or the cache and can't be downloaded, whether because
+/// installing isn't permitted, this platform doesn't have precompiled
+/// binaries, or the download failed.
 pub fn find_wasm_opt(cache: &Cache, install_permitted: bool) -> Result<Option<PathBuf>> {
     // First attempt to look up in PATH. If found assume it works.
     if let Ok(path) = which::which("wasm-opt") {
@@ -51,14 +46,40 @@ pub fn find_wasm_opt(cache: &Cache, install_permitted: bool) -> Result<Option<Pa
         return Ok(Some(path));
     }
 
-    match install::download_prebuilt(&install::Tool::WasmOpt, cache, "latest", install_permitted)? {
-        install::Status::Found(download) => Ok(Some(download.binary("bin/wasm-opt")?)),
+    let status = match install::download_prebuilt(
+        &install::Tool::WasmOpt,
+        cache,
+        "latest",
+        install_permitted,
+    ) {
+        Ok(status) => status,
+        Err(e) => {
+            PBAR.warn(&format!(
+                "Skipping wasm-opt, so the wasm won't be optimized, because it isn't on the \
+                 $PATH and downloading it failed: {:#}",
+                e
+            ));
+            return Ok(None);
+        }
+    };
+    match status {
+        install::Status::Found(download) => {
+            let path = download.binary("bin/wasm-opt")?;
+            info!("Using wasm-opt from the cache at {}", path.display());
+            Ok(Some(path))
+        }
         install::Status::CannotInstall => {
-            PBAR.info("Skipping wasm-opt as no downloading was requested");
+            PBAR.warn(
+                "Skipping wasm-opt, so the wasm won't be optimized, because it isn't on the \
+                 $PATH or in the cache, and downloading it isn't allowed",
+            );
             Ok(None)
         }
         install::Status::PlatformNotSupported => {
-            PBAR.info("Skipping wasm-opt because it is not supported on this platform");
+            PBAR.warn(
+                "Skipping wasm-opt, so the wasm won't be optimized, because it isn't on the \
+                 $PATH and there are no precompiled binaries for this platform",
+            );
             Ok(None)
         }
     }
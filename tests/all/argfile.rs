@@ -0,0 +1,115 @@
+use crate::utils;
+use assert_cmd::prelude::*;
+use std::fs;
+use wasm_pack::argfile;
+
+#[test]
+fn argument_files_are_split_like_a_shell_would() {
+    let args = argfile::parse(
+        "# How the release is built.\n\
+         --target nodejs   --scope corp # the npm organization\n\
+         --out-dir \"my pkg\" --out-name 'it''s'\n\
+         --features a\\ b ''\n",
+    )
+    .unwrap();
+    assert_eq!(
+        args,
+        [
+            "--target",
+            "nodejs",
+            "--scope",
+            "corp",
+            "--out-dir",
+            "my pkg",
+            "--out-name",
+            "its",
+            "--features",
+            "a b",
+            ""
+        ]
+    );
+
+    assert!(argfile::parse("--scope 'corp").is_err());
+    assert!(argfile::parse("--scope \"corp\\").is_err());
+}
+
+#[test]
+fn argument_files_are_expanded_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("build.args");
+    fs::write(&file, "--target web\n--dev\n").unwrap();
+    let at_file = format!("@{}", file.display());
+
+    let expanded = argfile::expand(
+        ["wasm-pack", "build", &at_file, "--no-opt", "--", &at_file]
+            .iter()
+            .map(Into::into),
+    )
+    .unwrap();
+    assert_eq!(
+        expanded,
+        [
+            "wasm-pack",
+            "build",
+            "--target",
+            "web",
+            "--dev",
+            "--no-opt",
+            "--",
+            &at_file
+        ]
+    );
+
+    // Without such a file, it is an argument that starts with an `@`.
+    let missing = format!("@{}", dir.path().join("missing.args").display());
+    let expanded = argfile::expand(
+        ["wasm-pack", "build", &missing, "--scope", "@corp"]
+            .iter()
+            .map(Into::into),
+    )
+    .unwrap();
+    assert_eq!(
+        expanded,
+        ["wasm-pack", "build", &missing, "--scope", "@corp"]
+    );
+
+    // A file that is there but can't be read is still an error.
+    let error = argfile::expand(
+        ["wasm-pack", "build", &format!("@{}", dir.path().display())]
+            .iter()
+            .map(Into::into),
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(
+        error.contains("failed to read the argument file"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn build_applies_the_arguments_in_a_file() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture.file(
+        "build.args",
+        "# Reproduces the release build.\n--target nodejs\n--out-dir \"node pkg\"\n--no-opt\n",
+    );
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("@build.args")
+        .assert()
+        .success();
+
+    let package_json: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(fixture.path.join("node pkg/package.json")).unwrap(),
+    )
+    .unwrap();
+    assert!(package_json.get("module").is_none(), "{}", package_json);
+    assert!(!fixture.path.join("pkg").exists());
+}
@@ -1,5 +1,6 @@
 use crate::utils;
 use assert_cmd::prelude::*;
+use predicates::prelude::*;
 use std::fs;
 use std::path::Path;
 
@@ -99,6 +100,108 @@ fn it_should_build_crates_in_a_workspace() {
         .success();
 }
 
+#[test]
+#[cfg(unix)]
+fn it_should_build_all_the_cdylib_crates_in_a_workspace() {
+    let fixture = utils::fixture::Fixture::new();
+    fixture.file(
+        "Cargo.toml",
+        r#"
+            [workspace]
+            members = ["first", "second", "util"]
+        "#,
+    );
+    for (name, crate_type) in [("first", "cdylib"), ("second", "cdylib"), ("util", "rlib")] {
+        fixture
+            .file(
+                Path::new(name).join("Cargo.toml"),
+                format!(
+                    r#"
+                        [package]
+                        authors = ["The wasm-pack developers"]
+                        description = "so awesome rust+wasm package"
+                        license = "WTFPL"
+                        name = "{}"
+                        repository = "https://github.com/rustwasm/wasm-pack.git"
+                        version = "0.1.0"
+
+                        [lib]
+                        crate-type = ["{}"]
+
+                        [dependencies]
+                        wasm-bindgen = "=0.2.95"
+                    "#,
+                    name, crate_type
+                ),
+            )
+            .file(Path::new(name).join("README.md"), format!("# {}\n", name))
+            .file(
+                Path::new(name).join("src").join("lib.rs"),
+                r#"
+                    extern crate wasm_bindgen;
+                    use wasm_bindgen::prelude::*;
+
+                    #[wasm_bindgen]
+                    pub fn hello() -> u32 { 42 }
+                "#,
+            );
+    }
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--all")
+        .arg("--no-opt")
+        .arg("--summary-json")
+        .arg("summary.json")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Building first..."))
+        .stderr(predicates::str::contains("Building second..."))
+        .stderr(predicates::str::contains("Building util").not())
+        .stderr(predicates::str::contains("first  built in"))
+        .stderr(predicates::str::contains("second built in"))
+        .stderr(predicates::str::contains("Peak memory of the tools run: ").count(1));
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(fixture.path.join("summary.json")).unwrap())
+            .unwrap();
+    let members = summary["members"].as_array().unwrap();
+    assert_eq!(members.len(), 2);
+    for member in members {
+        assert!(
+            member.get("peak_child_memory_bytes").is_none(),
+            "{}",
+            member
+        );
+    }
+    assert!(summary["peak_child_memory_bytes"].as_u64().unwrap() > 0);
+    for name in ["first", "second"] {
+        let package_json: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(fixture.path.join(name).join("pkg/package.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(package_json["name"], name);
+    }
+    assert!(!fixture.path.join("util/pkg").exists());
+
+    fs::remove_dir_all(fixture.path.join("second/pkg")).unwrap();
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--workspace")
+        .arg("--exclude")
+        .arg("second")
+        .arg("--no-opt")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Building second").not());
+    assert!(fixture.path.join("first/pkg/package.json").is_file());
+    assert!(!fixture.path.join("second/pkg").exists());
+}
+
 #[test]
 fn renamed_crate_name_works() {
     let fixture = utils::fixture::Fixture::new();
@@ -420,3 +523,1467 @@ fn build_crates_with_same_names() {
         .assert()
         .success();
 }
+
+#[test]
+fn build_skips_bindgen_when_nothing_changed() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.install_local_wasm_bindgen();
+    fixture.wasm_pack().arg("build").assert().success();
+
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("wasm bindings are up to date"));
+    assert!(fixture.path.join("pkg").join("js_hello_world.js").exists());
+
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--force")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("wasm bindings are up to date").not());
+}
+
+#[test]
+#[cfg(unix)]
+fn build_with_deno_target() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--target")
+        .arg("deno")
+        .arg("--no-opt")
+        .assert()
+        .success();
+
+    let log = fs::read_to_string(fixture.path.join("wasm-bindgen.log")).unwrap();
+    assert!(log.contains("--target deno"), "{}", log);
+
+    let out_dir = fixture.path.join("pkg");
+    assert!(!out_dir.join("package.json").exists());
+    let deno_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("deno.json")).unwrap()).unwrap();
+    assert_eq!(deno_json["name"], "js-hello-world");
+    assert_eq!(deno_json["exports"], "./js_hello_world.js");
+}
+
+#[test]
+#[cfg(unix)]
+fn build_keeps_intermediate_artifacts_with_keep_tmp() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("CARGO_TARGET_DIR", fixture.path.join("target"))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--keep-tmp")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Kept the wasm built by cargo at"))
+        .stderr(predicates::str::contains("Kept the wasm-bindgen output at"));
+
+    let kept = fixture.path.join("target/wasm-pack-tmp/js_hello_world");
+    assert!(kept.join("js_hello_world.wasm").is_file());
+    assert!(kept.join("wasm-bindgen/js_hello_world_bg.wasm").is_file());
+}
+
+#[test]
+#[cfg(unix)]
+fn build_enables_required_features_of_lib_target() {
+    let fixture = utils::fixture::required_features();
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--dev")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Enabling features required by the lib target: `wasm`",
+        ));
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--dev")
+        .arg("--")
+        .arg("--features")
+        .arg("wasm")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Enabling features").not());
+}
+
+#[test]
+fn build_without_default_features_reports_missing_required_features() {
+    let fixture = utils::fixture::required_features();
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--")
+        .arg("--no-default-features")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "The lib target of `required_features` requires the features `wasm`, which are not enabled. Pass `--features wasm` to enable them.",
+        ));
+}
+
+/// Find the value of the first `attr="..."` in `contents`.
+fn attribute<'a>(contents: &'a str, attr: &str) -> &'a str {
+    let start = contents.find(&format!("{}=\"", attr)).unwrap() + attr.len() + 2;
+    let len = contents[start..].find('"').unwrap();
+    &contents[start..start + len]
+}
+
+#[test]
+#[cfg(unix)]
+fn build_with_demo_html_for_no_modules() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--target")
+        .arg("no-modules")
+        .arg("--out-name")
+        .arg("demo")
+        .arg("--no-opt")
+        .arg("--demo-html")
+        .assert()
+        .success();
+
+    let out_dir = fixture.path.join("pkg");
+    let html = fs::read_to_string(out_dir.join("index.html")).unwrap();
+    let script = attribute(&html, "src");
+    assert_eq!(script, "./demo.js");
+    assert!(out_dir.join(script).is_file());
+    assert!(html.contains("wasm_bindgen(\"./demo_bg.wasm\")"));
+
+    let package_json = fs::read_to_string(out_dir.join("package.json")).unwrap();
+    assert!(!package_json.contains("index.html"), "{}", package_json);
+}
+
+#[test]
+#[cfg(unix)]
+fn build_with_demo_html_for_web() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--target")
+        .arg("web")
+        .arg("--no-opt")
+        .arg("--demo-html")
+        .arg("--demo-html-in-package")
+        .assert()
+        .success();
+
+    let out_dir = fixture.path.join("pkg");
+    let html = fs::read_to_string(out_dir.join("index.html")).unwrap();
+    assert_eq!(attribute(&html, "src"), "./init.js");
+    let init = fs::read_to_string(out_dir.join("init.js")).unwrap();
+    let script = init
+        .lines()
+        .find_map(|line| line.strip_prefix("import init from \""))
+        .and_then(|rest| rest.strip_suffix("\";"))
+        .unwrap();
+    assert_eq!(script, "./js_hello_world.js");
+    assert!(out_dir.join(script).is_file());
+
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("package.json")).unwrap()).unwrap();
+    let files = package_json["files"].as_array().unwrap();
+    assert!(files.contains(&"index.html".into()));
+    assert!(files.contains(&"init.js".into()));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_does_not_overwrite_modified_demo_html() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let build = || {
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .arg("build")
+            .arg("--target")
+            .arg("no-modules")
+            .arg("--no-opt")
+            .arg("--demo-html");
+        cmd
+    };
+    build().assert().success();
+
+    let index = fixture.path.join("pkg").join("index.html");
+    let generated = fs::read_to_string(&index).unwrap();
+    build()
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("has been modified").not());
+    assert_eq!(fs::read_to_string(&index).unwrap(), generated);
+
+    fs::write(&index, "<p>mine</p>\n").unwrap();
+    build()
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("has been modified"));
+    assert_eq!(fs::read_to_string(&index).unwrap(), "<p>mine</p>\n");
+
+    build().arg("--force").assert().success();
+    assert_eq!(fs::read_to_string(&index).unwrap(), generated);
+}
+
+#[test]
+fn build_with_demo_html_rejects_other_targets() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--target")
+        .arg("nodejs")
+        .arg("--demo-html")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`--demo-html` is only supported for the `web` and `no-modules` targets, not `nodejs`",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_uses_wasm_bindgen_version_pinned_in_wasm_pack_toml() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95").file(
+        "wasm-pack.toml",
+        r#"
+            [wasm-bindgen]
+            version = "0.2.93"
+        "#,
+    );
+    let bin = fixture.install_fake_wasm_bindgen("0.2.93");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("[WARN]"))
+        .stderr(predicates::str::contains(
+            "Using wasm-bindgen CLI 0.2.93 pinned in",
+        ))
+        .stderr(predicates::str::contains(
+            "while the crate uses wasm-bindgen 0.2.95",
+        ));
+    assert!(fixture.path.join("wasm-bindgen.log").is_file());
+}
+
+#[test]
+#[cfg(unix)]
+fn build_resolves_symlinked_target_dir() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let cache_volume = tempfile::TempDir::new().unwrap();
+    let target_link = fixture.path.join("target-link");
+    std::os::unix::fs::symlink(cache_volume.path(), &target_link).unwrap();
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("CARGO_TARGET_DIR", &target_link)
+        .arg("build")
+        .arg("--dev")
+        .assert()
+        .success();
+
+    let log = fs::read_to_string(fixture.path.join("wasm-bindgen.log")).unwrap();
+    let real_wasm = fs::canonicalize(cache_volume.path())
+        .unwrap()
+        .join("wasm32-unknown-unknown")
+        .join("debug")
+        .join("js_hello_world.wasm");
+    assert!(
+        log.starts_with(&format!("{} ", real_wasm.display())),
+        "{}",
+        log
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn build_with_emit_index_html_references_out_name() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--target")
+        .arg("web")
+        .arg("--out-name")
+        .arg("app")
+        .arg("--no-opt")
+        .arg("--emit-index-html")
+        .assert()
+        .success();
+
+    let out_dir = fixture.path.join("pkg");
+    let html = fs::read_to_string(out_dir.join("index.html")).unwrap();
+    let module = attribute(&html, "href");
+    assert_eq!(module, "./app.js");
+    assert!(out_dir.join(module).is_file());
+    let init = fs::read_to_string(out_dir.join("init.js")).unwrap();
+    assert!(init.contains("import init from \"./app.js\";"), "{}", init);
+}
+
+#[test]
+#[cfg(unix)]
+fn build_with_out_name_points_types_at_the_renamed_declarations() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--out-name")
+        .arg("foo")
+        .arg("--no-opt")
+        .assert()
+        .success();
+
+    let out_dir = fixture.path.join("pkg");
+    assert!(out_dir.join("foo.d.ts").is_file());
+    assert!(!out_dir.join("js_hello_world.d.ts").exists());
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("package.json")).unwrap()).unwrap();
+    assert_eq!(package_json["types"], "foo.d.ts");
+    assert!(package_json["files"]
+        .as_array()
+        .unwrap()
+        .contains(&"foo.d.ts".into()));
+
+    // The declarations of the wasm module are named after `--out-name` too.
+    fs::write(
+        out_dir.join("foo_bg.wasm.d.ts"),
+        "export const memory: WebAssembly.Memory;\n",
+    )
+    .unwrap();
+    fs::remove_file(out_dir.join("package.json")).unwrap();
+    let crate_data =
+        wasm_pack::manifest::CrateData::new(&fixture.path, Some("foo".to_string())).unwrap();
+    crate_data
+        .write_package_json(
+            &out_dir,
+            &None,
+            false,
+            wasm_pack::command::build::Target::Bundler,
+        )
+        .unwrap();
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("package.json")).unwrap()).unwrap();
+    assert!(package_json["files"]
+        .as_array()
+        .unwrap()
+        .contains(&"foo_bg.wasm.d.ts".into()));
+
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--out-name")
+        .arg("foo.js")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`--out-name foo` names `foo.js`, `foo.d.ts` and `foo_bg.wasm`",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_reports_global_wasm_bindgen_source() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--summary-json")
+        .arg("summary.json")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(format!(
+            "Using wasm-bindgen 0.2.95 (source: global, path: {})",
+            bin.join("wasm-bindgen").display()
+        )));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(fixture.path.join("summary.json")).unwrap())
+            .unwrap();
+    assert_eq!(
+        summary["tools"],
+        serde_json::json!([{
+            "tool": "wasm-bindgen",
+            "version": "0.2.95",
+            "source": "global",
+            "path": bin.join("wasm-bindgen"),
+        }])
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn build_fails_on_warnings_when_asked_to() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let manifest = fixture.path.join("Cargo.toml");
+    let contents = fs::read_to_string(&manifest).unwrap().replacen(
+        "[package]\n",
+        "[package]\nreadme = \"README.md\"\n",
+        1,
+    );
+    fs::write(&manifest, contents).unwrap();
+    fs::remove_file(fixture.path.join("README.md")).unwrap();
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("[WARN]"))
+        .stderr(predicates::str::contains("origin crate has no README"));
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("--fail-on-warnings")
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("[ERR]"))
+        .stderr(predicates::str::contains("origin crate has no README"))
+        .stderr(predicates::str::contains("wasm-pack emitted 1 warning"));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_echoes_child_commands_when_asked_to() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Running `cargo build").not());
+
+    for flag in ["--verbose-child", "-vvv"] {
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .arg(flag)
+            .arg("build")
+            .arg("--no-opt")
+            .assert()
+            .success()
+            .stderr(predicates::str::contains(
+                "Running `cargo build --lib --release --target wasm32-unknown-unknown",
+            ))
+            .stderr(predicates::str::contains("wasm-bindgen --version`"));
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn build_reports_how_long_cargo_install_took() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    // No prebuilt binaries exist for this version, so wasm-pack has to fall
+    // back to `cargo install`.
+    fixture.file(
+        "wasm-pack.toml",
+        r#"
+            [wasm-bindgen]
+            version = "0.2.9999"
+        "#,
+    );
+    let bin = fixture.install_fake_cargo_install("0.2.9999");
+    // Keep the fake install out of the cache shared by the other tests.
+    let cache = fixture.path.join("cache");
+    let output = fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("WASM_PACK_CACHE", &cache)
+        .arg("build")
+        .arg("--no-opt")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let installed = stderr
+        .find("Installed wasm-bindgen 0.2.9999 in ")
+        .unwrap_or_else(|| panic!("{}", stderr));
+    let finished = stderr.find("fake cargo install finished").unwrap();
+    assert!(finished < installed, "{}", stderr);
+
+    fs::remove_dir_all(&cache).unwrap();
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("WASM_PACK_CACHE", &cache)
+        .arg("--quiet")
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Installed wasm-bindgen").not());
+}
+
+#[test]
+#[cfg(unix)]
+fn build_without_bundling_the_wasm() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    for (target, expected) in [
+        (
+            "web",
+            "new URL('https://cdn.example.com/js/js_hello_world_bg.wasm', import.meta.url)",
+        ),
+        (
+            "no-modules",
+            "'https://cdn.example.com/js/js_hello_world_bg.wasm'",
+        ),
+    ] {
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .arg("build")
+            .arg("--target")
+            .arg(target)
+            .arg("--no-opt")
+            .arg("--no-bundle-wasm")
+            .arg("--wasm-base-url")
+            .arg("https://cdn.example.com/js/")
+            .assert()
+            .success();
+
+        let out_dir = fixture.path.join("pkg");
+        let pkg = utils::manifest::read_package_json(&fixture.path, &out_dir).unwrap();
+        assert!(pkg.files.contains(&"js_hello_world.js".to_string()));
+        assert!(!pkg.files.contains(&"js_hello_world_bg.wasm".to_string()));
+        let glue = fs::read_to_string(out_dir.join("js_hello_world.js")).unwrap();
+        assert!(glue.contains(expected), "{}", glue);
+    }
+
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--target")
+        .arg("nodejs")
+        .arg("--no-bundle-wasm")
+        .arg("--wasm-base-url")
+        .arg("https://cdn.example.com/js/")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`--no-bundle-wasm` is only supported for the `web` and `no-modules` targets",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_exports_the_init_function_under_the_init_name() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--target")
+        .arg("web")
+        .arg("--no-opt")
+        .arg("--init-name")
+        .arg("initHelloWorld")
+        .assert()
+        .success();
+
+    let out_dir = fixture.path.join("pkg");
+    let export = "export { __wbg_init as initHelloWorld };\n";
+    let glue = fs::read_to_string(out_dir.join("js_hello_world.js")).unwrap();
+    assert!(glue.contains("export default __wbg_init;"), "{}", glue);
+    assert!(glue.ends_with(export), "{}", glue);
+    let dts = fs::read_to_string(out_dir.join("js_hello_world.d.ts")).unwrap();
+    assert!(
+        dts.contains("export default function __wbg_init"),
+        "{}",
+        dts
+    );
+    assert!(dts.ends_with(export), "{}", dts);
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--target")
+        .arg("web")
+        .arg("--no-opt")
+        .arg("--init-name")
+        .arg("greet")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "already exports `greet`, so the init function can't be exported under that name",
+        ));
+
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--target")
+        .arg("bundler")
+        .arg("--init-name")
+        .arg("initHelloWorld")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`--init-name` is only supported for the `web` target",
+        ));
+
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--target")
+        .arg("web")
+        .arg("--init-name")
+        .arg("init-wasm")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`init-wasm` can't be the name of the init function",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_transforms_the_generated_js() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--transform-js")
+        .arg("sed -e 's/export function/export default function/'")
+        .assert()
+        .success();
+
+    let out_dir = fixture.path.join("pkg");
+    let glue = fs::read_to_string(out_dir.join("js_hello_world.js")).unwrap();
+    assert!(
+        glue.contains("export default function greet() {}"),
+        "{}",
+        glue
+    );
+    let dts = fs::read_to_string(out_dir.join("js_hello_world.d.ts")).unwrap();
+    assert!(dts.contains("export function greet(): void;"), "{}", dts);
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--transform-js")
+        .arg("exit 3")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Transforming"))
+        .stderr(predicates::str::contains("with `exit 3` failed"));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_runs_a_changed_transform_script_again() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let build = || {
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .arg("build")
+            .arg("--no-opt")
+            .arg("--transform-js")
+            .arg("sh transform.sh")
+            .assert()
+            .success()
+    };
+
+    fixture.file("transform.sh", "sed -e 's/greet/first/'\n");
+    build();
+    fixture.file("transform.sh", "sed -e 's/greet/second/'\n");
+    build().stderr(predicates::str::contains("up to date").not());
+
+    let glue = fs::read_to_string(fixture.path.join("pkg/js_hello_world.js")).unwrap();
+    assert!(glue.contains("export function second() {}"), "{}", glue);
+}
+
+#[test]
+#[cfg(unix)]
+fn failed_build_does_not_leave_a_complete_looking_out_dir() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let failing_build = |args: &[&str]| {
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .arg("build")
+            .arg("--no-opt")
+            .arg("--transform-js")
+            .arg("exit 3")
+            .args(args)
+            .assert()
+            .failure();
+    };
+
+    failing_build(&["--out-dir", "new-pkg"]);
+    assert!(!fixture.path.join("new-pkg").exists());
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success();
+    let out_dir = fixture.path.join("pkg");
+    std::os::unix::fs::symlink("package.json", out_dir.join("package-link.json")).unwrap();
+    let listing = || {
+        let mut files: Vec<_> = fs::read_dir(&out_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        files.sort();
+        files
+    };
+    let built = listing();
+    let package_json = fs::read_to_string(out_dir.join("package.json")).unwrap();
+
+    failing_build(&["--out-name", "renamed"]);
+    assert_eq!(listing(), built);
+    assert_eq!(
+        fs::read_to_string(out_dir.join("package.json")).unwrap(),
+        package_json
+    );
+    assert!(fs::symlink_metadata(out_dir.join("package-link.json"))
+        .unwrap()
+        .file_type()
+        .is_symlink());
+
+    failing_build(&["--out-name", "renamed", "--no-rollback"]);
+    let marker = fs::read_to_string(out_dir.join(".incomplete")).unwrap();
+    assert!(marker.contains("with `exit 3` failed"), "{}", marker);
+    fixture
+        .wasm_pack()
+        .arg("pack")
+        .arg("--no-build")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "was left incomplete by a failed build",
+        ));
+    // Building it again repeats the build that failed.
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("pack")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "building it again with `wasm-pack build --target bundler --no-opt --out-name \
+             renamed --transform-js exit 3`",
+        ));
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success();
+    assert!(!out_dir.join(".incomplete").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn build_summary_times_each_phase() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--summary-json")
+        .arg("summary.json")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Time spent: checks "))
+        .stderr(predicates::str::contains(", cargo build "))
+        .stderr(predicates::str::contains(", wasm-bindgen "))
+        .stderr(predicates::str::contains("Peak memory of the tools run: "));
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(fixture.path.join("summary.json")).unwrap())
+            .unwrap();
+    assert_eq!(summary["crate"], "js-hello-world");
+    assert_eq!(summary["success"], true);
+    assert!(summary["peak_child_memory_bytes"].as_u64().unwrap() > 0);
+    let phases: Vec<(&str, f64)> = summary["phases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|phase| {
+            (
+                phase["name"].as_str().unwrap(),
+                phase["secs"].as_f64().unwrap(),
+            )
+        })
+        .collect();
+    let names: Vec<&str> = phases.iter().map(|(name, _)| *name).collect();
+    assert_eq!(
+        names,
+        [
+            "checks",
+            "installing tools",
+            "cargo build",
+            "packaging",
+            "wasm-bindgen"
+        ]
+    );
+    for (name, secs) in phases {
+        assert!(secs > 0.0, "{} took {}s", name, secs);
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn build_reports_the_wasm_features_it_needs() {
+    // A crate of its own, so that cargo doesn't reuse another fixture's
+    // `js-hello-world` build from the shared target directory.
+    let fixture = utils::fixture::Fixture::new();
+    fixture.readme().cargo_toml("wasm-features").file(
+        "src/lib.rs",
+        r#"
+            extern crate wasm_bindgen;
+            use std::arch::wasm32::*;
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn greet() {}
+
+            #[no_mangle]
+            #[target_feature(enable = "simd128")]
+            pub unsafe extern "C" fn add4(a: *const v128, b: *const v128, out: *mut v128) {
+                v128_store(out, i32x4_add(v128_load(a), v128_load(b)));
+            }
+        "#,
+    );
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--target-features")
+        .arg("--summary-json")
+        .arg("summary.json")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "The wasm needs these WebAssembly features:",
+        ))
+        .stderr(predicates::str::contains("simd128"));
+    let summary: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(fixture.path.join("summary.json")).unwrap())
+            .unwrap();
+    assert!(
+        summary["wasm_features"]
+            .as_array()
+            .unwrap()
+            .contains(&"simd128".into()),
+        "{}",
+        summary
+    );
+
+    let out_dir = fixture.path.join("pkg");
+    let features: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("features.json")).unwrap()).unwrap();
+    let features = features["features"].as_array().unwrap();
+    assert!(features.contains(&"simd128".into()), "{:?}", features);
+    let pkg = utils::manifest::read_package_json(&fixture.path, &out_dir).unwrap();
+    assert!(pkg.files.contains(&"features.json".to_string()));
+}
+
+#[test]
+fn build_with_threads_enables_atomics_and_rebuilds_std() {
+    let fixture = utils::fixture::js_hello_world();
+    let cmd = wasm_pack::build::cargo_build_wasm_command(
+        &fixture.path,
+        wasm_pack::command::build::BuildProfile::Release,
+        &[],
+        &wasm_pack::build::Codegen {
+            threads: true,
+            ..Default::default()
+        },
+        None,
+        None,
+    )
+    .unwrap();
+
+    let args: Vec<_> = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect();
+    let build_std = args
+        .iter()
+        .position(|arg| *arg == "build-std=panic_abort,std");
+    assert!(build_std.is_some_and(|i| args[i - 1] == "-Z"), "{:?}", args);
+    let rustflags = added_rustflags(&cmd);
+    assert!(
+        rustflags
+            .iter()
+            .any(|flag| flag.contains("target-feature=+atomics,+bulk-memory")),
+        "{:?}",
+        rustflags
+    );
+}
+
+/// The rustflags the `cargo` command `cmd` compiles with: the ones it sets in
+/// the environment, or else the ones it passes with `--config`.
+fn added_rustflags(cmd: &std::process::Command) -> Vec<String> {
+    for (key, value) in cmd.get_envs() {
+        let value = value.and_then(|value| value.to_str()).unwrap_or_default();
+        if key == "CARGO_ENCODED_RUSTFLAGS" {
+            return value.split('\x1f').map(str::to_string).collect();
+        }
+        if key == "RUSTFLAGS" {
+            return value.split_whitespace().map(str::to_string).collect();
+        }
+    }
+    let args: Vec<_> = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect();
+    let config = args
+        .windows(2)
+        .find(|w| w[0] == "--config" && w[1].starts_with("target.wasm32-unknown-unknown."))
+        .unwrap_or_else(|| panic!("no rustflags in {:?}", args))[1];
+    let config: toml::Value = toml::from_str(config).unwrap();
+    config["target"]["wasm32-unknown-unknown"]["rustflags"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|flag| flag.as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn build_lists_the_targets_without_building() {
+    // Outside of any crate, since nothing is built.
+    let fixture = utils::fixture::not_a_crate();
+    let output = fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--list-targets")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let listed: Vec<_> = output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    assert_eq!(
+        listed,
+        ["bundler", "web", "nodejs", "no-modules", "deno"],
+        "{}",
+        output
+    );
+    for target in wasm_pack::command::build::Target::ALL {
+        assert!(output.contains(target.description()), "{}", output);
+    }
+}
+
+#[test]
+fn build_with_threads_needs_the_web_or_no_modules_target() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--threads")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`--threads` is only supported for the `web` and `no-modules` targets, not `bundler`",
+        ));
+}
+
+#[test]
+fn build_with_a_linker_passes_it_and_the_link_args_to_rustc() {
+    let fixture = utils::fixture::js_hello_world();
+    let cmd = wasm_pack::build::cargo_build_wasm_command(
+        &fixture.path,
+        wasm_pack::command::build::BuildProfile::Release,
+        &[],
+        &wasm_pack::build::Codegen {
+            linker: Some("wasm-ld-wrapper".to_string()),
+            link_args: vec![
+                "--import-memory".to_string(),
+                "-z stack-size=65536".to_string(),
+            ],
+            ..Default::default()
+        },
+        None,
+        None,
+    )
+    .unwrap();
+
+    // The space in the last link arg is kept.
+    let flags = added_rustflags(&cmd);
+    assert!(
+        flags.ends_with(
+            &[
+                "-Clinker=wasm-ld-wrapper",
+                "-Clink-arg=--import-memory",
+                "-Clink-arg=-z stack-size=65536",
+            ]
+            .map(String::from)
+        ),
+        "{:?}",
+        flags
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn build_link_args_are_added_to_the_rustflags_of_the_environment_or_the_config() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.path.join("cargo-bin");
+    fs::create_dir_all(&bin).unwrap();
+    let cargo = bin.join("cargo");
+    fs::write(
+        &cargo,
+        format!(
+            "#!/bin/sh\nif [ \"$1\" = check ]; then\n    echo \"${{RUSTFLAGS-unset}}|$*\" > {}\nfi\nexec {} \"$@\"\n",
+            fixture.path.join("rustflags").display(),
+            which::which("cargo").unwrap().display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&cargo, fs::Permissions::from_mode(0o755)).unwrap();
+    let check = |rustflags: Option<&str>| {
+        let mut cmd = fixture.wasm_pack();
+        match rustflags {
+            Some(rustflags) => cmd.env("RUSTFLAGS", rustflags),
+            None => cmd.env_remove("RUSTFLAGS"),
+        };
+        cmd.env("PATH", fixture.path_with(&bin))
+            .env_remove("CARGO_ENCODED_RUSTFLAGS")
+            .arg("build")
+            .arg("--check")
+            .arg("--link-arg=--import-memory")
+            .assert()
+            .success();
+        fs::read_to_string(fixture.path.join("rustflags")).unwrap()
+    };
+
+    let log = check(Some("--cfg from_env"));
+    assert!(
+        log.starts_with("--cfg from_env -Clink-arg=--import-memory|"),
+        "{}",
+        log
+    );
+    assert!(!log.contains("--config"), "{}", log);
+
+    // Setting `RUSTFLAGS` would hide the ones of the cargo config.
+    let log = check(None);
+    assert!(log.starts_with("unset|"), "{}", log);
+    assert!(
+        log.contains(
+            "--config target.wasm32-unknown-unknown.rustflags=[\"-Clink-arg=--import-memory\"]"
+        ),
+        "{}",
+        log
+    );
+}
+
+#[test]
+fn build_with_jobs_limits_cargo_build_and_cargo_install() {
+    let fixture = utils::fixture::js_hello_world();
+    let cmd = wasm_pack::build::cargo_build_wasm_command(
+        &fixture.path,
+        wasm_pack::command::build::BuildProfile::Release,
+        &[],
+        &wasm_pack::build::Codegen::default(),
+        None,
+        Some(2),
+    )
+    .unwrap();
+    let args: Vec<_> = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect();
+    assert!(args.windows(2).any(|w| w == ["--jobs", "2"]), "{:?}", args);
+
+    let cmd = wasm_pack::install::cargo_install_command(
+        &wasm_pack::install::Tool::WasmBindgen,
+        "0.2.95",
+        Path::new("root"),
+        &[],
+        Some(2),
+        &[],
+    );
+    let args: Vec<_> = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect();
+    assert!(args.windows(2).any(|w| w == ["--jobs", "2"]), "{:?}", args);
+}
+
+#[test]
+fn build_with_zero_jobs_is_rejected() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("-j")
+        .arg("0")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "expected a positive number of jobs, not \"0\"",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_writes_the_package_to_stdout_as_a_tarball() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    let output = fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--out-dir")
+        .arg("-")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Your wasm pkg was written to stdout as a tarball.",
+        ))
+        .get_output()
+        .stdout
+        .clone();
+
+    let mut archive = tar::Archive::new(&output[..]);
+    let mut entries: Vec<String> = archive
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+        .collect();
+    entries.sort();
+    assert_eq!(
+        entries,
+        [
+            "package/README.md",
+            "package/js_hello_world.d.ts",
+            "package/js_hello_world.js",
+            "package/js_hello_world_bg.wasm",
+            "package/package.json",
+        ]
+    );
+
+    let mut archive = tar::Archive::new(&output[..]);
+    let package_json = archive
+        .entries()
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .find(|entry| entry.path().unwrap().ends_with("package.json"))
+        .map(|mut entry| {
+            let mut json = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut json).unwrap();
+            json
+        })
+        .unwrap();
+    assert!(
+        package_json.contains("\"name\": \"js-hello-world\""),
+        "{}",
+        package_json
+    );
+
+    // Nothing was left on disk.
+    assert!(!fixture.path.join("pkg").exists());
+    assert!(!fixture.path.join("-").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn build_with_a_local_wasm_bindgen_checkout() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let marker = fixture.path.join("local-wasm-bindgen-ran");
+    // A stand-in for the wasm-bindgen CLI crate, handing the real work to the
+    // fake wasm-bindgen so that its output can be checked as usual.
+    fixture
+        .file(
+            "local-wasm-bindgen/Cargo.toml",
+            r#"
+                [package]
+                name = "wasm-bindgen-cli"
+                version = "0.2.95"
+                edition = "2021"
+
+                [[bin]]
+                name = "wasm-bindgen"
+                path = "src/main.rs"
+
+                [workspace]
+            "#,
+        )
+        .file(
+            "local-wasm-bindgen/src/main.rs",
+            format!(
+                r#"
+                    use std::process::Command;
+
+                    fn main() {{
+                        std::fs::write({marker:?}, "").unwrap();
+                        let status = Command::new({script:?})
+                            .args(std::env::args_os().skip(1))
+                            .status()
+                            .unwrap();
+                        std::process::exit(status.code().unwrap_or(1));
+                    }}
+                "#,
+                marker = marker,
+                script = bin.join("wasm-bindgen"),
+            ),
+        );
+
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--bindgen-path")
+        .arg(fixture.path.join("local-wasm-bindgen"))
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Building wasm-bindgen from"))
+        .stderr(predicates::str::contains("source: built from"));
+
+    assert!(marker.exists());
+    assert!(fixture.path.join("pkg/js_hello_world_bg.wasm").exists());
+    let log = fs::read_to_string(fixture.path.join("wasm-bindgen.log")).unwrap();
+    assert!(log.contains("--out-dir"), "{}", log);
+}
+
+#[test]
+fn build_with_a_bindgen_path_that_isnt_wasm_bindgen() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--bindgen-path")
+        .arg(fixture.path.join("Cargo.toml"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("isn't a `wasm-bindgen` binary"));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_emit_manifest_only_regenerates_package_json() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success();
+    let wasm = fixture.path.join("pkg/js_hello_world_bg.wasm");
+    let modified = fs::metadata(&wasm).unwrap().modified().unwrap();
+    let bytes = fs::read(&wasm).unwrap();
+
+    let manifest = fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap();
+    fixture.file(
+        "Cargo.toml",
+        manifest.replace(
+            "description = \"",
+            "description = \"Now with a new description. ",
+        ),
+    );
+    fs::remove_file(fixture.path.join("wasm-bindgen.log")).unwrap();
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--emit-manifest-only")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Compiling to Wasm").not());
+
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(fixture.path.join("pkg/package.json")).unwrap())
+            .unwrap();
+    assert!(package_json["description"]
+        .as_str()
+        .unwrap()
+        .starts_with("Now with a new description."));
+    assert_eq!(fs::metadata(&wasm).unwrap().modified().unwrap(), modified);
+    assert_eq!(fs::read(&wasm).unwrap(), bytes);
+    assert!(!fixture.path.join("wasm-bindgen.log").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn build_with_no_license_skips_the_license() {
+    let fixture = utils::fixture::single_license();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--no-license")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Optional field missing").not());
+
+    let pkg = fixture.path.join("pkg");
+    assert!(!pkg.join("LICENSE").exists());
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(pkg.join("package.json")).unwrap()).unwrap();
+    assert!(package_json.get("license").is_none(), "{}", package_json);
+}
+
+#[test]
+fn build_emit_manifest_only_needs_an_existing_build() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--emit-manifest-only")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "`--emit-manifest-only` needs an existing build",
+        ));
+    assert!(!fixture.path.join("pkg/package.json").exists());
+}
+
+#[test]
+fn build_asks_for_lib_or_bin_when_a_crate_has_both() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.file("src/main.rs", "fn main() {}\n");
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "has both a `cdylib` and bin targets, so choose which one to build",
+        ))
+        .stderr(predicates::str::contains("--bin js-hello-world"))
+        .stderr(predicates::str::contains("Compiling to Wasm").not());
+}
+
+#[test]
+#[cfg(unix)]
+fn build_with_lib_builds_the_cdylib_of_a_crate_with_bins() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    fixture.file("src/main.rs", "fn main() {}\n");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--lib")
+        .assert()
+        .success();
+    assert!(fixture.path.join("pkg/js_hello_world_bg.wasm").exists());
+}
+
+#[test]
+fn build_check_only_checks_the_crate() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--check")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Checking the crate for Wasm"))
+        .stderr(predicates::str::contains("This was a check-only run"));
+    assert!(!fixture.path.join("pkg").exists());
+
+    fixture.file(
+        "src/lib.rs",
+        "pub fn broken() -> u32 { \"not a number\" }\n",
+    );
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--check")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Checking your crate for WebAssembly failed",
+        ));
+    assert!(!fixture.path.join("pkg").exists());
+}
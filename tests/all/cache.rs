@@ -0,0 +1,150 @@
+use crate::utils;
+use assert_cmd::prelude::*;
+use std::fs;
+use wasm_pack::cache;
+
+#[test]
+fn cache_list_shows_each_cached_version() {
+    let fixture = utils::fixture::Fixture::new();
+    let dir = fixture.path.join("cache");
+    for (entry, url) in [
+        (
+            "geckodriver-0123456789abcdef",
+            Some("https://github.com/mozilla/geckodriver/releases/download/v0.31.0/geckodriver-v0.31.0-linux64.tar.gz"),
+        ),
+        (
+            "geckodriver-fedcba9876543210",
+            Some("https://github.com/mozilla/geckodriver/releases/download/v0.35.0/geckodriver-v0.35.0-linux64.tar.gz"),
+        ),
+        (
+            "chromedriver-00112233445566ff",
+            Some("https://chromedriver.storage.googleapis.com/114.0.5735.90/chromedriver_linux64.zip"),
+        ),
+        ("wasm-bindgen-cargo-install-0.2.9999", None),
+        ("wasm-opt-0011223344556677", None),
+        (".geckodriver-aabbccddeeff0011-1234", None),
+    ] {
+        fs::create_dir_all(dir.join(entry)).unwrap();
+        if let Some(url) = url {
+            fs::write(dir.join(entry).join(cache::SOURCE_URL_FILE), url).unwrap();
+        }
+    }
+
+    let output = fixture
+        .wasm_pack()
+        .env("WASM_PACK_CACHE", &dir)
+        .arg("cache")
+        .arg("list")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<Vec<&str>> = stdout
+        .lines()
+        .map(|line| line.split_whitespace().take(2).collect())
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            ["chromedriver", "114.0.5735.90"],
+            ["geckodriver", "0.31.0"],
+            ["geckodriver", "0.35.0"],
+            ["wasm-bindgen", "0.2.9999"],
+            ["wasm-opt", "unknown"],
+        ],
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(&*dir.join("geckodriver-0123456789abcdef").to_string_lossy()));
+}
+
+#[test]
+fn cache_clean_prunes_old_versions() {
+    let fixture = utils::fixture::Fixture::new();
+    let dir = fixture.path.join("cache");
+    for (entry, size) in [
+        ("wasm-bindgen-cargo-install-0.2.9", 10),
+        ("wasm-bindgen-cargo-install-0.2.10", 20),
+        ("wasm-bindgen-cargo-install-0.2.74", 30),
+        ("wasm-opt-0011223344556677", 40),
+        (".geckodriver-aabbccddeeff0011-1234", 50),
+    ] {
+        fs::create_dir_all(dir.join(entry)).unwrap();
+        fs::write(dir.join(entry).join("bin"), vec![0; size]).unwrap();
+    }
+    let remaining = || {
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    };
+    let clean = |args: &[&str]| {
+        fixture
+            .wasm_pack()
+            .env("WASM_PACK_CACHE", &dir)
+            .arg("cache")
+            .arg("clean")
+            .args(args)
+            .assert()
+            .success()
+    };
+
+    clean(&["--keep-latest", "1", "--dry-run"])
+        .stderr(predicates::str::contains(
+            "[dry run] Would remove wasm-bindgen 0.2.9, 10 B",
+        ))
+        .stderr(predicates::str::contains(
+            "[dry run] Would remove wasm-bindgen 0.2.10, 20 B",
+        ))
+        .stderr(predicates::str::contains(
+            "[dry run] Would free 30 B in 2 entries",
+        ));
+    assert_eq!(remaining().len(), 5);
+
+    clean(&["--tool", "wasm-bindgen", "--keep-latest", "2"])
+        .stderr(predicates::str::contains("Freed 10 B in 1 entry"));
+    assert_eq!(
+        remaining(),
+        [
+            ".geckodriver-aabbccddeeff0011-1234",
+            "wasm-bindgen-cargo-install-0.2.10",
+            "wasm-bindgen-cargo-install-0.2.74",
+            "wasm-opt-0011223344556677",
+        ]
+    );
+
+    clean(&[]).stderr(predicates::str::contains("Freed 140 B in 4 entries"));
+    assert!(remaining().is_empty());
+}
+
+#[test]
+fn finds_the_version_in_download_urls() {
+    for (url, version) in [
+        (
+            "https://github.com/rustwasm/wasm-bindgen/releases/download/0.2.95/wasm-bindgen-0.2.95-x86_64-unknown-linux-musl.tar.gz",
+            Some("0.2.95"),
+        ),
+        (
+            "https://github.com/WebAssembly/binaryen/releases/download/version_117/binaryen-version_117-x86_64-linux.tar.gz",
+            Some("117"),
+        ),
+        (
+            "https://storage.googleapis.com/chrome-for-testing-public/129.0.6668.100/linux64/chromedriver-linux64.zip",
+            Some("129.0.6668.100"),
+        ),
+        (
+            "https://msedgedriver.azureedge.net/129.0.2792.79/edgedriver_win64.zip",
+            Some("129.0.2792.79"),
+        ),
+        ("https://example.com/tool.tar.gz", None),
+    ] {
+        assert_eq!(
+            cache::version_in_url(url).as_deref(),
+            version,
+            "{}",
+            url
+        );
+    }
+}
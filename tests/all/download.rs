@@ -1,3 +1,8 @@
+use crate::utils;
+use assert_cmd::prelude::*;
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
 use wasm_pack::install::{self, Arch, Os, Tool};
 
 #[test]
@@ -74,6 +79,49 @@ fn can_download_prebuilt_wasm_opt() {
     }
 }
 
+#[test]
+fn wasm_bindgen_downloads_can_come_from_a_mirror() {
+    for base in [
+        "https://mirror.corp.example/wasm-bindgen",
+        "https://mirror.corp.example/wasm-bindgen/",
+    ] {
+        assert_eq!(
+            install::wasm_bindgen_url(base, "0.2.95", &Arch::X86_64, &Os::Linux).unwrap(),
+            "https://mirror.corp.example/wasm-bindgen/0.2.95/\
+             wasm-bindgen-0.2.95-x86_64-unknown-linux-musl.tar.gz"
+        );
+    }
+    assert_eq!(
+        install::prebuilt_url_for(&Tool::WasmBindgen, "0.2.95", &Arch::AArch64, &Os::Linux)
+            .unwrap(),
+        format!(
+            "{}/0.2.95/wasm-bindgen-0.2.95-aarch64-unknown-linux-gnu.tar.gz",
+            install::DEFAULT_BINDGEN_URL_BASE
+        )
+    );
+
+    let fixture = utils::fixture::js_hello_world();
+    fixture
+        .wasm_pack()
+        .env(install::BINDGEN_URL_BASE_ENV, "mirror.corp.example")
+        .arg("build")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "invalid $WASM_PACK_BINDGEN_URL_BASE: expected an `http://` or `https://` URL",
+        ));
+    fixture
+        .wasm_pack()
+        .arg("--bindgen-downloads-base-url")
+        .arg("file:///srv/mirror")
+        .arg("build")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "expected an `http://` or `https://` URL",
+        ));
+}
+
 #[test]
 fn all_latest_tool_download_urls_valid() {
     let mut errors = Vec::new();
@@ -107,3 +155,187 @@ fn all_latest_tool_download_urls_valid() {
         );
     }
 }
+
+#[test]
+fn cargo_install_command_passes_features() {
+    let root = std::path::Path::new("root");
+    let features = vec!["vendored-openssl".to_string(), "other".to_string()];
+    let cmd =
+        install::cargo_install_command(&Tool::WasmBindgen, "0.2.95", root, &features, None, &[]);
+    let args: Vec<_> = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect();
+    assert_eq!(
+        args,
+        [
+            "install",
+            "--force",
+            "wasm-bindgen-cli",
+            "--root",
+            "root",
+            "--version",
+            "0.2.95",
+            "--features",
+            "vendored-openssl,other",
+        ]
+    );
+
+    let cmd = install::cargo_install_command(&Tool::WasmBindgen, "0.2.95", root, &[], None, &[]);
+    assert!(!cmd.get_args().any(|arg| arg == "--features"));
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn downloads_time_out_on_a_server_that_never_responds() {
+    // A proxy that accepts connections and then never answers them.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let proxy = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let mut connections = Vec::new();
+        for stream in listener.incoming() {
+            connections.push(stream);
+        }
+    });
+
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let cache = tempfile::TempDir::new().unwrap();
+    let started = Instant::now();
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_without(&bin, "wasm-opt"))
+        .env("WASM_PACK_CACHE", cache.path())
+        .env("HTTPS_PROXY", &proxy)
+        .env_remove("ALL_PROXY")
+        .env_remove("all_proxy")
+        // Keep cargo, which honors this unlike wasm-pack, away from the proxy.
+        .env("NO_PROXY", "*")
+        .arg("--network-timeout")
+        .arg("1")
+        .arg("build")
+        .assert()
+        // wasm-opt is optional, so failing to download it is only a warning.
+        .success()
+        .stderr(predicates::str::is_match(concat!(
+            r"\[WARN\]: .*Skipping wasm-opt.*failed to download from .*",
+            r"the server didn't respond within the network timeout \(1s to connect, 1s to read\)",
+        ))
+        .unwrap());
+    assert!(started.elapsed() < Duration::from_secs(120));
+}
+
+/// Serve `body` as the response to every request on a local port, after
+/// `headers`, then keep the connection open without sending anything more.
+fn serve(headers: String, body: Vec<u8>) -> String {
+    use std::io::{Read, Write};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for mut stream in listener.incoming().filter_map(|stream| stream.ok()) {
+            let mut request = [0; 4096];
+            drop(stream.read(&mut request));
+            stream.write_all(headers.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_secs(60));
+                drop(stream);
+            });
+        }
+    });
+    url
+}
+
+#[test]
+#[cfg(unix)]
+fn downloads_stream_into_the_cache_and_time_out_when_they_stall() {
+    use wasm_pack::http;
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let cache = binary_install::Cache::at(dir.path());
+
+    let mut tarball = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::default(),
+    ));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(10);
+    header.set_mode(0o755);
+    tarball
+        .append_data(&mut header, "tool-v1/tool", &b"#!/bin/sh\n"[..])
+        .unwrap();
+    let tarball = tarball.into_inner().unwrap().finish().unwrap();
+    let url = serve(
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+            tarball.len()
+        ),
+        tarball,
+    ) + "/tool.tar.gz";
+    let download = http::download(&cache, true, "tool", &["tool"], &url)
+        .unwrap()
+        .unwrap();
+    assert!(download.binary("tool").unwrap().is_file());
+    // Where binary-install would look for it too.
+    let cached = cache
+        .download(false, "tool", &["tool"], &url)
+        .unwrap()
+        .unwrap();
+    assert_eq!(cached.path(), download.path());
+
+    // Only the gzip header arrives before the server stalls.
+    let gzip_header = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    let url = serve(
+        "HTTP/1.1 200 OK\r\nContent-Length: 100000\r\n\r\n".to_string(),
+        gzip_header,
+    ) + "/stalled.tar.gz";
+    http::set_network_timeout(Some(2));
+    let started = Instant::now();
+    let result = http::download(&cache, true, "stalled", &["stalled"], &url);
+    http::set_network_timeout(None);
+    let error = format!("{:#}", result.unwrap_err());
+    assert!(
+        error.contains("the server didn't respond within the network timeout"),
+        "{}",
+        error
+    );
+    assert!(started.elapsed() < Duration::from_secs(30));
+    assert!(http::download(&cache, false, "stalled", &["stalled"], &url)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+fn quarantine_is_removed_from_downloaded_binaries() {
+    use std::process::Command;
+
+    let fixture = utils::fixture::Fixture::new();
+    fixture.file("wasm-bindgen", "#!/bin/sh\n");
+    let binary = fixture.path.join("wasm-bindgen");
+    let quarantined = || {
+        Command::new("xattr")
+            .arg("-p")
+            .arg(install::QUARANTINE_XATTR)
+            .arg(&binary)
+            .output()
+            .unwrap()
+            .status
+            .success()
+    };
+
+    let status = Command::new("xattr")
+        .arg("-w")
+        .arg(install::QUARANTINE_XATTR)
+        .arg("0081;00000000;Safari;")
+        .arg(&binary)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(quarantined());
+
+    install::remove_quarantine(&binary).unwrap();
+    assert!(!quarantined());
+
+    // A binary that isn't quarantined is fine too.
+    install::remove_quarantine(&binary).unwrap();
+}
@@ -0,0 +1,68 @@
+use crate::utils::fixture;
+use std::fs;
+use wasm_pack::fingerprint::Fingerprint;
+
+#[test]
+fn fingerprint_is_up_to_date_after_save() {
+    let fixture = fixture::Fixture::new();
+    fixture.file("input.wasm", b"\0asm fake module");
+    let wasm = fixture.path.join("input.wasm");
+    let out_dir = fixture.path.join("pkg");
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let fingerprint = Fingerprint::new(&wasm, "0.2.74", &["bundler".to_string()]).unwrap();
+    assert!(!fingerprint.is_up_to_date(&out_dir));
+
+    fingerprint.save(&out_dir).unwrap();
+    assert!(fingerprint.is_up_to_date(&out_dir));
+
+    Fingerprint::clear(&out_dir);
+    assert!(!fingerprint.is_up_to_date(&out_dir));
+}
+
+#[test]
+fn fingerprint_changes_with_its_inputs() {
+    let fixture = fixture::Fixture::new();
+    fixture.file("input.wasm", b"\0asm fake module");
+    let wasm = fixture.path.join("input.wasm");
+    let options = ["bundler".to_string()];
+
+    let original = Fingerprint::new(&wasm, "0.2.74", &options).unwrap();
+    assert_eq!(
+        original,
+        Fingerprint::new(&wasm, "0.2.74", &options).unwrap()
+    );
+    assert_ne!(
+        original,
+        Fingerprint::new(&wasm, "0.2.75", &options).unwrap()
+    );
+    assert_ne!(
+        original,
+        Fingerprint::new(&wasm, "0.2.74", &["web".to_string()]).unwrap()
+    );
+
+    fixture.file("input.wasm", b"\0asm changed module");
+    assert_ne!(
+        original,
+        Fingerprint::new(&wasm, "0.2.74", &options).unwrap()
+    );
+}
+
+#[test]
+fn fingerprint_restores_the_bindgen_package_json() {
+    let fixture = fixture::Fixture::new();
+    fixture
+        .file("input.wasm", b"\0asm fake module")
+        .file("pkg/package.json", r#"{"is-sorted": "^1.0.0"}"#);
+    let out_dir = fixture.path.join("pkg");
+
+    let fingerprint = Fingerprint::new(&fixture.path.join("input.wasm"), "0.2.74", &[]).unwrap();
+    fingerprint.save(&out_dir).unwrap();
+
+    fs::remove_file(out_dir.join("package.json")).unwrap();
+    Fingerprint::restore(&out_dir).unwrap();
+    assert_eq!(
+        fs::read_to_string(out_dir.join("package.json")).unwrap(),
+        r#"{"is-sorted": "^1.0.0"}"#
+    );
+}
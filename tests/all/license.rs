@@ -4,6 +4,7 @@ extern crate wasm_pack;
 use std::fs;
 
 use crate::utils::{self, fixture};
+use wasm_pack::command::build::Target;
 use wasm_pack::license;
 use wasm_pack::manifest::CrateData;
 
@@ -150,3 +151,44 @@ fn it_copies_a_non_standard_license_provided_path() {
     let pkg_license = utils::file::read_file(&pkg_license_path).unwrap();
     assert_eq!(crate_license, pkg_license);
 }
+
+#[test]
+fn it_marks_an_unlicensed_crate_private_and_copies_no_license() {
+    let fixture = fixture::single_license();
+    let manifest = fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap();
+    fixture.file(
+        "Cargo.toml",
+        manifest.replace("license = \"WTFPL\"", "license = \"UNLICENSED\""),
+    );
+    let out_dir = fixture.path.join("pkg");
+    fs::create_dir(&out_dir).unwrap();
+    let crate_data = CrateData::new(&fixture.path, None).unwrap();
+
+    license::copy_from_crate(&crate_data, &fixture.path, &out_dir).unwrap();
+    assert!(!out_dir.join("LICENSE").exists());
+
+    crate_data
+        .write_package_json(&out_dir, &None, false, Target::Bundler)
+        .unwrap();
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("package.json")).unwrap()).unwrap();
+    assert_eq!(package_json["license"], "UNLICENSED");
+    assert_eq!(package_json["private"], true);
+}
+
+#[test]
+fn it_leaves_the_license_out_with_no_license() {
+    let fixture = fixture::single_license();
+    let out_dir = fixture.path.join("pkg");
+    fs::create_dir(&out_dir).unwrap();
+    let mut crate_data = CrateData::new(&fixture.path, None).unwrap();
+    crate_data.set_no_license(true);
+
+    crate_data
+        .write_package_json(&out_dir, &None, false, Target::Bundler)
+        .unwrap();
+    let package_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("package.json")).unwrap()).unwrap();
+    assert!(package_json.get("license").is_none(), "{}", package_json);
+    assert!(package_json.get("private").is_none(), "{}", package_json);
+}
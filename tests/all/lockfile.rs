@@ -1,14 +1,26 @@
 use crate::utils::fixture;
-use wasm_pack::lockfile::Lockfile;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use wasm_pack::lockfile::{self, Lockfile};
 use wasm_pack::manifest::CrateData;
 
+/// Both ways of reading the resolved dependency graph: from `cargo metadata`
+/// and, as a fallback, straight from `Cargo.lock`.
+fn lockfiles(data: &CrateData) -> [Lockfile; 2] {
+    [
+        Lockfile::new(data).unwrap(),
+        Lockfile::from_cargo_lock(&data.workspace_root().join("Cargo.lock")).unwrap(),
+    ]
+}
+
 #[test]
 fn it_gets_wasm_bindgen_version() {
     let fixture = fixture::js_hello_world();
     fixture.cargo_check();
     let data = CrateData::new(&fixture.path, None).unwrap();
-    let lock = Lockfile::new(&data).unwrap();
-    assert_eq!(lock.wasm_bindgen_version(), Some("0.2.74"),);
+    for lock in lockfiles(&data) {
+        assert_eq!(lock.wasm_bindgen_version(), Some("0.2.74"));
+    }
 }
 
 #[test]
@@ -16,8 +28,65 @@ fn it_gets_wasm_bindgen_test_version() {
     let fixture = fixture::wbg_test_node();
     fixture.cargo_check();
     let data = CrateData::new(&fixture.path, None).unwrap();
-    let lock = Lockfile::new(&data).unwrap();
-    assert_eq!(lock.wasm_bindgen_test_version(), Some("0.3.24"),);
+    for lock in lockfiles(&data) {
+        assert_eq!(lock.wasm_bindgen_test_version(), Some("0.3.24"));
+    }
+}
+
+#[test]
+fn it_checks_wasm_bindgen_test_against_the_cli_version() {
+    let fixture = fixture::wbg_test_node();
+    fixture.cargo_check();
+    let data = CrateData::new(&fixture.path, None).unwrap();
+    for lock in lockfiles(&data) {
+        assert_eq!(lock.require_wasm_bindgen_test("0.2.74").unwrap(), "0.3.24");
+
+        let error = lock
+            .require_wasm_bindgen_test("0.2.95")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains(
+                "wasm-bindgen-test 0.3.24 is built against wasm-bindgen 0.2.74, \
+                 but the test runner comes from wasm-bindgen 0.2.95"
+            ),
+            "{}",
+            error
+        );
+    }
+}
+
+#[test]
+fn it_asks_for_a_missing_wasm_bindgen_test() {
+    let fixture = fixture::Fixture::new();
+    fixture.readme().hello_world_src_lib().file(
+        "Cargo.toml",
+        r#"
+            [package]
+            authors = ["The wasm-pack developers"]
+            description = "so awesome rust+wasm package"
+            license = "WTFPL"
+            name = "no-wasm-bindgen-test"
+            repository = "https://github.com/rustwasm/wasm-pack.git"
+            version = "0.1.0"
+
+            [lib]
+            crate-type = ["cdylib"]
+
+            [dependencies]
+            wasm-bindgen = "=0.2.74"
+        "#,
+    );
+    fixture.cargo_check();
+    let data = CrateData::new(&fixture.path, None).unwrap();
+    for lock in lockfiles(&data) {
+        let error = lock
+            .require_wasm_bindgen_test("0.2.74")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("to the [dev-dependencies]"), "{}", error);
+        assert!(error.contains("wasm-bindgen-test = \"0.3\""), "{}", error);
+    }
 }
 
 #[test]
@@ -61,8 +130,10 @@ fn it_gets_wasm_bindgen_version_in_crate_inside_workspace() {
         );
     fixture.cargo_check();
     let data = CrateData::new(&fixture.path.join("blah"), None).unwrap();
-    let lock = Lockfile::new(&data).unwrap();
-    assert_eq!(lock.wasm_bindgen_version(), Some("0.2.74"),);
+    assert!(!fixture.path.join("blah").join("Cargo.lock").exists());
+    for lock in lockfiles(&data) {
+        assert_eq!(lock.wasm_bindgen_version(), Some("0.2.74"));
+    }
 }
 
 #[test]
@@ -129,6 +200,310 @@ fn it_gets_wasm_bindgen_version_from_dependencies() {
         );
     fixture.cargo_check();
     let data = CrateData::new(&fixture.path.join("parent"), None).unwrap();
+    for lock in lockfiles(&data) {
+        assert_eq!(lock.wasm_bindgen_version(), Some("0.2.74"));
+    }
+}
+
+#[test]
+fn it_reads_versions_from_metadata_without_a_lockfile_on_disk() {
+    let fixture = fixture::js_hello_world();
+    let data = CrateData::new(&fixture.path, None).unwrap();
+    std::fs::remove_file(fixture.path.join("Cargo.lock")).unwrap();
+    let lock = Lockfile::new(&data).unwrap();
+    assert_eq!(lock.wasm_bindgen_version(), Some("0.2.74"));
+
+    let error = Lockfile::from_cargo_lock(&fixture.path.join("Cargo.lock"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("failed to read"), "{}", error);
+}
+
+#[test]
+fn it_gets_wasm_bindgen_version_from_indirect_dependency() {
+    let fixture = fixture::Fixture::new();
+    fixture
+        .file(
+            "framework/Cargo.toml",
+            r#"
+                [package]
+                name = "framework"
+                version = "0.1.0"
+
+                [dependencies]
+                wasm-bindgen = "=0.2.74"
+            "#,
+        )
+        .file(
+            "framework/src/lib.rs",
+            r#"
+                pub extern crate wasm_bindgen;
+            "#,
+        )
+        .file(
+            "app/Cargo.toml",
+            r#"
+                [package]
+                authors = ["The wasm-pack developers"]
+                description = "so awesome rust+wasm package"
+                license = "WTFPL"
+                name = "app"
+                repository = "https://github.com/rustwasm/wasm-pack.git"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["cdylib"]
+
+                [dependencies]
+                framework = { path = "../framework" }
+            "#,
+        )
+        .file(
+            "app/src/lib.rs",
+            r#"
+                extern crate framework;
+                use framework::wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn hello() -> u32 { 42 }
+            "#,
+        );
+    let data = CrateData::new(&fixture.path.join("app"), None).unwrap();
+    for lock in lockfiles(&data) {
+        assert_eq!(lock.require_wasm_bindgen().unwrap(), "0.2.74");
+    }
+}
+
+fn member(fixture: &fixture::Fixture, name: &str, dependencies: &str) {
+    fixture
+        .file(
+            format!("{}/Cargo.toml", name),
+            format!(
+                r#"
+                    [package]
+                    name = "{}"
+                    version = "0.1.0"
+
+                    [lib]
+                    crate-type = ["cdylib", "rlib"]
+
+                    [dependencies]
+                    {}
+                "#,
+                name, dependencies
+            ),
+        )
+        .file(format!("{}/src/lib.rs", name), "");
+}
+
+/// A workspace whose lock graph holds two `wasm-bindgen` packages: the one
+/// from crates.io and a vendored copy with an older version.
+fn workspace_with_two_wasm_bindgens() -> fixture::Fixture {
+    let fixture = fixture::Fixture::new();
+    fixture
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["registry", "vendored", "both", "pinned", "dev", "conflict"]
+            "#,
+        )
+        .file(
+            "vendor/wasm-bindgen/Cargo.toml",
+            r#"
+                [package]
+                name = "wasm-bindgen"
+                version = "0.2.74"
+            "#,
+        )
+        .file("vendor/wasm-bindgen/src/lib.rs", "");
+    member(&fixture, "registry", r#"wasm-bindgen = "=0.2.95""#);
+    member(
+        &fixture,
+        "vendored",
+        r#"wasm-bindgen = { path = "../vendor/wasm-bindgen" }"#,
+    );
+    member(
+        &fixture,
+        "both",
+        r#"
+            registry = { path = "../registry" }
+            vendored = { path = "../vendored" }
+        "#,
+    );
+    member(
+        &fixture,
+        "pinned",
+        r#"
+            registry = { path = "../registry" }
+            vendored = { path = "../vendored" }
+            wasm-bindgen = "=0.2.95"
+        "#,
+    );
+    member(
+        &fixture,
+        "dev",
+        r#"
+            [dev-dependencies]
+            wasm-bindgen = "=0.2.95"
+
+            [build-dependencies]
+            vendored-wasm-bindgen = { package = "wasm-bindgen", path = "../vendor/wasm-bindgen" }
+        "#,
+    );
+    member(
+        &fixture,
+        "conflict",
+        r#"
+            wasm-bindgen = { path = "../vendor/wasm-bindgen" }
+
+            [dev-dependencies]
+            registry-wasm-bindgen = { package = "wasm-bindgen", version = "=0.2.95" }
+        "#,
+    );
+    fixture
+}
+
+#[test]
+fn it_picks_the_wasm_bindgen_version_each_member_links() {
+    let fixture = workspace_with_two_wasm_bindgens();
+    for (member, version) in [
+        ("registry", "0.2.95"),
+        ("vendored", "0.2.74"),
+        ("pinned", "0.2.95"),
+    ] {
+        let data = CrateData::new(&fixture.path.join(member), None).unwrap();
+        let lock = Lockfile::new(&data).unwrap();
+        assert_eq!(lock.require_wasm_bindgen().unwrap(), version, "{}", member);
+    }
+}
+
+#[test]
+fn it_picks_the_dev_dependency_wasm_bindgen_for_tests() {
+    let fixture = workspace_with_two_wasm_bindgens();
+    let data = CrateData::new(&fixture.path.join("dev"), None).unwrap();
+    let lock = Lockfile::for_tests(&data).unwrap();
+    assert_eq!(lock.require_wasm_bindgen().unwrap(), "0.2.95");
+}
+
+#[test]
+fn it_errors_when_the_dependency_tables_disagree_on_wasm_bindgen() {
+    let fixture = workspace_with_two_wasm_bindgens();
+    let data = CrateData::new(&fixture.path.join("conflict"), None).unwrap();
     let lock = Lockfile::new(&data).unwrap();
-    assert_eq!(lock.wasm_bindgen_version(), Some("0.2.74"),);
+    assert_eq!(lock.require_wasm_bindgen().unwrap(), "0.2.74");
+
+    let lock = Lockfile::for_tests(&data).unwrap();
+    let error = lock.require_wasm_bindgen().unwrap_err().to_string();
+    assert!(
+        error.contains("0.2.74 in [dependencies], 0.2.95 in [dev-dependencies]"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn it_errors_when_several_wasm_bindgen_versions_are_reachable() {
+    let fixture = workspace_with_two_wasm_bindgens();
+    let data = CrateData::new(&fixture.path.join("both"), None).unwrap();
+    let lock = Lockfile::new(&data).unwrap();
+    let error = lock.require_wasm_bindgen().unwrap_err().to_string();
+    assert!(
+        error.contains("Found several versions of `wasm-bindgen`"),
+        "{}",
+        error
+    );
+    assert!(error.contains("0.2.95"), "{}", error);
+    assert!(error.contains("0.2.74"), "{}", error);
+    assert!(error.contains("wasm-pack.toml"), "{}", error);
+}
+
+#[test]
+fn it_generates_a_missing_lockfile() {
+    let fixture = fixture::js_hello_world();
+    assert!(!fixture.path.join("Cargo.lock").exists());
+
+    lockfile::generate_if_missing(&fixture.path, &[]).unwrap();
+    assert!(fixture.path.join("Cargo.lock").is_file());
+    let data = CrateData::new(&fixture.path, None).unwrap();
+    let lock = Lockfile::new(&data).unwrap();
+    assert_eq!(lock.wasm_bindgen_version(), Some("0.2.74"));
+}
+
+#[test]
+#[cfg(unix)]
+fn build_does_not_look_for_the_workspace_of_a_crate_with_a_lockfile() {
+    let fixture = fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    lockfile::generate_if_missing(&fixture.path, &[]).unwrap();
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("--verbose-child")
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Running `cargo build"))
+        .stderr(predicates::str::contains("cargo locate-project").not());
+}
+
+#[test]
+fn it_keeps_the_error_for_a_missing_lockfile_when_locked() {
+    let fixture = fixture::js_hello_world();
+    assert!(!fixture.path.join("Cargo.lock").exists());
+
+    let error = lockfile::generate_if_missing(&fixture.path, &["--locked".to_string()])
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Could not find lockfile"), "{}", error);
+    assert!(error.contains("Run `cargo generate-lockfile`"), "{}", error);
+    assert!(!fixture.path.join("Cargo.lock").exists());
+}
+
+#[test]
+fn build_keeps_the_error_for_a_missing_lockfile_when_locked() {
+    let fixture = fixture::js_hello_world();
+    assert!(!fixture.path.join("Cargo.lock").exists());
+
+    // `cargo metadata` would write the lock file before the build got to it.
+    fixture
+        .wasm_pack()
+        .arg("build")
+        .arg("--mode")
+        .arg("no-install")
+        .arg(".")
+        .arg("--")
+        .arg("--locked")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Could not find lockfile"));
+    assert!(!fixture.path.join("Cargo.lock").exists());
+}
+
+#[test]
+fn it_reports_a_lockfile_that_cannot_be_generated_offline() {
+    let fixture = fixture::js_hello_world();
+    assert!(!fixture.path.join("Cargo.lock").exists());
+    // A dependency that can't be in the local registry cache.
+    let manifest = std::fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap();
+    fixture.file(
+        "Cargo.toml",
+        manifest.replace(
+            "[dependencies]",
+            "[dependencies]\nwasm-pack-no-such-crate = \"1\"",
+        ),
+    );
+
+    let error =
+        lockfile::generate_if_missing(&fixture.path, &["--offline".to_string()]).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("Cargo.lock is missing and could not be generated offline"),
+        "{:?}",
+        error
+    );
 }
@@ -79,3 +79,79 @@ fn log_level_error() {
         .stdout("")
         .stderr(matches_cargo().and(matches_info().not()));
 }
+
+#[test]
+#[cfg(unix)]
+fn color_never() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let build = |args: &[&str]| {
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            // Colors would otherwise be off anyway, as the output isn't a
+            // terminal.
+            .env("CLICOLOR_FORCE", "1")
+            .env("CARGO_TERM_COLOR", "always")
+            .args(args)
+            .arg("build")
+            .arg("--no-opt")
+            .assert()
+            .success()
+    };
+
+    build(&[]).stderr(contains("\u{1b}["));
+    build(&["--color", "never"])
+        .stdout("")
+        .stderr(contains("[INFO]: ").and(contains("\u{1b}[").not()));
+}
+
+#[test]
+#[cfg(unix)]
+fn log_file() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let log_file = fixture.path.join("logs").join("wasm-pack.jsonl");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env_remove("RUST_LOG")
+        .arg("--log-file")
+        .arg(&log_file)
+        .arg("build")
+        .arg("--no-opt")
+        .assert()
+        .success()
+        .stderr(contains("finished in").not());
+
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    let entries: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let messages: Vec<&str> = entries
+        .iter()
+        .map(|entry| entry["message"].as_str().unwrap())
+        .collect();
+    assert!(
+        messages
+            .iter()
+            .any(|message| message.starts_with("Running ")
+                && message.contains("wasm-bindgen\"")
+                && message.contains("--out-dir")),
+        "{}",
+        log
+    );
+    assert!(
+        messages
+            .iter()
+            .any(|message| message.starts_with("`wasm-bindgen` finished in ")),
+        "{}",
+        log
+    );
+    assert!(entries
+        .iter()
+        .all(|entry| entry["time"].is_string() && entry["level"].is_string()));
+}
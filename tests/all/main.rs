@@ -12,16 +12,23 @@ extern crate clap;
 extern crate tempfile;
 extern crate wasm_pack;
 
+mod argfile;
 mod build;
+mod cache;
 mod download;
+mod fingerprint;
 mod generate;
 mod license;
 mod lockfile;
 mod log_level;
 mod manifest;
+mod npmrc;
+mod prepare;
+mod publish;
 mod readme;
 mod stamps;
 mod test;
 mod utils;
+mod version;
 mod wasm_opt;
 mod webdriver;
@@ -1,11 +1,13 @@
 use crate::utils::{self, fixture};
 use assert_cmd::prelude::*;
 use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
 use std::path::PathBuf;
-use wasm_pack::command::build::Target;
+use wasm_pack::command::build::{BuildProfile, ModuleType, Target};
 use wasm_pack::command::utils::get_crate_path;
-use wasm_pack::{self, emoji, license, manifest};
+use wasm_pack::lockfile::Lockfile;
+use wasm_pack::{self, license, manifest};
 
 #[test]
 fn it_gets_the_crate_name_default_path() {
@@ -22,6 +24,97 @@ fn it_gets_the_crate_name_provided_path() {
     assert_eq!(crate_data.crate_name(), "js_hello_world");
 }
 
+#[test]
+fn it_finds_the_wasm_by_the_lib_name() {
+    let fixture = fixture::Fixture::new();
+    fixture.readme().hello_world_src_lib().file(
+        "Cargo.toml",
+        r#"
+            [package]
+            authors = ["The wasm-pack developers"]
+            description = "so awesome rust+wasm package"
+            license = "WTFPL"
+            name = "generated-package"
+            repository = "https://github.com/rustwasm/wasm-pack.git"
+            version = "0.1.0"
+
+            [lib]
+            name = "actual_lib"
+
+            [dependencies]
+            wasm-bindgen = "=0.2.74"
+        "#,
+    );
+    let mut crate_data = manifest::CrateData::new(&fixture.path, None).unwrap();
+    assert_eq!(crate_data.crate_name(), "actual_lib");
+    let wasm = wasm_pack::bindgen::wasm_path(&crate_data, BuildProfile::Release, &[]);
+    assert_eq!(
+        wasm,
+        crate_data
+            .target_directory()
+            .join("wasm32-unknown-unknown")
+            .join("release")
+            .join("actual_lib.wasm")
+    );
+
+    crate_data.set_crate_name(Some("other-lib".to_string()));
+    assert_eq!(crate_data.crate_name(), "other_lib");
+    let wasm = wasm_pack::bindgen::wasm_path(&crate_data, BuildProfile::Dev, &[]);
+    assert!(wasm.ends_with("wasm32-unknown-unknown/debug/other_lib.wasm"));
+}
+
+#[test]
+fn it_asks_to_choose_between_a_cdylib_and_bins() {
+    let fixture = fixture::js_hello_world();
+    fixture
+        .file("src/main.rs", "fn main() {}\n")
+        .file("src/bin/other-tool.rs", "fn main() {}\n");
+    let mut crate_data = manifest::CrateData::new(&fixture.path, None).unwrap();
+
+    let error = crate_data
+        .select_target(false, None)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("has both a `cdylib` and bin targets, so choose which one to build"),
+        "{}",
+        error
+    );
+    assert!(
+        error.contains("--lib    (the `cdylib` js_hello_world)"),
+        "{}",
+        error
+    );
+    assert!(error.contains("--bin js-hello-world"), "{}", error);
+    assert!(error.contains("--bin other-tool"), "{}", error);
+
+    let error = crate_data
+        .select_target(false, Some("missing".to_string()))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("no bin target named `missing`"), "{}", error);
+    assert!(error.contains("--bin other-tool"), "{}", error);
+
+    crate_data.select_target(true, None).unwrap();
+    assert_eq!(crate_data.bin_target(), None);
+    assert_eq!(crate_data.crate_name(), "js_hello_world");
+
+    crate_data
+        .select_target(false, Some("other-tool".to_string()))
+        .unwrap();
+    assert_eq!(crate_data.crate_name(), "other-tool");
+    let wasm = wasm_pack::bindgen::wasm_path(&crate_data, BuildProfile::Release, &[]);
+    assert!(wasm.ends_with("wasm32-unknown-unknown/release/other-tool.wasm"));
+}
+
+#[test]
+fn it_builds_the_lib_of_a_crate_without_bins() {
+    let fixture = fixture::js_hello_world();
+    let mut crate_data = manifest::CrateData::new(&fixture.path, None).unwrap();
+    crate_data.select_target(false, None).unwrap();
+    assert_eq!(crate_data.bin_target(), None);
+}
+
 #[test]
 fn it_gets_the_default_name_prefix() {
     let path = &PathBuf::from(".");
@@ -172,6 +265,111 @@ fn it_creates_a_package_json_provided_path_with_scope() {
     assert_eq!(actual_files, expected_files);
 }
 
+#[test]
+fn it_creates_a_deno_json_for_deno_target() {
+    let fixture = fixture::js_hello_world();
+    let out_dir = fixture.path.join("pkg");
+    let crate_data = manifest::CrateData::new(&fixture.path, None).unwrap();
+    wasm_pack::command::utils::create_pkg_dir(&out_dir).unwrap();
+    assert!(crate_data
+        .write_package_json(&out_dir, &Some("test".to_string()), false, Target::Deno)
+        .is_ok());
+    assert!(!out_dir.join("package.json").exists());
+
+    let deno_json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("deno.json")).unwrap()).unwrap();
+    assert_eq!(deno_json["name"], "@test/js-hello-world");
+    assert_eq!(deno_json["version"], "0.1.0");
+    assert_eq!(deno_json["exports"], "./js_hello_world.js");
+
+    let actual_files: HashSet<String> = deno_json["publish"]["include"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f.as_str().unwrap().to_string())
+        .collect();
+    let expected_files: HashSet<String> = [
+        "js_hello_world.d.ts",
+        "js_hello_world_bg.wasm",
+        "js_hello_world.js",
+    ]
+    .iter()
+    .map(|&s| String::from(s))
+    .collect();
+    assert_eq!(actual_files, expected_files);
+}
+
+#[test]
+fn it_sets_the_module_type_per_target() {
+    let fixture = fixture::js_hello_world();
+    let out_dir = fixture.path.join("pkg");
+    let mut crate_data = manifest::CrateData::new(&fixture.path, None).unwrap();
+    wasm_pack::command::utils::create_pkg_dir(&out_dir).unwrap();
+
+    let targets = [
+        (Target::Bundler, "module"),
+        (Target::Web, "module"),
+        (Target::Nodejs, ""),
+        (Target::NoModules, ""),
+    ];
+    for (target, ty) in targets {
+        fs::remove_file(out_dir.join("package.json")).ok();
+        crate_data
+            .write_package_json(&out_dir, &None, false, target)
+            .unwrap();
+        let pkg = utils::manifest::read_package_json(&fixture.path, &out_dir).unwrap();
+        assert_eq!(pkg.ty, ty, "{}", target);
+    }
+
+    crate_data.set_module_type(Some(ModuleType::CommonJs));
+    fs::remove_file(out_dir.join("package.json")).unwrap();
+    crate_data
+        .write_package_json(&out_dir, &None, false, Target::Web)
+        .unwrap();
+    let pkg = utils::manifest::read_package_json(&fixture.path, &out_dir).unwrap();
+    assert_eq!(pkg.ty, "commonjs");
+
+    crate_data.set_module_type(Some(ModuleType::Module));
+    fs::remove_file(out_dir.join("package.json")).unwrap();
+    crate_data
+        .write_package_json(&out_dir, &None, false, Target::Nodejs)
+        .unwrap();
+    let pkg = utils::manifest::read_package_json(&fixture.path, &out_dir).unwrap();
+    assert_eq!(pkg.ty, "module");
+}
+
+#[test]
+fn it_sets_the_bundler_entry_points_and_checks_they_exist() {
+    let fixture = fixture::js_hello_world();
+    let out_dir = fixture.path.join("pkg");
+    let crate_data = manifest::CrateData::new(&fixture.path, None).unwrap();
+    wasm_pack::command::utils::create_pkg_dir(&out_dir).unwrap();
+    for file in ["js_hello_world.js", "js_hello_world.d.ts"] {
+        fs::write(out_dir.join(file), "").unwrap();
+    }
+
+    crate_data
+        .write_package_json(&out_dir, &None, false, Target::Bundler)
+        .unwrap();
+    let pkg = utils::manifest::read_package_json(&fixture.path, &out_dir).unwrap();
+    assert_eq!(pkg.main, "js_hello_world.js");
+    assert_eq!(pkg.module, "js_hello_world.js");
+    assert_eq!(pkg.types, "js_hello_world.d.ts");
+    manifest::check_package_entries(&out_dir).unwrap();
+
+    fs::remove_file(out_dir.join("js_hello_world.d.ts")).unwrap();
+    let error = manifest::check_package_entries(&out_dir)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains(
+            "names entry points that aren't in the package: `types` (js_hello_world.d.ts)"
+        ),
+        "{}",
+        error
+    );
+}
+
 #[test]
 fn it_creates_a_pkg_json_with_correct_files_on_node() {
     let fixture = fixture::js_hello_world();
@@ -531,7 +729,70 @@ fn configure_wasm_bindgen_debug_incorrectly_is_error() {
 }
 
 #[test]
-fn parse_crate_data_returns_unused_keys_in_cargo_toml() {
+fn it_reads_the_pinned_webdriver_versions() {
+    let fixture = utils::fixture::Fixture::new();
+    fixture.readme().hello_world_src_lib().file(
+        "Cargo.toml",
+        r#"
+            [package]
+            authors = ["The wasm-pack developers"]
+            description = "so awesome rust+wasm package"
+            license = "WTFPL"
+            name = "whatever"
+            repository = "https://github.com/rustwasm/wasm-pack.git"
+            version = "0.1.0"
+
+            [lib]
+            crate-type = ["cdylib"]
+
+            [dependencies]
+            wasm-bindgen = "0.2"
+
+            [package.metadata.wasm-pack]
+            msedgedriver-version = "129.0.2792.79"
+
+            [package.metadata.wasm-pack.test]
+            chromedriver = "129.0.6668.100"
+            geckodriver = "0.34.0"
+
+            [package.metadata.wasm-pack.webdriver-capabilities."goog:chromeOptions"]
+            args = ["--use-fake-ui-for-media-stream"]
+
+            [package.metadata.wasm-pack.test.firefox]
+            args = ["-private"]
+            prefs = { "media.navigator.streams.fake" = true }
+            window-size = "1280x720"
+            "#,
+    );
+    let manifest_and_keys =
+        manifest::CrateData::parse_crate_data(&fixture.path.join("Cargo.toml")).unwrap();
+    assert!(manifest_and_keys.unused_keys.is_empty());
+    let crate_data = manifest::CrateData::new(&fixture.path, None).unwrap();
+    assert_eq!(crate_data.chromedriver_version(), Some("129.0.6668.100"));
+    assert_eq!(crate_data.msedgedriver_version(), Some("129.0.2792.79"));
+    assert_eq!(crate_data.geckodriver_version(), Some("0.34.0"));
+    assert_eq!(
+        crate_data.webdriver_capabilities(),
+        Some(&serde_json::json!({
+            "goog:chromeOptions": { "args": ["--use-fake-ui-for-media-stream"] }
+        }))
+    );
+    let firefox = crate_data.browser_options("firefox").unwrap();
+    assert_eq!(firefox.args, ["-private"]);
+    assert_eq!(
+        firefox.prefs.get("media.navigator.streams.fake"),
+        Some(&serde_json::Value::Bool(true))
+    );
+    assert_eq!(firefox.window_size.as_deref(), Some("1280x720"));
+    assert!(crate_data
+        .browser_options("chrome")
+        .unwrap()
+        .args
+        .is_empty());
+}
+
+#[test]
+fn parse_crate_data_errors_on_unknown_keys_in_cargo_toml() {
     let fixture = utils::fixture::Fixture::new();
     fixture
         .readme()
@@ -557,18 +818,236 @@ fn parse_crate_data_returns_unused_keys_in_cargo_toml() {
             debug-js-glue = true
             "#,
         )
-        .hello_world_src_lib()
-        .install_local_wasm_bindgen();
+        .hello_world_src_lib();
     fixture
         .wasm_pack()
         .arg("build")
         .assert()
-        .success()
-        .stderr(predicates::str::contains(format!(
-        "[WARN]: {} \"package.metadata.wasm-pack.profile.production\" is an unknown key and will \
-         be ignored. Please check your Cargo.toml.",
-        emoji::WARN
-    )));
+        .failure()
+        .stderr(predicates::str::contains(
+            "unknown key `package.metadata.wasm-pack.profile.production`, expected one of: \
+             `dev`, `release`, `profiling`, `custom`",
+        ));
+}
+
+fn metadata_fixture(metadata: &str) -> utils::fixture::Fixture {
+    let fixture = utils::fixture::Fixture::new();
+    fixture.file(
+        "Cargo.toml",
+        format!(
+            r#"
+            [package]
+            name = "whatever"
+            version = "0.1.0"
+
+            [lib]
+            crate-type = ["cdylib"]
+
+            {}
+            "#,
+            metadata
+        ),
+    );
+    fixture
+}
+
+fn parse_metadata_error(metadata: &str) -> String {
+    let fixture = metadata_fixture(metadata);
+    let manifest_path = fixture.path.join("Cargo.toml");
+    let error = manifest::CrateData::parse_crate_data(&manifest_path)
+        .err()
+        .expect("malformed metadata should be rejected");
+    let message = format!("{:#}", error);
+    assert!(
+        message.contains(&manifest_path.display().to_string()),
+        "error does not name the manifest: {}",
+        message
+    );
+    message
+}
+
+#[test]
+fn metadata_error_names_key_and_expected_type_for_wasm_opt() {
+    let message = parse_metadata_error(
+        r#"
+            [package.metadata.wasm-pack.profile.release]
+            wasm-opt = "O3"
+        "#,
+    );
+    assert!(
+        message.contains("`package.metadata.wasm-pack.profile.release.wasm-opt`"),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains("expected a boolean or an array of `wasm-opt` arguments"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn metadata_error_names_key_and_expected_type_for_wasm_bindgen() {
+    let message = parse_metadata_error(
+        r#"
+            [package.metadata.wasm-pack.profile.dev.wasm-bindgen]
+            debug-js-glue = "not a boolean"
+        "#,
+    );
+    assert!(
+        message.contains(
+            "`package.metadata.wasm-pack.profile.dev.wasm-bindgen.debug-js-glue` on line 11"
+        ),
+        "{}",
+        message
+    );
+    assert!(message.contains("expected a boolean"), "{}", message);
+}
+
+#[test]
+fn metadata_error_rejects_non_string_wasm_opt_arguments() {
+    let message = parse_metadata_error(
+        r#"
+            [package.metadata.wasm-pack.profile.release]
+            wasm-opt = ["-O", 3]
+        "#,
+    );
+    assert!(
+        message.contains("`package.metadata.wasm-pack.profile.release.wasm-opt`"),
+        "{}",
+        message
+    );
+    assert!(message.contains("expected a string"), "{}", message);
+}
+
+#[test]
+fn metadata_error_suggests_closest_profile_key() {
+    let message = parse_metadata_error(
+        r#"
+            [package.metadata.wasm-pack.profile.release]
+            wasm-optx = false
+        "#,
+    );
+    assert!(
+        message.contains(
+            "unknown key `package.metadata.wasm-pack.profile.release.wasm-optx`, \
+             did you mean `wasm-opt`?"
+        ),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn metadata_error_suggests_closest_wasm_bindgen_key() {
+    let message = parse_metadata_error(
+        r#"
+            [package.metadata.wasm-pack.profile.dev.wasm-bindgen]
+            debug-js-glu = true
+        "#,
+    );
+    assert!(
+        message.contains(
+            "unknown key `package.metadata.wasm-pack.profile.dev.wasm-bindgen.debug-js-glu`, \
+             did you mean `debug-js-glue`?"
+        ),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn metadata_error_suggests_closest_top_level_key() {
+    let message = parse_metadata_error(
+        r#"
+            [package.metadata.wasm-pack.profiles.release]
+            wasm-opt = false
+        "#,
+    );
+    assert!(
+        message
+            .contains("unknown key `package.metadata.wasm-pack.profiles`, did you mean `profile`?"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn metadata_error_lists_every_unknown_key() {
+    let message = parse_metadata_error(
+        r#"
+            [package.metadata.wasm-pack.profile.release]
+            wasm-optx = false
+
+            [package.metadata.wasm-pack.profile.dev.wasm-bindgen]
+            dwarf = true
+        "#,
+    );
+    assert!(
+        message.contains("unknown key `package.metadata.wasm-pack.profile.release.wasm-optx`"),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains("unknown key `package.metadata.wasm-pack.profile.dev.wasm-bindgen.dwarf`"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn it_reads_the_deprecated_webdriver_pins() {
+    let fixture = metadata_fixture(
+        r#"
+            [dependencies]
+            wasm-bindgen = "0.2"
+
+            [package.metadata.wasm-pack]
+            chromedriver-version = "129.0.6668.100"
+            geckodriver = "0.34.0"
+        "#,
+    );
+    fixture.hello_world_src_lib();
+    let crate_data = manifest::CrateData::new(&fixture.path, None).unwrap();
+    assert_eq!(crate_data.chromedriver_version(), Some("129.0.6668.100"));
+    assert_eq!(crate_data.geckodriver_version(), Some("0.34.0"));
+}
+
+#[test]
+fn metadata_error_rejects_a_driver_pinned_with_both_keys() {
+    let message = parse_metadata_error(
+        r#"
+            [package.metadata.wasm-pack]
+            geckodriver = "0.34.0"
+
+            [package.metadata.wasm-pack.test]
+            geckodriver = "0.31.0"
+        "#,
+    );
+    assert!(
+        message.contains(
+            "`package.metadata.wasm-pack.geckodriver` and \
+             `package.metadata.wasm-pack.test.geckodriver` both pin the same driver; remove \
+             the deprecated `package.metadata.wasm-pack.geckodriver`"
+        ),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn metadata_typo_in_table_name_is_only_a_warning() {
+    let fixture = metadata_fixture(
+        r#"
+            [package.metadata.wasm_pack.profile.release]
+            wasm-opt = false
+        "#,
+    );
+    let manifest_and_keys =
+        manifest::CrateData::parse_crate_data(&fixture.path.join("Cargo.toml")).unwrap();
+    assert!(manifest_and_keys
+        .unused_keys
+        .contains("package.metadata.wasm_pack"));
 }
 
 #[test]
@@ -643,3 +1122,58 @@ fn it_doesnt_recurse_up_the_path_to_find_cargo_toml_when_default() {
     let crate_data = manifest::CrateData::new(&path, None);
     assert!(crate_data.is_err());
 }
+
+#[test]
+#[serial]
+#[cfg(unix)]
+fn it_runs_cargo_metadata_only_once() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = fixture::js_hello_world();
+    // Ensure that there is a `Cargo.lock`.
+    fixture.cargo_check();
+
+    // `cargo metadata` is spawned through `$CARGO`, so point it at a wrapper
+    // that records every invocation concerning this fixture.
+    let log = fixture.path.join("cargo-invocations.log");
+    let wrapper = fixture.path.join("cargo-wrapper.sh");
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    fs::write(
+        &wrapper,
+        format!(
+            "#!/bin/sh\ncase \"$*\" in *{path}*) echo \"$@\" >> {log} ;; esac\nexec {cargo} \"$@\"\n",
+            path = fixture.path.display(),
+            log = log.display(),
+            cargo = cargo,
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let original = env::var_os("CARGO");
+    env::set_var("CARGO", &wrapper);
+    let crate_data = manifest::CrateData::new(&fixture.path, None);
+    match original {
+        Some(cargo) => env::set_var("CARGO", cargo),
+        None => env::remove_var("CARGO"),
+    }
+    let crate_data = crate_data.unwrap();
+
+    let out_dir = fixture.path.join("pkg");
+    crate_data.check_crate_config().unwrap();
+    assert_eq!(crate_data.crate_name(), "js_hello_world");
+    assert!(crate_data.target_directory().is_absolute());
+    assert!(crate_data.workspace_root().is_absolute());
+    Lockfile::new(&crate_data)
+        .unwrap()
+        .require_wasm_bindgen()
+        .unwrap();
+    wasm_pack::command::utils::create_pkg_dir(&out_dir).unwrap();
+    crate_data
+        .write_package_json(&out_dir, &None, false, Target::Bundler)
+        .unwrap();
+
+    let invocations = fs::read_to_string(&log).unwrap();
+    assert_eq!(invocations.lines().count(), 1, "{}", invocations);
+    assert!(invocations.starts_with("metadata"), "{}", invocations);
+}
@@ -0,0 +1,186 @@
+use crate::utils;
+use std::env;
+use std::ffi::OsString;
+use wasm_pack::npmrc;
+
+/// Sets environment variables while alive, starting with the user and global
+/// `.npmrc` pointed at ones in the fixture, so that the developer's own don't
+/// change the outcome. They are set for the whole test process, so the tests
+/// using this are `#[serial]`.
+struct NpmEnv(Vec<(String, Option<OsString>)>);
+
+impl NpmEnv {
+    fn in_fixture(fixture: &utils::fixture::Fixture) -> NpmEnv {
+        let mut npm_env = NpmEnv(Vec::new());
+        npm_env.set("NPM_CONFIG_USERCONFIG", fixture.path.join("home/.npmrc"));
+        npm_env.set("NPM_CONFIG_GLOBALCONFIG", fixture.path.join("global/npmrc"));
+        npm_env
+    }
+
+    fn set(&mut self, name: &str, value: impl AsRef<std::ffi::OsStr>) {
+        self.0.push((name.to_string(), env::var_os(name)));
+        env::set_var(name, value);
+    }
+
+    fn remove(&mut self, name: &str) {
+        self.0.push((name.to_string(), env::var_os(name)));
+        env::remove_var(name);
+    }
+}
+
+impl Drop for NpmEnv {
+    fn drop(&mut self) {
+        for (name, previous) in self.0.drain(..).rev() {
+            match previous {
+                Some(previous) => env::set_var(name, previous),
+                None => env::remove_var(name),
+            }
+        }
+    }
+}
+
+fn scoped_package(fixture: &utils::fixture::Fixture) {
+    fixture.file(
+        "pkg/package.json",
+        r#"{ "name": "@corp/js-hello-world", "version": "0.1.0" }"#,
+    );
+}
+
+fn check_auth(fixture: &utils::fixture::Fixture) -> Result<(), String> {
+    npmrc::check_registry_auth(&fixture.path.join("pkg"), &fixture.path, None)
+        .map_err(|e| format!("{:#}", e))
+}
+
+#[test]
+#[serial]
+fn registry_auth_is_missing_without_a_token_for_the_registry() {
+    let fixture = utils::fixture::js_hello_world();
+    let _npm_env = NpmEnv::in_fixture(&fixture);
+    scoped_package(&fixture);
+    fixture.file(".npmrc", "@corp:registry=https://npm.corp.example/\n");
+
+    let message = check_auth(&fixture).unwrap_err();
+    assert!(
+        message
+            .contains("No auth token found for the registry https://npm.corp.example/ (searched "),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains("run `npm login --registry=https://npm.corp.example/`"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+#[serial]
+fn registry_auth_is_found_in_project_npmrc() {
+    let fixture = utils::fixture::js_hello_world();
+    let mut npm_env = NpmEnv::in_fixture(&fixture);
+    scoped_package(&fixture);
+    fixture.file(
+        ".npmrc",
+        "@corp:registry=https://npm.corp.example/packages/\n\
+         //npm.corp.example/:_authToken=${CORP_TOKEN}\n",
+    );
+
+    // npm refuses a reference to a variable that isn't set.
+    npm_env.remove("CORP_TOKEN");
+    check_auth(&fixture).unwrap_err();
+    npm_env.set("CORP_TOKEN", "secret");
+    check_auth(&fixture).unwrap();
+}
+
+#[test]
+#[serial]
+fn registry_auth_needs_a_password_with_a_username() {
+    let fixture = utils::fixture::js_hello_world();
+    let _npm_env = NpmEnv::in_fixture(&fixture);
+    scoped_package(&fixture);
+    fixture.file(
+        ".npmrc",
+        "@corp:registry=https://npm.corp.example/\n\
+         //npm.corp.example/:username=me\n",
+    );
+    check_auth(&fixture).unwrap_err();
+
+    fixture.file(
+        ".npmrc",
+        "@corp:registry=https://npm.corp.example/\n\
+         //npm.corp.example/:username=me\n\
+         //npm.corp.example/:_password=c2VjcmV0\n",
+    );
+    check_auth(&fixture).unwrap();
+}
+
+#[test]
+#[serial]
+fn registry_auth_is_found_in_the_global_npmrc_and_the_environment() {
+    let fixture = utils::fixture::js_hello_world();
+    let mut npm_env = NpmEnv::in_fixture(&fixture);
+    scoped_package(&fixture);
+    fixture.file(".npmrc", "@corp:registry=https://npm.corp.example/\n");
+    check_auth(&fixture).unwrap_err();
+
+    fixture.file("global/npmrc", "//npm.corp.example/:_authToken=secret\n");
+    check_auth(&fixture).unwrap();
+    std::fs::remove_file(fixture.path.join("global/npmrc")).unwrap();
+
+    npm_env.set("npm_config_//npm.corp.example/:_authToken", "secret");
+    check_auth(&fixture).unwrap();
+}
+
+#[test]
+#[serial]
+fn registry_auth_uses_publish_config_registry() {
+    let fixture = utils::fixture::js_hello_world();
+    let _npm_env = NpmEnv::in_fixture(&fixture);
+    fixture.file(
+        "pkg/package.json",
+        r#"{
+            "name": "js-hello-world",
+            "version": "0.1.0",
+            "publishConfig": { "registry": "https://npm.other.example" }
+        }"#,
+    );
+    fixture.file(
+        ".npmrc",
+        "//npm.corp.example/:_authToken=secret\n\
+         //npm.other.example/:_authToken=secret\n",
+    );
+    check_auth(&fixture).unwrap();
+
+    fixture.file(".npmrc", "//npm.corp.example/:_authToken=secret\n");
+    let npmrc = npmrc::Npmrc::load(&fixture.path.join("pkg"), &fixture.path).unwrap();
+    assert!(!npmrc.has_auth_for("https://npm.other.example"));
+    assert!(npmrc.has_auth_for("https://npm.corp.example/some/path"));
+}
+
+#[test]
+fn saved_tokens_replace_the_entries_already_there() {
+    let updated = npmrc::set_entries(
+        "; my settings\n\
+         //npm.corp.example/:_authToken=old\n\
+         @corp:registry=https://npm.corp.example/\n\
+         //npm.corp.example/:_authToken = older\n",
+        &[(
+            "//npm.corp.example/:_authToken".to_string(),
+            "new".to_string(),
+        )],
+    );
+    assert_eq!(
+        updated,
+        "; my settings\n\
+         //npm.corp.example/:_authToken=new\n\
+         @corp:registry=https://npm.corp.example/\n"
+    );
+
+    let fixture = utils::fixture::js_hello_world();
+    let path = fixture.path.join("home/.npmrc");
+    npmrc::set_auth_token(&path, "https://npm.corp.example", Some("corp"), "t0k3n").unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "//npm.corp.example/:_authToken=t0k3n\n@corp:registry=https://npm.corp.example\n"
+    );
+}
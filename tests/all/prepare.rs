@@ -0,0 +1,125 @@
+use crate::utils;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+
+#[test]
+#[cfg(unix)]
+fn prepare_installs_wasm_bindgen_without_building() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    // No prebuilt binaries exist for this version, so wasm-pack has to fall
+    // back to `cargo install`.
+    fixture.file(
+        "wasm-pack.toml",
+        r#"
+            [wasm-bindgen]
+            version = "0.2.9999"
+        "#,
+    );
+    let bin = fixture.install_fake_cargo_install("0.2.9999");
+    // Keep the fake install out of the cache shared by the other tests.
+    let cache = fixture.path.join("cache");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("WASM_PACK_CACHE", &cache)
+        .arg("prepare")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("fake cargo install finished"))
+        .stderr(predicates::str::contains("The tools are ready!"));
+    assert!(!fixture.path.join("target").exists());
+    assert!(!fixture.path.join("pkg").exists());
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("WASM_PACK_CACHE", &cache)
+        .arg("prepare")
+        .arg("--mode")
+        .arg("no-install")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("fake cargo install finished").not());
+}
+
+#[test]
+#[cfg(unix)]
+fn cargo_install_uses_the_crate_cargo_config_and_the_registry_mirror() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    fixture.file(
+        "wasm-pack.toml",
+        r#"
+            [wasm-bindgen]
+            version = "0.2.9999"
+        "#,
+    );
+    fixture.file(
+        ".cargo/config.toml",
+        r#"
+            [source.corp]
+            registry = "sparse+http://corp.example/index/"
+
+            [source.vendored]
+            directory = "vendor"
+        "#,
+    );
+    let bin = fixture.install_fake_cargo_install("0.2.9999");
+    let cache = fixture.path.join("cache");
+    let elsewhere = tempfile::TempDir::new().unwrap();
+    fixture
+        .wasm_pack()
+        .current_dir(elsewhere.path())
+        .env("PATH", fixture.path_with(&bin))
+        .env("WASM_PACK_CACHE", &cache)
+        .arg("--registry-mirror")
+        .arg("sparse+http://mirror.example/index/")
+        .arg("prepare")
+        .arg(&fixture.path)
+        .assert()
+        .success();
+
+    // `cargo install` doesn't read the crate's `.cargo/config.toml`, so its
+    // sources are passed on, before the mirror that overrides them.
+    let log = std::fs::read_to_string(fixture.path.join("cargo-install.log")).unwrap();
+    assert!(
+        log.contains(&format!(
+            "--config source.corp.registry=\"sparse+http://corp.example/index/\" \
+             --config source.vendored.directory=\"{}\" ",
+            fixture.path.join("vendor").display()
+        )),
+        "{}",
+        log
+    );
+    assert!(
+        log.contains(
+            "--config source.crates-io.replace-with=\"wasm-pack-mirror\" \
+             --config source.wasm-pack-mirror.registry=\"sparse+http://mirror.example/index/\""
+        ),
+        "{}",
+        log
+    );
+
+    // A source that serves another version is caught before it is cached.
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    fixture.file(
+        "wasm-pack.toml",
+        r#"
+            [wasm-bindgen]
+            version = "0.2.9999"
+        "#,
+    );
+    let bin = fixture.install_fake_cargo_install("0.2.9998");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("WASM_PACK_CACHE", fixture.path.join("cache"))
+        .arg("prepare")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "installed wasm-bindgen 0.2.9998 instead of 0.2.9999",
+        ));
+}
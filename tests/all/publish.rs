@@ -0,0 +1,1492 @@
+use crate::utils;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use wasm_pack::command::publish::bump::{self, Bump};
+use wasm_pack::npm;
+
+/// Serve `body` with `status` to every request, like a registry would serve
+/// a packument. Returns the registry's URL.
+fn mock_registry(status: &'static str, body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+    url
+}
+
+fn package_on(fixture: &utils::fixture::Fixture, registry: &str) {
+    fixture.file(
+        "pkg/package.json",
+        r#"{ "name": "js-hello-world", "version": "0.1.0" }"#,
+    );
+    let host = registry.trim_start_matches("http:");
+    fixture.file(
+        ".npmrc",
+        format!("registry={}\n{}:_authToken=secret\n", registry, host),
+    );
+}
+
+#[test]
+fn publish_refuses_a_version_that_is_already_published() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("200 OK", r#"{"versions":{"0.0.1":{},"0.1.0":{}}}"#);
+    package_on(&fixture, &registry);
+
+    fixture
+        .wasm_pack()
+        .env("HOME", &fixture.path)
+        .env_remove("NPM_CONFIG_USERCONFIG")
+        .env_remove("npm_config_userconfig")
+        .arg("publish")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(format!(
+            "js-hello-world@0.1.0 is already published to {}",
+            registry
+        )))
+        .stderr(predicates::str::contains("--bump patch"));
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_skip_existing_succeeds_without_publishing_an_existing_version() {
+    let fixture = utils::fixture::js_hello_world();
+    let bin = install_package_manager_stubs(&fixture);
+    let calls = fixture.path.join("calls");
+    let publish = || {
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("publish")
+            .arg("--skip-existing");
+        cmd.assert()
+    };
+
+    let registry = mock_registry("200 OK", r#"{"versions":{"0.0.1":{},"0.1.0":{}}}"#);
+    package_on(&fixture, &registry);
+    publish()
+        .success()
+        .stderr(predicates::str::contains(format!(
+            "js-hello-world@0.1.0 is already published to {}, skipping the publish.",
+            registry
+        )));
+    assert!(!calls.exists());
+
+    let registry = mock_registry("200 OK", r#"{"versions":{"0.0.1":{}}}"#);
+    package_on(&fixture, &registry);
+    publish().success();
+    assert_eq!(
+        fs::read_to_string(&calls).unwrap(),
+        "npm pack --dry-run --json\nnpm publish\n"
+    );
+    fs::remove_file(&calls).unwrap();
+
+    // A registry that can't be asked doesn't keep the publish from going on.
+    let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+    let registry = format!("http://{}/", closed.local_addr().unwrap());
+    drop(closed);
+    package_on(&fixture, &registry);
+    publish()
+        .success()
+        .stderr(predicates::str::contains("Could not check whether"));
+    assert_eq!(
+        fs::read_to_string(&calls).unwrap(),
+        "npm pack --dry-run --json\nnpm publish\n"
+    );
+}
+
+#[test]
+fn version_check_passes_for_new_versions_and_packages() {
+    let fixture = utils::fixture::js_hello_world();
+    let pkg = fixture.path.join("pkg");
+
+    let registry = mock_registry("200 OK", r#"{"versions":{"0.0.1":{}}}"#);
+    package_on(&fixture, &registry);
+    npm::check_version_unpublished(&pkg, &fixture.path, None).unwrap();
+
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    npm::check_version_unpublished(&pkg, &fixture.path, None).unwrap();
+}
+
+#[test]
+fn bump_increments_the_package_version() {
+    let manifest = r#"[package]
+name = "whatever"
+version = "1.2.3-beta.1" # bumped by wasm-pack
+
+[dependencies.wasm-bindgen]
+version = "0.2"
+"#;
+    for (part, expected) in [
+        (Bump::Patch, "1.2.4"),
+        (Bump::Minor, "1.3.0"),
+        (Bump::Major, "2.0.0"),
+    ] {
+        let (bumped, version) = bump::set_manifest_version(manifest, part).unwrap();
+        assert_eq!(version.to_string(), expected);
+        assert_eq!(
+            bumped,
+            manifest.replace("1.2.3-beta.1", expected),
+            "{:?}",
+            part
+        );
+    }
+
+    let commented = "[package] # the crate\nname = \"whatever\"\nversion = '0.1.0'\n";
+    let (bumped, _) = bump::set_manifest_version(commented, Bump::Minor).unwrap();
+    assert_eq!(
+        bumped,
+        "[package] # the crate\nname = \"whatever\"\nversion = \"0.2.0\"\n"
+    );
+
+    let inherited = "[package]\nname = \"whatever\"\nversion.workspace = true\n";
+    let error = bump::set_manifest_version(inherited, Bump::Patch).unwrap_err();
+    assert!(error.to_string().contains("inherited from the workspace"));
+}
+
+#[test]
+fn bump_updates_cargo_toml_and_package_json() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.file(
+        "pkg/package.json",
+        r#"{ "name": "js-hello-world", "version": "0.1.0" }"#,
+    );
+
+    let version =
+        bump::bump_version(&fixture.path, &fixture.path.join("pkg"), Bump::Minor).unwrap();
+    assert_eq!(version.to_string(), "0.2.0");
+
+    let manifest = fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap();
+    assert!(manifest.contains("version = \"0.2.0\""), "{}", manifest);
+    let pkg: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(fixture.path.join("pkg/package.json")).unwrap())
+            .unwrap();
+    assert_eq!(pkg["version"], "0.2.0");
+}
+
+/// Install `npm`, `yarn` and `pnpm` stubs that log their arguments to
+/// `calls` and print `$NPM_STUB_STDOUT`, and return the directory they're in.
+#[cfg(unix)]
+fn install_package_manager_stubs(fixture: &utils::fixture::Fixture) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = fixture.path.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let calls = fixture.path.join("calls");
+    for name in ["npm", "yarn", "pnpm"] {
+        let path = bin.join(name);
+        fs::write(
+            &path,
+            format!(
+                "#!/bin/sh\nif [ \"$1\" = --version ]; then\n    echo \"$YARN_STUB_VERSION\"\n    exit 0\nfi\nif [ \"$1\" = pack ] && [ -n \"$NPM_STUB_TARBALL\" ]; then\n    echo tarball > \"$NPM_STUB_TARBALL\"\nfi\necho \"{} $*\" >> {}\nprintf '%s' \"$NPM_STUB_STDOUT\"\n",
+                name,
+                calls.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    bin
+}
+
+#[test]
+#[cfg(unix)]
+fn pack_and_publish_use_the_chosen_package_manager() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let bin = install_package_manager_stubs(&fixture);
+    let calls = fixture.path.join("calls");
+
+    let run = |args: &[&str], yarn_version: &str| {
+        let _ = fs::remove_file(&calls);
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env("YARN_STUB_VERSION", yarn_version)
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .args(args)
+            .assert()
+            .success();
+        fs::read_to_string(&calls).unwrap()
+    };
+
+    assert_eq!(run(&["pack"], "1.22.19"), "npm pack\n");
+    assert_eq!(
+        run(&["pack", "--package-manager", "yarn"], "1.22.19"),
+        "yarn pack\n"
+    );
+    assert_eq!(
+        run(
+            &["publish", "--package-manager", "npm", "--tag", "next"],
+            "1.22.19"
+        ),
+        "npm pack --dry-run --json\nnpm publish --tag next\n"
+    );
+    assert_eq!(
+        run(
+            &["publish", "--package-manager", "yarn", "--access", "public"],
+            "1.22.19"
+        ),
+        "yarn publish --non-interactive --access=public\n"
+    );
+    assert_eq!(
+        run(
+            &["publish", "--package-manager", "yarn", "--tag", "next"],
+            "4.1.0"
+        ),
+        "yarn npm publish --tag next\n"
+    );
+    assert_eq!(
+        run(&["publish", "--package-manager", "pnpm"], "1.22.19"),
+        "pnpm publish --no-git-checks\n"
+    );
+
+    // Without `--package-manager`, the closest lockfile decides.
+    fixture.file("pnpm-lock.yaml", "lockfileVersion: '9.0'\n");
+    assert_eq!(run(&["pack"], "1.22.19"), "pnpm pack\n");
+    fixture.file("pkg/yarn.lock", "");
+    assert_eq!(
+        run(&["publish"], "1.22.19"),
+        "yarn publish --non-interactive\n"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_reports_the_dist_tag() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let bin = install_package_manager_stubs(&fixture);
+
+    for (args, tag) in [(&["--tag", "next"][..], "next"), (&[][..], "latest")] {
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("publish")
+            .args(args)
+            .assert()
+            .success()
+            .stderr(predicates::str::contains(format!(
+                "published your package under the `{}` tag",
+                tag
+            )));
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_dry_run_only_passes_dry_run_to_npm() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    fixture.file(
+        "pkg/package.json",
+        r#"{ "name": "js-hello-world", "version": "0.1.0" }"#,
+    );
+    // No credentials for the registry, which the dry run only warns about.
+    fixture.file(".npmrc", format!("registry={}\n", registry));
+    let bin = install_package_manager_stubs(&fixture);
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("HOME", &fixture.path)
+        .env(
+            "NPM_STUB_STDOUT",
+            r#"[{"files":[{"path":"package.json","size":52},{"path":"js_hello_world_bg.wasm","size":1500}]}]"#,
+        )
+        .env_remove("NPM_CONFIG_USERCONFIG")
+        .env_remove("npm_config_userconfig")
+        .arg("publish")
+        .arg("--dry-run")
+        .arg("--tag")
+        .arg("next")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("[dry run] No auth token found"))
+        .stderr(predicates::str::contains(
+            "[dry run] The tarball of js-hello-world@0.1.0 contains 2 files, 1.6 kB unpacked:",
+        ))
+        .stderr(predicates::str::contains(
+            "1.5 kB  js_hello_world_bg.wasm",
+        ))
+        .stderr(predicates::str::contains(
+            "would be published under the `next` tag. Nothing was published.",
+        ));
+    assert_eq!(
+        fs::read_to_string(fixture.path.join("calls")).unwrap(),
+        "npm pack --dry-run --json\nnpm publish --tag next --dry-run\n"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_lists_the_tarball_and_warns_about_missing_artifacts() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    fixture.file(
+        "pkg/package.json",
+        r#"{ "name": "js-hello-world", "version": "0.1.0", "main": "./js_hello_world.js" }"#,
+    );
+    let bin = install_package_manager_stubs(&fixture);
+
+    // Without a terminal, the contents are listed but nothing is asked.
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("HOME", &fixture.path)
+        .env(
+            "NPM_STUB_STDOUT",
+            r#"[{"files":[{"path":"package.json","size":90},{"path":"README.md","size":2048}],"unpackedSize":2138}]"#,
+        )
+        .env_remove("NPM_CONFIG_USERCONFIG")
+        .env_remove("npm_config_userconfig")
+        .arg("publish")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "The tarball of js-hello-world@0.1.0 contains 2 files, 2.1 kB unpacked:",
+        ))
+        .stderr(predicates::str::contains("90 B  package.json"))
+        .stderr(predicates::str::contains("2.0 kB  README.md"))
+        .stderr(predicates::str::contains(
+            "The tarball doesn't contain the `_bg.wasm` file.",
+        ))
+        .stderr(predicates::str::contains(
+            "The tarball doesn't contain `js_hello_world.js`, the `main` entry.",
+        ));
+    assert_eq!(
+        fs::read_to_string(fixture.path.join("calls")).unwrap(),
+        "npm pack --dry-run --json\nnpm publish\n"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_only_lists_the_tarball_with_npm_and_respects_no_bundle_wasm() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    // As `--no-bundle-wasm` leaves it.
+    fixture.file(
+        "pkg/package.json",
+        r#"{ "name": "js-hello-world", "version": "0.1.0", "files": ["js_hello_world.js"] }"#,
+    );
+    let bin = install_package_manager_stubs(&fixture);
+    let calls = fixture.path.join("calls");
+    let publish = |package_manager: &str| {
+        let _ = fs::remove_file(&calls);
+        let assert = fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env("YARN_STUB_VERSION", "1.22.19")
+            .env(
+                "NPM_STUB_STDOUT",
+                r#"[{"files":[{"path":"package.json","size":90},{"path":"js_hello_world.js","size":500}]}]"#,
+            )
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("publish")
+            .arg("--package-manager")
+            .arg(package_manager)
+            .assert()
+            .success();
+        (assert, fs::read_to_string(&calls).unwrap())
+    };
+
+    let (assert, calls) = publish("npm");
+    assert
+        .stderr(predicates::str::contains("contains 2 files"))
+        .stderr(predicates::str::contains("`_bg.wasm`").not());
+    assert_eq!(calls, "npm pack --dry-run --json\nnpm publish\n");
+
+    let (assert, calls) = publish("pnpm");
+    assert.stderr(predicates::str::contains(
+        "Could not list the contents of the tarball: wasm-pack can only list them with npm, \
+         and pnpm may pick other files",
+    ));
+    assert_eq!(calls, "pnpm publish --no-git-checks\n");
+}
+
+#[test]
+fn tarball_contents_are_read_from_both_npm_reports() {
+    let pack = r#"[{"files":[{"path":"a.js","size":10},{"path":"a_bg.wasm","size":20}],"unpackedSize":30}]"#;
+    let publish = r#"{"a":{"files":[{"path":"a.js","size":10},{"path":"a_bg.wasm","size":20}]}}"#;
+    for json in [pack, publish] {
+        assert_eq!(
+            npm::TarballContents::parse(json).unwrap(),
+            npm::TarballContents {
+                files: vec![("a.js".to_string(), 10), ("a_bg.wasm".to_string(), 20)],
+                unpacked_size: 30,
+            }
+        );
+    }
+    assert_eq!(npm::format_size(999), "999 B");
+    assert_eq!(npm::format_size(1_234), "1.2 kB");
+    assert_eq!(npm::format_size(5_600_000), "5.6 MB");
+}
+
+#[test]
+fn publish_dry_run_fails_like_a_real_publish() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("200 OK", r#"{"versions":{"0.1.0":{}}}"#);
+    package_on(&fixture, &registry);
+
+    fixture
+        .wasm_pack()
+        .env("HOME", &fixture.path)
+        .env_remove("NPM_CONFIG_USERCONFIG")
+        .env_remove("npm_config_userconfig")
+        .arg("publish")
+        .arg("--dry-run")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("is already published"));
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_forwards_the_otp_and_explains_a_missing_one() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let bin = fixture.path.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let calls = fixture.path.join("calls");
+    // Like npm for an account with two-factor authentication.
+    fs::write(
+        bin.join("npm"),
+        format!(
+            r#"#!/bin/sh
+echo "npm $*" >> {}
+case "$*" in
+    *--otp*) exit 0 ;;
+esac
+echo "npm error code EOTP" >&2
+echo "npm error This operation requires a one-time password from your authenticator." >&2
+exit 1
+"#,
+            calls.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(bin.join("npm"), fs::Permissions::from_mode(0o755)).unwrap();
+    let publish = || {
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("publish");
+        cmd
+    };
+
+    // Without a terminal to ask on, the user is told what to do instead.
+    publish()
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Pass it with `--otp <code>`"))
+        .stderr(predicates::str::contains("automation token"));
+    assert_eq!(
+        fs::read_to_string(&calls).unwrap(),
+        "npm pack --dry-run --json\nnpm publish\n"
+    );
+
+    fs::remove_file(&calls).unwrap();
+    publish().arg("--otp").arg("123456").assert().success();
+    assert_eq!(
+        fs::read_to_string(&calls).unwrap(),
+        "npm pack --dry-run --json\nnpm publish --otp 123456\n"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_hints_at_access_for_a_scoped_package() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let bin = fixture.path.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(
+        bin.join("npm"),
+        "#!/bin/sh\necho 'npm error code E402' >&2\n\
+         echo 'npm error 402 Payment Required - PUT https://registry.npmjs.org/@org%2fjs-hello-world - You must sign up for private packages' >&2\nexit 1\n",
+    )
+    .unwrap();
+    fs::set_permissions(bin.join("npm"), fs::Permissions::from_mode(0o755)).unwrap();
+    let publish = |package_json: &str, args: &[&str]| {
+        fixture.file("pkg/package.json", package_json);
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("publish")
+            .args(args)
+            .assert()
+            .failure()
+    };
+    let hint = "@org/js-hello-world is a scoped package";
+
+    publish(
+        r#"{ "name": "@org/js-hello-world", "version": "0.1.0" }"#,
+        &[],
+    )
+    .stderr(predicates::str::contains(
+        "only publishes this package for a paid account",
+    ))
+    .stderr(predicates::str::contains(hint))
+    .stderr(predicates::str::contains("pass `--access public`"));
+    publish(
+        r#"{ "name": "@org/js-hello-world", "version": "0.1.0" }"#,
+        &["--access", "restricted"],
+    )
+    .stderr(predicates::str::contains(hint).not());
+    publish(
+        r#"{ "name": "@org/js-hello-world", "version": "0.1.0", "publishConfig": { "access": "public" } }"#,
+        &[],
+    )
+    .stderr(predicates::str::contains(hint).not());
+    publish(r#"{ "name": "js-hello-world", "version": "0.1.0" }"#, &[])
+        .stderr(predicates::str::contains("is a scoped package").not());
+}
+
+#[test]
+fn access_levels_other_than_public_and_restricted_are_rejected() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture
+        .wasm_pack()
+        .arg("publish")
+        .arg("--access")
+        .arg("everyone")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "everyone is not a supported access level, expected public or restricted",
+        ));
+    fixture
+        .wasm_pack()
+        .arg("publish")
+        .arg("--access")
+        .arg("private")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "npm calls it restricted: pass `--access restricted`",
+        ));
+}
+
+#[test]
+fn publish_failures_are_classified_from_stderr() {
+    use wasm_pack::npm::PublishFailure;
+
+    for (stderr, failure) in [
+        (
+            "npm ERR! code EOTP\nnpm ERR! This operation requires a one-time password.\n",
+            Some(PublishFailure::OneTimePassword),
+        ),
+        (
+            "npm error code EOTP\n\
+             npm error This operation requires a one-time password from your authenticator.\n\
+             npm error You can provide a one-time password by passing --otp=<code> to the \
+             command you ran.\n",
+            Some(PublishFailure::OneTimePassword),
+        ),
+        (
+            "npm error code ENEEDAUTH\nnpm error need auth\n",
+            Some(PublishFailure::Unauthorized { registry: None }),
+        ),
+        (
+            "npm error code ENEEDAUTH\n\
+             npm error need auth This command requires you to be logged in to \
+             https://npm.corp.example/\n\
+             npm error need auth You need to authorize this machine using `npm adduser`\n\
+             npm error A complete log of this run can be found in: \
+             /home/me/.npm/_logs/2024-05-01T10_00_00_000Z-debug-0.log\n",
+            Some(PublishFailure::Unauthorized {
+                registry: Some("https://npm.corp.example/".to_string()),
+            }),
+        ),
+        (
+            "npm ERR! code E401\nnpm ERR! 401 Unauthorized\n",
+            Some(PublishFailure::Unauthorized { registry: None }),
+        ),
+        (
+            "npm error code E403\nnpm error 403 Forbidden\n",
+            Some(PublishFailure::Forbidden { access_hint: None }),
+        ),
+        (
+            "npm notice Publishing to https://registry.npmjs.org/ with tag latest and default \
+             access\n\
+             npm error code E403\n\
+             npm error 403 403 Forbidden - PUT https://registry.npmjs.org/@org%2fjs-hello-world \
+             - You do not have permission to publish \"@org/js-hello-world\". Are you logged in \
+             as the correct user?\n",
+            Some(PublishFailure::Forbidden { access_hint: None }),
+        ),
+        (
+            "npm notice Publishing to https://registry.npmjs.org/ with tag latest and default \
+             access\n\
+             npm error code E402\n\
+             npm error 402 Payment Required - PUT https://registry.npmjs.org/@org%2fjs-hello-world \
+             - You must sign up for private packages\n",
+            Some(PublishFailure::PaymentRequired { access_hint: None }),
+        ),
+        (
+            "npm ERR! code E403\n\
+             npm ERR! 403 403 Forbidden - PUT https://registry.npmjs.org/js-hello-world - You \
+             cannot publish over the previously published versions: 0.1.0.\n",
+            Some(PublishFailure::AlreadyPublished),
+        ),
+        (
+            "npm ERR! publish fail Cannot publish over existing version.\n\
+             npm ERR! publish fail Update the 'version' field in package.json and try again.\n\
+             npm ERR! code EPUBLISHCONFLICT\n",
+            Some(PublishFailure::AlreadyPublished),
+        ),
+        (
+            "npm error code ENOTFOUND\n\
+             npm error syscall getaddrinfo\n\
+             npm error errno ENOTFOUND\n\
+             npm error network request to https://npm.corp.example/js-hello-world failed, \
+             reason: getaddrinfo ENOTFOUND npm.corp.example\n\
+             npm error network This is a problem related to network connectivity.\n",
+            Some(PublishFailure::Unreachable {
+                registry: Some("https://npm.corp.example/".to_string()),
+            }),
+        ),
+        (
+            "npm ERR! code ECONNREFUSED\n\
+             npm ERR! syscall connect\n\
+             npm ERR! errno ECONNREFUSED\n\
+             npm ERR! FetchError: request to http://localhost:4873/js-hello-world failed, \
+             reason: connect ECONNREFUSED 127.0.0.1:4873\n",
+            Some(PublishFailure::Unreachable {
+                registry: Some("http://localhost:4873/".to_string()),
+            }),
+        ),
+        ("npm error code E500\n", None),
+    ] {
+        assert_eq!(PublishFailure::classify(stderr), failure, "{}", stderr);
+    }
+
+    let message = PublishFailure::Unauthorized {
+        registry: Some("https://npm.corp.example/".to_string()),
+    }
+    .to_string();
+    assert!(
+        message.contains("`wasm-pack login --registry https://npm.corp.example/`"),
+        "{}",
+        message
+    );
+    assert!(PublishFailure::AlreadyPublished
+        .to_string()
+        .contains("--bump patch"));
+}
+
+#[test]
+fn dist_tags_that_npm_refuses_are_rejected() {
+    for tag in ["next", "beta-2", "canary.1", "v2-preview"] {
+        assert_eq!(npm::parse_dist_tag(tag).unwrap(), tag);
+    }
+    for tag in ["1.2.3", "v1.2.3", "1", "1.x", "^1.2", "*", "x"] {
+        let error = npm::parse_dist_tag(tag).unwrap_err().to_string();
+        assert!(error.contains("looks like a version"), "{}: {}", tag, error);
+    }
+    for tag in ["", "with space", "a/b"] {
+        assert!(npm::parse_dist_tag(tag).is_err(), "{:?}", tag);
+    }
+
+    let fixture = utils::fixture::js_hello_world();
+    fixture
+        .wasm_pack()
+        .arg("publish")
+        .arg("--tag")
+        .arg("2.0.0")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "the dist-tag \"2.0.0\" looks like a version",
+        ));
+}
+
+#[test]
+fn unknown_package_managers_are_rejected() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture
+        .wasm_pack()
+        .arg("pack")
+        .arg("--package-manager")
+        .arg("bun")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "bun is not a supported package manager, expected npm, yarn or pnpm",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn registry_is_forwarded_to_pack_publish_and_login() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let bin = install_package_manager_stubs(&fixture);
+    let calls = fixture.path.join("calls");
+
+    let run = |args: &[&str]| {
+        let _ = fs::remove_file(&calls);
+        fixture
+            .wasm_pack()
+            .env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env("YARN_STUB_VERSION", "1.22.19")
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .args(args)
+            .assert()
+            .success();
+        fs::read_to_string(&calls).unwrap()
+    };
+
+    assert_eq!(
+        run(&["pack", "--registry", &registry]),
+        format!("npm pack --registry {}\n", registry)
+    );
+    assert_eq!(
+        run(&["publish", "--registry", &registry, "--tag", "next"]),
+        format!(
+            "npm pack --dry-run --json\nnpm publish --registry {} --tag next\n",
+            registry
+        )
+    );
+    assert_eq!(
+        run(&["login", "--registry", &registry, "--scope", "corp"]),
+        format!("npm login --registry={} --scope=corp\n", registry)
+    );
+
+    // Without `--registry`, the one in `publishConfig` is passed on.
+    fixture.file(
+        "pkg/package.json",
+        format!(
+            r#"{{ "name": "js-hello-world", "version": "0.1.0", "publishConfig": {{ "registry": "{}" }} }}"#,
+            registry
+        ),
+    );
+    assert_eq!(
+        run(&["pack", "--package-manager", "pnpm"]),
+        format!("pnpm pack --registry {}\n", registry)
+    );
+    assert_eq!(
+        run(&["publish", "--package-manager", "yarn"]),
+        format!("yarn publish --non-interactive --registry {}\n", registry)
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn login_works_in_a_virtual_workspace_root() {
+    let fixture = utils::fixture::Fixture::new();
+    fixture.file("Cargo.toml", "[workspace]\nmembers = []\n");
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    let bin = install_package_manager_stubs(&fixture);
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("HOME", &fixture.path)
+        .env_remove("NPM_CONFIG_USERCONFIG")
+        .env_remove("npm_config_userconfig")
+        .arg("login")
+        .arg("--registry")
+        .arg(&registry)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(fixture.path.join("calls")).unwrap(),
+        format!("npm login --registry={}\n", registry)
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn pack_moves_the_tarball_to_out_and_prints_where_it_is() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let bin = install_package_manager_stubs(&fixture);
+    let fixture_path = fixture.path.canonicalize().unwrap();
+
+    let pack = |args: &[&str]| {
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env("NPM_STUB_TARBALL", "js-hello-world-0.1.0.tgz")
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("pack")
+            .args(args);
+        cmd.assert()
+    };
+
+    // Into a directory, created if it doesn't exist yet.
+    let tarball = fixture_path.join("dist").join("js-hello-world-0.1.0.tgz");
+    pack(&["--out", "dist/"])
+        .success()
+        .stdout(format!("{}\n", tarball.display()));
+    assert!(tarball.is_file());
+    assert!(!fixture.path.join("pkg/js-hello-world-0.1.0.tgz").exists());
+
+    // To a file, which isn't overwritten without `--force`.
+    let renamed = fixture_path.join("dist").join("release.tgz");
+    pack(&["--out", "dist/release.tgz"])
+        .success()
+        .stdout(format!("{}\n", renamed.display()));
+    assert!(renamed.is_file());
+    pack(&["--out", "dist"])
+        .failure()
+        .stderr(predicates::str::contains("already exists; pass `--force`"));
+    pack(&["--out", "dist", "--force"])
+        .success()
+        .stdout(format!("{}\n", tarball.display()));
+}
+
+/// A crate that builds with a stand-in wasm-bindgen, with the package
+/// manager stubs next to it on the `$PATH`.
+#[cfg(unix)]
+fn buildable_fixture() -> (utils::fixture::Fixture, std::path::PathBuf) {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let manifest = fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap();
+    fixture.file(
+        "Cargo.toml",
+        format!(
+            "{}\n[package.metadata.wasm-pack.profile.release]\nwasm-opt = false\n",
+            manifest
+        ),
+    );
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let stubs = install_package_manager_stubs(&fixture);
+    fs::copy(stubs.join("npm"), bin.join("npm")).unwrap();
+    (fixture, bin)
+}
+
+#[test]
+#[cfg(unix)]
+fn pack_builds_a_missing_pkg_directory_first() {
+    let (fixture, bin) = buildable_fixture();
+    let pack = |args: &[&str]| {
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .arg("pack")
+            .args(args);
+        cmd.assert()
+    };
+
+    pack(&["--no-build"])
+        .failure()
+        .stderr(predicates::str::contains(
+            "Unable to find the pkg directory",
+        ));
+    assert!(!fixture.path.join("calls").exists());
+
+    pack(&["--target", "nodejs"])
+        .success()
+        .stderr(predicates::str::contains(
+            "hasn't been built yet, building it first with `wasm-pack build --target nodejs`",
+        ));
+    assert!(fixture.path.join("pkg/package.json").is_file());
+    assert_eq!(
+        fs::read_to_string(fixture.path.join("calls")).unwrap(),
+        "npm pack\n"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn pack_leaves_a_fresh_pkg_directory_alone_and_notices_a_stale_one() {
+    let (fixture, bin) = buildable_fixture();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let pack = |args: &[&str]| {
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .arg("pack")
+            .args(args);
+        cmd.assert()
+    };
+
+    // Written after the sources, so it is up to date.
+    pack(&["--target", "nodejs"])
+        .success()
+        .stderr(predicates::str::contains("built").not());
+
+    let lib = fs::File::options()
+        .append(true)
+        .open(fixture.path.join("src/lib.rs"))
+        .unwrap();
+    lib.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+        .unwrap();
+    pack(&["--no-build"])
+        .success()
+        .stderr(predicates::str::contains("changed since").not());
+    // It wasn't written by `wasm-pack build`, so it isn't known how to
+    // rebuild it.
+    pack(&["--target", "nodejs"])
+        .success()
+        .stderr(predicates::str::contains("changed since"))
+        .stderr(predicates::str::contains("may be out of date"))
+        .stderr(predicates::str::contains("rebuilding").not());
+
+    // Once it is, it is rebuilt the way it was built.
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--target")
+        .arg("nodejs")
+        .arg("--no-typescript")
+        .assert()
+        .success();
+    lib.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(120))
+        .unwrap();
+    pack(&[]).success().stderr(predicates::str::contains(
+        "rebuilding it with `wasm-pack build --target nodejs --no-typescript`",
+    ));
+    let package_json = fs::read_to_string(fixture.path.join("pkg/package.json")).unwrap();
+    assert!(
+        package_json.contains("js_hello_world.js") && !package_json.contains(".d.ts"),
+        "{}",
+        package_json
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn pack_takes_the_package_directory_to_pack() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    for dir in ["pkg-web", "pkg-node", "artifacts/pkg"] {
+        fixture.file(
+            format!("{}/package.json", dir),
+            r#"{ "name": "js-hello-world", "version": "0.1.0" }"#,
+        );
+        fixture.file(format!("{}/js_hello_world_bg.wasm", dir), "");
+    }
+    fixture.file(
+        "no-wasm/package.json",
+        r#"{ "name": "no-wasm", "version": "0.1.0" }"#,
+    );
+    fs::create_dir_all(fixture.path.join("empty")).unwrap();
+    let bin = install_package_manager_stubs(&fixture);
+    let fixture_path = fixture.path.canonicalize().unwrap();
+    let pack = |args: &[&str]| {
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env("NPM_STUB_TARBALL", "js-hello-world-0.1.0.tgz")
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("pack")
+            .args(args);
+        cmd.assert()
+    };
+
+    pack(&["--pkg-dir", "pkg-node"])
+        .success()
+        .stderr(predicates::str::contains(format!(
+            "Using the package in {}",
+            fixture_path.join("pkg-node").display()
+        )));
+    assert!(fixture
+        .path
+        .join("pkg-node/js-hello-world-0.1.0.tgz")
+        .is_file());
+    assert!(!fixture.path.join("pkg/js-hello-world-0.1.0.tgz").exists());
+
+    // A package restored from elsewhere, given as the path.
+    pack(&["artifacts/pkg"])
+        .success()
+        .stderr(predicates::str::contains(format!(
+            "Using the package in {}",
+            fixture_path.join("artifacts/pkg").display()
+        )));
+    assert!(fixture
+        .path
+        .join("artifacts/pkg/js-hello-world-0.1.0.tgz")
+        .is_file());
+
+    // With the crate given as the path, it is found in the crate rather than
+    // the current directory.
+    let outside = fixture.path.parent().unwrap().join("pkg-web");
+    fs::create_dir_all(&outside).unwrap();
+    fs::write(
+        outside.join("package.json"),
+        r#"{ "name": "outside", "version": "0.1.0" }"#,
+    )
+    .unwrap();
+    let mut cmd = fixture.wasm_pack();
+    cmd.current_dir(fixture.path.parent().unwrap())
+        .env("PATH", fixture.path_with(&bin))
+        .env("HOME", &fixture.path)
+        .env("NPM_STUB_TARBALL", "js-hello-world-0.1.0.tgz")
+        .env_remove("NPM_CONFIG_USERCONFIG")
+        .env_remove("npm_config_userconfig")
+        .arg("pack")
+        .arg("wasm-pack")
+        .arg("--pkg-dir")
+        .arg("pkg-web")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(format!(
+            "Using the package in {}",
+            fixture_path.join("pkg-web").display()
+        )));
+
+    pack(&["--pkg-dir", "no-wasm"])
+        .success()
+        .stderr(predicates::str::contains(
+            "no-wasm has no .wasm file, so it may not be a package built by wasm-pack",
+        ));
+    pack(&["--pkg-dir", "./empty"])
+        .failure()
+        .stderr(predicates::str::contains(
+            "empty has no package.json, so it isn't a package built by wasm-pack",
+        ));
+}
+
+#[test]
+fn registries_without_a_scheme_are_rejected() {
+    let fixture = utils::fixture::js_hello_world();
+    for command in ["pack", "publish", "login"] {
+        fixture
+            .wasm_pack()
+            .arg(command)
+            .arg("--registry")
+            .arg("localhost:4873")
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains(
+                "the registry \"localhost:4873\" needs an `http://` or `https://` scheme",
+            ));
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn package_managers_build_their_own_command_lines() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let bin = install_package_manager_stubs(&fixture);
+    let calls = fixture.path.join("calls");
+    let manifest = fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap();
+
+    let mut snapshot = String::new();
+    for (package_manager, yarn_version) in [
+        ("npm", "1.22.19"),
+        ("yarn", "1.22.19"),
+        ("yarn", "4.1.0"),
+        ("pnpm", "1.22.19"),
+    ] {
+        // Chosen in the `Cargo.toml`, as a monorepo would.
+        fixture.file(
+            "Cargo.toml",
+            format!(
+                "{}\n[package.metadata.wasm-pack]\npackage-manager = \"{}\"\n",
+                manifest, package_manager
+            ),
+        );
+        let _ = fs::remove_file(&calls);
+        for args in [
+            &["pack"][..],
+            &["publish", "--tag", "next"][..],
+            &["login", "--registry", "https://npm.corp.example/"][..],
+        ] {
+            fixture
+                .wasm_pack()
+                .env("PATH", fixture.path_with(&bin))
+                .env("HOME", &fixture.path)
+                .env("YARN_STUB_VERSION", yarn_version)
+                .env_remove("NPM_CONFIG_USERCONFIG")
+                .env_remove("npm_config_userconfig")
+                .args(args)
+                .assert()
+                .success();
+        }
+        snapshot.push_str(&fs::read_to_string(&calls).unwrap());
+    }
+    assert_eq!(
+        snapshot,
+        "npm pack\n\
+         npm pack --dry-run --json\n\
+         npm publish --tag next\n\
+         npm login --registry=https://npm.corp.example/\n\
+         yarn pack\n\
+         yarn publish --non-interactive --tag next\n\
+         yarn login --registry https://npm.corp.example/\n\
+         yarn pack\n\
+         yarn npm publish --tag next\n\
+         yarn npm login\n\
+         pnpm pack\n\
+         pnpm publish --no-git-checks --tag next\n\
+         pnpm login --registry=https://npm.corp.example/\n"
+    );
+
+    // `--pkg-manager` wins over the `Cargo.toml`.
+    let _ = fs::remove_file(&calls);
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("YARN_STUB_VERSION", "1.22.19")
+        .arg("pack")
+        .arg("--pkg-manager")
+        .arg("npm")
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&calls).unwrap(), "npm pack\n");
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("YARN_STUB_VERSION", "1.22.19")
+        .arg("login")
+        .arg("--pkg-manager")
+        .arg("yarn")
+        .arg("--scope")
+        .arg("corp")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Yarn 1 can't log in for a scope"));
+}
+
+#[test]
+fn a_missing_package_manager_explains_how_to_get_it() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.file(
+        "pkg/package.json",
+        r#"{ "name": "js-hello-world", "version": "0.1.0" }"#,
+    );
+    let empty = fixture.path.join("empty");
+    fs::create_dir_all(&empty).unwrap();
+
+    fixture
+        .wasm_pack()
+        .env("PATH", &empty)
+        .arg("pack")
+        .arg("--package-manager")
+        .arg("pnpm")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "pnpm isn't on the $PATH. Enable it with `corepack enable`",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn package_manager_executables_are_found_as_cmd_scripts_too() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = utils::fixture::Fixture::new();
+    let bin = fixture.path.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let paths = || Some(bin.clone().into_os_string());
+    let install = |name: &str| {
+        let path = bin.join(name);
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    };
+
+    assert_eq!(npm::find_executable("npm", paths()), None);
+    install("npm.exe");
+    assert_eq!(
+        npm::find_executable("npm", paths()),
+        Some(bin.join("npm.exe"))
+    );
+    install("npm.cmd");
+    assert_eq!(
+        npm::find_executable("npm", paths()),
+        Some(bin.join("npm.cmd"))
+    );
+    install("npm");
+    assert_eq!(npm::find_executable("npm", paths()), Some(bin.join("npm")));
+}
+
+#[test]
+fn a_missing_npm_points_at_node_js() {
+    let fixture = utils::fixture::js_hello_world();
+    let empty = fixture.path.join("empty");
+    fs::create_dir_all(&empty).unwrap();
+
+    fixture
+        .wasm_pack()
+        .env("PATH", &empty)
+        .arg("login")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "npm isn't on the $PATH. Install Node.js, which comes with npm, from https://nodejs.org/",
+        ));
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_workspace_publishes_the_members_in_dependency_order() {
+    use std::path::Path;
+
+    let fixture = utils::fixture::Fixture::new();
+    fixture.file(
+        "Cargo.toml",
+        r#"
+            [workspace]
+            members = ["alpha", "beta", "gamma"]
+        "#,
+    );
+    for (name, dependencies) in [
+        ("alpha", ""),
+        ("beta", "gamma = { path = \"../gamma\" }"),
+        ("gamma", ""),
+    ] {
+        fixture
+            .file(
+                Path::new(name).join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[lib]\n\
+                     crate-type = [\"cdylib\", \"rlib\"]\n\n[dependencies]\n{}\n",
+                    name, dependencies
+                ),
+            )
+            .file(Path::new(name).join("src/lib.rs"), "");
+    }
+    // alpha hasn't been built, so with `--no-build` it fails to publish.
+    for name in ["beta", "gamma"] {
+        fixture.file(
+            Path::new(name).join("pkg/package.json"),
+            format!(r#"{{ "name": "{}", "version": "0.1.0" }}"#, name),
+        );
+    }
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    let host = registry.trim_start_matches("http:");
+    fixture.file(
+        ".npmrc",
+        format!("registry={}\n{}:_authToken=secret\n", registry, host),
+    );
+    let bin = install_package_manager_stubs(&fixture);
+    let calls = fixture.path.join("calls");
+    let publish = |args: &[&str]| {
+        let _ = fs::remove_file(&calls);
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("publish")
+            .arg("--no-build")
+            .arg("--tag")
+            .arg("next")
+            .args(args);
+        cmd.assert()
+    };
+    let published = || {
+        fs::read_to_string(&calls)
+            .unwrap_or_default()
+            .lines()
+            .filter(|call| call.starts_with("npm publish"))
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+
+    let output = publish(&["--workspace"])
+        .failure()
+        .stderr(predicates::str::contains("alpha failed"))
+        .stderr(predicates::str::contains("gamma published gamma@0.1.0"))
+        .stderr(predicates::str::contains("beta  published beta@0.1.0"))
+        .stderr(predicates::str::contains("Failed to publish alpha."))
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    let position = |name: &str| stderr.find(&format!("Publishing {}...", name)).unwrap();
+    assert!(position("alpha") < position("gamma"), "{}", stderr);
+    assert!(position("gamma") < position("beta"), "{}", stderr);
+    assert_eq!(
+        published(),
+        ["npm publish --tag next", "npm publish --tag next"]
+    );
+
+    publish(&["--workspace", "--fail-fast"])
+        .failure()
+        .stderr(predicates::str::contains("Failed to publish alpha"));
+    assert!(published().is_empty());
+
+    publish(&["-p", "beta", "-p", "gamma", "--dry-run"])
+        .success()
+        .stderr(predicates::str::contains(
+            "gamma [dry run] would publish gamma@0.1.0",
+        ))
+        .stderr(predicates::str::contains(
+            "beta  [dry run] would publish beta@0.1.0",
+        ));
+    assert_eq!(
+        published(),
+        [
+            "npm publish --tag next --dry-run",
+            "npm publish --tag next --dry-run"
+        ]
+    );
+
+    publish(&["-p", "delta"])
+        .failure()
+        .stderr(predicates::str::contains(
+            "package `delta` is not a member of the workspace",
+        ));
+}
+
+#[test]
+fn login_with_a_token_checks_and_saves_it_without_echoing_it() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("200 OK", r#"{"username":"ci-bot"}"#);
+    let host = registry.trim_start_matches("http:");
+    fixture.file(
+        "home/.npmrc",
+        format!("{}:_authToken=stale\nsave-exact=true\n", host),
+    );
+    let log_file = fixture.path.join("wasm-pack.log");
+
+    fixture
+        .wasm_pack()
+        .env("HOME", fixture.path.join("home"))
+        .env_remove("NPM_CONFIG_USERCONFIG")
+        .env_remove("npm_config_userconfig")
+        .env("NPM_TOKEN", "npm_s3cr3t")
+        .arg("--log-file")
+        .arg(&log_file)
+        .arg("login")
+        .arg("--registry")
+        .arg(&registry)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("as ci-bot"))
+        .stderr(predicates::str::contains("npm_s3cr3t").not());
+    assert_eq!(
+        fs::read_to_string(fixture.path.join("home/.npmrc")).unwrap(),
+        format!("{}:_authToken=npm_s3cr3t\nsave-exact=true\n", host)
+    );
+    assert!(!fs::read_to_string(&log_file)
+        .unwrap()
+        .contains("npm_s3cr3t"));
+
+    let registry = mock_registry("401 Unauthorized", "{}");
+    fixture
+        .wasm_pack()
+        .arg("login")
+        .arg("--project")
+        .arg("--token")
+        .arg("expired")
+        .arg("--registry")
+        .arg(&registry)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("didn't accept the token"));
+    assert!(!fixture.path.join(".npmrc").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn publish_passes_provenance_and_npm_args_through() {
+    let fixture = utils::fixture::js_hello_world();
+    let registry = mock_registry("404 Not Found", r#"{"error":"Not found"}"#);
+    package_on(&fixture, &registry);
+    let bin = install_package_manager_stubs(&fixture);
+    let calls = fixture.path.join("calls");
+
+    let publish = |args: &[&str], npm_version: &str| {
+        let _ = fs::remove_file(&calls);
+        let mut cmd = fixture.wasm_pack();
+        cmd.env("PATH", fixture.path_with(&bin))
+            .env("HOME", &fixture.path)
+            .env("YARN_STUB_VERSION", npm_version)
+            .env_remove("NPM_CONFIG_USERCONFIG")
+            .env_remove("npm_config_userconfig")
+            .arg("publish")
+            .args(args);
+        cmd
+    };
+
+    publish(
+        &[
+            "--tag",
+            "next",
+            "--provenance",
+            "--npm-arg",
+            "--foreground-scripts",
+            "--npm-arg=--ignore-scripts=false",
+        ],
+        "10.8.2",
+    )
+    .assert()
+    .success();
+    assert_eq!(
+        fs::read_to_string(&calls).unwrap(),
+        "npm pack --dry-run --json\n\
+         npm publish --tag next --provenance --foreground-scripts --ignore-scripts=false\n"
+    );
+
+    publish(&["--provenance"], "9.4.0")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "needs npm 9.5.0 or later, but npm 9.4.0 is installed",
+        ));
+    assert!(!fs::read_to_string(&calls).unwrap().contains("npm publish"));
+
+    publish(&["--npm-arg", "--tag=next"], "10.8.2")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "use `wasm-pack publish --tag` instead of `--npm-arg --tag=next`",
+        ));
+
+    assert_eq!(
+        npm::PublishFailure::classify(
+            "npm error code EUSAGE\nnpm error Unknown option: --provenance\n"
+        ),
+        Some(npm::PublishFailure::ProvenanceUnsupported)
+    );
+}
@@ -133,3 +133,61 @@ fn it_ignores_a_disabled_readme() {
         .success()
         .stderr(predicates::str::contains("origin crate has no README").not());
 }
+
+#[test]
+#[cfg(unix)]
+fn it_writes_a_readme_from_the_crate_docs() {
+    let fixture = fixture::Fixture::new();
+    fixture
+        .cargo_toml("js-hello-world")
+        .wasm_bindgen_version("0.2.95")
+        .license()
+        .file(
+            "src/lib.rs",
+            r#"
+                //! # Hello world
+                //!
+                //! Greets people through [`greet`], see [the docs](crate::greet).
+                //!
+                //! ```
+                //! js_hello_world::greet("you");
+                //! ```
+                /*!
+                 * Also in a block.
+                 */
+
+                extern crate wasm_bindgen;
+                use wasm_bindgen::prelude::*;
+
+                /// Not a crate doc.
+                #[wasm_bindgen]
+                pub fn greet(name: &str) -> String {
+                    format!("Hello, {}!", name)
+                }
+            "#,
+        );
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .arg("build")
+        .arg("--no-opt")
+        .arg("--readme-from-docs")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("origin crate has no README").not());
+
+    let readme = fs::read_to_string(fixture.path.join("pkg/README.md")).unwrap();
+    assert_eq!(
+        readme,
+        "# Hello world\n\
+         \n\
+         Greets people through [`greet`], see [the docs](crate::greet).\n\
+         \n\
+         ```\n\
+         js_hello_world::greet(\"you\");\n\
+         ```\n\
+         \n\
+         Also in a block.\n"
+    );
+}
@@ -274,7 +274,7 @@ impl Fixture {
                 return Ok(download);
             }
 
-            install::cargo_install(Tool::WasmBindgen, &cache, version, true)
+            install::cargo_install(Tool::WasmBindgen, &cache, version, true, &[], None, None)
         };
 
         // Only one thread can perform the actual download, and then afterwards
@@ -289,6 +289,140 @@ impl Fixture {
         }
     }
 
+    /// Pin the fixture's `wasm-bindgen` dependency to `version` instead of the
+    /// one written by `cargo_toml`.
+    pub fn wasm_bindgen_version(&self, version: &str) -> &Self {
+        let manifest = fs::read_to_string(self.path.join("Cargo.toml")).unwrap();
+        self.file(
+            "Cargo.toml",
+            manifest.replace(
+                r#"wasm-bindgen = "=0.2.74""#,
+                &format!(r#"wasm-bindgen = "={}""#, version),
+            ),
+        )
+    }
+
+    /// Install a stand-in `wasm-bindgen` for this fixture that reports
+    /// `version`, appends its arguments to `wasm-bindgen.log` in the fixture
+    /// directory and writes placeholder bindings.
+    ///
+    /// Returns the directory containing it, to be put at the front of `PATH`.
+    #[cfg(unix)]
+    pub fn install_fake_wasm_bindgen(&self, version: &str) -> PathBuf {
+        let bin = self.path.join("fake-bin");
+        fs::create_dir_all(&bin).unwrap();
+        write_fake_tool(
+            &bin.join("wasm-bindgen"),
+            &format!(
+                r#"if [ "$1" = "--version" ]; then
+    echo "wasm-bindgen {version}"
+    exit 0
+fi
+echo "$@" >> "{log}"
+wasm="$1"
+name=$(basename "$wasm" .wasm)
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --out-dir) out="$2"; shift ;;
+        --out-name) name="$2"; shift ;;
+        --target) target="$2"; shift ;;
+    esac
+    shift
+done
+cp "$wasm" "$out/${{name}}_bg.wasm"
+echo "export function greet() {{}}" > "$out/$name.js"
+case "$target" in
+    web) echo "const url = new URL('${{name}}_bg.wasm', import.meta.url);" >> "$out/$name.js" ;;
+    no-modules) echo "const url = script_src.replace(/\.js$/, '_bg.wasm');" >> "$out/$name.js" ;;
+esac
+echo "export function greet(): void;" > "$out/$name.d.ts"
+if [ "$target" = web ]; then
+    printf 'async function __wbg_init(module_or_path) {{}}\nexport default __wbg_init;\n' >> "$out/$name.js"
+    echo "export default function __wbg_init (module_or_path?: string): Promise<void>;" >> "$out/$name.d.ts"
+fi
+"#,
+                version = version,
+                log = self.path.join("wasm-bindgen.log").display(),
+            ),
+        );
+        bin
+    }
+
+    /// Install a stand-in `cargo` whose `cargo install` puts the fake
+    /// `wasm-bindgen` from `install_fake_wasm_bindgen` into `--root` instead
+    /// of building anything. Every other subcommand goes to the real `cargo`.
+    /// The arguments of `cargo install` are written to `cargo-install.log` in
+    /// the fixture.
+    ///
+    /// Returns the directory containing it, to be put at the front of `PATH`.
+    #[cfg(unix)]
+    pub fn install_fake_cargo_install(&self, version: &str) -> PathBuf {
+        let wasm_bindgen = self.install_fake_wasm_bindgen(version).join("wasm-bindgen");
+        let bin = self.path.join("fake-cargo");
+        fs::create_dir_all(&bin).unwrap();
+        write_fake_tool(
+            &bin.join("cargo"),
+            &format!(
+                r#"if [ "$1" = "install" ]; then
+    echo "$@" > "{log}"
+    while [ $# -gt 0 ]; do
+        case "$1" in
+            --root) root="$2"; shift ;;
+        esac
+        shift
+    done
+    mkdir -p "$root/bin"
+    cp "{wasm_bindgen}" "$root/bin/wasm-bindgen"
+    cp "{wasm_bindgen}" "$root/bin/wasm-bindgen-test-runner"
+    echo "fake cargo install finished" >&2
+    exit 0
+fi
+exec "{cargo}" "$@"
+"#,
+                wasm_bindgen = wasm_bindgen.display(),
+                log = self.path.join("cargo-install.log").display(),
+                cargo = env!("CARGO"),
+            ),
+        );
+        bin
+    }
+
+    /// Get a `PATH` with `dir` in front of the current one.
+    pub fn path_with(&self, dir: &Path) -> std::ffi::OsString {
+        let mut paths = vec![dir.to_path_buf()];
+        paths.extend(env::split_paths(&env::var_os("PATH").unwrap_or_default()));
+        env::join_paths(paths).unwrap()
+    }
+
+    /// Get a `PATH` with `dir` in front of the current one, on which `tool`
+    /// can't be found. Directories of the current one that have `tool` are
+    /// replaced by ones in the fixture linking to everything else in them.
+    #[cfg(unix)]
+    pub fn path_without(&self, dir: &Path, tool: &str) -> std::ffi::OsString {
+        let mut paths = vec![dir.to_path_buf()];
+        for (i, path) in env::split_paths(&env::var_os("PATH").unwrap_or_default()).enumerate() {
+            if !path.join(tool).exists() {
+                paths.push(path);
+                continue;
+            }
+            let without = self
+                .path
+                .join(format!("path-without-{}", tool))
+                .join(i.to_string());
+            fs::create_dir_all(&without).unwrap();
+            for entry in fs::read_dir(&path).unwrap().filter_map(|entry| entry.ok()) {
+                if entry.file_name() != tool {
+                    drop(std::os::unix::fs::symlink(
+                        entry.path(),
+                        without.join(entry.file_name()),
+                    ));
+                }
+            }
+            paths.push(without);
+        }
+        env::join_paths(paths).unwrap()
+    }
+
     pub fn install_wasm_opt(&self) {
         static INSTALL_WASM_OPT: Once = Once::new();
         let cache = self.cache();
@@ -313,7 +447,7 @@ impl Fixture {
                 return Ok(download);
             }
 
-            install::cargo_install(Tool::CargoGenerate, &cache, "latest", true)
+            install::cargo_install(Tool::CargoGenerate, &cache, "latest", true, &[], None, None)
         };
 
         // Only one thread can perform the actual download, and then afterwards
@@ -338,9 +472,9 @@ impl Fixture {
 
         // like above for synchronization
         FETCH_GECKODRIVER.call_once(|| {
-            wasm_pack::test::webdriver::install_geckodriver(&cache, true).unwrap();
+            wasm_pack::test::webdriver::install_geckodriver(&cache, true, None).unwrap();
         });
-        wasm_pack::test::webdriver::install_geckodriver(&cache, true).unwrap()
+        wasm_pack::test::webdriver::install_geckodriver(&cache, true, None).unwrap()
     }
 
     /// Download `chromedriver` and return its path.
@@ -353,9 +487,9 @@ impl Fixture {
 
         // like above for synchronization
         FETCH_CHROMEDRIVER.call_once(|| {
-            wasm_pack::test::webdriver::install_chromedriver(&cache, true).unwrap();
+            wasm_pack::test::webdriver::install_chromedriver(&cache, true, None, None).unwrap();
         });
-        wasm_pack::test::webdriver::install_chromedriver(&cache, true).unwrap()
+        wasm_pack::test::webdriver::install_chromedriver(&cache, true, None, None).unwrap()
     }
 
     pub fn cache_dir(&self) -> PathBuf {
@@ -410,6 +544,16 @@ impl Fixture {
     }
 }
 
+/// Write a stand-in for a tool to `path`: a shell script running `script`,
+/// made executable.
+#[cfg(unix)]
+pub fn write_fake_tool(path: &Path, script: &str) {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::write(path, format!("#!/bin/sh\n{}", script)).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
 impl Drop for Fixture {
     fn drop(&mut self) {
         if !thread::panicking() {
@@ -509,6 +653,50 @@ pub fn serde_feature() -> Fixture {
     fixture
 }
 
+pub fn required_features() -> Fixture {
+    let fixture = Fixture::new();
+    fixture
+        .readme()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                authors = ["The wasm-pack developers"]
+                description = "so awesome rust+wasm package"
+                license = "WTFPL"
+                name = "required-features"
+                repository = "https://github.com/rustwasm/wasm-pack.git"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["cdylib"]
+                required-features = ["wasm"]
+
+                [features]
+                wasm = []
+
+                [dependencies]
+                wasm-bindgen = "=0.2.95"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[cfg(not(feature = "wasm"))]
+                compile_error!("the `wasm` feature is required");
+
+                extern crate wasm_bindgen;
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn answer() -> u32 {
+                    42
+                }
+            "#,
+        );
+    fixture
+}
+
 pub fn wbg_test_diff_versions() -> Fixture {
     let fixture = Fixture::new();
     fixture
@@ -626,6 +814,141 @@ pub fn wbg_test_node() -> Fixture {
     fixture
 }
 
+pub fn wbg_test_features() -> Fixture {
+    let fixture = Fixture::new();
+    fixture
+        .readme()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                authors = ["The wasm-pack developers"]
+                description = "so awesome rust+wasm package"
+                license = "WTFPL"
+                name = "wbg-test-features"
+                repository = "https://github.com/rustwasm/wasm-pack.git"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["cdylib", "rlib"]
+
+                [features]
+                extra = []
+
+                [dependencies]
+                wasm-bindgen = "=0.2.95"
+
+                [dev-dependencies]
+                wasm-bindgen-test = "0.3"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                extern crate wasm_bindgen;
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn greet() -> u32 {
+                    1
+                }
+
+                #[cfg(feature = "extra")]
+                pub mod extra {
+                    pub fn answer() -> u32 {
+                        42
+                    }
+                }
+            "#,
+        )
+        .file(
+            "tests/node.rs",
+            r#"
+                extern crate wasm_bindgen_test;
+                use wasm_bindgen_test::*;
+
+                // Only compiles with the `extra` feature.
+                #[wasm_bindgen_test]
+                fn answer() {
+                    assert_eq!(wbg_test_features::extra::answer(), 42);
+                }
+            "#,
+        );
+    fixture
+}
+
+/// A workspace whose `alpha` and `beta` members have tests, each launching
+/// Chrome with its own argument, and whose `gamma` member has none.
+pub fn wbg_test_workspace() -> Fixture {
+    let fixture = Fixture::new();
+    fixture.file(
+        "Cargo.toml",
+        r#"
+            [workspace]
+            members = ["alpha", "beta", "gamma"]
+        "#,
+    );
+    for name in ["alpha", "beta", "gamma"] {
+        let dev_dependencies = if name == "gamma" {
+            ""
+        } else {
+            "[dev-dependencies]\nwasm-bindgen-test = \"0.3\""
+        };
+        fixture
+            .file(
+                Path::new(name).join("Cargo.toml"),
+                format!(
+                    r#"
+                        [package]
+                        authors = ["The wasm-pack developers"]
+                        description = "so awesome rust+wasm package"
+                        license = "WTFPL"
+                        name = "{name}"
+                        repository = "https://github.com/rustwasm/wasm-pack.git"
+                        version = "0.1.0"
+
+                        [lib]
+                        crate-type = ["cdylib"]
+
+                        [dependencies]
+                        wasm-bindgen = "=0.2.95"
+
+                        [package.metadata.wasm-pack.test.chrome]
+                        args = ["--{name}-flag"]
+
+                        {dev_dependencies}
+                    "#,
+                    name = name,
+                    dev_dependencies = dev_dependencies,
+                ),
+            )
+            .file(Path::new(name).join("README.md"), format!("# {}\n", name))
+            .file(
+                Path::new(name).join("src/lib.rs"),
+                r#"
+                    extern crate wasm_bindgen;
+                    use wasm_bindgen::prelude::*;
+
+                    #[wasm_bindgen]
+                    pub fn hello() -> u32 { 42 }
+                "#,
+            );
+        if name != "gamma" {
+            fixture.file(
+                Path::new(name).join("tests").join(format!("{}.rs", name)),
+                r#"
+                    extern crate wasm_bindgen_test;
+                    use wasm_bindgen_test::*;
+
+                    #[wasm_bindgen_test]
+                    fn pass() {}
+                "#,
+            );
+        }
+    }
+    fixture
+}
+
 pub fn transitive_dependencies() -> Fixture {
     fn project_main_fixture(fixture: &mut Fixture) {
         fixture.file(PathBuf::from("main/README"), "# Main Fixture\n");
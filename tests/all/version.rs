@@ -0,0 +1,112 @@
+use crate::utils;
+use assert_cmd::prelude::*;
+use std::fs;
+use wasm_pack::command::publish::bump::{Bump, NewVersion};
+
+#[test]
+fn new_versions_are_bumps_or_exact_versions() {
+    let version = semver::Version::parse("1.2.3").unwrap();
+    for (new, expected) in [
+        ("patch", "1.2.4"),
+        ("minor", "1.3.0"),
+        ("major", "2.0.0"),
+        ("prerelease", "1.2.4-0"),
+        ("3.0.0-rc.1", "3.0.0-rc.1"),
+        ("v3.1.0", "3.1.0"),
+    ] {
+        let new: NewVersion = new.parse().unwrap();
+        assert_eq!(new.apply(&version).to_string(), expected);
+    }
+    for (pre, expected) in [
+        ("1.2.4-beta.1", "1.2.4-beta.2"),
+        ("1.2.4-beta", "1.2.4-beta.0"),
+    ] {
+        let pre = semver::Version::parse(pre).unwrap();
+        assert_eq!(Bump::Prerelease.apply(&pre).to_string(), expected);
+    }
+    assert!("1.2".parse::<NewVersion>().is_err());
+}
+
+#[test]
+fn version_updates_cargo_toml_cargo_lock_and_package_json() {
+    let fixture = utils::fixture::js_hello_world();
+    let manifest = fs::read_to_string(fixture.path.join("Cargo.toml"))
+        .unwrap()
+        .replace(
+            "version = \"0.1.0\"",
+            "version = \"0.1.0\" # released with wasm-pack",
+        );
+    fixture.file("Cargo.toml", &manifest);
+    fixture.file(
+        "pkg/package.json",
+        r#"{ "name": "js-hello-world", "version": "0.1.0" }"#,
+    );
+    let lock_version = |fixture: &utils::fixture::Fixture| {
+        let lock = fs::read_to_string(fixture.path.join("Cargo.lock")).unwrap();
+        let entry = lock.split("name = \"js-hello-world\"\n").nth(1).unwrap();
+        entry.lines().next().unwrap().to_string()
+    };
+
+    fixture
+        .wasm_pack()
+        .arg("version")
+        .arg("minor")
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "[dry run] js-hello-world 0.1.0 → 0.2.0",
+        ))
+        .stderr(predicates::str::contains("Would update"));
+    assert_eq!(
+        fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap(),
+        manifest
+    );
+    assert_eq!(lock_version(&fixture), "version = \"0.1.0\"");
+
+    fixture
+        .wasm_pack()
+        .arg("version")
+        .arg("minor")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("js-hello-world 0.1.0 → 0.2.0"));
+    assert_eq!(
+        fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap(),
+        manifest.replace("version = \"0.1.0\" #", "version = \"0.2.0\" #")
+    );
+    assert_eq!(lock_version(&fixture), "version = \"0.2.0\"");
+    let pkg: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(fixture.path.join("pkg/package.json")).unwrap())
+            .unwrap();
+    assert_eq!(pkg["version"], "0.2.0");
+}
+
+#[test]
+fn version_changes_an_inherited_version_at_the_workspace_root() {
+    let fixture = utils::fixture::Fixture::new();
+    fixture
+        .file(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"member\"]\n\n[workspace.package]\nversion = \"1.0.0\"\n",
+        )
+        .file(
+            "member/Cargo.toml",
+            "[package]\nname = \"member\"\nversion.workspace = true\n\n[lib]\ncrate-type = [\"cdylib\"]\n",
+        )
+        .file("member/src/lib.rs", "");
+
+    fixture
+        .wasm_pack()
+        .arg("version")
+        .arg("1.1.0")
+        .arg("member")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("inherited from the workspace"))
+        .stderr(predicates::str::contains("member 1.0.0 → 1.1.0"));
+    let root = fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap();
+    assert!(root.contains("version = \"1.1.0\""), "{}", root);
+    let member = fs::read_to_string(fixture.path.join("member/Cargo.toml")).unwrap();
+    assert!(member.contains("version.workspace = true"), "{}", member);
+}
@@ -187,3 +187,81 @@ fn misconfigured() {
         .stderr(predicates::str::contains("failed to parse manifest"))
         .failure();
 }
+
+#[test]
+#[cfg(unix)]
+fn a_wasm_opt_downloaded_into_the_cache_is_used() {
+    use std::hash::{Hash, Hasher};
+    use std::os::unix::fs::PermissionsExt;
+    use wasm_pack::install::{self, Arch, Os, Tool};
+
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    // Where an earlier build would have downloaded it to.
+    let url = install::prebuilt_url_for(
+        &Tool::WasmOpt,
+        "latest",
+        &Arch::get().unwrap(),
+        &Os::get().unwrap(),
+    )
+    .unwrap();
+    let cache = fixture.path.join("cache");
+    // binary-install names the entry after a hash of the URL.
+    let mut hasher = siphasher::sip::SipHasher13::new();
+    url.hash(&mut hasher);
+    let hash: String = hasher
+        .finish()
+        .to_le_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let wasm_opt = cache
+        .join(format!("wasm-opt-{}", hash))
+        .join("bin/wasm-opt");
+    std::fs::create_dir_all(wasm_opt.parent().unwrap()).unwrap();
+    std::fs::write(
+        &wasm_opt,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> {}\ncp \"$1\" \"$3\"\n",
+            fixture.path.join("wasm-opt.log").display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&wasm_opt, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_without(&bin, "wasm-opt"))
+        .env("WASM_PACK_CACHE", &cache)
+        .arg("build")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Optimizing wasm binaries"))
+        .stderr(predicates::str::contains("Skipping wasm-opt").not());
+    let log = std::fs::read_to_string(fixture.path.join("wasm-opt.log")).unwrap();
+    assert!(log.contains("js_hello_world_bg.wasm -o"), "{}", log);
+}
+
+#[test]
+#[cfg(unix)]
+fn a_missing_wasm_opt_is_skipped_with_a_warning() {
+    let fixture = utils::fixture::js_hello_world();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_without(&bin, "wasm-opt"))
+        .env("WASM_PACK_CACHE", fixture.path.join("cache"))
+        .arg("build")
+        .arg("--mode")
+        .arg("no-install")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Skipping wasm-opt, so the wasm won't be optimized",
+        ))
+        .stderr(predicates::str::contains("Optimizing wasm binaries").not());
+}
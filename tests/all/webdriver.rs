@@ -1,18 +1,32 @@
 use crate::utils::fixture;
+use assert_cmd::prelude::*;
 use binary_install::Cache;
+use predicates::prelude::*;
 use wasm_pack::test::webdriver;
 
 #[test]
 #[cfg(any(
     all(target_os = "linux", target_arch = "x86_64"),
     all(target_os = "macos", target_arch = "x86_64"),
+    all(target_os = "macos", target_arch = "aarch64"),
     all(target_os = "windows", target_arch = "x86"),
     all(target_os = "windows", target_arch = "x86_64")
 ))]
 fn can_install_chromedriver() {
     let fixture = fixture::js_hello_world();
     let cache = Cache::at(&fixture.path);
-    assert!(webdriver::install_chromedriver(&cache, true).is_ok());
+    assert!(webdriver::install_chromedriver(&cache, true, None, None).is_ok());
+}
+
+#[test]
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+fn chromedriver_is_unavailable_on_linux_aarch64() {
+    let fixture = fixture::js_hello_world();
+    let cache = Cache::at(&fixture.path);
+    let error = webdriver::install_chromedriver(&cache, true, None, None).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("not published for linux on aarch64"));
 }
 
 #[test]
@@ -28,5 +42,120 @@ fn can_install_chromedriver() {
 fn can_install_geckodriver() {
     let fixture = fixture::js_hello_world();
     let cache = Cache::at(&fixture.path);
-    assert!(webdriver::install_geckodriver(&cache, true).is_ok());
+    assert!(webdriver::install_geckodriver(&cache, true, None).is_ok());
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+fn can_find_safaridriver() {
+    assert!(webdriver::get_safaridriver().is_ok());
+}
+
+#[test]
+#[cfg(not(target_os = "macos"))]
+fn safaridriver_is_only_available_on_macos() {
+    let error = webdriver::get_safaridriver().unwrap_err().to_string();
+    assert!(error.contains("only run on macOS"), "{}", error);
+}
+
+#[test]
+fn safaridriver_failing_to_start_is_told_apart_from_failing_tests() {
+    assert!(webdriver::failed_to_start(
+        "Error: SessionNotCreated: Could not create a session: You must enable the \
+         'Allow Remote Automation' option in Safari's Develop menu to control Safari via \
+         WebDriver."
+    ));
+    assert!(!webdriver::failed_to_start(
+        "test result: FAILED. 1 passed; 1 failed; 0 ignored"
+    ));
+}
+
+#[test]
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn can_install_msedgedriver() {
+    let fixture = fixture::js_hello_world();
+    let cache = Cache::at(&fixture.path);
+    assert!(webdriver::install_msedgedriver(&cache, true, None).is_ok());
+}
+
+#[test]
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn msedgedriver_is_unsupported_elsewhere() {
+    let fixture = fixture::js_hello_world();
+    let cache = Cache::at(&fixture.path);
+    let error = webdriver::install_msedgedriver(&cache, true, None)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("only supported on Windows and macOS"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn parses_common_browser_version_strings() {
+    for (output, version) in [
+        ("Google Chrome 120.0.6099.109 \n", "120.0.6099.109"),
+        ("Google Chrome 121.0.6167.57 beta\n", "121.0.6167.57"),
+        ("Chromium 120.0.6099.71 snap\n", "120.0.6099.71"),
+        (
+            "Chromium 119.0.6045.159 built on Debian 12.2, running on Debian 12.2\n",
+            "119.0.6045.159",
+        ),
+        (
+            "\r\nHKEY_CURRENT_USER\\Software\\Google\\Chrome\\BLBeacon\r\n    version    REG_SZ    \
+             120.0.6099.130\r\n\r\n",
+            "120.0.6099.130",
+        ),
+        (
+            "ChromeDriver 120.0.6099.109 (3419140ab665596f21b385ce136419fde0924272-refs/branch-heads/6099@{#1483})\n",
+            "120.0.6099.109",
+        ),
+        ("Microsoft Edge 120.0.2210.91 \n", "120.0.2210.91"),
+        ("Mozilla Firefox 121.0\n", "121.0"),
+    ] {
+        assert_eq!(
+            webdriver::parse_browser_version(output),
+            Some(version),
+            "{:?}",
+            output
+        );
+    }
+    assert_eq!(webdriver::parse_browser_version("Google Chrome"), None);
+}
+
+#[test]
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn a_pinned_geckodriver_is_used_instead_of_the_one_on_the_path() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = fixture::wbg_test_node();
+    fixture.wasm_bindgen_version("0.2.95");
+    let bin = fixture.install_fake_wasm_bindgen("0.2.95");
+    let geckodriver = bin.join("geckodriver");
+    std::fs::write(&geckodriver, "#!/bin/sh\necho geckodriver 0.35.0\n").unwrap();
+    std::fs::set_permissions(&geckodriver, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let manifest = std::fs::read_to_string(fixture.path.join("Cargo.toml")).unwrap();
+    fixture.file(
+        "Cargo.toml",
+        format!(
+            "{}\n[package.metadata.wasm-pack.test]\ngeckodriver = \"0.0.1\"\n",
+            manifest
+        ),
+    );
+
+    // No geckodriver 0.0.1 was ever published, so the download has to fail.
+    fixture
+        .wasm_pack()
+        .env("PATH", fixture.path_with(&bin))
+        .env("WASM_PACK_CACHE", fixture.path.join("cache"))
+        .arg("prepare")
+        .arg("--firefox")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "failed to download the pinned geckodriver 0.0.1",
+        ))
+        .stderr(predicates::str::contains("found on the $PATH").not());
 }